@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use simptui::parse_csv;
+use std::io::Write;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(content) = std::str::from_utf8(data) {
+        parse_csv(content);
+    }
+    // The file reader also decodes non-UTF-8 input
+    let mut file = tempfile::NamedTempFile::with_suffix(".csv").unwrap();
+    file.write_all(data).unwrap();
+    let _ = simptui::read_csv_file(file.path());
+});
@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use simptui::{export_csv, parse_csv, parse_markdown};
+
+fuzz_target!(|content: &str| {
+    let equations = parse_markdown(content);
+    // Whatever was found must survive a trip through CSV
+    let read = parse_csv(&export_csv(&equations));
+    assert_eq!(read.len(), equations.len());
+});
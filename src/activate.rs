@@ -0,0 +1,119 @@
+use crate::{
+    csv_columns, csv_field, csv_row, detect_file_type, load_source, Equation, Extractor, SourceSpan,
+};
+use regex::Regex;
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Marks `equations`, which must have been read from the markdown, CSV or
+/// TOML file `path` with the built-in syntax, active or inactive in it:
+/// `%%yes%%`/`%%no%%` opening a markdown block (a block without one
+/// counts as active and only gets one to turn it off), the Active column of
+/// a CSV row, `active = ..` in a TOML table. Markdown equations an
+/// `[extract]` rule found are refused, as its markers may be anything. The
+/// file is written back as UTF-8 with LF line endings.
+pub fn set_active_in_source(path: &Path, equations: &[Equation], active: bool) -> io::Result<()> {
+    let content = load_source(path)?;
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let header_columns = lines.first().map_or(3, |header| csv_columns(header));
+    let file_type = detect_file_type(path);
+    let marker = Regex::new(r"^%%(yes|no)?%%").unwrap();
+    let active_key = Regex::new(r"^[ \t]*active[ \t]*=").unwrap();
+    // Blocks in the built-in syntax, the only one whose markers are known
+    let builtin: Vec<SourceSpan> = match file_type {
+        "markdown" => Extractor::default()
+            .extract(&content)
+            .iter()
+            .filter_map(|equation| equation.span)
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    // Bottom up, so inserted lines don't shift the spans still to come
+    let mut changes: Vec<&Equation> = equations.iter().collect();
+    changes.sort_by_key(|equation| Reverse(equation.span.map(|span| span.start_line)));
+    for equation in changes {
+        let span = equation
+            .span
+            .filter(|span| span.end_line <= lines.len())
+            .ok_or_else(|| not_switchable(path, equation))?;
+        let block = span.start_line - 1..span.end_line;
+        match file_type {
+            "csv" => {
+                let row = &mut lines[block.start];
+                let (mut columns, _) = csv_row(row, header_columns);
+                if columns.len() < 3 {
+                    return Err(not_switchable(path, equation));
+                }
+                columns[0] = if active { "yes" } else { "no" }.to_string();
+                *row = columns
+                    .iter()
+                    .map(|column| csv_field(column))
+                    .collect::<Vec<_>>()
+                    .join(",");
+            }
+            "markdown" if !builtin.contains(&span) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!(
+                    "{}: {} was found by an [extract] rule, whose markers can't be switched here",
+                    path.display(),
+                    equation.name
+                ),
+                ))
+            }
+            "markdown" => {
+                // Where the block opens, text before it on the line aside
+                let first = &mut lines[block.start];
+                let state = if active { "%%yes%%" } else { "%%no%%" };
+                let at = ["%%", "$$", r"\begin{"]
+                    .iter()
+                    .filter_map(|open| first.find(open))
+                    .min()
+                    .ok_or_else(|| not_switchable(path, equation))?;
+                if marker.is_match(&first[at..]) {
+                    let rest = marker.replace(&first[at..], state).into_owned();
+                    first.replace_range(at.., &rest);
+                } else if !active && at == 0 {
+                    lines.insert(block.start, state.to_string());
+                } else if !active {
+                    first.insert_str(at, state);
+                }
+            }
+            "toml" => {
+                let entry = format!("active = {}", active);
+                match lines[block.clone()]
+                    .iter()
+                    .position(|line| active_key.is_match(line))
+                {
+                    Some(at) => lines[block.start + at] = entry,
+                    None if active => {} // The default
+                    None => lines.insert(block.start + 1, entry),
+                }
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!(
+                        "{}: only markdown, csv and toml equations can be switched on and off",
+                        path.display()
+                    ),
+                ))
+            }
+        }
+    }
+    fs::write(path, lines.join("\n") + "\n")
+}
+
+fn not_switchable(path: &Path, equation: &Equation) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!(
+            "{}: can't find where {} is marked active; was it read with a custom rule?",
+            path.display(),
+            equation.name
+        ),
+    )
+}
@@ -0,0 +1,106 @@
+use crate::{missing_tool, tool_command, Engine, OutputFormat};
+use std::io;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// The external tools of a render: TeX to PDF, and PDF to SVG/PNG. Everything
+/// else (parsing, caching, post-processing) is shared, so a test double
+/// exercises the real pipeline without a TeX installation.
+pub trait RenderBackend {
+    /// Compiles `tex_file` with `engine` into `<output_dir>/<stem>.pdf`, plus
+    /// `<stem>.log` with `keep_logs`. Returns `Ok(false)` when TeX reports an
+    /// error, and a `TimedOut` error when it takes longer than `timeout`.
+    fn compile(
+        &self,
+        engine: Engine,
+        tex_file: &Path,
+        output_dir: &Path,
+        keep_logs: bool,
+        timeout: Option<Duration>,
+    ) -> io::Result<bool>;
+
+    /// Converts `pdf_file` into `target`, an SVG or PNG path.
+    fn convert(
+        &self,
+        pdf_file: &Path,
+        target: &Path,
+        format: OutputFormat,
+        dpi: u32,
+    ) -> io::Result<()>;
+}
+
+/// The real thing: TeX engines plus pdftocairo. `deterministic` has TeX
+/// date its PDFs `SOURCE_DATE_EPOCH`, or 1970, instead of now.
+#[derive(Debug, Default)]
+pub struct TexBackend {
+    pub deterministic: bool,
+}
+
+impl RenderBackend for TexBackend {
+    fn compile(
+        &self,
+        engine: Engine,
+        tex_file: &Path,
+        output_dir: &Path,
+        keep_logs: bool,
+        timeout: Option<Duration>,
+    ) -> io::Result<bool> {
+        engine.compile_with(tex_file, output_dir, keep_logs, timeout, self.deterministic)
+    }
+
+    fn convert(
+        &self,
+        pdf_file: &Path,
+        target: &Path,
+        format: OutputFormat,
+        dpi: u32,
+    ) -> io::Result<()> {
+        if !pdf_file.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("PDF file not found: {}", pdf_file.display()),
+            ));
+        }
+
+        let mut command = tool_command("pdftocairo");
+        match format {
+            OutputFormat::Svg => command.arg("-svg").arg(pdf_file).arg(target),
+            // pdftocairo appends the .png extension itself
+            OutputFormat::Png => command
+                .arg("-png")
+                .arg("-singlefile")
+                .arg("-transp")
+                .arg("-r")
+                .arg(dpi.to_string())
+                .arg(pdf_file)
+                .arg(target.with_extension("")),
+            OutputFormat::Pdf | OutputFormat::MathML => return Ok(()),
+        };
+        debug!("Running {:?}", command);
+        // Captured rather than left on a terminal the TUI may be drawing on
+        let output = command
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| missing_tool("pdftocairo", e))?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stderr = stderr.trim_end();
+
+        if !output.status.success() {
+            let mut message = format!(
+                "Failed to convert {} to {}",
+                pdf_file.display(),
+                format.extension().to_uppercase()
+            );
+            if !stderr.is_empty() {
+                message.push_str(&format!(": {}", stderr));
+            }
+            return Err(io::Error::other(message));
+        }
+        if !stderr.is_empty() {
+            warn!("pdftocairo on {}: {}", pdf_file.display(), stderr);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt;
+
+/// What opens a group that has to be closed in a LaTeX body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Opener {
+    Brace,               // `{ }`
+    Delimiter(String),   // `\left( \right)`, with the opening delimiter
+    Environment(String), // `\begin{name} \end{name}`
+}
+
+impl fmt::Display for Opener {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Opener::Brace => f.write_str("{"),
+            Opener::Delimiter(delimiter) => write!(f, r"\left{}", delimiter),
+            Opener::Environment(name) => write!(f, r"\begin{{{}}}", name),
+        }
+    }
+}
+
+/// How the groups of a body stand at its end.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Balance {
+    pub open: Vec<(Opener, usize)>, // Not closed, outermost first, with their 1-based line
+    pub stray: Option<(String, usize)>, // First closer with nothing to close, and its line
+}
+
+impl Balance {
+    pub fn is_balanced(&self) -> bool {
+        self.open.is_empty() && self.stray.is_none()
+    }
+
+    /// How many groups are open; at the end of a prefix of the body, the
+    /// nesting depth there.
+    pub fn depth(&self) -> usize {
+        self.open.len()
+    }
+
+    /// What's wrong, for a status line or an error; None if balanced.
+    pub fn problem(&self) -> Option<String> {
+        if let Some((closer, line)) = &self.stray {
+            return Some(match self.open.last() {
+                Some((group, _)) if closer.starts_with('\\') => {
+                    format!("{} on line {} doesn't close {}", closer, line, group)
+                }
+                _ => format!("{} on line {} closes nothing", closer, line),
+            });
+        }
+        let (group, line) = self.open.last()?;
+        Some(format!("{} on line {} is never closed", group, line))
+    }
+}
+
+/// Checks the braces, `\left`/`\right` pairs and environments of `body`,
+/// leaving out escaped braces and `%` comments. Stops at the first closer
+/// that doesn't match, as what follows can't be told apart any more.
+pub fn balance(body: &str) -> Balance {
+    let mut balance = Balance::default();
+    let mut line = 1;
+    let mut rest = body;
+    while let Some(c) = rest.chars().next() {
+        let mut step = c.len_utf8();
+        match c {
+            '\n' => line += 1,
+            '%' => step = rest.find('\n').unwrap_or(rest.len()),
+            '{' => balance.open.push((Opener::Brace, line)),
+            '}' => {
+                if balance.open.last().map(|(group, _)| group) != Some(&Opener::Brace) {
+                    balance.stray = Some(("}".to_string(), line));
+                    return balance;
+                }
+                balance.open.pop();
+            }
+            '\\' => {
+                let (command, argument) = command_at(rest);
+                step = command.len() + argument.map_or(0, str::len);
+                let closed = match (command, argument) {
+                    (r"\left", Some(delimiter)) => {
+                        let group = Opener::Delimiter(delimiter.to_string());
+                        balance.open.push((group, line));
+                        None
+                    }
+                    (r"\begin", Some(name)) => {
+                        let group = Opener::Environment(environment_name(name));
+                        balance.open.push((group, line));
+                        None
+                    }
+                    (r"\right", Some(_)) => Some(matches!(
+                        balance.open.last(),
+                        Some((Opener::Delimiter(_), _))
+                    )),
+                    (r"\end", Some(name)) => Some(matches!(
+                        balance.open.last(),
+                        Some((Opener::Environment(open), _)) if *open == environment_name(name)
+                    )),
+                    _ => None,
+                };
+                match closed {
+                    Some(true) => {
+                        balance.open.pop();
+                    }
+                    Some(false) => {
+                        let closer = format!("{}{}", command, argument.unwrap_or_default());
+                        balance.stray = Some((closer, line));
+                        return balance;
+                    }
+                    None => {}
+                }
+            }
+            _ => {}
+        }
+        rest = &rest[step.min(rest.len())..];
+    }
+    balance
+}
+
+// The control sequence `text` starts with (`\left`, `\{`, `\\`), and for
+// those that take one, the delimiter or `{name}` after it
+fn command_at(text: &str) -> (&str, Option<&str>) {
+    let letters = text[1..]
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .map_or(text.len(), |end| end + 1);
+    if letters == 1 {
+        // A control symbol: one character, whatever it is
+        let end = 1 + text[1..].chars().next().map_or(0, char::len_utf8);
+        return (&text[..end], None);
+    }
+    let command = &text[..letters];
+    let after = &text[letters..];
+    let skipped = after.len() - after.trim_start().len();
+    let argument = match command {
+        r"\left" | r"\right" => delimiter_at(after.trim_start()),
+        r"\begin" | r"\end" => after
+            .trim_start()
+            .strip_prefix('{')
+            .and_then(|name| name.find('}'))
+            .map(|end| &after.trim_start()[..end + 2]),
+        _ => None,
+    };
+    match argument {
+        Some(argument) => (command, Some(&after[..skipped + argument.len()])),
+        None => (command, None),
+    }
+}
+
+// `(`, `.`, `\{`, `\langle`, ...
+fn delimiter_at(text: &str) -> Option<&str> {
+    let c = text.chars().next()?;
+    if c != '\\' {
+        return Some(&text[..c.len_utf8()]);
+    }
+    Some(command_at(text).0)
+}
+
+// `{align*}` or ` {align*}` -> `align*`
+fn environment_name(argument: &str) -> String {
+    argument
+        .trim()
+        .trim_start_matches('{')
+        .trim_end_matches('}')
+        .trim()
+        .to_string()
+}
@@ -0,0 +1,142 @@
+use crate::{Equation, ParserRegistry};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// An equation set aside from any file, to be collected with the others.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub file: PathBuf, // Absolute where it could be resolved
+    pub name: String,
+    pub body: String, // When bookmarked; finds the equation again after a rename
+}
+
+/// The bookmark list, kept in a JSON file and written on every change.
+#[derive(Debug, Clone)]
+pub struct Bookmarks {
+    path: PathBuf,
+    bookmarks: Vec<Bookmark>,
+}
+
+impl Bookmarks {
+    /// The list kept in `path`; empty if there is no such file yet.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let bookmarks = match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {}", path.display(), e),
+                )
+            })?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Bookmarks {
+            path: path.to_path_buf(),
+            bookmarks,
+        })
+    }
+
+    pub fn list(&self) -> &[Bookmark] {
+        &self.bookmarks
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bookmarks.is_empty()
+    }
+
+    pub fn contains(&self, source: &Path, name: &str) -> bool {
+        let file = absolute(source);
+        self.bookmarks
+            .iter()
+            .any(|bookmark| bookmark.file == file && bookmark.name == name)
+    }
+
+    /// Bookmarks `equation` of `source`, or drops its bookmark if it had one.
+    /// Tells whether it's bookmarked now.
+    pub fn toggle(&mut self, source: &Path, equation: &Equation) -> io::Result<bool> {
+        let file = absolute(source);
+        let before = self.bookmarks.len();
+        self.bookmarks
+            .retain(|bookmark| bookmark.file != file || bookmark.name != equation.name);
+        let added = self.bookmarks.len() == before;
+        if added {
+            self.bookmarks.push(Bookmark {
+                file,
+                name: equation.name.clone(),
+                body: equation.body.clone(),
+            });
+        }
+        self.save()?;
+        Ok(added)
+    }
+
+    pub fn remove(&mut self, index: usize) -> io::Result<()> {
+        if index < self.bookmarks.len() {
+            self.bookmarks.remove(index);
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// The bookmarked equations as their files have them now, active and
+    /// ready to render into one directory: an equation is found by its name,
+    /// else by the body it had, and names that repeat across files get the
+    /// file stem in front. Bookmarks whose equation or file is gone come back
+    /// as `name (file)` in the second list.
+    pub fn equations(&self, parsers: &ParserRegistry) -> (Vec<Equation>, Vec<String>) {
+        let mut files: HashMap<&Path, io::Result<Vec<Equation>>> = HashMap::new();
+        let mut found_in = Vec::new();
+        let mut missing = Vec::new();
+        for bookmark in &self.bookmarks {
+            let loaded = files
+                .entry(&bookmark.file)
+                .or_insert_with(|| parsers.load(&bookmark.file));
+            let found = loaded.as_ref().ok().and_then(|loaded| {
+                loaded
+                    .iter()
+                    .find(|equation| equation.name == bookmark.name)
+                    .or_else(|| loaded.iter().find(|eq| eq.body == bookmark.body))
+            });
+            match found {
+                Some(found) => found_in.push((&bookmark.file, found.clone())),
+                None => missing.push(format!("{} ({})", bookmark.name, bookmark.file.display())),
+            }
+        }
+        let mut uses: HashMap<String, usize> = HashMap::new();
+        for (_, equation) in &found_in {
+            *uses.entry(equation.name.clone()).or_default() += 1;
+        }
+        let equations = found_in
+            .into_iter()
+            .map(|(file, equation)| {
+                let mut equation = Equation {
+                    active: true,
+                    ..equation
+                };
+                if uses[&equation.name] > 1 {
+                    let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+                    let name = format!("{}_{}", stem, equation.name);
+                    equation.origin = Some(equation.name.clone());
+                    equation.name = Equation::sanitize_filename(&name);
+                }
+                equation
+            })
+            .collect();
+        (equations, missing)
+    }
+
+    fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(&self.bookmarks).map_err(io::Error::other)?;
+        fs::write(&self.path, json + "\n")
+    }
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
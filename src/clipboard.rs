@@ -0,0 +1,163 @@
+use crate::{
+    find_tool, rasterize_svg, tool_command, Equation, OutputFormat, OutputLayout, OutputNaming,
+    RenderBackend, RenderOptions, RenderPipeline, Rgb,
+};
+use resvg::tiny_skia::Color;
+use std::env;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use tracing::debug;
+
+/// `equation` as a PNG, without touching the output directory: rendered to
+/// SVG in a scratch directory and rasterized at `options.dpi`, on
+/// `background` or else transparent.
+pub fn render_png(
+    equation: &Equation,
+    options: &RenderOptions,
+    backend: &dyn RenderBackend,
+    background: Option<Rgb>,
+) -> io::Result<Vec<u8>> {
+    let scratch = tempfile::tempdir()?;
+    let mut options = options.clone();
+    options.output_dir = scratch.path().to_path_buf();
+    options.format = OutputFormat::Svg;
+    options.layout = OutputLayout::PerEquation;
+    options.naming = OutputNaming::Name;
+    options.hash_names = false;
+    options.baseline_align = false;
+    options.split_lines = false;
+    options.resume = false;
+    options.pipeline = RenderPipeline::default(); // Hooks are for real outputs
+    let equation = Equation {
+        active: true,
+        ..equation.clone()
+    };
+    equation.render_with(&options, backend)?;
+
+    let svg = scratch.path().join(format!("{}.svg", equation.file_stem()));
+    // usvg measures in CSS pixels, 96 to the inch
+    let mut pixmap = rasterize_svg(&svg, options.dpi as f32 / 96.0)?;
+    if let Some(Rgb(r, g, b)) = background {
+        let mut filled = pixmap.clone();
+        filled.fill(Color::from_rgba8(r, g, b, 255));
+        filled.draw_pixmap(
+            0,
+            0,
+            pixmap.as_ref(),
+            &Default::default(),
+            Default::default(),
+            None,
+        );
+        pixmap = filled;
+    }
+    pixmap.encode_png().map_err(io::Error::other)
+}
+
+/// Puts `png` on the system clipboard as an image, through `wl-copy` or
+/// `xclip` on Linux, `osascript` on macOS and PowerShell on Windows.
+/// Returns the tool that took it.
+pub fn copy_png(png: &[u8]) -> io::Result<&'static str> {
+    if cfg!(target_os = "macos") {
+        let file = png_file(png)?;
+        let script = format!(
+            "set the clipboard to (read (POSIX file \"{}\") as «class PNGf»)",
+            file.path().display()
+        );
+        run(tool_command("osascript").args(["-e", &script]), None)?;
+        return Ok("osascript");
+    }
+    if cfg!(windows) {
+        let file = png_file(png)?;
+        let script = format!(
+            "Add-Type -AssemblyName System.Windows.Forms, System.Drawing; \
+             [System.Windows.Forms.Clipboard]::SetImage([System.Drawing.Image]::FromFile('{}'))",
+            file.path().display().to_string().replace('\'', "''")
+        );
+        let mut command = tool_command("powershell");
+        command.args(["-NoProfile", "-STA", "-Command", &script]);
+        run(&mut command, None)?;
+        return Ok("powershell");
+    }
+
+    // Under Wayland, X11 tools only reach XWayland clients
+    let wayland = env::var_os("WAYLAND_DISPLAY").is_some();
+    if wayland && find_tool("wl-copy").is_some() {
+        run(
+            tool_command("wl-copy").args(["--type", "image/png"]),
+            Some(png),
+        )?;
+        return Ok("wl-copy");
+    }
+    if find_tool("xclip").is_some() {
+        let mut command = tool_command("xclip");
+        command.args(["-selection", "clipboard", "-target", "image/png", "-in"]);
+        run(&mut command, Some(png))?;
+        return Ok("xclip");
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "No clipboard tool found, install wl-clipboard (Wayland) or xclip (X11)",
+    ))
+}
+
+/// Puts `text` on the system clipboard, through `pbcopy` on macOS, `clip`
+/// on Windows and `wl-copy` or `xclip` elsewhere. Returns the tool that
+/// took it.
+pub fn copy_text(text: &str) -> io::Result<&'static str> {
+    let tool = if cfg!(target_os = "macos") {
+        "pbcopy"
+    } else if cfg!(windows) {
+        "clip"
+    } else if env::var_os("WAYLAND_DISPLAY").is_some() && find_tool("wl-copy").is_some() {
+        "wl-copy"
+    } else if find_tool("xclip").is_some() {
+        "xclip"
+    } else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "No clipboard tool found, install wl-clipboard (Wayland) or xclip (X11)",
+        ));
+    };
+    let mut command = tool_command(tool);
+    if tool == "xclip" {
+        command.args(["-selection", "clipboard", "-in"]);
+    }
+    run(&mut command, Some(text.as_bytes()))?;
+    Ok(tool)
+}
+
+// The PNG in a temporary file, for tools that can't read it from stdin
+fn png_file(png: &[u8]) -> io::Result<tempfile::NamedTempFile> {
+    let mut file = tempfile::Builder::new().suffix(".png").tempfile()?;
+    file.write_all(png)?;
+    file.flush()?;
+    Ok(file)
+}
+
+// wl-copy and xclip fork to serve the selection once stdin is closed, so
+// waiting for them doesn't wait for a paste; that child inherits stderr,
+// which is why it isn't captured
+fn run(command: &mut Command, stdin: Option<&[u8]>) -> io::Result<()> {
+    debug!("Copying to the clipboard with {:?}", command);
+    let mut child = command
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let (Some(data), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        pipe.write_all(data)?;
+    }
+    let status = child.wait()?;
+    if status.success() {
+        return Ok(());
+    }
+    Err(io::Error::other(format!(
+        "Copying to the clipboard failed: {:?} exited with {}",
+        command.get_program(),
+        status
+    )))
+}
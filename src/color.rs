@@ -0,0 +1,291 @@
+use crate::group;
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use tracing::warn;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.trim().trim_start_matches('#');
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some(Rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+
+    pub fn to_hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+
+    /// Relative luminance as defined by WCAG 2.x, in the range 0.0..=1.0.
+    pub fn luminance(self) -> f64 {
+        fn linear(c: u8) -> f64 {
+            let c = c as f64 / 255.0;
+            if c <= 0.03928 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        }
+        0.2126 * linear(self.0) + 0.7152 * linear(self.1) + 0.0722 * linear(self.2)
+    }
+
+    pub fn is_dark(self) -> bool {
+        self.luminance() < 0.179
+    }
+
+    /// WCAG contrast ratio with `other`, from 1.0 (none) to 21.0.
+    pub fn contrast(self, other: Rgb) -> f64 {
+        let (a, b) = (self.luminance(), other.luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
+    // `t` of the way from self to `other`
+    fn mix(self, other: Rgb, t: f64) -> Rgb {
+        let channel = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+        Rgb(
+            channel(self.0, other.0),
+            channel(self.1, other.1),
+            channel(self.2, other.2),
+        )
+    }
+}
+
+/// WCAG AA minimum for normal text.
+pub const MIN_CONTRAST: f64 = 4.5;
+
+/// Takes the `\textcolor{..}` and `\color{..}` commands out of `body`,
+/// leaving what they painted, so all of it comes out in the one color.
+pub fn strip_colors(body: &str) -> String {
+    let command = Regex::new(r"\\(?:textcolor|color)\s*(?:\[[^\]]*\])?\s*\{").unwrap();
+    let mut stripped = String::new();
+    let mut rest = body;
+    while let Some(found) = command.find(rest) {
+        // The color's opening brace ends the match
+        let Some(color) = group(&rest[found.end() - 1..]) else {
+            break;
+        };
+        stripped.push_str(&rest[..found.start()]);
+        rest = &rest[found.end() - 1 + color..];
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+/// Darkens (on light backgrounds) or lightens `color` just enough to reach
+/// `min_contrast` against `background`, keeping its hue where possible.
+pub fn adjust_contrast(color: Rgb, background: Rgb, min_contrast: f64) -> Rgb {
+    let target = contrasting_color(background);
+    (0..=100)
+        .map(|step| color.mix(target, step as f64 / 100.0))
+        .find(|candidate| candidate.contrast(background) >= min_contrast)
+        .unwrap_or(target)
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_hex())
+    }
+}
+
+impl FromStr for Rgb {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Rgb::from_hex(s).ok_or_else(|| format!("invalid color '{}': expected a hex code", s))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ColorSpec {
+    Hex(Rgb),
+    Auto,
+}
+
+impl FromStr for ColorSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(ColorSpec::Auto);
+        }
+        Rgb::from_hex(s)
+            .map(ColorSpec::Hex)
+            .ok_or_else(|| format!("invalid color '{}': expected 'auto' or a hex code", s))
+    }
+}
+
+/// What a rendered equation is drawn on: nothing, so it blends into any
+/// page, or a solid color, e.g. to keep it readable on a dark slide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fill {
+    #[default]
+    Transparent,
+    Color(Rgb),
+}
+
+impl FromStr for Fill {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("transparent") || s.eq_ignore_ascii_case("none") {
+            return Ok(Fill::Transparent);
+        }
+        Rgb::from_hex(s)
+            .map(Fill::Color)
+            .ok_or_else(|| format!("invalid fill '{}': expected 'transparent' or a hex code", s))
+    }
+}
+
+impl<'de> Deserialize<'de> for Fill {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Base16Theme {
+    pub background: Option<Rgb>, // base00
+    pub foreground: Option<Rgb>, // base05
+}
+
+pub(crate) fn read_base16_theme(path: &Path) -> io::Result<Base16Theme> {
+    let content = fs::read_to_string(path)?;
+    let re = Regex::new(r#"(?m)^\s*(base0[0-9A-Fa-f])\s*:\s*"?#?([0-9A-Fa-f]{6})"?"#).unwrap();
+    let mut theme = Base16Theme::default();
+
+    for cap in re.captures_iter(&content) {
+        let color = Rgb::from_hex(&cap[2]);
+        match cap[1].to_ascii_lowercase().as_str() {
+            "base00" => theme.background = color,
+            "base05" => theme.foreground = color,
+            _ => {}
+        }
+    }
+
+    if theme.background.is_none() && theme.foreground.is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("no base00/base05 colors found in {}", path.display()),
+        ));
+    }
+    Ok(theme)
+}
+
+pub(crate) fn contrasting_color(background: Rgb) -> Rgb {
+    if background.is_dark() {
+        Rgb(0xff, 0xff, 0xff)
+    } else {
+        Rgb(0x00, 0x00, 0x00)
+    }
+}
+
+/// Turns a color spec into a concrete hex code. `auto` prefers the theme's
+/// foreground, then a color contrasting the theme or terminal background,
+/// and falls back to black when nothing can be detected.
+pub fn resolve_color(spec: &ColorSpec, theme: Option<&Path>) -> io::Result<String> {
+    let rgb = match spec {
+        ColorSpec::Hex(rgb) => *rgb,
+        ColorSpec::Auto => {
+            let theme = theme.map(read_base16_theme).transpose()?;
+            match theme {
+                Some(Base16Theme {
+                    foreground: Some(fg),
+                    ..
+                }) => fg,
+                Some(Base16Theme {
+                    background: Some(bg),
+                    ..
+                }) => contrasting_color(bg),
+                _ => match query_terminal_background() {
+                    Some(bg) => contrasting_color(bg),
+                    None => {
+                        warn!("Could not detect terminal background, using black");
+                        Rgb(0x00, 0x00, 0x00)
+                    }
+                },
+            }
+        }
+    };
+    Ok(rgb.to_hex())
+}
+
+#[cfg_attr(not(unix), allow(dead_code))]
+fn parse_osc11_response(response: &str) -> Option<Rgb> {
+    // e.g. "\x1b]11;rgb:1e1e/1e1e/2e2e\x07", channels may have 1-4 hex digits
+    let re = Regex::new(r"rgb:([0-9A-Fa-f]{1,4})/([0-9A-Fa-f]{1,4})/([0-9A-Fa-f]{1,4})").unwrap();
+    let cap = re.captures(response)?;
+    let channel = |i: usize| {
+        let digits = &cap[i];
+        let value = u32::from_str_radix(digits, 16).ok()?;
+        let max = (1u32 << (4 * digits.len())) - 1;
+        Some((value * 255 / max) as u8)
+    };
+    Some(Rgb(channel(1)?, channel(2)?, channel(3)?))
+}
+
+/// Asks the terminal for its background color via OSC 11. Returns `None` when
+/// there is no controlling terminal or it does not answer in time.
+#[cfg(unix)]
+pub(crate) fn query_terminal_background() -> Option<Rgb> {
+    use crossterm::terminal::{disable_raw_mode, enable_raw_mode, is_raw_mode_enabled};
+    use std::fs::OpenOptions;
+    use std::io::{Read, Write};
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::time::{Duration, Instant};
+
+    let mut tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open("/dev/tty")
+        .ok()?;
+
+    let was_raw = is_raw_mode_enabled().unwrap_or(false);
+    if !was_raw {
+        enable_raw_mode().ok()?;
+    }
+
+    let mut response = Vec::new();
+    if tty
+        .write_all(b"\x1b]11;?\x07")
+        .and_then(|_| tty.flush())
+        .is_ok()
+    {
+        let deadline = Instant::now() + Duration::from_millis(200);
+        let mut buf = [0u8; 64];
+        while Instant::now() < deadline {
+            match tty.read(&mut buf) {
+                Ok(n) if n > 0 => {
+                    response.extend_from_slice(&buf[..n]);
+                    // Terminated by BEL or ST (ESC \)
+                    if response.ends_with(b"\x07") || response.ends_with(b"\x1b\\") {
+                        break;
+                    }
+                }
+                _ => std::thread::sleep(Duration::from_millis(10)),
+            }
+        }
+    }
+
+    if !was_raw {
+        disable_raw_mode().ok();
+    }
+    parse_osc11_response(&String::from_utf8_lossy(&response))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn query_terminal_background() -> Option<Rgb> {
+    None
+}
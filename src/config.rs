@@ -0,0 +1,394 @@
+use crate::{
+    load_snippets, BatchHooks, BoundingMode, DuplicateNames, Engine, ExtractRule, Extractor, Fill,
+    Font, FontSize, OutputFormat, OutputNaming, OutputRoute, ParserRegistry, Paths, RemoteBackend,
+    RenderOptions, Retention, ShellHook, Snippet,
+};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const CONFIG_FILE_NAME: &str = "simptui.toml";
+const DEFAULT_OUTPUT_DIR: &str = "equations";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct Config {
+    pub roots: Vec<PathBuf>,
+    pub scan: ScanConfig,
+    pub output: OutputConfig,
+    pub profile: BTreeMap<String, Profile>, // `[profile.<name>]` tables
+    pub extract: BTreeMap<String, ExtractRule>, // `[extract.<name>]` tables
+    pub hook: Vec<HookConfig>,              // `[[hook]]` tables
+    pub notify: BatchHooks,                 // `[notify]` table
+    pub viewer: BTreeMap<String, String>,   // Extension -> command opening it
+    pub snippet: BTreeMap<String, String>,  // Snippet name -> LaTeX body
+    pub ignore: Vec<String>,                // Regexes matching bodies to leave out
+    pub remote: Option<RemoteConfig>,       // `[remote]` table
+    pub route: Vec<RouteConfig>,            // `[[route]]` tables
+}
+
+/// `[[route]]` table: equations tagged `tag` and/or named after the regex
+/// `name` render into `dir` under the output directory. The first matching
+/// route wins.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct RouteConfig {
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub dir: PathBuf,
+}
+
+/// `[remote]` table: render on a service at `url` instead of local TeX,
+/// authenticated with `token`.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct RemoteConfig {
+    pub url: String,
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// `[[hook]]` table: a shell command run for every rendered equation, as a
+/// render stage placed after the stage named in `after`. It may change the
+/// outputs, and a failure fails the equation; `[notify]` is for commands
+/// that only want to hear how a batch went.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct HookConfig {
+    pub name: String,
+    pub run: String,
+    #[serde(default = "default_hook_stage")]
+    pub after: String, // `generate-tex`, `compile`, `convert`, `post-process`, ...
+    #[serde(default)]
+    pub on_cache_hit: bool, // Also for outputs restored from the render cache
+}
+
+// While the `.tex`, `.log` and `.pdf` are still around
+fn default_hook_stage() -> String {
+    "post-process".to_string()
+}
+
+/// A named bundle of render settings. Unset fields keep the values already in
+/// the `RenderOptions` the profile is applied to.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct Profile {
+    pub color: Option<String>,
+    pub format: Option<OutputFormat>,
+    pub dpi: Option<u32>,
+    pub template: Option<PathBuf>,
+    pub bounding: Option<BoundingMode>,
+    pub engine: Option<Engine>,
+    pub font: Option<Font>,
+    pub split_lines: Option<bool>,
+    pub normalize_styles: Option<bool>,
+    pub keep_labels: Option<bool>,
+    pub keep_colors: Option<bool>,
+    pub source_map: Option<bool>,
+    pub size: Option<FontSize>, // `Large`, `small`, `14pt`, ...
+    pub fill: Option<Fill>,     // `transparent` or a hex color
+    pub padding: Option<f32>,   // In pt
+    pub corner_radius: Option<f32>,
+    pub retention: Option<Retention>, // `keep-all`, `keep-pdf`, `keep-tex-on-failure`, `delete-all`
+    pub timeout: Option<u64>,         // Seconds TeX gets for a simple equation, 0 for no limit
+}
+
+impl Profile {
+    pub fn apply(&self, options: &mut RenderOptions) {
+        if let Some(format) = self.format {
+            options.format = format;
+        }
+        if let Some(dpi) = self.dpi {
+            options.dpi = dpi;
+        }
+        if let Some(template) = &self.template {
+            options.template = Some(expand_home(template));
+        }
+        if let Some(bounding) = self.bounding {
+            options.bounding = bounding;
+        }
+        if let Some(engine) = self.engine {
+            options.engine = engine;
+        }
+        if let Some(font) = self.font {
+            options.font = font;
+        }
+        if let Some(split_lines) = self.split_lines {
+            options.split_lines = split_lines;
+        }
+        if let Some(normalize_styles) = self.normalize_styles {
+            options.normalize_styles = normalize_styles;
+        }
+        if let Some(keep_labels) = self.keep_labels {
+            options.keep_labels = keep_labels;
+        }
+        if let Some(keep_colors) = self.keep_colors {
+            options.keep_colors = keep_colors;
+        }
+        if let Some(source_map) = self.source_map {
+            options.source_map = source_map;
+        }
+        if let Some(size) = self.size {
+            options.size = size;
+        }
+        if let Some(fill) = self.fill {
+            options.fill = fill;
+        }
+        if let Some(padding) = self.padding {
+            options.padding = padding;
+        }
+        if let Some(corner_radius) = self.corner_radius {
+            options.corner_radius = corner_radius;
+        }
+        if let Some(retention) = self.retention {
+            options.retention = retention;
+        }
+        if let Some(timeout) = self.timeout {
+            options.timeout = (timeout > 0).then(|| Duration::from_secs(timeout));
+        }
+    }
+}
+
+fn builtin_profiles() -> BTreeMap<String, Profile> {
+    let web = Profile {
+        format: Some(OutputFormat::Svg),
+        ..Profile::default()
+    };
+    let print = Profile {
+        format: Some(OutputFormat::Pdf),
+        bounding: Some(BoundingMode::Tight),
+        ..Profile::default()
+    };
+    let slides = Profile {
+        color: Some("#ffffff".to_string()),
+        format: Some(OutputFormat::Png),
+        dpi: Some(300),
+        ..Profile::default()
+    };
+    BTreeMap::from([
+        ("web".to_string(), web),
+        ("print".to_string(), print),
+        ("slides".to_string(), slides),
+    ])
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct ScanConfig {
+    pub max_depth: usize,
+    pub max_files: usize,
+    pub respect_gitignore: bool,
+    pub watch: bool, // Keep the file index fresh via filesystem events
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        ScanConfig {
+            max_depth: 12,
+            max_files: 20_000,
+            respect_gitignore: true,
+            watch: true,
+        }
+    }
+}
+
+/// Where renders go when no `--out` is given. A relative `dir` is resolved
+/// against the source file's directory, or the CWD with `relative_to = "cwd"`.
+/// `naming` picks the output file names: name, hash, number or file-index.
+/// `duplicate_names` how equations sharing a name are told apart.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct OutputConfig {
+    pub dir: PathBuf,
+    pub relative_to: OutputBase,
+    pub naming: OutputNaming,
+    pub duplicate_names: DuplicateNames, // `counter` or `hash`
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        OutputConfig {
+            dir: PathBuf::from(DEFAULT_OUTPUT_DIR),
+            relative_to: OutputBase::Source,
+            naming: OutputNaming::default(),
+            duplicate_names: DuplicateNames::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum OutputBase {
+    Source, // Next to the file the equations came from
+    Cwd,
+}
+
+impl Config {
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let mut config: Config = toml::from_str(&content).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid config {}: {}", path.display(), e),
+            )
+        })?;
+        for (name, profile) in builtin_profiles() {
+            config.profile.entry(name).or_insert(profile);
+        }
+        Ok(config)
+    }
+
+    /// Loads `./simptui.toml`, falling back to the user config directory, or
+    /// the defaults when neither exists.
+    pub fn load() -> io::Result<Self> {
+        let candidates = [PathBuf::from(CONFIG_FILE_NAME), Paths::new().config_file()];
+
+        match candidates.iter().find(|path| path.is_file()) {
+            Some(path) => Config::from_file(path),
+            None => Ok(Config {
+                profile: builtin_profiles(),
+                ..Config::default()
+            }),
+        }
+    }
+
+    pub fn profile(&self, name: &str) -> io::Result<&Profile> {
+        self.profile.get(name).ok_or_else(|| {
+            let known: Vec<&str> = self.profile.keys().map(String::as_str).collect();
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Unknown profile '{}' (available: {})",
+                    name,
+                    known.join(", ")
+                ),
+            )
+        })
+    }
+
+    /// Roots given on the command line win over the configured ones; with
+    /// neither, the current directory is scanned.
+    pub fn scan_roots(&self, cli_roots: &[PathBuf]) -> Vec<PathBuf> {
+        let roots = if !cli_roots.is_empty() {
+            cli_roots
+        } else if !self.roots.is_empty() {
+            &self.roots
+        } else {
+            return vec![PathBuf::from("./")];
+        };
+        roots.iter().map(|root| expand_home(root)).collect()
+    }
+
+    /// The built-in markdown syntax plus the configured extraction rules.
+    pub fn extractor(&self) -> io::Result<Extractor> {
+        Extractor::new(&self.extract)
+    }
+
+    /// The built-in formats, with markdown read through the configured rules
+    /// and the `ignore` patterns applied.
+    pub fn parsers(&self) -> io::Result<ParserRegistry> {
+        let mut parsers = ParserRegistry::with_extractor(self.extractor()?);
+        parsers.set_duplicate_names(self.output.duplicate_names);
+        let ignore = self
+            .ignore
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid ignore pattern '{}': {}", pattern, e),
+                    )
+                })
+            })
+            .collect::<io::Result<_>>()?;
+        parsers.set_ignore(ignore);
+        Ok(parsers)
+    }
+
+    /// The built-in snippets with the `[snippet]` table and the snippet files
+    /// of the user config directory merged in.
+    pub fn snippets(&self) -> Vec<Snippet> {
+        load_snippets(&self.snippet, &Paths::new().snippets_dir())
+    }
+
+    /// Adds the `[[hook]]` commands to the render pipeline, in the order
+    /// they are listed, and sets the `[notify]` batch commands.
+    pub fn apply_hooks(&self, options: &mut RenderOptions) -> io::Result<()> {
+        options.hooks = self.notify.clone();
+        // Each goes after the previous one placed at the same stage
+        let mut anchors: BTreeMap<&str, &str> = BTreeMap::new();
+        for hook in &self.hook {
+            let after = anchors
+                .get(hook.after.as_str())
+                .copied()
+                .unwrap_or(&hook.after);
+            options.pipeline.insert_after(
+                after,
+                ShellHook {
+                    name: hook.name.clone(),
+                    command: hook.run.clone(),
+                    on_cache_hit: hook.on_cache_hit,
+                },
+            )?;
+            anchors.insert(&hook.after, &hook.name);
+        }
+        Ok(())
+    }
+
+    /// The `[[route]]` tables, in order.
+    pub fn routes(&self) -> io::Result<Vec<OutputRoute>> {
+        self.route
+            .iter()
+            .map(|route| {
+                let name = route
+                    .name
+                    .as_deref()
+                    .map(Regex::new)
+                    .transpose()
+                    .map_err(|e| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("Invalid route name pattern: {}", e),
+                        )
+                    })?;
+                OutputRoute::new(route.tag.as_deref(), name, &route.dir)
+            })
+            .collect()
+    }
+
+    /// The `[remote]` service, when one is configured.
+    pub fn remote_backend(&self) -> Option<RemoteBackend> {
+        let remote = self.remote.as_ref()?;
+        Some(RemoteBackend::new(&remote.url, remote.token.as_deref()))
+    }
+
+    /// Default output directory for equations read from `source`.
+    pub fn output_dir(&self, source: &Path) -> PathBuf {
+        let dir = expand_home(&self.output.dir);
+        match self.output.relative_to {
+            OutputBase::Source if dir.is_relative() => source
+                .parent()
+                .map_or_else(|| dir.clone(), |parent| parent.join(&dir)),
+            _ => dir,
+        }
+    }
+}
+
+pub(crate) fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
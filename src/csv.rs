@@ -0,0 +1,218 @@
+use crate::{Equation, MathWrap};
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+/// Header of the CSV files `export_csv` writes. `read_csv_file` only needs
+/// the first three columns.
+pub(crate) const CSV_HEADER: &str = "Active,Body,Name,Options,Line";
+
+/// The fields of one CSV row, trimmed. A field in double quotes may hold
+/// commas, with `""` for a quote.
+pub(crate) fn csv_fields(row: &str) -> Vec<String> {
+    raw_fields(row)
+        .into_iter()
+        .map(|field| field.value)
+        .collect()
+}
+
+/// The number of columns rows under `header` have: its own count for an
+/// `Active,Body,Name..` header, three otherwise.
+pub(crate) fn csv_columns(header: &str) -> usize {
+    if header
+        .trim()
+        .to_ascii_lowercase()
+        .starts_with("active,body,name")
+    {
+        csv_fields(header).len()
+    } else {
+        3
+    }
+}
+
+/// The fields of `row` in a file of `columns` columns, with commas a legacy
+/// file left unquoted in the body put back: a body field that opens more
+/// braces than it closes takes the fields up to its balance, and a row that
+/// is still too long gives its extra fields to the body. A fourth field
+/// holding `=` counts as options even when the header has no such column.
+/// The flag says whether the row needed repair.
+pub(crate) fn csv_row(row: &str, columns: usize) -> (Vec<String>, bool) {
+    let fields = raw_fields(row);
+    if fields.len() < 3 {
+        return (fields.into_iter().map(|field| field.value).collect(), false);
+    }
+    // Fields the body takes, itself included
+    let mut taken = 1;
+    let mut depth = brace_depth(&fields[1]);
+    while depth > 0 && fields.len() - taken > 2 {
+        depth += brace_depth(&fields[1 + taken]);
+        taken += 1;
+    }
+    let rest = fields.len() - taken + 1;
+    let has_options = fields[fields.len() - 1].value.contains('=');
+    let allowed = if columns < 4 && rest >= 4 && has_options {
+        4
+    } else {
+        columns.max(3)
+    };
+    if rest > allowed {
+        taken += rest - allowed;
+    }
+    if taken == 1 {
+        return (fields.into_iter().map(|field| field.value).collect(), false);
+    }
+
+    let joined = &fields[1..1 + taken];
+    let body = if joined.iter().any(|field| field.quoted) {
+        joined
+            .iter()
+            .map(|field| field.value.as_str())
+            .collect::<Vec<_>>()
+            .join(",")
+    } else {
+        row[joined[0].at.start..joined[taken - 1].at.end]
+            .trim()
+            .to_string()
+    };
+    let mut values: Vec<String> = fields.into_iter().map(|field| field.value).collect();
+    values.splice(1..1 + taken, [body]);
+    (values, true)
+}
+
+struct Field {
+    value: String,    // Unquoted and trimmed
+    at: Range<usize>, // Bytes of the field in the row, quotes included
+    quoted: bool,     // Whether the field was in double quotes
+}
+
+fn raw_fields(row: &str) -> Vec<Field> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut was_quoted = false;
+    let mut chars = row.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek().map(|&(_, c)| c) == Some('"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+                was_quoted = true;
+            }
+            ',' if !quoted => {
+                fields.push(Field {
+                    value: std::mem::take(&mut field).trim().to_string(),
+                    at: start..i,
+                    quoted: std::mem::take(&mut was_quoted),
+                });
+                start = i + 1;
+            }
+            _ => field.push(c),
+        }
+    }
+    fields.push(Field {
+        value: field.trim().to_string(),
+        at: start..row.len(),
+        quoted: was_quoted,
+    });
+    fields
+}
+
+// Braces `field` opens minus those it closes, `\{` and `\}` aside
+fn brace_depth(field: &Field) -> i32 {
+    let mut depth = 0;
+    let mut escaped = false;
+    for c in field.value.chars() {
+        match c {
+            '{' if !escaped => depth += 1,
+            '}' if !escaped => depth -= 1,
+            _ => {}
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    depth
+}
+
+/// `field` as a CSV field, quoted when it holds a comma or a quote.
+pub(crate) fn csv_field(field: &str) -> String {
+    if field.contains([',', '"']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// `equations` as CSV rows under `CSV_HEADER`: the body as it renders, on
+/// one line and without `%` comments, the `%%engine=.. packages=.. size=..
+/// color=.. tags=.. wrap=..%%` directives under Options and the source line the equation starts on.
+pub fn export_csv(equations: &[Equation]) -> String {
+    let mut csv = format!("{}\n", CSV_HEADER);
+    for equation in equations {
+        let mut options = Vec::new();
+        if let Some(engine) = equation.engine {
+            options.push(format!("engine={}", engine));
+        }
+        if !equation.packages.is_empty() {
+            options.push(format!("packages={}", equation.packages.join(",")));
+        }
+        if let Some(size) = equation.size {
+            options.push(format!("size={}", size));
+        }
+        if let Some(color) = &equation.color {
+            options.push(format!("color={}", color));
+        }
+        if !equation.tags.is_empty() {
+            options.push(format!("tags={}", equation.tags.join(",")));
+        }
+        match equation.math_wrap() {
+            MathWrap::Inline => {}
+            wrap => options.push(format!("wrap={}", wrap)),
+        }
+        let row = [
+            if equation.active { "yes" } else { "no" }.to_string(),
+            csv_field(&one_line(&equation.math_body())),
+            csv_field(&equation.name),
+            csv_field(&options.join(" ")),
+            equation
+                .span
+                .map(|span| span.start_line.to_string())
+                .unwrap_or_default(),
+        ];
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+pub fn write_csv_file(path: &Path, equations: &[Equation]) -> io::Result<()> {
+    fs::write(path, export_csv(equations))
+}
+
+// Rows are lines, so the lines of a body are joined; their comments would
+// swallow whatever follows
+fn one_line(body: &str) -> String {
+    body.lines()
+        .map(|line| strip_comment(line).trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// `line` up to its first `%` that isn't escaped as `\%`
+fn strip_comment(line: &str) -> &str {
+    let mut backslashes = 0;
+    for (i, c) in line.char_indices() {
+        match c {
+            '%' if backslashes % 2 == 0 => return &line[..i],
+            '\\' => backslashes += 1,
+            _ => backslashes = 0,
+        }
+    }
+    line
+}
@@ -0,0 +1,226 @@
+use crate::{Equation, ParserRegistry};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// An equation file as read: its equations along with what the document
+/// says about itself.
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub path: PathBuf,
+    pub format: &'static str, // Name of the parser that read it
+    pub source: String,       // Decoded text of the file
+    pub equations: Vec<Equation>,
+    pub frontmatter: BTreeMap<String, String>, // Keys lowercase
+    pub macros: Vec<Macro>,                    // In document order, see `RenderOptions::macros`
+}
+
+impl Document {
+    pub fn equation(&self, name: &str) -> Option<&Equation> {
+        self.equations.iter().find(|eq| eq.name == name)
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.frontmatter.get("title").map(String::as_str)
+    }
+}
+
+/// A `\newcommand`, `\renewcommand`, `\DeclareMathOperator` or `\def`
+/// found in a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Macro {
+    pub name: String,       // With the backslash
+    pub definition: String, // The whole command, ready for a preamble
+}
+
+/// Reads `path` with the built-in formats; see `ParserRegistry::load_document`.
+pub fn load_document(path: &Path) -> io::Result<Document> {
+    ParserRegistry::default().load_document(path)
+}
+
+/// The `key: value` lines of a `---` YAML block opening a markdown file.
+/// Nested keys and lists aren't read.
+pub(crate) fn yaml_frontmatter(content: &str) -> BTreeMap<String, String> {
+    let mut lines = content.lines();
+    if lines.next().map(str::trim_end) != Some("---") {
+        return BTreeMap::new();
+    }
+    let mut frontmatter = BTreeMap::new();
+    for line in lines {
+        if matches!(line.trim_end(), "---" | "...") {
+            return frontmatter;
+        }
+        if line.starts_with([' ', '\t', '-', '#']) {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .or_else(|| {
+                    value
+                        .strip_prefix('\'')
+                        .and_then(|value| value.strip_suffix('\''))
+                })
+                .unwrap_or(value);
+            frontmatter.insert(key.trim().to_ascii_lowercase(), value.to_string());
+        }
+    }
+    BTreeMap::new() // Never closed, so not frontmatter
+}
+
+/// The `#+KEY: value` lines heading an Org document, up to its first text.
+pub(crate) fn org_keywords(content: &str) -> BTreeMap<String, String> {
+    let mut keywords = BTreeMap::new();
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with("# ") || line == "#" {
+            continue;
+        }
+        let Some((key, value)) = line
+            .strip_prefix("#+")
+            .and_then(|keyword| keyword.split_once(':'))
+        else {
+            break;
+        };
+        let key = key.to_ascii_lowercase();
+        if key.starts_with("begin_") || key == "name" {
+            break;
+        }
+        keywords.insert(key, value.trim().to_string());
+    }
+    keywords
+}
+
+/// The macros the active `equations` define, for the others to use. Only
+/// the first definition of a name counts; samples in code fences and prose
+/// never become equations, so they stay out.
+pub(crate) fn equation_macros(equations: &[Equation]) -> Vec<Macro> {
+    let mut macros: Vec<Macro> = Vec::new();
+    for definition in equations
+        .iter()
+        .filter(|eq| eq.active)
+        .flat_map(|eq| document_macros(&eq.body))
+    {
+        if !macros.iter().any(|m| m.name == definition.name) {
+            macros.push(definition);
+        }
+    }
+    macros
+}
+
+/// The macro definitions anywhere in `content`, math blocks included. A
+/// definition whose braces never close is left out.
+pub(crate) fn document_macros(content: &str) -> Vec<Macro> {
+    let mut macros = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find('\\') {
+        let command = &rest[start..];
+        let parsed = [
+            "\\newcommand",
+            "\\renewcommand",
+            "\\DeclareMathOperator",
+            "\\def",
+        ]
+        .into_iter()
+        .find(|keyword| {
+            command.starts_with(keyword)
+                && !command[keyword.len()..].starts_with(|c: char| c.is_ascii_alphabetic())
+        })
+        .and_then(|keyword| definition(command, keyword));
+        match parsed {
+            Some((name, len)) => {
+                macros.push(Macro {
+                    name,
+                    definition: command[..len].to_string(),
+                });
+                rest = &command[len..];
+            }
+            None => rest = &command[1..],
+        }
+    }
+    macros
+}
+
+// The defined name and the length of the definition at the start of
+// `command`, which begins with `keyword`
+fn definition(command: &str, keyword: &str) -> Option<(String, usize)> {
+    let mut at = keyword.len();
+    if keyword != "\\def" && command[at..].starts_with('*') {
+        at += 1;
+    }
+    at += blank(&command[at..]);
+    // `{\name}` or a bare `\name`
+    let name = if command[at..].starts_with('{') {
+        let len = group(&command[at..])?;
+        let name = command[at + 1..at + len - 1].trim();
+        at += len;
+        name
+    } else {
+        let len = control_sequence(&command[at..])?;
+        let name = &command[at..at + len];
+        at += len;
+        name
+    };
+    if !name.starts_with('\\') || name.len() < 2 {
+        return None;
+    }
+    match keyword {
+        // Parameter text like `#1#2` up to the body
+        "\\def" => at += command[at..].find('{')?,
+        "\\DeclareMathOperator" => at += blank(&command[at..]),
+        // `[args]` and `[default]`
+        _ => {
+            for _ in 0..2 {
+                at += blank(&command[at..]);
+                if command[at..].starts_with('[') {
+                    at += command[at..].find(']')? + 1;
+                }
+            }
+            at += blank(&command[at..]);
+        }
+    }
+    if !command[at..].starts_with('{') {
+        return None;
+    }
+    at += group(&command[at..])?;
+    Some((name.to_string(), at))
+}
+
+fn blank(text: &str) -> usize {
+    text.len() - text.trim_start().len()
+}
+
+// Length of `\name` or `\<symbol>` at the start of `text`
+fn control_sequence(text: &str) -> Option<usize> {
+    let rest = text.strip_prefix('\\')?;
+    let letters = rest.len()
+        - rest
+            .trim_start_matches(|c: char| c.is_ascii_alphabetic())
+            .len();
+    match letters {
+        0 => rest.chars().next().map(|c| 1 + c.len_utf8()),
+        letters => Some(1 + letters),
+    }
+}
+
+// Length of the balanced `{...}` group at the start of `text`
+pub(crate) fn group(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
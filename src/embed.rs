@@ -0,0 +1,143 @@
+use crate::{detect_file_type, load_source, Equation};
+use regex::Regex;
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
+use std::path::{Component, Path};
+
+/// The equations whose image link `embed_links` added, and those whose link
+/// it pointed at a new file.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EmbedReport {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+}
+
+/// `content`, markdown the `equations` of `links` were read from, with an
+/// `![name](target)` line right after the block of each (its `%%name%%`
+/// line included), pointing at its target. A link with the equation's name
+/// as its text, directly after the block or after one blank line, is taken
+/// for the one to keep up to date, so running it again changes nothing.
+/// Failing that, so is one into the target's directory named after none of
+/// the other equations: the link from before the equation was renamed.
+pub fn embed_links(content: &str, links: &[(&Equation, String)]) -> (String, EmbedReport) {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let image = Regex::new(r"^[ \t]*!\[([^\]]*)\]\(([^)]*)\)[ \t]*$").unwrap();
+    let mut report = EmbedReport::default();
+
+    // Bottom up, so inserted lines don't shift the blocks still to come
+    let mut links: Vec<&(&Equation, String)> = links
+        .iter()
+        .filter(|(equation, _)| {
+            equation
+                .span
+                .is_some_and(|span| span.end_line <= lines.len())
+        })
+        .collect();
+    links.sort_by_key(|(equation, _)| Reverse(equation.span.map(|span| span.end_line)));
+    let names: Vec<&str> = links.iter().map(|(eq, _)| eq.name.as_str()).collect();
+    for (equation, target) in links {
+        let link = format!("![{}]({})", equation.name, target);
+        let after = equation.span.map_or(0, |span| span.end_line);
+        let is_image = |line: Option<&String>| line.is_some_and(|line| image.is_match(line));
+
+        // The run of image lines after the block, which a blank line may
+        // set apart
+        let mut start = after;
+        if lines.get(start).is_some_and(|line| line.trim().is_empty())
+            && is_image(lines.get(start + 1))
+        {
+            start += 1;
+        }
+        let mut end = start;
+        while is_image(lines.get(end)) {
+            end += 1;
+        }
+        let own = (start..end)
+            .find(|&at| {
+                image
+                    .captures(&lines[at])
+                    .is_some_and(|cap| cap[1] == equation.name)
+            })
+            .or_else(|| {
+                (start..end).find(|&at| {
+                    image.captures(&lines[at]).is_some_and(|cap| {
+                        !names.contains(&&cap[1]) && target_dir(&cap[2]) == target_dir(target)
+                    })
+                })
+            });
+        match own {
+            Some(at) if lines[at].trim() == link => {}
+            Some(at) => {
+                lines[at] = link;
+                report.updated.push(equation.name.clone());
+            }
+            None => {
+                let at = if start == end { after } else { end };
+                lines.insert(at, link);
+                report.added.push(equation.name.clone());
+            }
+        }
+    }
+    report.added.reverse();
+    report.updated.reverse();
+    (lines.join("\n") + "\n", report)
+}
+
+// The directory part of a link target, without its `< >`
+fn target_dir(target: &str) -> &str {
+    let target = target.trim().trim_start_matches('<').trim_end_matches('>');
+    target.rsplit_once('/').map_or("", |(dir, _)| dir)
+}
+
+/// Puts the links of `embed_links` into the markdown file `path`, writing
+/// it back as UTF-8 with LF line endings if anything changed.
+pub fn embed_images(path: &Path, links: &[(&Equation, String)]) -> io::Result<EmbedReport> {
+    if detect_file_type(path) != "markdown" {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "{}: image links can only be embedded in markdown",
+                path.display()
+            ),
+        ));
+    }
+    let (content, report) = embed_links(&load_source(path)?, links);
+    if !report.added.is_empty() || !report.updated.is_empty() {
+        fs::write(path, content)?;
+    }
+    Ok(report)
+}
+
+/// How a link in the markdown file `markdown` reaches `image`: relative to
+/// the file's directory, with `/` separators, in `< >` if it has spaces.
+pub fn link_target(markdown: &Path, image: &Path) -> String {
+    let absolute = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let base = match markdown.parent() {
+        Some(parent) if parent != Path::new("") => absolute(parent),
+        _ => absolute(Path::new(".")),
+    };
+    let image = absolute(image);
+    let common = base
+        .components()
+        .zip(image.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    // Different drives on Windows have nothing in common
+    let target = if common == 0 {
+        image.to_string_lossy().into_owned()
+    } else {
+        base.components()
+            .skip(common)
+            .map(|_| Component::ParentDir)
+            .chain(image.components().skip(common))
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+            .join("/")
+    };
+    if target.contains(' ') {
+        format!("<{}>", target)
+    } else {
+        target
+    }
+}
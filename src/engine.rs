@@ -0,0 +1,257 @@
+use crate::{find_tool, interrupted, missing_tool, tex_path, tool_command};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// TeX program used to turn `.tex` into PDF. `Auto` picks the first one found
+/// on the PATH, in the order of `Engine::CANDIDATES`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Engine {
+    #[default]
+    Auto,
+    Tectonic,
+    Latexmk,
+    Pdflatex,
+    Xelatex,
+    Lualatex,
+}
+
+impl Engine {
+    pub const CANDIDATES: [Engine; 5] = [
+        Engine::Tectonic,
+        Engine::Latexmk,
+        Engine::Pdflatex,
+        Engine::Xelatex,
+        Engine::Lualatex,
+    ];
+
+    pub fn program(self) -> &'static str {
+        match self {
+            Engine::Auto => "auto",
+            Engine::Tectonic => "tectonic",
+            Engine::Latexmk => "latexmk",
+            Engine::Pdflatex => "pdflatex",
+            Engine::Xelatex => "xelatex",
+            Engine::Lualatex => "lualatex",
+        }
+    }
+
+    pub fn is_installed(self) -> bool {
+        find_tool(self.program()).is_some()
+    }
+
+    /// Whether the engine loads OpenType math fonts through unicode-math.
+    /// latexmk is run with `-pdf`, i.e. pdflatex.
+    pub fn supports_unicode_math(self) -> bool {
+        matches!(self, Engine::Tectonic | Engine::Xelatex | Engine::Lualatex)
+    }
+
+    /// Replaces `Auto` with the first installed engine.
+    pub fn resolve(self) -> io::Result<Engine> {
+        if self != Engine::Auto {
+            return Ok(self);
+        }
+        Engine::CANDIDATES
+            .into_iter()
+            .find(|engine| engine.is_installed())
+            .ok_or_else(|| {
+                let tried: Vec<&str> = Engine::CANDIDATES.iter().map(|e| e.program()).collect();
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("No TeX engine found (tried {})", tried.join(", ")),
+                )
+            })
+    }
+
+    /// Compiles `tex_file_path` into `<output_dir>/<stem>.pdf`. The log is
+    /// copied next to it when `keep_logs` is set or compilation failed.
+    /// Returns whether compilation succeeded; a TeX still running after
+    /// `timeout` is killed with a `TimedOut` error.
+    pub fn compile(
+        self,
+        tex_file_path: &Path,
+        output_dir: &Path,
+        keep_logs: bool,
+        timeout: Option<Duration>,
+    ) -> io::Result<bool> {
+        self.compile_with(tex_file_path, output_dir, keep_logs, timeout, false)
+    }
+
+    /// `compile`, with the PDF dated `SOURCE_DATE_EPOCH` (1970 if unset)
+    /// rather than now when `deterministic`.
+    pub(crate) fn compile_with(
+        self,
+        tex_file_path: &Path,
+        output_dir: &Path,
+        keep_logs: bool,
+        timeout: Option<Duration>,
+        deterministic: bool,
+    ) -> io::Result<bool> {
+        let engine = self.resolve()?;
+        let date = |command: &mut Command| {
+            if deterministic {
+                let epoch = env::var_os("SOURCE_DATE_EPOCH").unwrap_or_else(|| "0".into());
+                // pdfTeX and LuaTeX only use it for the PDF dates when forced to
+                command
+                    .env("SOURCE_DATE_EPOCH", epoch)
+                    .env("FORCE_SOURCE_DATE", "1");
+            }
+        };
+        if engine == Engine::Tectonic {
+            let mut command = tool_command("tectonic");
+            command.arg(tex_file_path).arg("--outdir").arg(output_dir);
+            date(&mut command);
+            if keep_logs {
+                command.arg("--keep-logs");
+            }
+            return run_quietly(&mut command, engine, timeout);
+        }
+
+        // TeX Live engines litter aux files, so build in a scratch directory
+        let build_dir = tempfile::tempdir()?;
+        let out_arg = |flag: &str| {
+            let mut arg = OsString::from(format!("{}=", flag));
+            arg.push(tex_path(build_dir.path()));
+            arg
+        };
+        let mut command = tool_command(engine.program());
+        command
+            .arg("-interaction=nonstopmode")
+            .arg("-halt-on-error");
+        match engine {
+            Engine::Latexmk => command.arg("-pdf").arg(out_arg("-outdir")),
+            _ => command.arg(out_arg("-output-directory")),
+        };
+        command.arg(tex_path(tex_file_path));
+        date(&mut command);
+        let success = run_quietly(&mut command, engine, timeout)?;
+
+        let stem = tex_file_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        let mut outputs = vec![format!("{}.log", stem)];
+        if success {
+            outputs.push(format!("{}.pdf", stem));
+        }
+        for file_name in outputs {
+            let built = build_dir.path().join(&file_name);
+            let keep = file_name.ends_with(".pdf") || keep_logs || !success;
+            if keep && built.exists() {
+                fs::copy(&built, output_dir.join(&file_name))?;
+            }
+        }
+        Ok(success)
+    }
+}
+
+// The TeX log has the output that matters; stderr is kept for the debug log,
+// or as a warning when the run failed. The engine runs in a process group of
+// its own, so stopping it also stops what it started, as latexmk does pdflatex
+fn run_quietly(
+    command: &mut Command,
+    engine: Engine,
+    timeout: Option<Duration>,
+) -> io::Result<bool> {
+    debug!("Running {:?}", command);
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(command, 0);
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| missing_tool(engine.program(), e))?;
+    // Drained aside, so a chatty engine can't block on a full pipe
+    let mut pipe = child.stderr.take();
+    let stderr = thread::spawn(move || {
+        let mut stderr = String::new();
+        if let Some(pipe) = &mut pipe {
+            pipe.read_to_string(&mut stderr).ok();
+        }
+        stderr
+    });
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        // Out of the terminal's process group, Ctrl-C no longer reaches it
+        if interrupted() {
+            kill_group(&mut child);
+            break child.wait()?;
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            kill_group(&mut child);
+            child.wait()?;
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!(
+                    "{} was still running after {:.0}s",
+                    engine.program(),
+                    timeout.unwrap_or_default().as_secs_f64()
+                ),
+            ));
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    let stderr = stderr.join().unwrap_or_default();
+    let stderr = stderr.trim_end();
+    match (status.success(), stderr.trim().is_empty()) {
+        (_, true) => {}
+        (true, false) => debug!("{} wrote to stderr: {}", engine.program(), stderr),
+        (false, false) => warn!("{} failed: {}", engine.program(), stderr),
+    }
+    Ok(status.success())
+}
+
+#[cfg(unix)]
+fn kill_group(child: &mut Child) {
+    match libc::pid_t::try_from(child.id()) {
+        // The group has the child's id
+        Ok(group) => unsafe {
+            libc::kill(-group, libc::SIGKILL);
+        },
+        Err(_) => {
+            child.kill().ok();
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_group(child: &mut Child) {
+    child.kill().ok();
+}
+
+impl fmt::Display for Engine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.program())
+    }
+}
+
+impl FromStr for Engine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        std::iter::once(Engine::Auto)
+            .chain(Engine::CANDIDATES)
+            .find(|engine| engine.program() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown engine '{}': expected auto, tectonic, latexmk, pdflatex, xelatex or lualatex",
+                    s
+                )
+            })
+    }
+}
@@ -0,0 +1,67 @@
+use crossterm::event::{self, Event};
+use simptui::IndexEvent;
+use std::io;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
+use tui_textarea::Input;
+
+/// What the TUI loop reacts to: the terminal, the clock and background work.
+pub enum AppEvent {
+    Key(Input),
+    Resize,
+    Tick,                 // Once per tick rate, also the frame rate limit
+    RenderProgress,       // A background render, copy or file load finished
+    FsChange(IndexEvent), // From the file scan and watcher
+}
+
+/// The merged stream of `AppEvent`s. Background work sends into `sender()`;
+/// terminal input is polled between ticks.
+pub struct Events {
+    sender: Sender<AppEvent>,
+    receiver: Receiver<AppEvent>,
+    tick_rate: Duration,
+    last_tick: Instant,
+}
+
+impl Events {
+    pub fn new(tick_rate: Duration) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Events {
+            sender,
+            receiver,
+            tick_rate,
+            last_tick: Instant::now(),
+        }
+    }
+
+    pub fn sender(&self) -> Sender<AppEvent> {
+        self.sender.clone()
+    }
+
+    pub fn tick_rate(&self) -> Duration {
+        self.tick_rate
+    }
+
+    /// Waits for the next event. Background events are picked up at the
+    /// latest on the following tick, which a stream of keys can't hold off.
+    pub fn next(&mut self) -> io::Result<AppEvent> {
+        loop {
+            if let Ok(event) = self.receiver.try_recv() {
+                return Ok(event);
+            }
+            let next_tick = self.last_tick + self.tick_rate;
+            let now = Instant::now();
+            if now >= next_tick {
+                self.last_tick = now;
+                return Ok(AppEvent::Tick);
+            }
+            if event::poll(next_tick - now)? {
+                match event::read()? {
+                    Event::Key(key) => return Ok(AppEvent::Key(Input::from(key))),
+                    Event::Resize(..) => return Ok(AppEvent::Resize),
+                    _ => {} // Mouse, focus and paste events aren't used
+                }
+            }
+        }
+    }
+}
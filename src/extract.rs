@@ -0,0 +1,318 @@
+use crate::{
+    find_label, unique_names, DuplicateNames, Engine, Equation, ParserRegistry, Rgb, SourceSpan,
+};
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use tracing::warn;
+
+// `%%yes%%` / `%%engine=... packages=...%%` / `$$ body $$` / `%%name%%`, all
+// but the body optional; `%%skip%%` in place of `%%yes%%` drops the block
+const MARKDOWN_PATTERN: &str = r"(?s)(%%(yes|no|skip)?%%)?[\n\r]*(%%([a-z]+=[^%]*)%%[\n\r]*)?\$\$[\n\r]*(.*?)\$\$[\n\r]*(%%([^%=]*?)%%)?";
+
+// `\begin{align} body \end{align}` and the other amsmath display environments,
+// with the same optional `%%yes%%` / `%%name%%` around them
+const ENVIRONMENT_PATTERN: &str = r"(?s)(%%(yes|no|skip)?%%)?[\n\r]*\\begin\{((?:equation|align|flalign|alignat|gather|multline|eqnarray)\*?)\}(.*?)\\end\{(?:equation|align|flalign|alignat|gather|multline|eqnarray)\*?\}[\n\r]*(%%([^%=]*?)%%)?";
+
+/// A capture group, by index (`body = 1`) or by name (`body = "body"`).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+#[non_exhaustive]
+pub enum Group {
+    Index(usize),
+    Name(String),
+}
+
+impl Group {
+    fn get<'h>(&self, cap: &Captures<'h>) -> Option<&'h str> {
+        match self {
+            Group::Index(i) => cap.get(*i),
+            Group::Name(name) => cap.name(name),
+        }
+        .map(|m| m.as_str())
+    }
+}
+
+/// `[extract.<rule>]` table: a regex plus which groups hold the body, name and
+/// active flag. Without a `name` group equations get the default name; without
+/// an `active` group they are active. With `skip_code`, matches inside fenced
+/// code blocks are ignored, as they are for the built-in syntax.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExtractRule {
+    pub pattern: String,
+    #[serde(default = "default_body_group")]
+    pub body: Group,
+    pub name: Option<Group>,
+    pub active: Option<Group>, // "no", "false", "off" or "0" mark it inactive, "skip" drops it
+    pub options: Option<Group>, // `engine=lualatex packages=mhchem,siunitx size=small`
+    pub environment: Option<Group>, // `align`, `gather*`, ...
+    #[serde(default)]
+    pub skip_code: bool,
+}
+
+fn default_body_group() -> Group {
+    Group::Name("body".to_string())
+}
+
+/// Finds equations in note files using the built-in markdown syntax plus any
+/// rules from the config. Where matches overlap the earliest one wins, with the
+/// built-in syntax taking precedence on ties.
+#[derive(Debug, Clone)]
+pub struct Extractor {
+    rules: Vec<(Regex, ExtractRule)>,
+}
+
+impl Default for Extractor {
+    fn default() -> Self {
+        let markdown = ExtractRule {
+            pattern: MARKDOWN_PATTERN.to_string(),
+            body: Group::Index(5),
+            name: Some(Group::Index(7)),
+            active: Some(Group::Index(2)),
+            options: Some(Group::Index(4)),
+            environment: None,
+            skip_code: true,
+        };
+        let environments = ExtractRule {
+            pattern: ENVIRONMENT_PATTERN.to_string(),
+            body: Group::Index(4),
+            name: Some(Group::Index(6)),
+            active: Some(Group::Index(2)),
+            options: None,
+            environment: Some(Group::Index(3)),
+            skip_code: true,
+        };
+        Extractor {
+            rules: vec![
+                (Regex::new(MARKDOWN_PATTERN).unwrap(), markdown),
+                (Regex::new(ENVIRONMENT_PATTERN).unwrap(), environments),
+            ],
+        }
+    }
+}
+
+impl Extractor {
+    pub fn new(custom: &BTreeMap<String, ExtractRule>) -> io::Result<Self> {
+        let mut extractor = Extractor::default();
+        for (rule_name, rule) in custom {
+            let re = Regex::new(&rule.pattern).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid pattern in extract rule '{}': {}", rule_name, e),
+                )
+            })?;
+            extractor.rules.push((re, rule.clone()));
+        }
+        Ok(extractor)
+    }
+
+    /// The equations in `content`, where repeated names get a `_1`, `_2`, ...
+    /// suffix in document order.
+    pub fn parse(&self, content: &str) -> Vec<Equation> {
+        let mut equations = self.extract(content);
+        unique_names(&mut equations, DuplicateNames::Counter);
+        equations
+    }
+
+    // The equations with their names as written, repeats and all
+    pub(crate) fn extract(&self, content: &str) -> Vec<Equation> {
+        let fences = code_fences(content);
+        let mut matches = Vec::new();
+        for (re, rule) in &self.rules {
+            for cap in re.captures_iter(content) {
+                let Some(body) = rule.body.get(&cap) else {
+                    continue;
+                };
+                let block = cap.get(0).unwrap();
+                // Where the block itself starts; the line breaks the pattern
+                // takes in before it may still belong to a closing fence
+                let at = block.end() - block.as_str().trim_start().len();
+                if rule.skip_code && fences.iter().any(|fence| fence.contains(&at)) {
+                    continue;
+                }
+                let flag = rule.active.as_ref().and_then(|group| group.get(&cap));
+                let skip = flag.is_some_and(|flag| flag.trim().eq_ignore_ascii_case("skip"));
+                let active = flag.is_none_or(is_active);
+                let name = rule.name.as_ref().and_then(|group| group.get(&cap));
+                let environment = rule.environment.as_ref().and_then(|group| group.get(&cap));
+                // A `\label{..}` names it when nothing else does
+                let name = name.or_else(|| find_label(body));
+                matches.push(Match {
+                    start: block.start(),
+                    end: block.end(),
+                    skip,
+                    active,
+                    name,
+                    body: body.trim(),
+                    options: rule.options.as_ref().and_then(|group| group.get(&cap)),
+                    environment,
+                });
+            }
+        }
+        matches.sort_by_key(|m| m.start); // Stable, so earlier rules win ties
+
+        let mut equations = Vec::new();
+        let mut label_lines: HashMap<String, usize> = HashMap::new();
+        let mut covered_until = 0;
+        let mut line = 1;
+        let mut line_offset = 0;
+        let mut line_at = |offset: usize| {
+            line += content[line_offset..offset].matches('\n').count();
+            line_offset = offset;
+            line
+        };
+
+        for Match {
+            start,
+            end,
+            skip,
+            active,
+            name,
+            body,
+            options,
+            environment,
+        } in matches
+        {
+            if start < covered_until {
+                continue;
+            }
+            covered_until = end;
+            // Still covering its range, so no other rule picks the block up
+            if skip {
+                continue;
+            }
+
+            let block_text = &content[start..end];
+            let leading = block_text.len() - block_text.trim_start().len();
+            let trailing = block_text.trim_end().len();
+            let span = SourceSpan {
+                start_line: line_at(start + leading),
+                end_line: line_at(start + trailing),
+            };
+
+            let name = name.unwrap_or("default_equation");
+            let mut equation = Equation::new(active, name, body);
+            equation.span = Some(span);
+            equation.environment = environment.map(str::to_string);
+            if let Some(label) = &equation.label {
+                match label_lines.get(label) {
+                    Some(first) => warn!(
+                        "Duplicate label '{}' on lines {} and {}",
+                        label, first, span.start_line
+                    ),
+                    None => {
+                        label_lines.insert(label.clone(), span.start_line);
+                    }
+                }
+            }
+            if let Some(options) = options {
+                apply_options(&mut equation, options);
+            }
+            equations.push(equation);
+        }
+
+        equations
+    }
+
+    /// Like `load_equations`, but markdown files go through these rules.
+    pub fn load(&self, path: &Path) -> io::Result<Vec<Equation>> {
+        ParserRegistry::with_extractor(self.clone()).load(path)
+    }
+}
+
+struct Match<'h> {
+    start: usize,
+    end: usize,
+    skip: bool, // Marked `%%skip%%`
+    active: bool,
+    name: Option<&'h str>,
+    body: &'h str,
+    options: Option<&'h str>,
+    environment: Option<&'h str>,
+}
+
+// Byte ranges of the ``` and ~~~ fenced blocks; an unclosed fence runs to
+// the end
+pub(crate) fn code_fences(content: &str) -> Vec<Range<usize>> {
+    let mut fences = Vec::new();
+    let mut open: Option<(usize, &str)> = None;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let marker = ["```", "~~~"]
+            .into_iter()
+            .find(|marker| trimmed.starts_with(marker));
+        match (open, marker) {
+            (None, Some(marker)) => open = Some((offset, marker)),
+            (Some((start, opened)), Some(marker)) if marker == opened => {
+                fences.push(start..offset + line.len());
+                open = None;
+            }
+            _ => {}
+        }
+        offset += line.len();
+    }
+    if let Some((start, _)) = open {
+        fences.push(start..content.len());
+    }
+    fences
+}
+
+// `engine=<engine>`, `packages=<a>,<b>`, `size=<size>`, `tags=<a>,<b>` and `wrap=<wrap>`; anything else is
+// reported and skipped so one typo doesn't hide the equation
+pub(crate) fn apply_options(equation: &mut Equation, options: &str) {
+    for option in options.split_whitespace() {
+        match option.split_once('=') {
+            Some((key, value)) if OPTION_KEYS.contains(&key) => apply_option(equation, key, value),
+            _ => warn!(
+                "{}: unknown option '{}', expected color=, engine=, packages=, size=, tags= or wrap=",
+                equation.name, option
+            ),
+        }
+    }
+}
+
+// The options `apply_option` knows
+const OPTION_KEYS: [&str; 6] = ["color", "engine", "packages", "size", "tags", "wrap"];
+
+// Lists of packages and tags are comma-separated
+pub(crate) fn apply_option(equation: &mut Equation, key: &str, value: &str) {
+    let list = || {
+        value
+            .split(',')
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+    };
+    match key {
+        "engine" => match value.parse::<Engine>() {
+            Ok(Engine::Auto) => equation.engine = None,
+            Ok(engine) => equation.engine = Some(engine),
+            Err(e) => warn!("{}: {}", equation.name, e),
+        },
+        "size" => match value.parse() {
+            Ok(size) => equation.size = Some(size),
+            Err(e) => warn!("{}: {}", equation.name, e),
+        },
+        "packages" => equation.packages.extend(list()),
+        "color" => match Rgb::from_hex(value) {
+            Some(color) => equation.color = Some(color.to_hex()),
+            None => warn!("{}: '{}' is not a color like #rrggbb", equation.name, value),
+        },
+        "tags" => equation.tags.extend(list()),
+        "wrap" => match value.parse() {
+            Ok(wrap) => equation.wrap = wrap,
+            Err(e) => warn!("{}: {}", equation.name, e),
+        },
+        _ => {}
+    }
+}
+
+fn is_active(flag: &str) -> bool {
+    !matches!(
+        flag.trim().to_ascii_lowercase().as_str(),
+        "no" | "false" | "off" | "0"
+    )
+}
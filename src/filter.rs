@@ -0,0 +1,461 @@
+use crate::Equation;
+use regex::Regex;
+use std::fmt;
+use std::str::FromStr;
+
+/// A condition on equations, e.g.
+/// `active && tags.contains("exam") && len(body) < 200`.
+///
+/// Fields: `active`, `name`, `body`, `tags`, `packages`, `engine`,
+/// `environment`, `label` and `line` (where the equation starts, 0 if
+/// unknown); missing text fields are empty. Functions: `len(x)` and
+/// `lower(x)`; text has `.contains`, `.starts_with`, `.ends_with` and
+/// `.matches(regex)`, lists `.contains`. Combine with `&&`, `||`, `!` and
+/// the comparisons `== != < <= > >=`. Strings take `"` or `'`; `\"` and
+/// `\\` are the only escapes, so `"\frac"` means what it says.
+#[derive(Debug, Clone)]
+pub struct EquationFilter {
+    source: String,
+    expr: Expr,
+}
+
+impl EquationFilter {
+    pub fn matches(&self, equation: &Equation) -> bool {
+        matches!(self.expr.eval(equation), Value::Bool(true))
+    }
+}
+
+impl fmt::Display for EquationFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl FromStr for EquationFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = ExprParser {
+            tokens: tokenize(s)?,
+            at: 0,
+        };
+        let expr = parser.or()?;
+        if let Some(token) = parser.tokens.get(parser.at) {
+            return Err(format!("unexpected {} in filter", token));
+        }
+        if expr.ty() != Type::Bool {
+            return Err(format!("filter '{}' is not a condition", s));
+        }
+        Ok(EquationFilter {
+            source: s.to_string(),
+            expr,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Type {
+    Bool,
+    Num,
+    Text,
+    List,
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Type::Bool => "a condition",
+            Type::Num => "a number",
+            Type::Text => "text",
+            Type::List => "a list",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Bool(bool),
+    Num(f64),
+    Text(String),
+    List(Vec<String>),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Active,
+    Name,
+    Body,
+    Tags,
+    Packages,
+    Engine,
+    Environment,
+    Label,
+    Line,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Method {
+    Contains,
+    StartsWith,
+    EndsWith,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Value(Value),
+    Field(Field),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Op, Box<Expr>, Box<Expr>),
+    Len(Box<Expr>),
+    Lower(Box<Expr>),
+    Call(Method, Box<Expr>, Box<Expr>),
+    Matches(Box<Expr>, Regex),
+}
+
+impl Expr {
+    // Known while parsing, which rejects every mismatch
+    fn ty(&self) -> Type {
+        match self {
+            Expr::Value(Value::Bool(_)) => Type::Bool,
+            Expr::Value(Value::Num(_)) => Type::Num,
+            Expr::Value(Value::Text(_)) => Type::Text,
+            Expr::Value(Value::List(_)) => Type::List,
+            Expr::Field(Field::Active) => Type::Bool,
+            Expr::Field(Field::Tags | Field::Packages) => Type::List,
+            Expr::Field(Field::Line) => Type::Num,
+            Expr::Field(_) => Type::Text,
+            Expr::Len(_) => Type::Num,
+            Expr::Lower(_) => Type::Text,
+            _ => Type::Bool,
+        }
+    }
+
+    fn eval(&self, equation: &Equation) -> Value {
+        let text = |expr: &Expr| match expr.eval(equation) {
+            Value::Text(text) => text,
+            _ => String::new(),
+        };
+        match self {
+            Expr::Value(value) => value.clone(),
+            Expr::Field(field) => field.of(equation),
+            Expr::Not(expr) => Value::Bool(expr.eval(equation) != Value::Bool(true)),
+            Expr::And(a, b) => Value::Bool(
+                a.eval(equation) == Value::Bool(true) && b.eval(equation) == Value::Bool(true),
+            ),
+            Expr::Or(a, b) => Value::Bool(
+                a.eval(equation) == Value::Bool(true) || b.eval(equation) == Value::Bool(true),
+            ),
+            Expr::Compare(op, a, b) => {
+                let ordering = match (a.eval(equation), b.eval(equation)) {
+                    (Value::Num(a), Value::Num(b)) => a.partial_cmp(&b),
+                    (a, b) if matches!(op, Op::Eq | Op::Ne) => {
+                        return Value::Bool((a == b) == (*op == Op::Eq))
+                    }
+                    (Value::Text(a), Value::Text(b)) => Some(a.cmp(&b)),
+                    _ => None,
+                };
+                Value::Bool(ordering.is_some_and(|ordering| match op {
+                    Op::Eq => ordering.is_eq(),
+                    Op::Ne => ordering.is_ne(),
+                    Op::Lt => ordering.is_lt(),
+                    Op::Le => ordering.is_le(),
+                    Op::Gt => ordering.is_gt(),
+                    Op::Ge => ordering.is_ge(),
+                }))
+            }
+            Expr::Len(expr) => Value::Num(match expr.eval(equation) {
+                Value::Text(text) => text.chars().count() as f64,
+                Value::List(items) => items.len() as f64,
+                _ => 0.0,
+            }),
+            Expr::Lower(expr) => Value::Text(text(expr).to_lowercase()),
+            Expr::Call(method, target, argument) => {
+                let argument = text(argument);
+                Value::Bool(match (method, target.eval(equation)) {
+                    (Method::Contains, Value::List(items)) => items.contains(&argument),
+                    (Method::Contains, Value::Text(text)) => text.contains(&argument),
+                    (Method::StartsWith, Value::Text(text)) => text.starts_with(&argument),
+                    (Method::EndsWith, Value::Text(text)) => text.ends_with(&argument),
+                    _ => false,
+                })
+            }
+            Expr::Matches(target, re) => Value::Bool(re.is_match(&text(target))),
+        }
+    }
+}
+
+impl Field {
+    fn of(self, equation: &Equation) -> Value {
+        let text = |text: Option<&str>| Value::Text(text.unwrap_or_default().to_string());
+        match self {
+            Field::Active => Value::Bool(equation.active),
+            Field::Name => text(Some(&equation.name)),
+            Field::Body => text(Some(&equation.body)),
+            Field::Tags => Value::List(equation.tags.clone()),
+            Field::Packages => Value::List(equation.packages.clone()),
+            Field::Engine => text(equation.engine.map(|engine| engine.program())),
+            Field::Environment => text(equation.environment.as_deref()),
+            Field::Label => text(equation.label.as_deref()),
+            Field::Line => Value::Num(equation.span.map_or(0, |span| span.start_line) as f64),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Num(f64),
+    Text(String),
+    Symbol(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(ident) => write!(f, "'{}'", ident),
+            Token::Num(num) => write!(f, "{}", num),
+            Token::Text(text) => write!(f, "{:?}", text),
+            Token::Symbol(symbol) => write!(f, "'{}'", symbol),
+        }
+    }
+}
+
+const SYMBOLS: [&str; 13] = [
+    "&&", "||", "==", "!=", "<=", ">=", "<", ">", "!", "(", ")", ",", ".",
+];
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = s.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(**symbol)) {
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        } else if c == '"' || c == '\'' {
+            let mut text = String::new();
+            let mut chars = rest[1..].char_indices();
+            let end = loop {
+                match chars.next() {
+                    Some((i, quote)) if quote == c => break i + 2,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, escaped)) if escaped == c || escaped == '\\' => text.push(escaped),
+                        Some((_, other)) => {
+                            text.push('\\');
+                            text.push(other);
+                        }
+                        None => return Err(format!("unclosed string in filter '{}'", s)),
+                    },
+                    Some((_, other)) => text.push(other),
+                    None => return Err(format!("unclosed string in filter '{}'", s)),
+                }
+            };
+            tokens.push(Token::Text(text));
+            rest = &rest[end..];
+        } else if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(rest.len());
+            let num = rest[..len]
+                .parse()
+                .map_err(|_| format!("invalid number '{}' in filter", &rest[..len]))?;
+            tokens.push(Token::Num(num));
+            rest = &rest[len..];
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..len].to_string()));
+            rest = &rest[len..];
+        } else {
+            return Err(format!("unexpected '{}' in filter", c));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+// Recursive descent, loosest binding first
+struct ExprParser {
+    tokens: Vec<Token>,
+    at: usize,
+}
+
+impl ExprParser {
+    fn eat(&mut self, symbol: &str) -> bool {
+        if matches!(self.tokens.get(self.at), Some(Token::Symbol(found)) if *found == symbol) {
+            self.at += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), String> {
+        if self.eat(symbol) {
+            return Ok(());
+        }
+        Err(match self.tokens.get(self.at) {
+            Some(token) => format!("expected '{}' in filter, found {}", symbol, token),
+            None => format!("expected '{}' at the end of the filter", symbol),
+        })
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.eat("||") {
+            let right = self.and()?;
+            expr = Expr::Or(condition(expr, "||")?, condition(right, "||")?);
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        while self.eat("&&") {
+            let right = self.not()?;
+            expr = Expr::And(condition(expr, "&&")?, condition(right, "&&")?);
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.eat("!") {
+            return Ok(Expr::Not(condition(self.not()?, "!")?));
+        }
+        self.compare()
+    }
+
+    fn compare(&mut self) -> Result<Expr, String> {
+        let left = self.postfix()?;
+        let op = [
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ]
+        .into_iter()
+        .find(|(symbol, _)| self.eat(symbol));
+        let Some((symbol, op)) = op else {
+            return Ok(left);
+        };
+        let right = self.postfix()?;
+        let comparable = match (left.ty(), right.ty()) {
+            (Type::Num, Type::Num) | (Type::Text, Type::Text) => true,
+            (Type::Bool, Type::Bool) => matches!(op, Op::Eq | Op::Ne),
+            _ => false,
+        };
+        if !comparable {
+            return Err(format!(
+                "can't compare {} {} {} in filter",
+                left.ty(),
+                symbol,
+                right.ty()
+            ));
+        }
+        Ok(Expr::Compare(op, Box::new(left), Box::new(right)))
+    }
+
+    fn postfix(&mut self) -> Result<Expr, String> {
+        let mut expr = self.primary()?;
+        while self.eat(".") {
+            let Some(Token::Ident(method)) = self.tokens.get(self.at).cloned() else {
+                return Err("expected a method name after '.' in filter".to_string());
+            };
+            self.at += 1;
+            self.expect("(")?;
+            let argument = self.or()?;
+            self.expect(")")?;
+            if argument.ty() != Type::Text {
+                return Err(format!(".{}() takes text, not {}", method, argument.ty()));
+            }
+            expr = match (method.as_str(), expr.ty()) {
+                ("contains", Type::Text | Type::List) => {
+                    Expr::Call(Method::Contains, Box::new(expr), Box::new(argument))
+                }
+                ("starts_with", Type::Text) => {
+                    Expr::Call(Method::StartsWith, Box::new(expr), Box::new(argument))
+                }
+                ("ends_with", Type::Text) => {
+                    Expr::Call(Method::EndsWith, Box::new(expr), Box::new(argument))
+                }
+                ("matches", Type::Text) => {
+                    let Expr::Value(Value::Text(pattern)) = argument else {
+                        return Err(".matches() takes a string literal".to_string());
+                    };
+                    let re = Regex::new(&pattern)
+                        .map_err(|e| format!("invalid regex in .matches(): {}", e))?;
+                    Expr::Matches(Box::new(expr), re)
+                }
+                (_, ty) => return Err(format!("{} has no method .{}()", ty, method)),
+            };
+        }
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let Some(token) = self.tokens.get(self.at).cloned() else {
+            return Err("filter ends too early".to_string());
+        };
+        self.at += 1;
+        match token {
+            Token::Num(num) => Ok(Expr::Value(Value::Num(num))),
+            Token::Text(text) => Ok(Expr::Value(Value::Text(text))),
+            Token::Symbol("(") => {
+                let expr = self.or()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Token::Ident(ident) if self.eat("(") => {
+                let argument = self.or()?;
+                self.expect(")")?;
+                match (ident.as_str(), argument.ty()) {
+                    ("len", Type::Text | Type::List) => Ok(Expr::Len(Box::new(argument))),
+                    ("lower", Type::Text) => Ok(Expr::Lower(Box::new(argument))),
+                    ("len" | "lower", ty) => Err(format!("{}() doesn't take {}", ident, ty)),
+                    _ => Err(format!("unknown function {}() in filter", ident)),
+                }
+            }
+            Token::Ident(ident) => Ok(match ident.as_str() {
+                "true" => Expr::Value(Value::Bool(true)),
+                "false" => Expr::Value(Value::Bool(false)),
+                "active" => Expr::Field(Field::Active),
+                "name" => Expr::Field(Field::Name),
+                "body" => Expr::Field(Field::Body),
+                "tags" => Expr::Field(Field::Tags),
+                "packages" => Expr::Field(Field::Packages),
+                "engine" => Expr::Field(Field::Engine),
+                "environment" => Expr::Field(Field::Environment),
+                "label" => Expr::Field(Field::Label),
+                "line" => Expr::Field(Field::Line),
+                _ => {
+                    return Err(format!(
+                        "unknown field '{}': expected active, name, body, tags, packages, engine, environment, label or line",
+                        ident
+                    ))
+                }
+            }),
+            token => Err(format!("unexpected {} in filter", token)),
+        }
+    }
+}
+
+fn condition(expr: Expr, operator: &str) -> Result<Box<Expr>, String> {
+    match expr.ty() {
+        Type::Bool => Ok(Box::new(expr)),
+        ty => Err(format!("'{}' takes conditions, not {}", operator, ty)),
+    }
+}
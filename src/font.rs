@@ -0,0 +1,96 @@
+use crate::Engine;
+use serde::Deserialize;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+/// Math font of rendered equations. Each one maps to an 8-bit package for
+/// pdfTeX and to an OpenType math font for the Unicode engines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum Font {
+    #[default]
+    GfsNeohellenic,
+    LatinModern,
+    Stix2,
+    Euler,
+    FiraMath, // OpenType only, needs xelatex, lualatex or tectonic
+}
+
+impl Font {
+    pub const ALL: [Font; 5] = [
+        Font::GfsNeohellenic,
+        Font::LatinModern,
+        Font::Stix2,
+        Font::Euler,
+        Font::FiraMath,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Font::GfsNeohellenic => "gfs-neohellenic",
+            Font::LatinModern => "latin-modern",
+            Font::Stix2 => "stix2",
+            Font::Euler => "euler",
+            Font::FiraMath => "fira-math",
+        }
+    }
+
+    /// Fails if `engine` can't typeset this font. `Auto` must be resolved
+    /// first.
+    pub fn check(self, engine: Engine) -> io::Result<()> {
+        if self == Font::FiraMath && !engine.supports_unicode_math() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "font '{}' needs xelatex, lualatex or tectonic, not {}",
+                    self, engine
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Preamble lines loading the font, after amsmath.
+    pub fn preamble(self, engine: Engine) -> String {
+        let math_font = match self {
+            Font::GfsNeohellenic => None,
+            Font::LatinModern => Some("Latin Modern Math"),
+            Font::Stix2 => Some("STIX Two Math"),
+            Font::Euler => Some("Euler Math"),
+            Font::FiraMath => Some("Fira Math"),
+        };
+        if let (true, Some(math_font)) = (engine.supports_unicode_math(), math_font) {
+            return format!(r"\usepackage{{unicode-math}}\setmathfont{{{}}}", math_font);
+        }
+        match self {
+            Font::GfsNeohellenic => r"\usepackage{gfsneohellenicot}",
+            Font::LatinModern => r"\usepackage{lmodern}",
+            Font::Stix2 => r"\usepackage{stix2}",
+            Font::Euler => r"\usepackage{eulervm}",
+            Font::FiraMath => "", // Rejected by `check`
+        }
+        .to_string()
+    }
+}
+
+impl fmt::Display for Font {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Font {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Font::ALL
+            .into_iter()
+            .find(|font| font.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Font::ALL.iter().map(|f| f.name()).collect();
+                format!("unknown font '{}': expected {}", s, names.join(", "))
+            })
+    }
+}
@@ -0,0 +1,411 @@
+use crate::{
+    csv_columns, csv_field, csv_row, detect_file_type, load_source, toml_body, Equation,
+    TEXT_COMMANDS,
+};
+use regex::Regex;
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Spaced on both sides outside braces
+const RELATIONS: [&str; 3] = ["=", "<", ">"];
+
+// Commands after which `<` and `>` are delimiters, not relations
+const DELIMITER_COMMANDS: [&str; 5] = [r"\left", r"\right", r"\middle", r"\big", r"\Big"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Space,
+    Word,   // `\alpha`
+    Symbol, // `\{`, `\\`, `\ `
+    Open,
+    Close,
+    Comment, // `%` to the end of the line
+    Other,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    kind: Kind,
+    text: &'a str,
+    at: usize, // Byte offset in the line
+}
+
+impl Token<'_> {
+    fn is(&self, text: &str) -> bool {
+        self.text == text
+    }
+}
+
+/// `body` laid out for reading: runs of whitespace collapsed, no spaces
+/// around braces, `^` and `_`, one on each side of `=`, `<` and `>` outside
+/// braces, and the lines of a multi-line body indented two spaces for each
+/// brace or environment they are nested in, with the `&`s of consecutive
+/// lines lined up. Text arguments and `%` comments stay as written. The
+/// result renders like `body`, and formatting it again changes nothing.
+pub fn format_body(body: &str) -> String {
+    if body.trim().is_empty() {
+        return body.to_string();
+    }
+    // Blank lines around the body stay; the first line's indent doesn't
+    let start = body.len() - body.trim_start().len();
+    let leading = &body[..body[..start].rfind('\n').map_or(0, |at| at + 1)];
+    let trailing = &body[body.trim_end().len()..];
+    let content = body.trim();
+
+    let mut lines = Vec::new(); // Nesting level and spaced text of each line
+    let mut depth = 0isize;
+    for line in content.lines() {
+        let line = line.trim();
+        let tokens = tokens(line);
+        // A closing line sits with its opening one
+        let closing = tokens
+            .first()
+            .is_some_and(|token| token.kind == Kind::Close || token.is(r"\end"));
+        let level = (depth - isize::from(closing)).max(0) as usize;
+        depth += nesting(&tokens);
+        lines.push((level, space_line(&tokens)));
+    }
+    format!("{}{}{}", leading, align(&lines).join("\n"), trailing)
+}
+
+// The tokens of one line; a comment is the last
+fn tokens(line: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    while let Some((at, c)) = chars.next() {
+        let mut end = at + c.len_utf8();
+        let kind = match c {
+            '%' => {
+                tokens.push(Token {
+                    kind: Kind::Comment,
+                    text: &line[at..],
+                    at,
+                });
+                break;
+            }
+            '{' => Kind::Open,
+            '}' => Kind::Close,
+            '\\' => {
+                while let Some(&(i, next)) = chars.peek() {
+                    if !next.is_ascii_alphabetic() {
+                        break;
+                    }
+                    end = i + 1;
+                    chars.next();
+                }
+                if end > at + 1 {
+                    Kind::Word
+                } else {
+                    if let Some((i, symbol)) = chars.next() {
+                        end = i + symbol.len_utf8();
+                    }
+                    Kind::Symbol
+                }
+            }
+            c if c.is_whitespace() => {
+                while let Some(&(i, next)) = chars.peek() {
+                    if !next.is_whitespace() {
+                        break;
+                    }
+                    end = i + next.len_utf8();
+                    chars.next();
+                }
+                Kind::Space
+            }
+            _ => Kind::Other,
+        };
+        tokens.push(Token {
+            kind,
+            text: &line[at..end],
+            at,
+        });
+    }
+    tokens
+}
+
+// How many more braces and environments are open after the line
+fn nesting(tokens: &[Token]) -> isize {
+    tokens
+        .iter()
+        .map(|token| match token.kind {
+            Kind::Open => 1,
+            Kind::Close => -1,
+            Kind::Word if token.is(r"\begin") => 1,
+            Kind::Word if token.is(r"\end") => -1,
+            _ => 0,
+        })
+        .sum()
+}
+
+// The line with its spacing normalized
+fn space_line(tokens: &[Token]) -> String {
+    let mut spaced = String::new();
+    let mut depth = 0isize; // Relative to the start of the line
+    let mut text_depth = None; // Where a text argument started
+    let mut text_next = false; // A text command waits for its argument
+    let mut pending_space = false;
+    let mut after_relation = false;
+    let mut last_word = "";
+
+    for token in tokens {
+        if text_depth.is_some() {
+            match token.kind {
+                Kind::Open => depth += 1,
+                Kind::Close => {
+                    depth -= 1;
+                    if text_depth.is_some_and(|start| depth <= start) {
+                        text_depth = None;
+                    }
+                }
+                _ => {}
+            }
+            spaced.push_str(token.text);
+            continue;
+        }
+        match token.kind {
+            Kind::Space => {
+                pending_space = true;
+                continue;
+            }
+            Kind::Comment => {
+                spaced.truncate(spaced.trim_end().len());
+                if !spaced.is_empty() {
+                    spaced.push(' ');
+                }
+                spaced.push_str(token.text.trim_end());
+                break;
+            }
+            _ => {}
+        }
+
+        let relation = depth <= 0
+            && RELATIONS.contains(&token.text)
+            && !DELIMITER_COMMANDS
+                .iter()
+                .any(|command| last_word.starts_with(command));
+        let last = spaced.chars().next_back();
+        let glued = matches!(last, None | Some(' ' | '{' | '^' | '_'))
+            || token.kind == Kind::Close
+            || token.is("^")
+            || token.is("_");
+        let space = if relation {
+            // Relations written together, like `>=`, stay together
+            !after_relation && last != Some('&')
+        } else if after_relation {
+            true
+        } else {
+            pending_space && token.kind != Kind::Open
+        };
+        if space && !glued {
+            spaced.push(' ');
+        }
+        spaced.push_str(token.text);
+
+        match token.kind {
+            Kind::Open => {
+                if text_next {
+                    text_depth = Some(depth);
+                }
+                depth += 1;
+            }
+            Kind::Close => depth -= 1,
+            _ => {}
+        }
+        text_next = token.kind == Kind::Word && TEXT_COMMANDS.contains(&&token.text[1..]);
+        last_word = if token.kind == Kind::Word {
+            token.text
+        } else {
+            ""
+        };
+        after_relation = relation;
+        pending_space = false;
+    }
+    spaced
+}
+
+// The line cut at its `&`s, leaving out those in braces and environments
+// that open on the line
+fn cells(line: &str) -> Vec<&str> {
+    let mut cells = Vec::new();
+    let mut depth = 0isize;
+    let mut start = 0;
+    for token in tokens(line) {
+        match token.kind {
+            Kind::Open => depth += 1,
+            Kind::Close => depth -= 1,
+            Kind::Word if token.is(r"\begin") => depth += 1,
+            Kind::Word if token.is(r"\end") => depth -= 1,
+            Kind::Other if token.is("&") && depth <= 0 => {
+                cells.push(&line[start..token.at]);
+                start = token.at + 1;
+            }
+            _ => {}
+        }
+    }
+    cells.push(&line[start..]);
+    cells
+}
+
+// The indented lines, the cells of consecutive lines at the same level
+// padded so their `&`s line up
+fn align(lines: &[(usize, String)]) -> Vec<String> {
+    let cells: Vec<Vec<&str>> = lines.iter().map(|(_, line)| cells(line)).collect();
+    let mut aligned = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let level = lines[start].0;
+        let mut end = start + 1;
+        if cells[start].len() > 1 {
+            while end < lines.len() && cells[end].len() > 1 && lines[end].0 == level {
+                end += 1;
+            }
+        }
+        let group = &cells[start..end];
+        let cell = |row: &[&str], k: usize| -> String {
+            match k {
+                0 => row[0].trim().to_string(),
+                _ => row[k].trim_end().to_string(),
+            }
+        };
+        let columns = group.iter().map(Vec::len).max().unwrap_or(1);
+        let widths: Vec<usize> = (0..columns - 1)
+            .map(|k| {
+                group
+                    .iter()
+                    .filter(|row| row.len() > k + 1)
+                    .map(|row| cell(row, k).chars().count())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        for row in group {
+            let mut text = String::new();
+            for k in 0..row.len() {
+                if k > 0 {
+                    text.push_str(if text.is_empty() { "&" } else { " &" });
+                }
+                text.push_str(&cell(row, k));
+                if k + 1 < row.len() {
+                    let width = text.chars().count();
+                    // `&` alone opens lines with nothing before it
+                    let separators = 2 * k - usize::from(widths[0] == 0 && k > 0);
+                    let column_end = widths[..=k].iter().sum::<usize>() + separators;
+                    text.extend(std::iter::repeat_n(' ', column_end.saturating_sub(width)));
+                }
+            }
+            let text = text.trim_end();
+            aligned.push(if text.is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", "  ".repeat(level), text)
+            });
+        }
+        start = end;
+    }
+    aligned
+}
+
+/// Writes `bodies` over the bodies of `equations`, read from the markdown,
+/// csv or toml file `path`, in the same order. Equations whose body stays
+/// the same are left alone. The file is written back as UTF-8 with LF line
+/// endings.
+pub fn rewrite_bodies(path: &Path, equations: &[Equation], bodies: &[String]) -> io::Result<()> {
+    let content = load_source(path)?;
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let header_columns = lines.first().map_or(3, |header| csv_columns(header));
+    let file_type = detect_file_type(path);
+    let body_key = Regex::new(r"^[ \t]*body[ \t]*=[ \t]*").unwrap();
+
+    // Bottom up, so a body with more or fewer lines doesn't shift the spans
+    // still to come
+    let mut changes: Vec<(&Equation, &String)> = equations
+        .iter()
+        .zip(bodies)
+        .filter(|(equation, body)| equation.body != **body)
+        .collect();
+    changes.sort_by_key(|(equation, _)| Reverse(equation.span.map(|span| span.start_line)));
+    for (equation, body) in changes {
+        let span = equation
+            .span
+            .filter(|span| span.end_line <= lines.len())
+            .ok_or_else(|| not_rewritable(path, equation))?;
+        let block = span.start_line - 1..span.end_line;
+        match file_type {
+            "csv" => {
+                let row = &mut lines[block.start];
+                let (mut columns, _) = csv_row(row, header_columns);
+                if columns.len() < 2 || body.contains('\n') {
+                    return Err(not_rewritable(path, equation));
+                }
+                columns[1] = body.clone();
+                *row = columns
+                    .iter()
+                    .map(|column| csv_field(column))
+                    .collect::<Vec<_>>()
+                    .join(",");
+            }
+            "markdown" => {
+                let text = lines[block.clone()].join("\n");
+                let open = match &equation.environment {
+                    Some(environment) => format!(r"\begin{{{}}}", environment),
+                    None => "$$".to_string(),
+                };
+                let from = text.find(&open).map_or(0, |at| at + open.len());
+                let at = text[from..]
+                    .find(&equation.body)
+                    .ok_or_else(|| not_rewritable(path, equation))?
+                    + from;
+                let rewritten = format!(
+                    "{}{}{}",
+                    &text[..at],
+                    body,
+                    &text[at + equation.body.len()..]
+                );
+                lines.splice(block, rewritten.lines().map(str::to_string));
+            }
+            "toml" => {
+                let at = lines[block.clone()]
+                    .iter()
+                    .position(|line| body_key.is_match(line))
+                    .ok_or_else(|| not_rewritable(path, equation))?
+                    + block.start;
+                let value = body_key.replace(&lines[at], "");
+                // A multi-line string runs to the line with its closing quotes
+                let mut end = at;
+                if let Some(quotes) = ["'''", "\"\"\""]
+                    .into_iter()
+                    .find(|quotes| value.starts_with(quotes) && !value[3..].contains(quotes))
+                {
+                    end = (at + 1..block.end)
+                        .find(|&i| lines[i].contains(quotes))
+                        .ok_or_else(|| not_rewritable(path, equation))?;
+                }
+                let entry = format!("body = {}", toml_body(body));
+                lines.splice(at..=end, entry.lines().map(str::to_string));
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!(
+                        "{}: only markdown, csv and toml bodies can be rewritten",
+                        path.display()
+                    ),
+                ))
+            }
+        }
+    }
+    fs::write(path, lines.join("\n") + "\n")
+}
+
+fn not_rewritable(path: &Path, equation: &Equation) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!(
+            "{}: can't find the body of {}; was it read with a custom rule?",
+            path.display(),
+            equation.name
+        ),
+    )
+}
@@ -0,0 +1,141 @@
+use crate::RenderReport;
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use tracing::{debug, warn};
+
+/// `[notify]` table: shell commands around a batch render, e.g. to commit the
+/// outputs or push them to a CDN. Unlike `[[hook]]` stages, which are steps
+/// of each equation's render, they run before and after the batch and after
+/// every equation, failed and cached ones too, and only `before_batch` can
+/// stop anything. Each runs in the output directory.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct BatchHooks {
+    pub before_batch: Option<String>, // Cancels the batch when it fails
+    pub after_equation: Option<String>,
+    pub after_batch: Option<String>,
+}
+
+impl BatchHooks {
+    /// Runs `before_batch` with `SIMPTUI_OUTPUT_DIR`, `SIMPTUI_SOURCE` (the
+    /// source file's stem, if known) and `SIMPTUI_EQUATIONS`, the number of
+    /// active equations.
+    pub fn before_batch(
+        &self,
+        output_dir: &Path,
+        source: Option<&str>,
+        equations: usize,
+    ) -> io::Result<()> {
+        let Some(command) = &self.before_batch else {
+            return Ok(());
+        };
+        fs::create_dir_all(output_dir)?;
+        let count = equations.to_string();
+        run_hook(
+            "before_batch",
+            command,
+            output_dir,
+            &[
+                ("SIMPTUI_OUTPUT_DIR", &output_dir.to_string_lossy()),
+                ("SIMPTUI_SOURCE", source.unwrap_or_default()),
+                ("SIMPTUI_EQUATIONS", &count),
+            ],
+        )
+    }
+
+    /// Runs `after_equation` with `SIMPTUI_NAME`, `SIMPTUI_OUTPUT` (empty
+    /// after a failure), `SIMPTUI_STATUS` (`rendered`, `cached`, `resumed` or
+    /// `failed`) and `SIMPTUI_ERROR`. A failing command only warns.
+    pub fn after_equation(
+        &self,
+        output_dir: &Path,
+        name: &str,
+        output: Option<&str>,
+        status: &str,
+        error: Option<&str>,
+    ) {
+        let Some(command) = &self.after_equation else {
+            return;
+        };
+        let output = output.map(|file| output_dir.join(file).to_string_lossy().into_owned());
+        let result = run_hook(
+            "after_equation",
+            command,
+            output_dir,
+            &[
+                ("SIMPTUI_OUTPUT_DIR", &output_dir.to_string_lossy()),
+                ("SIMPTUI_NAME", name),
+                ("SIMPTUI_OUTPUT", output.as_deref().unwrap_or_default()),
+                ("SIMPTUI_STATUS", status),
+                ("SIMPTUI_ERROR", error.unwrap_or_default()),
+            ],
+        );
+        if let Err(e) = result {
+            warn!("{}: {}", name, e);
+        }
+    }
+
+    /// Runs `after_batch` with `SIMPTUI_RENDERED`, `SIMPTUI_FAILED` and
+    /// `SIMPTUI_STATUS` (`ok`, `failed` or `interrupted`). A failing command
+    /// only warns.
+    pub fn after_batch(&self, output_dir: &Path, report: &RenderReport) {
+        let Some(command) = &self.after_batch else {
+            return;
+        };
+        let status = if report.is_interrupted() {
+            "interrupted"
+        } else if report.failed.is_empty() {
+            "ok"
+        } else {
+            "failed"
+        };
+        let result = run_hook(
+            "after_batch",
+            command,
+            output_dir,
+            &[
+                ("SIMPTUI_OUTPUT_DIR", &output_dir.to_string_lossy()),
+                ("SIMPTUI_RENDERED", &report.rendered.len().to_string()),
+                ("SIMPTUI_FAILED", &report.failed.len().to_string()),
+                ("SIMPTUI_STATUS", status),
+            ],
+        );
+        if let Err(e) = result {
+            warn!("{}", e);
+        }
+    }
+}
+
+/// `command` as the platform's shell runs it: `sh -c`, or `cmd /C` on
+/// Windows.
+pub(crate) fn shell_command(command: &str) -> Command {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut shell = Command::new(shell);
+    shell.args([flag, command]);
+    shell
+}
+
+fn run_hook(which: &str, command: &str, dir: &Path, env: &[(&str, &str)]) -> io::Result<()> {
+    debug!("Running the {} hook: {}", which, command);
+    let mut shell = shell_command(command);
+    if dir.is_dir() {
+        shell.current_dir(dir);
+    }
+    let status = shell.envs(env.iter().copied()).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "The {} hook failed ({})",
+            which, status
+        )))
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{unique_names, DuplicateNames, Equation, SourceSpan};
+use ego_tree::NodeRef;
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+
+/// Equations in exported HTML notes (Notion, Evernote, ...): the TeX
+/// annotation of MathML elements and `\(...\)` / `\[...\]` in the text.
+/// Names come from the `id` of the `<math>` element where there is one.
+pub fn parse_html(content: &str) -> Vec<Equation> {
+    let mut equations = html_equations(content);
+    unique_names(&mut equations, DuplicateNames::Counter);
+    equations
+}
+
+// The equations with their names as written, repeats and all
+pub(crate) fn html_equations(content: &str) -> Vec<Equation> {
+    let document = Html::parse_document(content);
+    let mut found = Vec::new();
+    let mut text = String::new();
+    walk(document.tree.root(), &mut text, &mut found);
+    flush_text(&mut text, &mut found);
+
+    let mut equations = Vec::new();
+    let mut cursor = 0;
+    for (id, body) in found {
+        let name = id.as_deref().unwrap_or("default_equation");
+        let mut equation = Equation::new(true, name, &body);
+        // Only found when the TeX appears verbatim, i.e. without entities
+        if let Some(offset) = content[cursor..].find(body.as_str()) {
+            let start = cursor + offset;
+            cursor = start + body.len();
+            let line_at = |offset: usize| content[..offset].matches('\n').count() + 1;
+            equation.span = Some(SourceSpan {
+                start_line: line_at(start),
+                end_line: line_at(cursor),
+            });
+        }
+        equations.push(equation);
+    }
+    equations
+}
+
+// Collects equations in document order; text is buffered until the next
+// `<math>` so delimiters split across inline elements still pair up
+fn walk(node: NodeRef<Node>, text: &mut String, found: &mut Vec<(Option<String>, String)>) {
+    for child in node.children() {
+        match child.value() {
+            Node::Text(t) => text.push_str(t),
+            Node::Element(element) => match element.name() {
+                "script" | "style" => {}
+                "math" => {
+                    flush_text(text, found);
+                    if let Some(tex) = ElementRef::wrap(child).and_then(tex_annotation) {
+                        found.push((element.attr("id").map(str::to_string), tex));
+                    }
+                }
+                _ => walk(child, text, found),
+            },
+            _ => {}
+        }
+    }
+}
+
+fn tex_annotation(math: ElementRef) -> Option<String> {
+    let selector = Selector::parse(r#"annotation[encoding="application/x-tex"]"#).unwrap();
+    let tex: String = math.select(&selector).next()?.text().collect();
+    Some(tex.trim().to_string()).filter(|tex| !tex.is_empty())
+}
+
+fn flush_text(text: &mut String, found: &mut Vec<(Option<String>, String)>) {
+    let delimiters = Regex::new(r"(?s)\\\((.+?)\\\)|\\\[(.+?)\\\]").unwrap();
+    for cap in delimiters.captures_iter(text) {
+        let body = cap.get(1).or_else(|| cap.get(2)).unwrap().as_str().trim();
+        if !body.is_empty() {
+            found.push((None, body.to_string()));
+        }
+    }
+    text.clear();
+}
@@ -0,0 +1,55 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Whether Ctrl-C was pressed since `catch_interrupts`. Batch renders check
+/// it between equations and stop early.
+pub(crate) fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Ctrl-C caught while it lives; dropping it puts back the handler there was
+/// before and forgets any Ctrl-C, so prompts and later batches aren't
+/// affected.
+#[must_use = "Ctrl-C is only caught while the guard lives"]
+pub struct InterruptGuard {
+    #[cfg(unix)]
+    previous: libc::sighandler_t,
+}
+
+/// Turns the first SIGINT into a flag for `interrupted` instead of killing
+/// the process, so a batch can clean up and report what it got done. The
+/// TeX run under way is stopped along with it. A second Ctrl-C
+/// exits at once. Hold the guard for as long as the batch runs.
+#[cfg(unix)]
+pub fn catch_interrupts() -> InterruptGuard {
+    extern "C" fn on_sigint(_: libc::c_int) {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            // Only async-signal-safe calls in here
+            unsafe { libc::_exit(130) };
+        }
+    }
+    INTERRUPTED.store(false, Ordering::SeqCst);
+    let handler: extern "C" fn(libc::c_int) = on_sigint;
+    let previous = unsafe { libc::signal(libc::SIGINT, handler as libc::sighandler_t) };
+    InterruptGuard { previous }
+}
+
+#[cfg(not(unix))]
+pub fn catch_interrupts() -> InterruptGuard {
+    InterruptGuard {}
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        unsafe {
+            let previous = match self.previous {
+                libc::SIG_ERR => libc::SIG_DFL,
+                previous => previous,
+            };
+            libc::signal(libc::SIGINT, previous);
+        }
+        INTERRUPTED.store(false, Ordering::SeqCst);
+    }
+}
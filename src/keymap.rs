@@ -0,0 +1,440 @@
+use tui_textarea::{Input, Key};
+
+/// Pane receiving plain keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    Input,
+    Table,
+    Preview,
+    Tree,
+    Gallery,
+    Log,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Help,
+    Quit,
+    Search,
+    Bookmark,
+    ShowBookmarks,
+    PickProfile,
+    ToggleTree,
+    TreeOpen,
+    TreeExpand,
+    TreeCollapse,
+    Render,
+    LoadFile,
+    FocusTable,
+    FocusInput,
+    Filter,
+    CycleSort,
+    ReverseSort,
+    MoveUp,
+    MoveDown,
+    RenameAll,
+    FormatBodies,
+    ActivateShown,
+    DeactivateShown,
+    NewEquation,
+    TogglePreview,
+    SideBySide,
+    CopyImage,
+    OpenViewer,
+    CopyError,
+    GroupSections,
+    ToggleSection,
+    RenderSection,
+    FocusPreview,
+    Gallery,
+    GalleryLeft,
+    GalleryRight,
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    ToggleLog,
+    SearchLog,
+    ExportLog,
+    Palette,
+}
+
+impl Action {
+    /// Every action, in the order the command palette lists them.
+    pub const ALL: [Action; 51] = [
+        Action::LoadFile,
+        Action::ToggleTree,
+        Action::Search,
+        Action::Bookmark,
+        Action::ShowBookmarks,
+        Action::Render,
+        Action::RenderSection,
+        Action::PickProfile,
+        Action::Filter,
+        Action::CycleSort,
+        Action::ReverseSort,
+        Action::GroupSections,
+        Action::ToggleSection,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::RenameAll,
+        Action::FormatBodies,
+        Action::ActivateShown,
+        Action::DeactivateShown,
+        Action::NewEquation,
+        Action::TogglePreview,
+        Action::SideBySide,
+        Action::FocusPreview,
+        Action::Gallery,
+        Action::CopyImage,
+        Action::OpenViewer,
+        Action::CopyError,
+        Action::ToggleLog,
+        Action::SearchLog,
+        Action::ExportLog,
+        Action::FocusTable,
+        Action::FocusInput,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::ZoomReset,
+        Action::Help,
+        Action::Quit,
+        Action::TreeOpen,
+        Action::TreeExpand,
+        Action::TreeCollapse,
+        Action::GalleryLeft,
+        Action::GalleryRight,
+        Action::PanUp,
+        Action::PanDown,
+        Action::PanLeft,
+        Action::PanRight,
+        Action::Up,
+        Action::Down,
+        Action::PageUp,
+        Action::PageDown,
+        Action::Palette,
+    ];
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Help => "Show this help",
+            Action::Quit => "Quit",
+            Action::Search => "Search equations in all notes",
+            Action::Bookmark => "Bookmark the equation, or drop its bookmark",
+            Action::ShowBookmarks => "Show the bookmarked equations of all files",
+            Action::PickProfile => "Choose the render profile",
+            Action::ToggleTree => "Browse the scanned files",
+            Action::TreeOpen => "Open the file or fold the directory",
+            Action::TreeExpand => "Unfold the directory",
+            Action::TreeCollapse => "Fold the directory / go to its parent",
+            Action::Render => "Render the active equations",
+            Action::LoadFile => "Open the typed file",
+            Action::FocusTable => "Focus the equation table",
+            Action::FocusInput => "Back to the filename field",
+            Action::Filter => "Filter rows by a regex",
+            Action::CycleSort => "Sort by the next column",
+            Action::ReverseSort => "Reverse the sort order",
+            Action::MoveUp => "Move the equation up (document order only)",
+            Action::MoveDown => "Move the equation down (document order only)",
+            Action::RenameAll => "Rename all equations after a pattern",
+            Action::FormatBodies => "Tidy the spacing and layout of every body",
+            Action::ActivateShown => "Mark the shown equations active (the section, while grouped)",
+            Action::DeactivateShown => {
+                "Mark the shown equations inactive (the section, while grouped)"
+            }
+            Action::NewEquation => "Add an equation to the file",
+            Action::TogglePreview => "Show the rendered image instead of the source",
+            Action::SideBySide => "Show the LaTeX next to the rendered image",
+            Action::CopyImage => "Copy the equation to the clipboard as PNG",
+            Action::OpenViewer => "Open the rendered equation in the system viewer",
+            Action::CopyError => "Copy the open error report to the clipboard",
+            Action::GroupSections => "Group the equations by the heading they are under",
+            Action::ToggleSection => "Fold or unfold the selected section",
+            Action::RenderSection => "Render the active equations of the section",
+            Action::FocusPreview => "Zoom and pan the preview",
+            Action::Gallery => "Show the rendered equations as thumbnails",
+            Action::GalleryLeft => "Previous thumbnail",
+            Action::GalleryRight => "Next thumbnail",
+            Action::ZoomIn => "Zoom in",
+            Action::ZoomOut => "Zoom out",
+            Action::ZoomReset => "Fit the image to the pane",
+            Action::PanUp => "Pan up",
+            Action::PanDown => "Pan down",
+            Action::PanLeft => "Pan left",
+            Action::PanRight => "Pan right",
+            Action::Up => "Previous row / scroll up",
+            Action::Down => "Next row / scroll down",
+            Action::PageUp => "Page up",
+            Action::PageDown => "Page down",
+            Action::ToggleLog => "Show the session log",
+            Action::SearchLog => "Show only log entries containing a text",
+            Action::ExportLog => "Write the session log to simptui-session.log",
+            Action::Palette => "Find and run any action by name",
+        }
+    }
+
+    /// Whether the command palette can offer the action. Moving around is
+    /// left to the keys, and copying an error to the error screen.
+    pub fn in_palette(self) -> bool {
+        !matches!(
+            self,
+            Action::Up
+                | Action::Down
+                | Action::PageUp
+                | Action::PageDown
+                | Action::PanUp
+                | Action::PanDown
+                | Action::PanLeft
+                | Action::PanRight
+                | Action::GalleryLeft
+                | Action::GalleryRight
+                | Action::TreeExpand
+                | Action::TreeCollapse
+                | Action::CopyError
+                | Action::Palette
+        )
+    }
+
+    // Short form for the hint bar
+    pub fn hint(self) -> &'static str {
+        match self {
+            Action::Help => "help",
+            Action::Quit => "quit",
+            Action::Search => "search",
+            Action::Bookmark => "bookmark",
+            Action::ShowBookmarks => "bookmarks",
+            Action::PickProfile => "profile",
+            Action::ToggleTree => "files",
+            Action::TreeOpen => "open",
+            Action::TreeExpand | Action::TreeCollapse => "",
+            Action::Render => "render",
+            Action::LoadFile => "open",
+            Action::FocusTable => "table",
+            Action::FocusInput => "back",
+            Action::Filter => "filter",
+            Action::CycleSort => "sort",
+            Action::ReverseSort => "reverse",
+            Action::MoveUp => "move",
+            Action::RenameAll => "rename",
+            Action::FormatBodies => "format",
+            Action::ActivateShown => "activate",
+            Action::DeactivateShown => "deactivate",
+            Action::NewEquation => "new",
+            Action::TogglePreview => "preview",
+            Action::SideBySide => "layout",
+            Action::CopyImage => "copy",
+            Action::OpenViewer => "open",
+            Action::CopyError => "", // Shown by the error screen itself
+            Action::GroupSections => "group",
+            Action::ToggleSection | Action::RenderSection => "", // Only while grouped
+            Action::FocusPreview => "zoom",
+            Action::Gallery => "gallery",
+            Action::GalleryLeft => "move",
+            Action::GalleryRight => "",
+            Action::ZoomIn => "zoom in",
+            Action::ZoomOut => "zoom out",
+            Action::ZoomReset => "fit",
+            Action::PanUp => "pan",
+            Action::MoveDown | Action::PanDown | Action::PanLeft | Action::PanRight => "",
+            Action::Up | Action::Down | Action::PageUp | Action::PageDown => "",
+            Action::ToggleLog => "log",
+            Action::SearchLog => "search",
+            Action::ExportLog => "export",
+            Action::Palette => "commands",
+        }
+    }
+}
+
+pub struct Binding {
+    pub key: Key,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub focus: Option<Focus>, // `None` applies everywhere
+    pub action: Action,
+}
+
+impl Binding {
+    pub fn label(&self) -> String {
+        let key = match self.key {
+            Key::Char(c) => c.to_string(),
+            Key::F(n) => format!("F{}", n),
+            Key::Enter => "Enter".to_string(),
+            Key::Esc => "Esc".to_string(),
+            Key::Tab => "Tab".to_string(),
+            Key::Up => "Up".to_string(),
+            Key::Down => "Down".to_string(),
+            Key::Left => "Left".to_string(),
+            Key::Right => "Right".to_string(),
+            Key::PageUp => "PgUp".to_string(),
+            Key::PageDown => "PgDn".to_string(),
+            other => format!("{:?}", other),
+        };
+        if self.ctrl && matches!(self.key, Key::Char(c) if c.is_ascii_uppercase()) {
+            format!("Ctrl-Shift-{}", key)
+        } else if self.ctrl {
+            format!("Ctrl-{}", key.to_uppercase())
+        } else if self.alt {
+            format!("Alt-{}", key)
+        } else {
+            key
+        }
+    }
+
+    fn matches(&self, input: &Input, focus: Focus) -> bool {
+        // Shift is implied by the character itself ('?', 'S')
+        input.key == self.key
+            && input.ctrl == self.ctrl
+            && input.alt == self.alt
+            && self.focus.is_none_or(|f| f == focus)
+    }
+}
+
+/// The single source of truth for TUI shortcuts: input handling, the help
+/// overlay and the hint bar all read from it.
+pub struct KeyMap {
+    bindings: Vec<Binding>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        use Action::*;
+        let bind = |key, ctrl, focus, action| Binding {
+            key,
+            ctrl,
+            alt: false,
+            focus,
+            action,
+        };
+        let alt = |key, focus, action| Binding {
+            alt: true,
+            ..bind(key, false, focus, action)
+        };
+        let table = Some(Focus::Table);
+        let input = Some(Focus::Input);
+        let preview = Some(Focus::Preview);
+        let tree = Some(Focus::Tree);
+        let gallery = Some(Focus::Gallery);
+        let log = Some(Focus::Log);
+        KeyMap {
+            bindings: vec![
+                bind(Key::Char('?'), false, table, Help),
+                bind(Key::Char('?'), false, preview, Help),
+                bind(Key::F(1), false, None, Help),
+                bind(Key::Enter, false, input, LoadFile),
+                bind(Key::Tab, false, input, FocusTable),
+                bind(Key::Tab, false, table, FocusInput),
+                bind(Key::Esc, false, table, FocusInput),
+                bind(Key::Char('/'), false, table, Filter),
+                bind(Key::Char('s'), false, table, CycleSort),
+                bind(Key::Char('S'), false, table, ReverseSort),
+                alt(Key::Up, table, MoveUp),
+                alt(Key::Down, table, MoveDown),
+                bind(Key::Char('R'), false, table, RenameAll),
+                bind(Key::Char('F'), false, table, FormatBodies),
+                bind(Key::Char('a'), false, table, ActivateShown),
+                bind(Key::Char('A'), false, table, DeactivateShown),
+                bind(Key::Char('n'), false, table, NewEquation),
+                bind(Key::Char('m'), false, table, Bookmark),
+                bind(Key::Char('p'), false, table, TogglePreview),
+                bind(Key::Char('l'), false, table, SideBySide),
+                bind(Key::Char('y'), false, table, CopyImage),
+                bind(Key::Char('o'), false, table, OpenViewer),
+                bind(Key::Char('g'), false, table, GroupSections),
+                bind(Key::Enter, false, table, ToggleSection),
+                bind(Key::Char('r'), false, table, RenderSection),
+                bind(Key::Char('v'), false, table, FocusPreview),
+                bind(Key::Char('G'), false, table, Gallery),
+                bind(Key::Left, false, gallery, GalleryLeft),
+                bind(Key::Right, false, gallery, GalleryRight),
+                bind(Key::Char('y'), false, gallery, CopyImage),
+                bind(Key::Char('o'), false, gallery, OpenViewer),
+                bind(Key::Enter, false, gallery, FocusTable),
+                bind(Key::Esc, false, gallery, FocusTable),
+                bind(Key::Tab, false, gallery, FocusInput),
+                bind(Key::Char('?'), false, gallery, Help),
+                bind(Key::Char('+'), false, preview, ZoomIn),
+                bind(Key::Char('='), false, preview, ZoomIn),
+                bind(Key::Char('-'), false, preview, ZoomOut),
+                bind(Key::Char('0'), false, preview, ZoomReset),
+                bind(Key::Up, false, preview, PanUp),
+                bind(Key::Down, false, preview, PanDown),
+                bind(Key::Left, false, preview, PanLeft),
+                bind(Key::Right, false, preview, PanRight),
+                bind(Key::Esc, false, preview, FocusTable),
+                bind(Key::Tab, false, preview, FocusInput),
+                bind(Key::Enter, false, tree, TreeOpen),
+                bind(Key::Right, false, tree, TreeExpand),
+                bind(Key::Left, false, tree, TreeCollapse),
+                bind(Key::Esc, false, tree, FocusInput),
+                bind(Key::Tab, false, tree, FocusInput),
+                bind(Key::Char('?'), false, tree, Help),
+                bind(Key::Char('/'), false, log, SearchLog),
+                bind(Key::Char('w'), false, log, ExportLog),
+                bind(Key::Esc, false, log, FocusInput),
+                bind(Key::Tab, false, log, FocusInput),
+                bind(Key::Char('?'), false, log, Help),
+                bind(Key::Char('k'), true, None, Palette),
+                bind(Key::Char('P'), true, None, Palette), // Ctrl-Shift-P, where told apart
+                bind(Key::Char('o'), true, None, ToggleTree),
+                bind(Key::Char('r'), true, None, Render),
+                bind(Key::Char('f'), true, None, Search),
+                bind(Key::Char('b'), true, None, ShowBookmarks),
+                bind(Key::Char('p'), true, None, PickProfile),
+                bind(Key::Char('y'), true, None, CopyError),
+                bind(Key::Char('l'), true, None, ToggleLog),
+                bind(Key::Up, false, None, Up),
+                bind(Key::Down, false, None, Down),
+                bind(Key::PageUp, false, None, PageUp),
+                bind(Key::PageDown, false, None, PageDown),
+                bind(Key::Esc, false, input, Quit),
+            ],
+        }
+    }
+}
+
+impl KeyMap {
+    pub fn action(&self, input: &Input, focus: Focus) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|binding| binding.matches(input, focus))
+            .map(|binding| binding.action)
+    }
+
+    pub fn bindings(&self) -> &[Binding] {
+        &self.bindings
+    }
+
+    /// Label of the first key bound to `action`.
+    pub fn label(&self, action: Action) -> Option<String> {
+        self.bindings
+            .iter()
+            .find(|binding| binding.action == action)
+            .map(Binding::label)
+    }
+
+    /// `key action` pairs usable in `focus`, for the hint bar. Only the first
+    /// key of each action is listed.
+    pub fn hints(&self, focus: Focus) -> Vec<(String, &'static str)> {
+        let mut seen = Vec::new();
+        let mut hints = Vec::new();
+        for binding in &self.bindings {
+            let action = binding.action;
+            if binding.focus.is_some_and(|f| f != focus)
+                || action.hint().is_empty()
+                || seen.contains(&action)
+            {
+                continue;
+            }
+            seen.push(action);
+            hints.push((binding.label(), action.hint()));
+        }
+        hints
+    }
+}
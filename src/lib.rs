@@ -1,19 +1,509 @@
-pub use self::core::*;
+pub use self::activate::set_active_in_source;
+pub use self::backend::{RenderBackend, TexBackend};
+pub use self::balance::{balance, Balance, Opener};
+pub use self::bookmarks::{Bookmark, Bookmarks};
+pub use self::clipboard::{copy_png, copy_text, render_png};
+pub use self::color::{
+    adjust_contrast, resolve_color, strip_colors, ColorSpec, Fill, Rgb, MIN_CONTRAST,
+};
+pub use self::config::{
+    Config, HookConfig, OutputBase, OutputConfig, Profile, RemoteConfig, RouteConfig, ScanConfig,
+};
+pub use self::core::{
+    ask_confirmation, detect_file_type, load_equations, parse_csv, parse_markdown, read_csv_file,
+    render_equations, render_equations_with, render_single_pdf, reorder_csv_file, tex_log_errors,
+    BoundingMode, Equation, OutputFormat, OutputLayout, OutputNaming, RenderFailure, RenderOptions,
+    RenderReport, RenderedEquation, Retention, SourceSpan,
+};
+pub use self::csv::{export_csv, write_csv_file};
+pub use self::document::{load_document, Document, Macro};
+pub use self::embed::{embed_images, embed_links, link_target, EmbedReport};
+pub use self::engine::Engine;
+pub use self::extract::{ExtractRule, Extractor, Group};
+pub use self::filter::EquationFilter;
+pub use self::font::Font;
+pub use self::format::{format_body, rewrite_bodies};
+pub use self::hooks::BatchHooks;
+pub use self::html::parse_html;
+pub use self::interrupt::{catch_interrupts, InterruptGuard};
+pub use self::manifest::{read_manifest, update_manifest, Manifest, MANIFEST_NAME};
+pub use self::names::DuplicateNames;
+pub use self::normalize::normalize_body;
+pub use self::org::parse_org;
+pub use self::overwrite::{back_up, changed_outputs, ChangedOutput};
+pub use self::parser::{
+    CsvParser, Detection, HtmlParser, MarkdownParser, NotebookParser, OrgParser, Parser,
+    ParserRegistry, TomlParser, WikiParser,
+};
+pub use self::paths::{ensure_dir, Paths};
+pub use self::pipeline::{
+    Cleanup, Compile, Convert, GenerateTex, PostProcess, RenderPipeline, RenderStage, ShellHook,
+    StageContext,
+};
+pub use self::project::{apply_order, EquationOverride, Project, ProjectOutput, PROJECT_FILE_NAME};
+pub use self::recolor::{dominant_color, recolor_dir, recolor_svg, RecolorReport};
+pub use self::remote::{RemoteBackend, RenderRequest, RenderResponse, REMOTE_PROTOCOL_VERSION};
+pub use self::rename::{append_equation, check_new_equation, rename_in_source, NamePattern};
+pub use self::reproducible::{
+    scrub_pdf, scrub_png, scrub_svg, verify_reproducible, verify_reproducible_with,
+    write_checksums, Scrub, CHECKSUMS_FILE,
+};
+pub use self::routes::{route_of, OutputRoute};
+pub use self::scan::{expand_inputs, scan_files, FileIndexer, IndexEvent, InputFile};
+pub use self::search::{search_equations, search_pattern, SearchHit};
+pub use self::sections::{equation_sections, headings, in_section, Heading};
+pub use self::size::FontSize;
+pub use self::snippets::{load_snippets, Snippet};
+pub use self::source::{decode_source, load_source};
+pub use self::source_map::{
+    add_svg_source_map, read_svg_source_map, strip_svg_source_map, SourceMap,
+};
+pub use self::split::split_lines;
+pub use self::ssg::SiteFlavor;
+pub use self::stats::{compile_timeout, complexity, EquationStats, Ranked};
+pub use self::status::{
+    read_status, JobState, JobStatus, LastResult, LOCK_FILE_NAME, STATUS_FILE_NAME,
+};
+pub use self::support::{unsupported_constructs, warn_unsupported, Unsupported};
+pub use self::toml_file::{export_toml, parse_toml, reorder_toml_file, write_toml_file};
+pub use self::tools::{find_tool, tex_path, tool_command};
+pub use self::verify::{svg_difference, verify_renders, Verdict, Verification};
+pub use self::viewer::{find_rendered, open_in_viewer};
+pub use self::wiki::parse_wiki;
+pub use self::wrap::{unwrap_body, MathWrap};
+
+// Helpers the modules share but the crate doesn't promise
+pub(crate) use self::core::{csv_equations, find_label, missing_tool};
+pub(crate) use self::csv::{csv_columns, csv_field, csv_fields, csv_row};
+pub(crate) use self::document::{
+    document_macros, equation_macros, group, org_keywords, yaml_frontmatter,
+};
+pub(crate) use self::extract::{apply_option, apply_options, code_fences};
+pub(crate) use self::hooks::shell_command;
+pub(crate) use self::html::html_equations;
+pub(crate) use self::interrupt::interrupted;
+pub(crate) use self::manifest::{content_hash, hash_output_file, sha256_hex};
+pub(crate) use self::meter::BatchMeter;
+pub(crate) use self::names::unique_names;
+pub(crate) use self::normalize::TEXT_COMMANDS;
+pub(crate) use self::notebook::*;
+pub(crate) use self::org::org_equations;
+pub(crate) use self::progress::*;
+pub(crate) use self::remote::remote_format_error;
+pub(crate) use self::rename::slug;
+pub(crate) use self::reproducible::scrub_file;
+pub(crate) use self::routes::routed_file;
+pub(crate) use self::source::load_source_start;
+pub(crate) use self::split::split_equations;
+pub(crate) use self::status::BatchStatus;
+pub(crate) use self::svg::*;
+pub(crate) use self::toml_file::{toml_body, toml_equations};
+pub(crate) use self::wiki::wiki_equations;
+pub(crate) use self::wrap::mathml_environments;
+
+pub mod prelude;
+
+mod activate;
+mod backend;
+mod balance;
+mod bookmarks;
+mod clipboard;
+mod color;
+mod config;
+mod csv;
+mod document;
+mod embed;
+mod engine;
+mod extract;
+mod filter;
+mod font;
+mod format;
+mod hooks;
+mod html;
+mod interrupt;
+mod manifest;
+mod meter;
+mod names;
+mod normalize;
+mod notebook;
+mod org;
+mod overwrite;
+mod parser;
+mod paths;
+mod pipeline;
+mod progress;
+mod project;
+mod recolor;
+mod remote;
+mod rename;
+mod reproducible;
+mod routes;
+mod scan;
+mod search;
+mod sections;
+mod size;
+mod snippets;
+mod source;
+mod source_map;
+mod split;
+mod ssg;
+mod stats;
+mod status;
+mod support;
+mod svg;
+mod toml_file;
+mod tools;
+mod verify;
+mod viewer;
+mod wiki;
+mod wrap;
 
 mod core {
-    use indicatif::{ProgressBar, ProgressStyle};
+    use crate::{
+        add_svg_source_map, apply_options, compile_timeout, content_hash, csv_columns, csv_row,
+        document_macros, hash_output_file, interrupted, load_source, load_source_start,
+        mathml_environments, normalize_body, optimize_svg_file, remote_format_error, route_of,
+        routed_file, scrub_file, set_vertical_align, sha256_hex, split_equations, strip_colors,
+        svg_vertical_align, unique_names, unwrap_body, update_manifest, BatchHooks, BatchMeter,
+        BatchProgress, BatchStatus, Completed, DuplicateNames, Engine, Extractor, Fill, Font,
+        FontSize, Macro, Manifest, MathWrap, OutputRoute, ParserRegistry, RemoteBackend,
+        RenderBackend, RenderPipeline, SourceMap, StageContext, SvgSavings, TexBackend,
+    };
+    use latex2mathml::{latex_to_mathml, DisplayStyle};
     use regex::Regex;
+    use serde::{Deserialize, Serialize};
     use std::collections::HashMap;
-    use std::fs::{self, File};
-    use std::io::{self, BufRead, BufReader, Read, Write};
-    use std::path::Path;
-    use std::process::Command;
+    use std::fs;
+    use std::io::{self, Write};
+    use std::path::{Path, PathBuf};
+    use std::str::FromStr;
+    use std::sync::{Mutex, OnceLock};
+    use std::time::{Duration, SystemTime};
+    use tracing::{debug, info, trace, warn};
+
+    const SINGLE_PDF_NAME: &str = "equations";
+    const BASELINE_CSS_NAME: &str = "baseline.css";
+    // Bytes `detect_file_type` reads to tell a file's format, and the files
+    // it remembers the answer for before starting over
+    const SNIFF_BYTES: u64 = 64 * 1024;
+    const MAX_SNIFFED: usize = 10_000;
+    pub(crate) const DEPTH_MARKER: &str = "SIMPTUI-DEPTH=";
+    const FAILED_DIR_NAME: &str = "failed";
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    #[non_exhaustive]
+    pub enum OutputLayout {
+        #[default]
+        PerEquation, // One .svg per equation
+        SinglePdf, // All active equations in one labeled, multi-page PDF
+    }
+
+    impl FromStr for OutputLayout {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "per-equation" => Ok(OutputLayout::PerEquation),
+                "single-pdf" => Ok(OutputLayout::SinglePdf),
+                _ => Err(format!(
+                    "unknown layout '{}': expected 'per-equation' or 'single-pdf'",
+                    s
+                )),
+            }
+        }
+    }
+
+    /// How per-equation output files are named. Anything but `Name` also
+    /// writes manifest.json, mapping equation names to their files.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    #[non_exhaustive]
+    pub enum OutputNaming {
+        #[default]
+        Name, // The sanitized equation name
+        Hash,      // Hash of the LaTeX source, shared by identical equations
+        Number,    // `001`, `002`, ... in document order
+        FileIndex, // `<source file stem>_<n>`
+    }
+
+    impl FromStr for OutputNaming {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "name" => Ok(OutputNaming::Name),
+                "hash" => Ok(OutputNaming::Hash),
+                "number" => Ok(OutputNaming::Number),
+                "file-index" => Ok(OutputNaming::FileIndex),
+                _ => Err(format!(
+                    "unknown naming '{}': expected name, hash, number or file-index",
+                    s
+                )),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    #[non_exhaustive]
+    pub enum OutputFormat {
+        #[default]
+        Svg,
+        Png,
+        Pdf,
+        MathML, // Converted in-process, no TeX toolchain needed
+    }
+
+    impl OutputFormat {
+        pub fn extension(self) -> &'static str {
+            match self {
+                OutputFormat::Svg => "svg",
+                OutputFormat::Png => "png",
+                OutputFormat::Pdf => "pdf",
+                OutputFormat::MathML => "mml",
+            }
+        }
+    }
+
+    impl FromStr for OutputFormat {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "svg" => Ok(OutputFormat::Svg),
+                "png" => Ok(OutputFormat::Png),
+                "pdf" => Ok(OutputFormat::Pdf),
+                "mathml" | "mml" => Ok(OutputFormat::MathML),
+                _ => Err(format!(
+                    "unknown format '{}': expected svg, png, pdf or mathml",
+                    s
+                )),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    #[non_exhaustive]
+    pub enum BoundingMode {
+        #[default]
+        Uniform, // Pad to a common minimum height/depth so equations line up
+        Tight, // Crop to the equation itself
+    }
+
+    /// What becomes of the `.tex`, `.log` and `.pdf` files TeX leaves next to
+    /// the outputs.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    #[non_exhaustive]
+    pub enum Retention {
+        KeepAll, // Nothing is deleted
+        KeepPdf, // The PDF stays, the .tex and .log go
+        #[default]
+        KeepTexOnFailure, // Deleted after a success; a failure's .tex and .log move to failed/
+        DeleteAll, // Deleted whether the render succeeds or not
+    }
+
+    impl FromStr for Retention {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "keep-all" => Ok(Retention::KeepAll),
+                "keep-pdf" => Ok(Retention::KeepPdf),
+                "keep-tex-on-failure" => Ok(Retention::KeepTexOnFailure),
+                "delete-all" => Ok(Retention::DeleteAll),
+                _ => Err(format!(
+                    "unknown retention '{}': expected keep-all, keep-pdf, keep-tex-on-failure or delete-all",
+                    s
+                )),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    #[non_exhaustive]
+    pub struct RenderOptions {
+        pub output_dir: PathBuf,
+        pub color: String,
+        pub retention: Retention,
+        pub layout: OutputLayout,
+        pub optimize_svg: bool,
+        pub format: OutputFormat,
+        pub dpi: u32,                  // Rasterization resolution for PNG
+        pub template: Option<PathBuf>, // Custom .tex with {{body}}/{{color}}/{{preamble}}/{{size}}
+        pub bounding: BoundingMode,
+        pub baseline_align: bool, // Shift SVGs onto the text baseline for inline HTML
+        pub fail_fast: bool,      // Stop the batch at the first failed equation
+        pub hash_names: bool,     // `name-<hash>.ext` outputs plus manifest.json
+        pub engine: Engine,
+        pub font: Font,
+        pub split_lines: bool, // One image per line of multi-line equations
+        pub cache_dir: Option<PathBuf>, // Reuse outputs of identical LaTeX sources
+        pub normalize_styles: bool, // Let \dfrac reuse the output of \frac and so on
+        pub keep_labels: bool, // Leave `\label{..}` in the rendered source
+        pub keep_colors: bool, // `\textcolor`/`\color` in bodies paint over `color`; else stripped
+        pub size: FontSize,
+        pub naming: OutputNaming,
+        pub source_name: Option<String>, // Stem of the source file, for `FileIndex`
+        pub source_file: Option<PathBuf>, // Recorded in each output's `SourceMap`
+        pub source_map: bool,            // Write a `SourceMap` into each `.tex` and SVG
+        pub resume: bool,                // Skip what an unfinished batch into `output_dir` got done
+        pub pipeline: RenderPipeline,    // Stages of each equation's render
+        pub hooks: BatchHooks,           // Shell commands around the batch
+        pub fill: Fill,                  // Background drawn behind each equation
+        pub padding: f32,                // Space around the equation, in pt
+        pub corner_radius: f32,          // Rounds the corners of a filled background, in pt
+        pub remote: Option<RemoteBackend>, // Render on this service instead of local TeX
+        pub routes: Vec<OutputRoute>,    // Subdirectories for matching equations
+        pub status_file: bool,           // Lock `output_dir` and keep `STATUS_FILE_NAME` in it
+        pub timeout: Option<Duration>,   // TeX time of a simple equation, see `compile_timeout`
+        pub macros: Vec<Macro>,          // Of the source document, for the preamble
+        pub deterministic: bool,         // PDFs dated `SOURCE_DATE_EPOCH` rather than now
+    }
+
+    impl RenderOptions {
+        pub fn new(output_dir: impl Into<PathBuf>, color: &str) -> Self {
+            RenderOptions {
+                output_dir: output_dir.into(),
+                color: color.to_string(),
+                retention: Retention::default(),
+                layout: OutputLayout::default(),
+                optimize_svg: false,
+                format: OutputFormat::default(),
+                dpi: 150,
+                template: None,
+                bounding: BoundingMode::default(),
+                baseline_align: false,
+                fail_fast: false,
+                hash_names: false,
+                engine: Engine::default(),
+                font: Font::default(),
+                split_lines: false,
+                cache_dir: None,
+                normalize_styles: false,
+                keep_labels: false,
+                keep_colors: true,
+                size: FontSize::default(),
+                naming: OutputNaming::default(),
+                source_name: None,
+                source_file: None,
+                source_map: false,
+                resume: true,
+                pipeline: RenderPipeline::default(),
+                hooks: BatchHooks::default(),
+                fill: Fill::default(),
+                padding: 1.0,
+                corner_radius: 0.0,
+                remote: None,
+                routes: Vec::new(),
+                status_file: false,
+                timeout: None,
+                macros: Vec::new(),
+                deterministic: false,
+            }
+        }
+
+        /// Where TeX runs: the remote service when one is set, else locally.
+        pub fn backend(&self) -> &dyn RenderBackend {
+            match &self.remote {
+                Some(remote) => remote,
+                None if self.deterministic => &TexBackend {
+                    deterministic: true,
+                },
+                None => &TexBackend {
+                    deterministic: false,
+                },
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct RenderFailure {
+        pub name: String,
+        pub error: String,
+    }
+
+    /// Outcome of a batch. Failed equations don't stop the batch (unless
+    /// `fail_fast` is set); their `.tex` and `.log` are moved to `failed/` in
+    /// the output directory, or the directory of their route, for inspection.
+    #[derive(Debug, Clone, Default)]
+    #[non_exhaustive]
+    pub struct RenderReport {
+        pub rendered: Vec<String>,
+        pub failed: Vec<RenderFailure>,
+        pub interrupted: Vec<String>, // Not rendered because of Ctrl-C
+        pub cache_hits: usize,        // Rendered equations copied from the cache
+        pub resumed: usize,           // Rendered equations an earlier run finished
+    }
+
+    impl RenderReport {
+        /// Whether the batch should count as a failure: any failure under
+        /// `fail_fast`, otherwise only when nothing rendered at all.
+        pub fn is_failure(&self, fail_fast: bool) -> bool {
+            !self.failed.is_empty() && (fail_fast || self.rendered.is_empty())
+        }
+
+        /// Adds the outcome of another batch, e.g. of the next file.
+        pub fn merge(&mut self, other: RenderReport) {
+            self.rendered.extend(other.rendered);
+            self.failed.extend(other.failed);
+            self.interrupted.extend(other.interrupted);
+            self.cache_hits += other.cache_hits;
+            self.resumed += other.resumed;
+        }
+
+        pub fn is_interrupted(&self) -> bool {
+            !self.interrupted.is_empty()
+        }
+
+        pub fn summary(&self) -> String {
+            let mut summary = format!(
+                "{} rendered, {} failed",
+                self.rendered.len(),
+                self.failed.len()
+            );
+            if self.is_interrupted() {
+                summary.push_str(&format!(", {} interrupted", self.interrupted.len()));
+            }
+            if self.resumed > 0 {
+                summary.push_str(&format!(", {} resumed", self.resumed));
+            }
+            summary
+        }
+    }
+
+    /// An equation rendered by `Equation::render_to_bytes`.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct RenderedEquation {
+        pub svg: Vec<u8>,
+        pub pdf: Option<Vec<u8>>, // None when the SVG came from the cache
+    }
+
+    /// 1-based, inclusive line range an equation occupies in its source file.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct SourceSpan {
+        pub start_line: usize,
+        pub end_line: usize,
+    }
 
     #[derive(Debug, Clone)]
+    #[non_exhaustive]
     pub struct Equation {
         pub active: bool,
         pub name: String,
         pub body: String,
+        pub span: Option<SourceSpan>,
+        pub engine: Option<Engine>, // Overrides `RenderOptions::engine`
+        pub packages: Vec<String>,  // Extra `\usepackage`s for this equation
+        pub environment: Option<String>, // `align`, `gather*`, ... when written as one
+        pub label: Option<String>,  // From the first `\label{..}` in the body
+        pub size: Option<FontSize>, // Overrides `RenderOptions::size`
+        pub tags: Vec<String>,      // From `tags=`, matched by output routes
+        pub color: Option<String>,  // Overrides `RenderOptions::color`, as `#rrggbb`
+        pub origin: Option<String>, // Name in the source when rendered under another
+        pub wrap: MathWrap,         // How the body goes into math mode
+        pub stem: Option<String>,   // File name, when others share `name`
     }
 
     impl Equation {
@@ -23,10 +513,40 @@ mod core {
                 active,
                 name: valid_name,
                 body: body.to_string(),
+                span: None,
+                engine: None,
+                packages: Vec::new(),
+                environment: None,
+                label: find_label(body).map(str::to_string),
+                size: None,
+                tags: Vec::new(),
+                color: None,
+                origin: None,
+                wrap: MathWrap::Auto,
+                stem: None,
             }
         }
 
-        fn sanitize_filename(name: &str) -> String {
+        /// The name its output files go by: `name`, unless the equation
+        /// shares it and got a `stem` of its own from `DuplicateNames::Hash`.
+        pub fn file_stem(&self) -> &str {
+            self.stem.as_deref().unwrap_or(&self.name)
+        }
+
+        /// The body as it goes between the delimiters of `math_wrap`:
+        /// without delimiters of its own, and with display environments
+        /// turned into their inline counterparts (`align` becomes `aligned`).
+        /// See `unwrap_body`.
+        pub fn math_body(&self) -> String {
+            unwrap_body(&self.body, self.environment.as_deref(), self.wrap).1
+        }
+
+        /// How the body goes into math mode, `MathWrap::Auto` decided.
+        pub fn math_wrap(&self) -> MathWrap {
+            unwrap_body(&self.body, self.environment.as_deref(), self.wrap).0
+        }
+
+        pub(crate) fn sanitize_filename(name: &str) -> String {
             let re = Regex::new(r"[^a-zA-Z0-9_.]").unwrap();
             let mut sanitized = re.replace_all(name, "_").to_string();
             if sanitized.is_empty() {
@@ -35,113 +555,614 @@ mod core {
             sanitized
         }
 
-        pub fn render(
+        pub fn render(&self, options: &RenderOptions) -> io::Result<()> {
+            self.render_with(options, options.backend())
+        }
+
+        pub fn render_with(
             &self,
-            output_dir: &Path,
-            color: &str,
-            delete_intermediates: bool,
+            options: &RenderOptions,
+            backend: &dyn RenderBackend,
         ) -> io::Result<()> {
+            if self.file_stem() != self.name {
+                let target = Equation {
+                    name: self.file_stem().to_string(),
+                    origin: Some(self.name.clone()),
+                    ..self.clone()
+                };
+                return target.render_with(options, backend);
+            }
+            self.render_cached(options, backend).map(|_| ())
+        }
+
+        /// Renders to SVG in a private temporary directory and returns the
+        /// bytes, leaving `options.output_dir` alone. Renders inactive
+        /// equations too; the format and layout options are ignored.
+        pub fn render_to_bytes(&self, options: &RenderOptions) -> io::Result<RenderedEquation> {
+            self.render_to_bytes_with(options, options.backend())
+        }
+
+        pub fn render_to_bytes_with(
+            &self,
+            options: &RenderOptions,
+            backend: &dyn RenderBackend,
+        ) -> io::Result<RenderedEquation> {
+            let dir = tempfile::tempdir()?;
+            let options = RenderOptions {
+                output_dir: dir.path().to_path_buf(),
+                format: OutputFormat::Svg,
+                layout: OutputLayout::PerEquation,
+                retention: Retention::KeepAll, // Keeps the PDF; the dir goes anyway
+                hash_names: false,
+                pipeline: RenderPipeline::default(), // No hooks on a throwaway render
+                ..options.clone()
+            };
+            let equation = Equation {
+                active: true,
+                ..self.clone()
+            };
+            equation.render_with(&options, backend)?;
+            let output = |extension: &str| {
+                dir.path()
+                    .join(format!("{}.{}", self.file_stem(), extension))
+            };
+            Ok(RenderedEquation {
+                svg: fs::read(output("svg"))?,
+                pdf: fs::read(output("pdf")).ok(),
+            })
+        }
+
+        // `render_with`, telling whether the output came from the cache
+        fn render_cached(
+            &self,
+            options: &RenderOptions,
+            backend: &dyn RenderBackend,
+        ) -> io::Result<bool> {
             if !self.active {
-                // println!("Skipping inactive equation: {}", self.name);
-                return Ok(());
+                trace!("Skipping inactive equation {}", self.name);
+                return Ok(false);
             }
 
+            let output_dir = options.output_dir.as_path();
             fs::create_dir_all(output_dir)?;
 
-            let latex_source = self.generate_latex(color);
-            let tex_file_path = output_dir.join(format!("{}.tex", self.name));
+            if options.format == OutputFormat::MathML {
+                let mml_file = output_dir.join(format!("{}.mml", self.name));
+                return fs::write(mml_file, self.to_mathml(self.color(options))?).map(|_| false);
+            }
 
-            fs::write(&tex_file_path, latex_source)?;
+            let overridden;
+            let options = match self.engine {
+                Some(engine) if engine != options.engine => {
+                    debug!("{} overrides the engine with {}", self.name, engine);
+                    options.font.check(engine)?;
+                    overridden = RenderOptions {
+                        engine,
+                        ..options.clone()
+                    };
+                    &overridden
+                }
+                _ => options,
+            };
 
-            let status = Command::new("tectonic")
-                .arg(&tex_file_path)
-                .arg("--outdir")
-                .arg(output_dir)
-                .stdout(std::process::Stdio::null()) // Suppress stdout
-                .stderr(std::process::Stdio::null()) // Suppress stderr
-                .status()?;
+            let extension = options.format.extension();
+            let output_file = output_dir.join(format!("{}.{}", self.name, extension));
+            let cached = self.cache_file(options)?;
+            let context = StageContext {
+                equation: self,
+                options,
+                backend,
+                tex_file: output_dir.join(format!("{}.tex", self.name)),
+                pdf_file: output_dir.join(format!("{}.pdf", self.name)),
+                output_file: output_file.clone(),
+            };
+            if let Some(cached) = cached.as_ref().filter(|path| path.exists()) {
+                debug!("{}: reusing {}", self.name, cached.display());
+                fs::copy(cached, &output_file)?;
+                options.pipeline.run_on_cache_hit(&context)?;
+                return Ok(true);
+            }
 
-            if status.success() {
-                // println!("Rendered PDF for {}", self.name);
-                self.convert_pdf_to_svg(output_dir)?;
+            options.pipeline.run(&context)?;
 
-                if delete_intermediates {
-                    self.cleanup_intermediate_files(output_dir)?;
+            if let Some(cached) = cached {
+                if let Some(dir) = cached.parent() {
+                    fs::create_dir_all(dir)?;
                 }
-            } else {
-                eprintln!("Failed to render PDF for {}", self.name);
+                fs::copy(&output_file, cached)?;
             }
+            Ok(false)
+        }
 
-            Ok(())
+        /// Where the render cache keeps this equation's output under
+        /// `options`, whose engine must already be resolved and overridden.
+        pub(crate) fn cache_file(&self, options: &RenderOptions) -> io::Result<Option<PathBuf>> {
+            let Some(dir) = &options.cache_dir else {
+                return Ok(None);
+            };
+            Ok(Some(dir.join(format!(
+                "{}.{}",
+                self.render_key(options)?,
+                options.format.extension()
+            ))))
+        }
+
+        /// The key simptui's render cache files this equation's output under
+        /// with `options`, as 64 hex digits. It covers the LaTeX source the
+        /// equation renders from, so the body (normalized, so `a+b` and
+        /// `a + b` agree), color, size, packages and template, plus the
+        /// engine, format, DPI and stages that shape the output. Equal hashes
+        /// mean equal inputs to the renderer, whatever the equation is named.
+        /// `Engine::Auto` counts as the engine `render_equations` would pick,
+        /// so this fails like it does when no TeX engine is installed.
+        pub fn content_hash(&self, options: &RenderOptions) -> io::Result<String> {
+            let mut engine = self.engine.unwrap_or(options.engine);
+            // MathML and the remote service don't go through a local engine
+            if self.engine.is_none()
+                && options.format != OutputFormat::MathML
+                && options.remote.is_none()
+            {
+                engine = engine.resolve()?;
+            }
+            let overridden;
+            let options = if engine != options.engine {
+                overridden = RenderOptions {
+                    engine,
+                    ..options.clone()
+                };
+                &overridden
+            } else {
+                options
+            };
+            self.render_key(options)
+        }
+
+        // `content_hash` for `options` as rendered with: engine resolved and
+        // overridden already
+        fn render_key(&self, options: &RenderOptions) -> io::Result<String> {
+            let normalized = Equation {
+                body: normalize_body(&self.body, options.normalize_styles),
+                ..self.clone()
+            };
+            Ok(cache_key(&normalized.generate_latex(options)?, options))
         }
 
-        fn convert_pdf_to_svg(&self, output_dir: &Path) -> io::Result<()> {
-            let check = Command::new("pdftocairo").arg("-version").output();
+        /// Converts the body to a block-level `<math>` element tinted with
+        /// `color`. Only the LaTeX subset latex2mathml understands is supported.
+        pub fn to_mathml(&self, color: &str) -> io::Result<String> {
+            let body = mathml_environments(&strip_labels(&self.math_body())).map_err(|name| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Cannot convert {} to MathML: the {} environment isn't supported",
+                        self.name, name
+                    ),
+                )
+            })?;
+            let mathml = latex_to_mathml(&body, DisplayStyle::Block).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Cannot convert {} to MathML: {}", self.name, e),
+                )
+            })?;
+            Ok(mathml.replacen("<math ", &format!("<math mathcolor=\"{}\" ", color), 1))
+        }
 
-            if check.is_err() {
-                eprintln!("Error: pdftocairo not found. Please install it to enable PDF to SVG conversion.");
-                return Ok(());
+        // Moves what's left of a failed render into `failed/` so a rerun
+        // starts clean and the log stays around for debugging.
+        fn quarantine(&self, output_dir: &Path) -> io::Result<()> {
+            let failed_dir = output_dir.join(FAILED_DIR_NAME);
+            fs::create_dir_all(&failed_dir)?;
+            for ext in ["tex", "log"] {
+                let file_name = format!("{}.{}", self.name, ext);
+                let file = output_dir.join(&file_name);
+                if file.exists() {
+                    fs::rename(&file, failed_dir.join(&file_name))?;
+                }
             }
+            fs::remove_file(output_dir.join(format!("{}.pdf", self.name))).ok();
+            Ok(())
+        }
 
-            let pdf_file = output_dir.join(format!("{}.pdf", self.name));
+        // Removes every file an unfinished render may have left behind.
+        fn discard(&self, output_dir: &Path, format: OutputFormat) {
+            for extension in ["tex", "log", "pdf", format.extension()] {
+                fs::remove_file(output_dir.join(format!("{}.{}", self.name, extension))).ok();
+            }
+        }
+
+        // Reads the box depth TeX reported in the log and moves the SVG down by
+        // that much (plus the padding) so its baseline meets the text's.
+        pub(crate) fn align_svg_to_baseline(&self, options: &RenderOptions) -> io::Result<()> {
+            let output_dir = &options.output_dir;
+            let log_file = output_dir.join(format!("{}.log", self.name));
             let svg_file = output_dir.join(format!("{}.svg", self.name));
 
-            if !pdf_file.exists() {
-                eprintln!("PDF file not found: {}", pdf_file.display());
+            let depth_pt = fs::read_to_string(&log_file)
+                .ok()
+                .and_then(|log| read_tex_depth(&log));
+            let Some(depth_pt) = depth_pt else {
+                warn!("No baseline information for {}", self.name);
+                return Ok(());
+            };
+            if !svg_file.exists() {
                 return Ok(());
             }
 
-            let status = Command::new("pdftocairo")
-                .arg("-svg")
-                .arg(&pdf_file)
-                .arg(&svg_file)
-                .status()?;
+            // TeX points to CSS pixels
+            let offset_px = -(depth_pt + options.padding as f64) * 96.0 / 72.27;
+            let svg = fs::read_to_string(&svg_file)?;
+            fs::write(&svg_file, set_vertical_align(&svg, offset_px))
+        }
 
-            if status.success() {
-                //println!("Converted {} to SVG", self.name);
-            } else {
-                eprintln!("Failed to convert {} to SVG", self.name);
+        // Whatever `retention` lets go of after a successful render
+        pub(crate) fn cleanup_intermediate_files(
+            &self,
+            output_dir: &Path,
+            format: OutputFormat,
+            retention: Retention,
+        ) -> io::Result<()> {
+            if retention == Retention::KeepAll {
+                return Ok(());
             }
+            let tex_file = output_dir.join(format!("{}.tex", self.name));
+            let pdf_file = output_dir.join(format!("{}.pdf", self.name));
 
+            fs::remove_file(tex_file).ok();
+            fs::remove_file(output_dir.join(format!("{}.log", self.name))).ok();
+            if format != OutputFormat::Pdf && retention != Retention::KeepPdf {
+                fs::remove_file(pdf_file).ok();
+            }
+            trace!("Intermediate files deleted for {}", self.name);
             Ok(())
         }
 
-        fn cleanup_intermediate_files(&self, output_dir: &Path) -> io::Result<()> {
-            let tex_file = output_dir.join(format!("{}.tex", self.name));
-            let pdf_file = output_dir.join(format!("{}.pdf", self.name));
+        fn color<'a>(&'a self, options: &'a RenderOptions) -> &'a str {
+            self.color.as_deref().unwrap_or(&options.color)
+        }
 
-            fs::remove_file(tex_file).ok();
-            fs::remove_file(pdf_file).ok();
+        fn tex_body(&self, options: &RenderOptions) -> String {
+            let body = match options.keep_labels {
+                true => self.math_body(),
+                false => strip_labels(&self.math_body()),
+            };
+            match options.keep_colors {
+                true => body,
+                false => strip_colors(&body),
+            }
+        }
 
-            //println!("Intermediate files deleted for {}", self.name);
-            Ok(())
+        // `tex_body` in its math mode
+        fn tex_math(&self, options: &RenderOptions) -> String {
+            let (open, close) = self.math_wrap().delimiters();
+            format!("{}{}{}", open, self.tex_body(options), close)
         }
 
-        fn generate_latex(&self, color: &str) -> String {
-            let color_code = color.trim_start_matches('#');
-            format!(
-                r#"\documentclass[border=1pt]{{standalone}}
-                \usepackage{{amsmath}}
-                \usepackage{{xfrac}}
-                \usepackage{{gfsneohellenicot}}
-                \usepackage{{xcolor}}
-                \definecolor{{equationcolor}}{{HTML}}{{{}}}
-                \begin{{document}}
-                \setbox0\hbox{{\Large \textcolor{{equationcolor}}{{$ {} $}}}}
-                \dimen0=12mm
+        pub(crate) fn generate_latex(&self, options: &RenderOptions) -> io::Result<String> {
+            if let Some(template) = &options.template {
+                let template = fs::read_to_string(template)?;
+                return Ok(template
+                    .replace(
+                        "{{preamble}}",
+                        &latex_preamble(options, self.color(options), &self.packages, &[self]),
+                    )
+                    .replace("{{color}}", self.color(options).trim_start_matches('#'))
+                    .replace("{{size}}", &self.size.unwrap_or(options.size).latex())
+                    .replace("{{body}}", &self.tex_body(options)));
+            }
+
+            let depth_report = if options.baseline_align {
+                format!(r"\typeout{{{}\the\dp0}}", DEPTH_MARKER)
+            } else {
+                String::new()
+            };
+            let bounding = match options.bounding {
+                BoundingMode::Uniform => {
+                    r#"\dimen0=12mm
                 \ifdim\ht0<\dimen0
                 \ht0=\dimen0
                 \fi
                 \ifdim\dp0<5mm
                 \dp0=5mm
-                \fi
-                \box0
+                \fi"#
+                }
+                BoundingMode::Tight => "",
+            };
+            // A filled background brings its own padding, so the page ends
+            // where the fill does
+            let (border, shipout) = match options.fill {
+                Fill::Transparent => (options.padding, r"\box0".to_string()),
+                Fill::Color(_) => (
+                    0.0,
+                    format!(
+                        r"\tikz[baseline=0pt]\node[anchor=base, inner sep={}pt, outer sep=0pt, rounded corners={}pt, fill=equationbackground]{{\box0}};",
+                        options.padding, options.corner_radius
+                    ),
+                ),
+            };
+            Ok(format!(
+                r#"\documentclass[border={}pt]{{standalone}}
+                {}
+                \begin{{document}}
+                \setbox0\hbox{{{} \textcolor{{equationcolor}}{{{}}}}}
+                {}
+                {}
+                {}
                 \end{{document}}"#,
-                color_code, self.body
+                border,
+                latex_preamble(options, self.color(options), &self.packages, &[self]),
+                self.size.unwrap_or(options.size).latex(),
+                self.tex_math(options),
+                bounding,
+                depth_report,
+                shipout
+            ))
+        }
+    }
+
+    // The document's macros go in too, once each, but not those one of
+    // `equations` defines itself, which TeX won't take twice
+    fn latex_preamble(
+        options: &RenderOptions,
+        color: &str,
+        packages: &[String],
+        equations: &[&Equation],
+    ) -> String {
+        let color_code = color.trim_start_matches('#');
+        let packages: String = packages
+            .iter()
+            .map(|package| format!("\\usepackage{{{}}}\n", package))
+            .collect();
+        let mut defined: Vec<String> = equations
+            .iter()
+            .flat_map(|eq| document_macros(&eq.body))
+            .map(|m| m.name)
+            .collect();
+        let mut macros = String::new();
+        for m in &options.macros {
+            if !defined.contains(&m.name) {
+                macros.push_str(&format!("\n{}", m.definition));
+                defined.push(m.name.clone());
+            }
+        }
+        let background = match options.fill {
+            Fill::Transparent => String::new(),
+            Fill::Color(background) => format!(
+                "\n\\usepackage{{tikz}}\n\\definecolor{{equationbackground}}{{HTML}}{{{}}}",
+                background.to_hex().trim_start_matches('#')
+            ),
+        };
+        format!(
+            r#"\usepackage{{amsmath}}
+                \usepackage{{xfrac}}
+                {}
+                {}\usepackage{{xcolor}}
+                \definecolor{{equationcolor}}{{HTML}}{{{}}}{}{}"#,
+            options.font.preamble(options.engine),
+            packages,
+            color_code,
+            background,
+            macros
+        )
+    }
+
+    /// The key of the first `\label{..}` in `body`.
+    pub(crate) fn find_label(body: &str) -> Option<&str> {
+        let start = body.find("\\label{")? + "\\label{".len();
+        let len = body[start..].find('}')?;
+        Some(body[start..start + len].trim()).filter(|label| !label.is_empty())
+    }
+
+    fn strip_labels(body: &str) -> String {
+        let label = Regex::new(r"\\label\{[^}]*\}").unwrap();
+        label.replace_all(body, "").into_owned()
+    }
+
+    /// Numbers the labelled equations 1, 2, ... in order and replaces
+    /// `\eqref{key}` with `(n)` and `\ref{key}` with `n` in all bodies, as a
+    /// standalone render has no document to look them up in. Unknown keys
+    /// are left alone.
+    pub(crate) fn resolve_references(equations: &[Equation]) -> Vec<Equation> {
+        let mut numbers: HashMap<&str, usize> = HashMap::new();
+        for eq in equations {
+            if let Some(label) = &eq.label {
+                let next = numbers.len() + 1;
+                numbers.entry(label).or_insert(next);
+            }
+        }
+        let reference = Regex::new(r"\\(eq)?ref\{([^}]*)\}").unwrap();
+        equations
+            .iter()
+            .map(|eq| {
+                let body = reference.replace_all(&eq.body, |cap: &regex::Captures| {
+                    let key = cap[2].trim();
+                    match (numbers.get(key), cap.get(1)) {
+                        (Some(n), Some(_)) => format!("({})", n),
+                        (Some(n), None) => n.to_string(),
+                        (None, _) => {
+                            warn!("{}: unknown reference '{}'", eq.name, key);
+                            cap[0].to_string()
+                        }
+                    }
+                });
+                Equation {
+                    body: body.into_owned(),
+                    ..eq.clone()
+                }
+            })
+            .collect()
+    }
+
+    /// The error messages of a TeX log: each `! ...` line up to the `l.<n>`
+    /// line showing where it happened.
+    pub fn tex_log_errors(log: &str) -> String {
+        let mut errors = Vec::new();
+        let mut in_error = false;
+        for line in log.lines() {
+            if line.starts_with('!') {
+                in_error = true;
+            }
+            if in_error {
+                errors.push(line);
+                in_error = !line.starts_with("l.");
+            }
+        }
+        errors.join("\n")
+    }
+
+    fn read_tex_depth(log: &str) -> Option<f64> {
+        let start = log.find(DEPTH_MARKER)? + DEPTH_MARKER.len();
+        let value = &log[start..];
+        let end = value.find("pt")?;
+        value[..end].trim().parse().ok()
+    }
+
+    // Everything that changes the rendered bytes goes into the key. Options
+    // that only change the LaTeX, like `keep_labels` and `keep_colors`, are
+    // in it through `latex_source`
+    fn cache_key(latex_source: &str, options: &RenderOptions) -> String {
+        sha256_hex(
+            format!(
+                "{}\0{}\0{}\0{}\0{}\0{}",
+                options.engine,
+                options.format.extension(),
+                options.dpi,
+                options.baseline_align,
+                options.pipeline.cache_key(),
+                latex_source
             )
+            .as_bytes(),
+        )
+    }
+
+    pub(crate) fn missing_tool(tool: &str, error: io::Error) -> io::Error {
+        if error.kind() == io::ErrorKind::NotFound {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} not found, please install it", tool),
+            )
+        } else {
+            error
+        }
+    }
+
+    // One document, so per-equation engines can't apply; packages are merged
+    fn generate_single_pdf_latex(equations: &[&Equation], options: &RenderOptions) -> String {
+        let mut packages: Vec<String> = Vec::new();
+        let mut pages = String::new();
+        for eq in equations {
+            for package in &eq.packages {
+                if !packages.contains(package) {
+                    packages.push(package.clone());
+                }
+            }
+            pages.push_str(&format!(
+                r#"
+                \begin{{center}}{{\large\ttfamily\detokenize{{{}}}}}\end{{center}}
+                \vspace*{{1cm}}
+                \begin{{center}}{} \textcolor{}{{{}}}\end{{center}}
+                \newpage"#,
+                eq.name,
+                match &eq.color {
+                    Some(color) => format!("[HTML]{{{}}}", color.trim_start_matches('#')),
+                    None => "{equationcolor}".to_string(),
+                },
+                eq.size.unwrap_or(options.size).latex(),
+                eq.tex_math(options)
+            ));
+        }
+        format!(
+            r#"\documentclass{{article}}
+                {}
+                \pagestyle{{empty}}
+                \begin{{document}}{}
+                \end{{document}}"#,
+            latex_preamble(options, &options.color, &packages, equations),
+            pages
+        )
+    }
+
+    /// Compiles all active equations into `equations.pdf`, one labeled page each.
+    pub fn render_single_pdf(
+        equations: &[Equation],
+        options: &RenderOptions,
+    ) -> io::Result<RenderReport> {
+        if options.remote.is_some() {
+            return Err(remote_format_error(OutputFormat::Pdf));
+        }
+        let options = &RenderOptions {
+            engine: options.engine.resolve()?,
+            ..options.clone()
+        };
+        options.font.check(options.engine)?;
+        render_single_pdf_with(equations, options, options.backend())
+    }
+
+    fn render_single_pdf_with(
+        equations: &[Equation],
+        options: &RenderOptions,
+        backend: &dyn RenderBackend,
+    ) -> io::Result<RenderReport> {
+        let mut report = RenderReport::default();
+        let active_equations: Vec<&Equation> = equations.iter().filter(|eq| eq.active).collect();
+        let inactive = equations.len() - active_equations.len();
+        if inactive > 0 {
+            info!("Skipping {} inactive equation(s)", inactive);
+        }
+        if active_equations.is_empty() {
+            return Ok(report);
+        }
+
+        let output_dir = options.output_dir.as_path();
+        fs::create_dir_all(output_dir)?;
+
+        let tex_file_path = output_dir.join(format!("{}.tex", SINGLE_PDF_NAME));
+        // The sheet's equations as a whole, under the sheet's name
+        let map = match options.source_map {
+            true => SourceMap::of(&Equation::new(true, SINGLE_PDF_NAME, ""), options).tex_comment(),
+            false => String::new(),
+        };
+        fs::write(
+            &tex_file_path,
+            map + &generate_single_pdf_latex(&active_equations, options),
+        )?;
+
+        // The whole sheet is one document, so it succeeds or fails as a unit;
+        // it gets the time its equations would have together
+        let timeout = options.timeout.map(|base| {
+            active_equations
+                .iter()
+                .map(|eq| compile_timeout(&eq.body, base))
+                .sum()
+        });
+        match backend.compile(options.engine, &tex_file_path, output_dir, false, timeout) {
+            Ok(true) => {
+                if options.retention != Retention::KeepAll {
+                    fs::remove_file(&tex_file_path).ok();
+                }
+                // The sheet has no pipeline to run it, but still gets scrubbed
+                if options.pipeline.names().contains(&"scrub") {
+                    scrub_file(&output_dir.join(format!("{}.pdf", SINGLE_PDF_NAME)))?;
+                }
+                report.rendered.push(SINGLE_PDF_NAME.to_string());
+            }
+            Ok(false) | Err(_) if interrupted() => {
+                fs::remove_file(&tex_file_path).ok();
+                report.interrupted.push(SINGLE_PDF_NAME.to_string());
+            }
+            Ok(false) => report.failed.push(RenderFailure {
+                name: SINGLE_PDF_NAME.to_string(),
+                error: format!("LaTeX compilation failed for {}.tex", SINGLE_PDF_NAME),
+            }),
+            Err(e) => report.failed.push(RenderFailure {
+                name: SINGLE_PDF_NAME.to_string(),
+                error: e.to_string(),
+            }),
         }
+        Ok(report)
     }
 
+    // Blocking stdin prompt for CLI mode; it can't be used inside the raw-mode TUI
     pub fn ask_confirmation(prompt: &str) -> bool {
         loop {
             print!("{} (y/n): ", prompt);
@@ -161,101 +1182,468 @@ mod core {
         }
     }
 
+    /// Renders every active equation and reports which ones failed. Only
+    /// problems that affect the whole batch (e.g. an unwritable output
+    /// directory for `baseline.css`) are returned as errors.
     pub fn render_equations(
         equations: &[Equation],
-        output_dir: &Path,
-        color: &str,
-        delete_intermediates: bool,
-    ) -> io::Result<()> {
+        options: &RenderOptions,
+    ) -> io::Result<RenderReport> {
+        let mut options = options.clone();
+        if options.format == OutputFormat::MathML {
+            // Converted here, without TeX or the service
+        } else if options.remote.is_some() {
+            // The service picks and checks its own engine
+            if options.format != OutputFormat::Svg {
+                return Err(remote_format_error(options.format));
+            }
+        } else {
+            // Probe for an engine once rather than per equation
+            if options.engine == Engine::Auto {
+                options.engine = options.engine.resolve()?;
+                info!("Using {} (first TeX engine found)", options.engine);
+            }
+            options.font.check(options.engine)?;
+        }
+        render_equations_with(equations, &options, options.backend())
+    }
+
+    /// `render_equations` with the TeX and conversion steps delegated to
+    /// `backend`. Identical equations are rendered once per batch even
+    /// without a `cache_dir`.
+    pub fn render_equations_with(
+        equations: &[Equation],
+        options: &RenderOptions,
+        backend: &dyn RenderBackend,
+    ) -> io::Result<RenderReport> {
+        let resolved = resolve_references(equations);
+        let split;
+        let equations = if options.split_lines {
+            split = split_equations(&resolved);
+            &split
+        } else {
+            &resolved
+        };
+        let active = equations.iter().filter(|eq| eq.active).count();
+        let hooks = &options.hooks;
+        let mut batch_status = match options.status_file {
+            true => Some(BatchStatus::start(
+                &options.output_dir,
+                options.source_name.as_deref(),
+                active,
+            )?),
+            false => None,
+        };
+        hooks.before_batch(&options.output_dir, options.source_name.as_deref(), active)?;
+        if options.layout == OutputLayout::SinglePdf {
+            let report = render_single_pdf_with(equations, options, backend)?;
+            hooks.after_batch(&options.output_dir, &report);
+            if let Some(status) = &mut batch_status {
+                status.end(&report);
+            }
+            return Ok(report);
+        }
+        // Kept in the persistent cache only; a batch cache dies with the batch
+        let persistent = options.cache_dir.is_some();
+        let mut progress = match &options.cache_dir {
+            Some(dir) => BatchProgress::load(dir, &options.output_dir),
+            None => BatchProgress::default(),
+        };
+        if !options.resume {
+            progress.clear()?;
+        }
+        let batch_cache = match options.cache_dir {
+            Some(_) => None,
+            None => Some(tempfile::tempdir()?),
+        };
+        let options = &RenderOptions {
+            cache_dir: options
+                .cache_dir
+                .clone()
+                .or_else(|| batch_cache.as_ref().map(|dir| dir.path().to_path_buf())),
+            ..options.clone()
+        };
+
         let active_equations: Vec<&Equation> = equations.iter().filter(|eq| eq.active).collect();
-        let bar = ProgressBar::new(active_equations.len() as u64);
+        let mut meter = BatchMeter::new(active_equations.len());
 
-        bar.set_style(
-            ProgressStyle::default_bar()
-                .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} {msg}")
-                .expect("Error setting template")
-                .progress_chars("#>-"),
-        );
+        let mut report = RenderReport::default();
+        let mut savings = SvgSavings::default();
+        let mut baseline_css = String::new();
+        let mut manifest = Manifest::new();
+        // Routed outputs are only found through the manifest
+        let use_manifest = options.hash_names
+            || options.naming != OutputNaming::Name
+            || !options.routes.is_empty();
+        for (i, eq) in active_equations.iter().enumerate() {
+            if interrupted() {
+                report.interrupted = active_equations[i..]
+                    .iter()
+                    .map(|eq| eq.name.clone())
+                    .collect();
+                break;
+            }
+            meter.begin(&eq.name);
+            if let Some(status) = &mut batch_status {
+                status.begin(&eq.name);
+            }
+            // Rendered under its output name; reported under its own
+            let target = match output_name(eq, i, options) {
+                Ok(name) => Equation {
+                    origin: eq
+                        .origin
+                        .clone()
+                        .or_else(|| (name != eq.name).then(|| eq.name.clone())),
+                    name,
+                    ..(*eq).clone()
+                },
+                Err(e) => {
+                    if let Some(status) = &mut batch_status {
+                        status.finish(&eq.name, "failed", Some(&e.to_string()));
+                    }
+                    report.failed.push(RenderFailure {
+                        name: eq.name.clone(),
+                        error: e.to_string(),
+                    });
+                    meter.finish(false, &report);
+                    continue;
+                }
+            };
+            let route = route_of(&options.routes, eq);
+            let routed;
+            let eq_options = match route {
+                Some(dir) => {
+                    routed = RenderOptions {
+                        output_dir: options.output_dir.join(dir),
+                        ..options.clone()
+                    };
+                    &routed
+                }
+                None => options,
+            };
+            let key = progress_key(&target, route, options)?;
+            if let Some(done) = progress.completed(eq.file_stem(), &key, &options.output_dir) {
+                debug!("{}: finished by an earlier run", eq.name);
+                baseline_css.push_str(done.css.as_deref().unwrap_or_default());
+                if use_manifest {
+                    manifest.insert(eq.file_stem().to_string(), done.file.clone());
+                }
+                report.resumed += 1;
+                report.rendered.push(eq.name.clone());
+                hooks.after_equation(
+                    &options.output_dir,
+                    &eq.name,
+                    Some(&done.file),
+                    "resumed",
+                    None,
+                );
+                if let Some(status) = &mut batch_status {
+                    status.finish(&eq.name, "resumed", None);
+                }
+                meter.finish(false, &report);
+                continue;
+            }
+            let compiled = match render_one(&target, eq_options, backend, &mut savings) {
+                Ok((file_name, css, cached)) => {
+                    // From here on relative to `options.output_dir`
+                    let file_name = routed_file(route, file_name);
+                    baseline_css.push_str(css.as_deref().unwrap_or_default());
+                    report.cache_hits += usize::from(cached);
+                    report.rendered.push(eq.name.clone());
+                    let status = if cached { "cached" } else { "rendered" };
+                    hooks.after_equation(
+                        &options.output_dir,
+                        &eq.name,
+                        Some(&file_name),
+                        status,
+                        None,
+                    );
+                    if let Some(batch) = &mut batch_status {
+                        batch.finish(&eq.name, status, None);
+                    }
+                    if use_manifest {
+                        manifest.insert(eq.file_stem().to_string(), file_name.clone());
+                    }
+                    if persistent {
+                        let completed = Completed {
+                            key,
+                            file: file_name,
+                            css,
+                        };
+                        if let Err(e) = progress.record(eq.file_stem(), completed) {
+                            warn!("Can't record the progress of the batch: {}", e);
+                        }
+                    }
+                    !cached
+                }
+                // Most likely the TeX child died from the same Ctrl-C; don't
+                // blame the equation, drop what it left half-written
+                Err(_) if interrupted() => {
+                    target.discard(&eq_options.output_dir, options.format);
+                    report.interrupted = active_equations[i..]
+                        .iter()
+                        .map(|eq| eq.name.clone())
+                        .collect();
+                    break;
+                }
+                Err(e) => {
+                    warn!("Failed to render {}: {}", eq.name, e);
+                    if options.retention == Retention::DeleteAll {
+                        target.discard(&eq_options.output_dir, options.format);
+                    } else {
+                        target.quarantine(&eq_options.output_dir).ok();
+                    }
+                    hooks.after_equation(
+                        &options.output_dir,
+                        &eq.name,
+                        None,
+                        "failed",
+                        Some(&e.to_string()),
+                    );
+                    if let Some(status) = &mut batch_status {
+                        status.finish(&eq.name, "failed", Some(&e.to_string()));
+                    }
+                    report.failed.push(RenderFailure {
+                        name: eq.name.clone(),
+                        error: e.to_string(),
+                    });
+                    if options.fail_fast {
+                        break;
+                    }
+                    true
+                }
+            };
+            meter.finish(compiled, &report);
+        }
+
+        if !baseline_css.is_empty() {
+            fs::write(options.output_dir.join(BASELINE_CSS_NAME), baseline_css)?;
+        }
+        if use_manifest {
+            update_manifest(&options.output_dir, manifest)?;
+        }
+
+        hooks.after_batch(&options.output_dir, &report);
+        if let Some(status) = &mut batch_status {
+            status.end(&report);
+        }
 
-        for eq in active_equations {
-            bar.set_message(format!("Rendering: {}", eq.name));
-            eq.render(output_dir, color, delete_intermediates)?;
-            bar.inc(1);
+        // Kept after failures too, so a rerun only retries those
+        if !report.is_interrupted() && report.failed.is_empty() {
+            progress.clear()?;
         }
+        meter.end(&report);
+        if options.optimize_svg {
+            println!(
+                "Optimized SVGs: {:.1} KiB -> {:.1} KiB ({:.0}% smaller)",
+                savings.before as f64 / 1024.0,
+                savings.after as f64 / 1024.0,
+                savings.percent()
+            );
+        }
+        Ok(report)
+    }
+
+    // Identifies what a finished equation's output came from, beyond what
+    // `cache_key` covers
+    fn progress_key(
+        eq: &Equation,
+        route: Option<&Path>,
+        options: &RenderOptions,
+    ) -> io::Result<String> {
+        let engine = eq.engine.unwrap_or(options.engine);
+        Ok(sha256_hex(
+            format!(
+                "{}\0{}\0{}\0{}\0{}",
+                engine,
+                options.optimize_svg,
+                options.hash_names,
+                route.unwrap_or(Path::new("")).display(),
+                cache_key(&eq.generate_latex(options)?, options)
+            )
+            .as_bytes(),
+        ))
+    }
 
-        bar.finish_with_message("Rendering complete!");
-        Ok(())
+    // File stem of the `index`th active equation under `options.naming`
+    fn output_name(eq: &Equation, index: usize, options: &RenderOptions) -> io::Result<String> {
+        Ok(match options.naming {
+            OutputNaming::Name => eq.file_stem().to_string(),
+            OutputNaming::Hash => content_hash(eq.generate_latex(options)?.as_bytes()),
+            OutputNaming::Number => format!("{:03}", index + 1),
+            OutputNaming::FileIndex => {
+                let file = options.source_name.as_deref().unwrap_or(SINGLE_PDF_NAME);
+                Equation::sanitize_filename(&format!("{}_{}", file, index + 1))
+            }
+        })
     }
 
-    pub fn read_file(path: &Path) -> io::Result<String> {
-        let mut file = File::open(path)?;
-        let mut content = String::new();
-        file.read_to_string(&mut content)?;
-        Ok(content)
+    // Renders and post-processes a single equation, returning the output
+    // file name, its baseline.css rule when baseline alignment is on and
+    // whether it came from the cache.
+    fn render_one(
+        eq: &Equation,
+        options: &RenderOptions,
+        backend: &dyn RenderBackend,
+        savings: &mut SvgSavings,
+    ) -> io::Result<(String, Option<String>, bool)> {
+        let cached = eq.render_cached(options, backend)?;
+        let extension = options.format.extension();
+        let svg_file = options.output_dir.join(format!("{}.svg", eq.name));
+        if options.optimize_svg && options.format == OutputFormat::Svg && svg_file.exists() {
+            savings.add(optimize_svg_file(&svg_file)?);
+        }
+        // Hashed before the source map goes in, so moving an equation to
+        // another line doesn't rename its output
+        let file_name = if options.hash_names {
+            hash_output_file(&options.output_dir, &eq.name, extension)?
+        } else {
+            format!("{}.{}", eq.name, extension)
+        };
+        // Added after the cache took its copy, which other equations may share
+        let output = options.output_dir.join(&file_name);
+        if options.source_map && options.format == OutputFormat::Svg && output.exists() {
+            let svg = fs::read_to_string(&output)?;
+            fs::write(
+                &output,
+                add_svg_source_map(&svg, &SourceMap::of(eq, options)),
+            )?;
+        }
+        if !options.baseline_align {
+            return Ok((file_name, None, cached));
+        }
+        let offset = fs::read_to_string(options.output_dir.join(&file_name))
+            .ok()
+            .and_then(|svg| svg_vertical_align(&svg));
+        let css = offset.map(|offset| {
+            format!(
+                "img[src$=\"{}\"] {{ vertical-align: {:.2}px; }}\n",
+                file_name, offset
+            )
+        });
+        Ok((file_name, css, cached))
     }
 
     pub fn read_csv_file(path: &Path) -> io::Result<Vec<Equation>> {
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
-        let mut equations = Vec::new();
-        let mut name_count: HashMap<String, usize> = HashMap::new();
+        Ok(parse_csv(&load_source(path)?))
+    }
+
+    /// `Active,Body,Name` rows after a header line, with an optional fourth
+    /// column of `engine=.. packages=.. size=.. color=.. tags=..` options. Fields
+    /// holding commas are quoted.
+    pub fn parse_csv(content: &str) -> Vec<Equation> {
+        let mut equations = csv_equations(content);
+        unique_names(&mut equations, DuplicateNames::Counter);
+        equations
+    }
 
-        for line in reader.lines().skip(1) {
-            let line = line?;
-            let parts: Vec<&str> = line.split(',').collect();
+    // The rows with their names as written, repeats and all
+    pub(crate) fn csv_equations(content: &str) -> Vec<Equation> {
+        let mut equations = Vec::new();
+        let columns = content.lines().next().map_or(3, csv_columns);
+        let mut repaired = 0;
+        for (index, line) in content.lines().enumerate().skip(1) {
+            let (parts, repair) = csv_row(line, columns);
             if parts.len() >= 3 {
-                let active = parts[0].trim().eq_ignore_ascii_case("yes");
-                let body = parts[1].trim();
-                let base_name = if parts[2].trim().is_empty() {
+                repaired += usize::from(repair);
+                let active = parts[0].eq_ignore_ascii_case("yes");
+                let name = if parts[2].is_empty() {
                     "default_equation"
                 } else {
-                    parts[2].trim()
+                    &parts[2]
                 };
-                let mut name = base_name.to_string();
-
-                let count = name_count.entry(name.clone()).or_insert(0);
-                if *count > 0 {
-                    name = format!("{}_{}", base_name, count);
+                let mut equation = Equation::new(active, name, &parts[1]);
+                if let Some(options) = parts.get(3) {
+                    apply_options(&mut equation, options);
                 }
-                *count += 1;
-
-                let equation = Equation::new(active, &name, body);
+                equation.span = Some(SourceSpan {
+                    start_line: index + 1,
+                    end_line: index + 1,
+                });
                 equations.push(equation);
             }
         }
-        Ok(equations)
-    }
-
-    pub fn detect_file_type(path: &Path) -> &'static str {
-        match path.extension().and_then(|s| s.to_str()) {
-            Some("csv") => "csv",
-            Some("md") | Some("markdown") => "markdown",
-            _ => "unknown",
+        if repaired > 0 {
+            warn!(
+                "Repaired {} CSV row(s) with unquoted commas in the body",
+                repaired
+            );
         }
+        equations
     }
 
-    pub fn parse_markdown(content: &str) -> Vec<Equation> {
-        let mut equations = Vec::new();
-        let mut name_count: HashMap<String, usize> = HashMap::new();
-        let re = Regex::new(r"(?s)(%%(yes|no)?%%)?[\n\r]*\$\$[\n\r]*(.*?)\$\$[\n\r]*(%%(.*?)%%)?")
-            .unwrap();
+    /// Rewrites the rows of the CSV file `path` in the order of `equations`,
+    /// which must have been read from it. The header stays first; rows that
+    /// aren't equations follow the equation rows. The file is written back as
+    /// UTF-8 with LF line endings.
+    pub fn reorder_csv_file(path: &Path, equations: &[Equation]) -> io::Result<()> {
+        let content = load_source(path)?;
+        let lines: Vec<&str> = content.lines().collect();
+        let Some((header, rows)) = lines.split_first() else {
+            return Ok(());
+        };
+        // Spans are 1-based file lines, so row `i` sits on line `i + 2`
+        let line_of = |eq: &Equation| eq.span.map(|span| span.start_line - 2);
+        let ordered: Vec<usize> = equations
+            .iter()
+            .filter_map(line_of)
+            .filter(|&i| i < rows.len())
+            .collect();
+
+        let mut output = vec![*header];
+        output.extend(ordered.iter().map(|&i| rows[i]));
+        output.extend(
+            (0..rows.len())
+                .filter(|i| !ordered.contains(i))
+                .map(|i| rows[i]),
+        );
+        fs::write(path, output.join("\n") + "\n")
+    }
 
-        for cap in re.captures_iter(content) {
-            let body = cap.get(3).unwrap().as_str().trim();
-            let active = cap.get(2).map_or(true, |m| m.as_str() == "yes");
-            let base_name = cap.get(5).map_or("default_equation", |m| m.as_str());
-            let mut name = base_name.to_string();
+    pub fn load_equations(path: &Path) -> io::Result<Vec<Equation>> {
+        Extractor::default().load(path)
+    }
 
-            let count = name_count.entry(name.clone()).or_insert(0);
-            if *count > 0 {
-                name = format!("{}_{}", base_name, count);
+    /// Name of the built-in format `path` is in, as `ParserRegistry::detect`
+    /// sees its first `SNIFF_BYTES`, or `unknown`, as binary files are.
+    /// Files are read once per modification; one that can't be read goes by
+    /// its extension.
+    pub fn detect_file_type(path: &Path) -> &'static str {
+        static BUILT_IN: OnceLock<ParserRegistry> = OnceLock::new();
+        static SNIFFED: OnceLock<Mutex<HashMap<PathBuf, (SystemTime, &'static str)>>> =
+            OnceLock::new();
+        let parsers = BUILT_IN.get_or_init(ParserRegistry::default);
+        let by_extension = || {
+            parsers
+                .for_path(path)
+                .map_or("unknown", |parser| parser.name())
+        };
+        let Ok(modified) = fs::metadata(path).and_then(|metadata| metadata.modified()) else {
+            return by_extension();
+        };
+        let sniffed = SNIFFED.get_or_init(Mutex::default);
+        if let Some(&(at, name)) = sniffed.lock().unwrap().get(path) {
+            if at == modified {
+                return name;
             }
-            *count += 1;
-
-            let equation = Equation::new(active, &name, body);
-            equations.push(equation);
         }
+        let name = match load_source_start(path, SNIFF_BYTES) {
+            Ok(Some(content)) => parsers
+                .detect(path, &content)
+                .map_or("unknown", |detection| detection.parser.name()),
+            Ok(None) => "unknown",
+            Err(_) => by_extension(),
+        };
+        let mut sniffed = sniffed.lock().unwrap();
+        if sniffed.len() >= MAX_SNIFFED {
+            sniffed.clear();
+        }
+        sniffed.insert(path.to_path_buf(), (modified, name));
+        name
+    }
 
-        equations
+    /// Equations in the built-in `$$` syntax; see `Extractor` for custom rules.
+    pub fn parse_markdown(content: &str) -> Vec<Equation> {
+        Extractor::default().parse(content)
     }
 }
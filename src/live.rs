@@ -0,0 +1,391 @@
+use crate::events::AppEvent;
+use notify::event::EventKind;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use resvg::tiny_skia::{Color, Pixmap, Transform};
+use resvg::usvg;
+use simptui::{
+    copy_png, load_source, render_png, tex_log_errors, Document, Equation, ParserRegistry,
+    RenderOptions, RenderPipeline,
+};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Instant, SystemTime};
+
+// Pixels per SVG pixel in the preview window
+const WINDOW_SCALE: f32 = 3.0;
+// Size the preview window opens at; it can be resized
+#[cfg(all(feature = "preview-window", not(target_os = "macos")))]
+const WINDOW_SIZE: (usize, usize) = (960, 320);
+
+/// Live template development: watches the profile's template file so the
+/// selected equation can be rendered again after every change.
+pub struct LiveTemplate {
+    changes: Receiver<()>,
+    _watcher: RecommendedWatcher,
+}
+
+/// One equation rendered on a background thread for the preview pane.
+pub struct PreviewRender {
+    running: Option<Receiver<Result<(), String>>>, // Render in progress
+    events: Sender<AppEvent>,                      // Told when it is done
+}
+
+/// One equation rendered to PNG and put on the clipboard in the background.
+pub struct ClipboardCopy {
+    running: Option<Receiver<Result<String, String>>>, // Name of the equation, when done
+    events: Sender<AppEvent>,                          // Told when it is done
+}
+
+/// A file read and parsed on a background thread, so a huge one doesn't
+/// freeze the interface.
+pub struct FileLoad {
+    running: Option<(PathBuf, Instant, Receiver<io::Result<LoadedFile>>)>, // File and start
+    events: Sender<AppEvent>,                                              // Told when it is done
+}
+
+/// The selected equation in a native window of its own, for terminals that
+/// can't draw images, redrawn whenever the selection or its render changes.
+/// The window runs on a thread of its own, so it needs the `preview-window`
+/// feature and a platform other than macOS, which keeps windows on the main
+/// thread.
+pub struct PreviewWindow {
+    frames: Sender<Frame>,                        // To the window's thread
+    window: JoinHandle<()>,                       // Ends when the window is closed
+    shown: Option<(PathBuf, Option<SystemTime>)>, // SVG in the window, and its age
+}
+
+// An equation drawn for the preview window, one 0RGB pixel per `u32`
+#[cfg_attr(
+    not(all(feature = "preview-window", not(target_os = "macos"))),
+    allow(dead_code)
+)]
+struct Frame {
+    title: String,
+    width: usize,
+    height: usize,
+    pixels: Vec<u32>,
+}
+
+/// What a file turned out to hold.
+pub enum LoadedFile {
+    Document(Document),
+    Text(String), // No equations in a format we know
+}
+
+/// Reads `path` and parses it with `parsers`.
+pub fn read_file(parsers: &ParserRegistry, path: &Path) -> io::Result<LoadedFile> {
+    let content = load_source(path)?;
+    Ok(match parsers.parse_document(path, &content) {
+        Some(document) => LoadedFile::Document(document),
+        None => LoadedFile::Text(content),
+    })
+}
+
+impl LiveTemplate {
+    pub fn watch(template: &Path) -> Option<Self> {
+        let template = template.canonicalize().ok()?;
+        let (sender, changes) = mpsc::channel();
+        let target = template.clone();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            let Ok(event) = res else {
+                return;
+            };
+            let written = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_));
+            if written && event.paths.contains(&target) {
+                sender.send(()).ok();
+            }
+        })
+        .ok()?;
+        // Editors often save by replacing the file, so watch its directory
+        watcher
+            .watch(template.parent()?, RecursiveMode::NonRecursive)
+            .ok()?;
+        Some(LiveTemplate {
+            changes,
+            _watcher: watcher,
+        })
+    }
+
+    /// Whether the template was saved since the last call.
+    pub fn changed(&self) -> bool {
+        let mut changed = false;
+        while self.changes.try_recv().is_ok() {
+            changed = true;
+        }
+        changed
+    }
+}
+
+impl PreviewRender {
+    pub fn new(events: Sender<AppEvent>) -> Self {
+        PreviewRender {
+            running: None,
+            events,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Renders `equation` in the background, replacing its output file.
+    /// `[[hook]]` stages are left out, as they'd run on every keystroke.
+    pub fn render(&mut self, equation: &Equation, mut options: RenderOptions) {
+        options.pipeline = RenderPipeline::default();
+        let mut equation = equation.clone();
+        equation.active = true; // Inactive equations are skipped by `render`
+        let (sender, receiver) = mpsc::channel();
+        let events = self.events.clone();
+        thread::spawn(move || {
+            let result = equation.render(&options).map_err(|e| {
+                let errors = compile_errors(&options.output_dir, equation.file_stem());
+                if errors.is_empty() {
+                    e.to_string()
+                } else {
+                    errors
+                }
+            });
+            sender.send(result).ok();
+            events.send(AppEvent::RenderProgress).ok();
+        });
+        self.running = Some(receiver);
+    }
+
+    /// Forgets the render in progress, which was for an equation no longer
+    /// selected. TeX still runs to the end, but its result goes unseen.
+    pub fn cancel(&mut self) {
+        self.running = None;
+    }
+
+    /// The outcome of the background render once it is done; errors carry
+    /// the messages from the TeX log.
+    pub fn finished(&mut self) -> Option<Result<(), String>> {
+        let result = self.running.as_ref()?.try_recv().ok()?;
+        self.running = None;
+        Some(result)
+    }
+}
+
+impl ClipboardCopy {
+    pub fn new(events: Sender<AppEvent>) -> Self {
+        ClipboardCopy {
+            running: None,
+            events,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    pub fn start(&mut self, equation: &Equation, options: RenderOptions) {
+        let equation = equation.clone();
+        let (sender, receiver) = mpsc::channel();
+        let events = self.events.clone();
+        thread::spawn(move || {
+            let result = render_png(&equation, &options, options.backend(), None)
+                .and_then(|png| copy_png(&png))
+                .map(|_| equation.name.clone())
+                .map_err(|e| e.to_string());
+            sender.send(result).ok();
+            events.send(AppEvent::RenderProgress).ok();
+        });
+        self.running = Some(receiver);
+    }
+
+    pub fn finished(&mut self) -> Option<Result<String, String>> {
+        let result = self.running.as_ref()?.try_recv().ok()?;
+        self.running = None;
+        Some(result)
+    }
+}
+
+impl FileLoad {
+    pub fn new(events: Sender<AppEvent>) -> Self {
+        FileLoad {
+            running: None,
+            events,
+        }
+    }
+
+    /// The file being loaded and when that started.
+    pub fn loading(&self) -> Option<(&Path, Instant)> {
+        let (path, started, _) = self.running.as_ref()?;
+        Some((path, *started))
+    }
+
+    /// Loads `path` in the background, forgetting any load in progress.
+    pub fn start(&mut self, path: PathBuf, parsers: Arc<ParserRegistry>) {
+        let (sender, receiver) = mpsc::channel();
+        let events = self.events.clone();
+        let file = path.clone();
+        thread::spawn(move || {
+            sender.send(read_file(&parsers, &file)).ok();
+            events.send(AppEvent::RenderProgress).ok();
+        });
+        self.running = Some((path, Instant::now(), receiver));
+    }
+
+    /// Forgets the load in progress, whose content a newer read replaced.
+    pub fn cancel(&mut self) {
+        self.running = None;
+    }
+
+    pub fn finished(&mut self) -> Option<(PathBuf, io::Result<LoadedFile>)> {
+        let loaded = self.running.as_ref()?.2.try_recv().ok()?;
+        let (path, _, _) = self.running.take()?;
+        Some((path, loaded))
+    }
+}
+
+impl PreviewWindow {
+    pub fn new() -> io::Result<Self> {
+        let (frames, window) = open_window()?;
+        Ok(PreviewWindow {
+            frames,
+            window,
+            shown: None,
+        })
+    }
+
+    /// Whether the window is still there; the user may have closed it.
+    pub fn is_open(&self) -> bool {
+        !self.window.is_finished()
+    }
+
+    /// Puts `svg`, the render of the equation `name`, in the window unless
+    /// it already shows this version of it. Nothing happens while `svg`
+    /// hasn't been rendered or once the window is closed.
+    pub fn show(&mut self, name: &str, svg: &Path) -> io::Result<()> {
+        let modified = fs::metadata(svg).and_then(|m| m.modified()).ok();
+        if modified.is_none() || !self.is_open() {
+            return Ok(());
+        }
+        let shown = Some((svg.to_path_buf(), modified));
+        if self.shown == shown {
+            return Ok(());
+        }
+        self.shown = shown;
+        let frame = window_frame(name, svg)?;
+        // Gone when the window was closed in the meantime
+        let _ = self.frames.send(frame);
+        Ok(())
+    }
+}
+
+// Opens the window on a thread that draws the frames sent to it until the
+// window is closed or the sender dropped
+#[cfg(all(feature = "preview-window", not(target_os = "macos")))]
+fn open_window() -> io::Result<(Sender<Frame>, JoinHandle<()>)> {
+    use minifb::{ScaleMode, Window, WindowOptions};
+    use std::sync::mpsc::TryRecvError;
+
+    let (sender, frames) = mpsc::channel::<Frame>();
+    let (opened, started) = mpsc::channel();
+    let window = thread::spawn(move || {
+        let options = WindowOptions {
+            resize: true,
+            scale_mode: ScaleMode::Center,
+            ..WindowOptions::default()
+        };
+        let mut window = match Window::new("simptui", WINDOW_SIZE.0, WINDOW_SIZE.1, options) {
+            Ok(window) => window,
+            Err(e) => {
+                let _ = opened.send(Err(e.to_string()));
+                return;
+            }
+        };
+        let _ = opened.send(Ok(()));
+        window.set_background_color(255, 255, 255);
+        window.set_target_fps(30);
+        let mut frame = Frame {
+            title: "simptui".to_string(),
+            width: 1,
+            height: 1,
+            pixels: vec![0x00ff_ffff],
+        };
+        while window.is_open() {
+            // Only the newest frame is worth drawing
+            let mut newest = None;
+            loop {
+                match frames.try_recv() {
+                    Ok(next) => newest = Some(next),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return,
+                }
+            }
+            if let Some(next) = newest {
+                window.set_title(&next.title);
+                frame = next;
+            }
+            if window
+                .update_with_buffer(&frame.pixels, frame.width, frame.height)
+                .is_err()
+            {
+                return;
+            }
+        }
+    });
+    match started.recv() {
+        Ok(Ok(())) => Ok((sender, window)),
+        Ok(Err(e)) => Err(io::Error::other(format!(
+            "can't open the preview window: {}",
+            e
+        ))),
+        Err(_) => Err(io::Error::other("the preview window's thread stopped")),
+    }
+}
+
+#[cfg(not(all(feature = "preview-window", not(target_os = "macos"))))]
+fn open_window() -> io::Result<(Sender<Frame>, JoinHandle<()>)> {
+    let reason = match cfg!(target_os = "macos") {
+        true => "the preview window isn't available on macOS",
+        false => "simptui was built without the preview-window feature",
+    };
+    Err(io::Error::new(io::ErrorKind::Unsupported, reason))
+}
+
+// The SVG at `WINDOW_SCALE` on white, as the window has no transparency
+fn window_frame(name: &str, svg: &Path) -> io::Result<Frame> {
+    let tree = usvg::Tree::from_data(&fs::read(svg)?, &usvg::Options::default())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    let size = tree.size();
+    let mut pixmap = Pixmap::new(
+        ((size.width() * WINDOW_SCALE).ceil() as u32).max(1),
+        ((size.height() * WINDOW_SCALE).ceil() as u32).max(1),
+    )
+    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "equation too large to show"))?;
+    pixmap.fill(Color::WHITE);
+    resvg::render(
+        &tree,
+        Transform::from_scale(WINDOW_SCALE, WINDOW_SCALE),
+        &mut pixmap.as_mut(),
+    );
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .map(|p| u32::from(p.red()) << 16 | u32::from(p.green()) << 8 | u32::from(p.blue()))
+        .collect();
+    Ok(Frame {
+        title: name.to_string(),
+        width: pixmap.width() as usize,
+        height: pixmap.height() as usize,
+        pixels,
+    })
+}
+
+// Reads the failed compile's log and removes what it left behind
+fn compile_errors(output_dir: &Path, name: &str) -> String {
+    let file = |ext: &str| -> PathBuf { output_dir.join(format!("{}.{}", name, ext)) };
+    let errors = fs::read_to_string(file("log"))
+        .map(|log| tex_log_errors(&log))
+        .unwrap_or_default();
+    for ext in ["tex", "log", "pdf"] {
+        fs::remove_file(file(ext)).ok();
+    }
+    errors
+}
@@ -0,0 +1,208 @@
+use std::collections::VecDeque;
+use std::fmt::{self, Write as _};
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+/// How much is logged to stderr while running, from `-q` / `-v` / `-vv`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verbosity {
+    Quiet,       // Errors only, no warning summary
+    Normal,      // Errors, then the warnings once at the end
+    Verbose(u8), // Number of `-v`
+}
+
+impl Verbosity {
+    pub fn new(quiet: bool, verbose: u8) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, n) => Verbosity::Verbose(n),
+        }
+    }
+
+    fn level(self) -> LevelFilter {
+        match self {
+            Verbosity::Quiet | Verbosity::Normal => LevelFilter::ERROR,
+            Verbosity::Verbose(1) => LevelFilter::INFO,
+            Verbosity::Verbose(2) => LevelFilter::DEBUG,
+            Verbosity::Verbose(_) => LevelFilter::TRACE,
+        }
+    }
+}
+
+/// Warnings logged during the run, kept for the summary at its end.
+#[derive(Clone, Default)]
+pub struct Warnings(Arc<Mutex<Vec<String>>>);
+
+impl Warnings {
+    /// Prints every distinct warning once, with a count for repeats.
+    pub fn print_summary(&self) {
+        let warnings = self.0.lock().unwrap();
+        if warnings.is_empty() {
+            return;
+        }
+        let mut distinct: Vec<(&str, usize)> = Vec::new();
+        for warning in warnings.iter() {
+            match distinct.iter_mut().find(|(seen, _)| seen == warning) {
+                Some((_, count)) => *count += 1,
+                None => distinct.push((warning, 1)),
+            }
+        }
+        eprintln!("{} warning(s):", warnings.len());
+        for (warning, count) in distinct {
+            match count {
+                1 => eprintln!("  {}", warning),
+                _ => eprintln!("  {} (x{})", warning, count),
+            }
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for Warnings {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if *event.metadata().level() == Level::WARN {
+            let mut message = Message::default();
+            event.record(&mut message);
+            self.0.lock().unwrap().push(message.0);
+        }
+    }
+}
+
+/// One event of the session log.
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub elapsed: Duration, // Since the session started
+    pub level: Level,
+    pub message: String,
+}
+
+impl fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[{:>7.1}s] {:<5} {}",
+            self.elapsed.as_secs_f64(),
+            self.level,
+            self.message
+        )
+    }
+}
+
+// Entries kept of a session; older ones make room for new ones
+const MAX_ENTRIES: usize = 10_000;
+
+/// The last `MAX_ENTRIES` events logged at info and above since the TUI
+/// started, which has no stderr to show them on.
+#[derive(Clone)]
+pub struct SessionLog {
+    entries: Arc<Mutex<(VecDeque<LogEntry>, usize)>>, // With the count ever logged
+    start: Instant,
+}
+
+impl SessionLog {
+    pub fn new() -> Self {
+        SessionLog {
+            entries: Arc::default(),
+            start: Instant::now(),
+        }
+    }
+
+    pub fn entries(&self) -> Vec<LogEntry> {
+        self.entries.lock().unwrap().0.iter().cloned().collect()
+    }
+
+    /// How many entries were logged, those no longer kept included; it
+    /// changes with every new one.
+    pub fn logged(&self) -> usize {
+        self.entries.lock().unwrap().1
+    }
+
+    /// Writes the whole log to `path`, one entry per line.
+    pub fn export(&self, path: &Path) -> io::Result<()> {
+        let mut text = String::new();
+        for entry in self.entries.lock().unwrap().0.iter() {
+            writeln!(text, "{}", entry).ok();
+        }
+        fs::write(path, text)
+    }
+}
+
+impl<S: Subscriber> Layer<S> for SessionLog {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut message = Message::default();
+        event.record(&mut message);
+        let mut entries = self.entries.lock().unwrap();
+        if entries.0.len() == MAX_ENTRIES {
+            entries.0.pop_front();
+        }
+        entries.0.push_back(LogEntry {
+            elapsed: self.start.elapsed(),
+            level: *event.metadata().level(),
+            message: message.0,
+        });
+        entries.1 += 1;
+    }
+}
+
+// The event's message followed by its other fields as `key=value`
+#[derive(Default)]
+struct Message(String);
+
+impl Visit for Message {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            write!(self.0, "{:?}", value).ok();
+        } else {
+            write!(self.0, " {}={:?}", field.name(), value).ok();
+        }
+    }
+}
+
+/// Installs the global subscriber: stderr at `verbosity`, an appending debug
+/// log at `log_file`, and the warning collector. The TUI, which owns the
+/// screen, passes a `session` log in place of stderr. Returns the collected
+/// warnings.
+pub fn init(
+    verbosity: Verbosity,
+    log_file: Option<&Path>,
+    session: Option<SessionLog>,
+) -> io::Result<Warnings> {
+    let warnings = Warnings::default();
+    let stderr_layer = session.is_none().then(|| {
+        tracing_subscriber::fmt::layer()
+            .with_writer(io::stderr)
+            .without_time()
+            .with_target(false)
+            .with_filter(verbosity.level())
+    });
+    let file_layer = match log_file {
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .with_writer(Mutex::new(file))
+                    .with_ansi(false)
+                    .with_filter(verbosity.level().max(LevelFilter::DEBUG)),
+            )
+        }
+        None => None,
+    };
+    let session_layer =
+        session.map(|session| session.with_filter(verbosity.level().max(LevelFilter::INFO)));
+    tracing_subscriber::registry()
+        .with(stderr_layer)
+        .with(file_layer)
+        .with(session_layer)
+        .with(warnings.clone())
+        .init();
+    Ok(warnings)
+}
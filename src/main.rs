@@ -1,20 +1,497 @@
+use clap::builder::PossibleValuesParser;
+use clap::{ArgAction, CommandFactory, Parser, Subcommand, ValueHint};
+use clap_complete::Shell;
 use core::*;
-use crossterm::event::{DisableMouseCapture, EnableMouseCapture, Event};
+use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
 };
-use prettytable::{row, Table};
+use events::{AppEvent, Events};
+use keymap::{Action, Focus, KeyMap};
+use live::{
+    read_file, ClipboardCopy, FileLoad, LiveTemplate, LoadedFile, PreviewRender, PreviewWindow,
+};
+use logging::{SessionLog, Verbosity};
 use ratatui::backend::CrosstermBackend;
-use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::style::{Color, Style};
-use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::widgets::{Block, Borders, Paragraph, TableState};
 use ratatui::Terminal;
-use simptui::{detect_file_type, parse_markdown};
+use regex::Regex;
+use simptui::{
+    adjust_contrast, append_equation, apply_order, ask_confirmation, back_up, catch_interrupts,
+    changed_outputs, check_new_equation, copy_png, copy_text, detect_file_type, embed_images,
+    embed_links, equation_sections, expand_inputs, find_rendered, format_body, in_section,
+    link_target, load_source, open_in_viewer, parse_csv, parse_toml, read_manifest, read_status,
+    recolor_dir, rename_in_source, render_equations, render_png, reorder_csv_file,
+    reorder_toml_file, resolve_color, rewrite_bodies, route_of, scan_files, search_equations,
+    search_pattern, set_active_in_source, verify_renders, verify_reproducible, warn_unsupported,
+    write_checksums, write_csv_file, write_toml_file, BatchHooks, Bookmarks, ChangedOutput,
+    ColorSpec, Config, Document, Engine, Equation, EquationFilter, EquationStats, FileIndexer,
+    Fill, Font, FontSize, Heading, IndexEvent, JobState, JobStatus, Manifest, NamePattern,
+    OutputFormat, OutputLayout, OutputNaming, OutputRoute, ParserRegistry, Paths, Project, Ranked,
+    RenderFailure, RenderOptions, RenderPipeline, RenderReport, Retention, Rgb, Scrub, SiteFlavor,
+    Snippet, Verdict, MIN_CONTRAST, PROJECT_FILE_NAME, STATUS_FILE_NAME,
+};
+use std::cell::OnceCell;
+use std::collections::HashSet;
 use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use terminal::Capabilities;
+use tracing::{error, info, warn};
 use tui_textarea::{Input, Key, TextArea};
-use walkdir::WalkDir;
+use widgets::{
+    body_truncated, equation_table, grouped_view, hint_bar, latex_source, sorted_view,
+    source_context, unicode_approximation, BookmarkOutcome, BookmarkScreen, CommandPalette,
+    ConfirmDialog, ErrorReport, ErrorScreen, FailuresPanel, FileTree, FileTreeView, GalleryItem,
+    GalleryState, HelpOverlay, ListPicker, LogPane, LogState, NewEquationForm, NewEquationOutcome,
+    OverwriteDialog, OverwriteOutcome, PaletteOutcome, PickerOutcome, PreviewPane, PreviewState,
+    SearchOutcome, SearchScreen, SortOrder, StatusLine, TableRow, ThumbnailGrid, ViewRow,
+};
+
+mod events;
+mod keymap;
+mod live;
+mod logging;
+mod plain;
+mod terminal;
+mod widgets;
+
+const DEFAULT_COLOR: &str = "#000000";
+const MAX_FPS: u64 = 30; // Also the tick rate of the TUI loop
+const PREVIEW_DELAY: Duration = Duration::from_millis(300); // Rest on a row before it renders
+const PREVIEW_CHUNK_LINES: u16 = 1000; // Of a plain file, brought into view as it is scrolled
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const NARROW_WIDTH: u16 = 80; // Terminals narrower than this show one pane at a time
+const SESSION_LOG: &str = "simptui-session.log"; // Export of the log pane, in the working directory
+const BOOKMARKS_DIR: &str = "bookmarks"; // Renders of the bookmarks, in the working directory
+
+#[derive(Parser)]
+#[command(
+    name = "simptui",
+    version,
+    about = "Render LaTeX equations from notes to SVG"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Folder to scan for notes; repeat for multiple roots (default: config or CWD)
+    #[arg(long = "root", global = true, value_hint = ValueHint::DirPath)]
+    roots: Vec<PathBuf>,
+    /// Render profile from the config (built-in: web, print, slides)
+    #[arg(long, global = true)]
+    profile: Option<String>,
+    /// Line-based prompts instead of the full-screen interface
+    #[arg(long)]
+    no_tui: bool,
+    /// Also show the selected equation in a window of its own that follows
+    /// the selection, for terminals that can't draw images (needs the
+    /// preview-window feature)
+    #[arg(long, conflicts_with = "no_tui")]
+    #[cfg_attr(
+        not(all(feature = "preview-window", not(target_os = "macos"))),
+        arg(hide = true)
+    )]
+    preview_window: bool,
+    /// Only print errors, not the warning summary
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Log what is being done as it happens; -vv for details
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
+    /// Append a debug log to this file (the only log while the TUI runs)
+    #[arg(long, global = true, value_hint = ValueHint::FilePath)]
+    log_file: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render the active equations of markdown, csv or html files
+    Render {
+        /// Files or quoted globs such as "notes/**/*.md"
+        #[arg(required = true, value_hint = ValueHint::FilePath)]
+        files: Vec<PathBuf>,
+        /// Output directory [default: `equations/` next to each file, see
+        /// `[output]` in the config]. With several files or a glob, each
+        /// file gets a subdirectory named after it
+        #[arg(short, long, value_hint = ValueHint::DirPath)]
+        out: Option<PathBuf>,
+        /// Hex color, or `auto` to contrast the terminal/theme background
+        /// [default: profile color or #000000]
+        #[arg(short, long)]
+        color: Option<ColorSpec>,
+        /// base16 theme file consulted by `--color auto`
+        #[arg(long, value_hint = ValueHint::FilePath)]
+        theme: Option<PathBuf>,
+        /// Background the equations will be shown on, to check the color's
+        /// contrast against
+        #[arg(long)]
+        background: Option<Rgb>,
+        /// Darken or lighten a low-contrast color instead of only warning
+        #[arg(long, requires = "background")]
+        fix_contrast: bool,
+        /// Hex color to draw behind each equation, or `transparent`
+        /// [default: profile fill or transparent]
+        #[arg(long)]
+        fill: Option<Fill>,
+        /// Space around each equation in pt, inside the fill if there is
+        /// one [default: profile padding or 1]
+        #[arg(long)]
+        padding: Option<f32>,
+        /// Round the corners of the fill by this many pt
+        #[arg(long, requires = "fill")]
+        corner_radius: Option<f32>,
+        /// Keep every .tex, .log and .pdf file; short for --retention keep-all
+        #[arg(long, conflicts_with = "retention")]
+        keep_intermediates: bool,
+        /// keep-all, keep-pdf, keep-tex-on-failure or delete-all
+        /// [default: profile retention or keep-tex-on-failure]
+        #[arg(long)]
+        retention: Option<Retention>,
+        /// TeX engine: auto, tectonic, latexmk, pdflatex, xelatex or lualatex
+        /// [default: profile engine or auto]
+        #[arg(long)]
+        engine: Option<Engine>,
+        /// Math font: gfs-neohellenic, latin-modern, stix2, euler or fira-math
+        /// [default: profile font or gfs-neohellenic]
+        #[arg(long)]
+        font: Option<Font>,
+        /// Text size: a LaTeX size such as small, large or Huge, or e.g. 14pt
+        /// [default: profile size or Large]
+        #[arg(long)]
+        size: Option<FontSize>,
+        /// svg, png, pdf or mathml [default: profile format or svg]
+        #[arg(short, long)]
+        format: Option<OutputFormat>,
+        /// `per-equation` SVGs or one `single-pdf` formula sheet
+        #[arg(long, default_value = "per-equation")]
+        layout: OutputLayout,
+        /// Strip metadata and precision noise from the SVGs and report savings
+        #[arg(long)]
+        optimize_svg: bool,
+        /// Align inline SVGs to the text baseline and write baseline.css
+        #[arg(long)]
+        baseline_align: bool,
+        /// Render each line of multi-line (align, gather, ...) equations as
+        /// `<name>_l1`, `<name>_l2`, ...
+        #[arg(long)]
+        split_lines: bool,
+        /// Let `\dfrac`/`\tfrac` reuse cached `\frac` renders (and
+        /// `\dbinom`/`\tbinom` those of `\binom`), at the cost of their size
+        #[arg(long)]
+        normalize_styles: bool,
+        /// Keep `\label{..}` in the rendered LaTeX instead of stripping it
+        #[arg(long)]
+        keep_labels: bool,
+        /// Take `\textcolor`/`\color` out of bodies, so each equation comes
+        /// out in the one color (by default they paint over it)
+        #[arg(long)]
+        strip_colors: bool,
+        /// Stop at the first failed equation and exit non-zero
+        #[arg(long)]
+        fail_fast: bool,
+        /// Seconds TeX gets for a simple equation, more for complex ones,
+        /// 0 for no limit [default: profile timeout, or no limit]
+        #[arg(long, value_name = "SECS")]
+        timeout: Option<u64>,
+        /// Suffix files with a content hash and write manifest.json
+        #[arg(long)]
+        hash_names: bool,
+        /// Record the source file, lines and simptui version in each .tex
+        /// and SVG
+        #[arg(long)]
+        source_map: bool,
+        /// Output file names: name, hash, number or file-index
+        /// [default: `naming` in `[output]`, or name]
+        #[arg(long)]
+        naming: Option<OutputNaming>,
+        /// Re-render everything instead of reusing cached outputs
+        #[arg(long)]
+        no_cache: bool,
+        /// Start over instead of resuming a batch that was cut short
+        #[arg(long)]
+        restart: bool,
+        /// Lock each output directory while rendering into it and keep a
+        /// .simptui-status.json there for other tools, see `simptui status`
+        #[arg(long)]
+        status_file: bool,
+        /// Render the equations this expression selects instead of the
+        /// active ones, e.g. `active && tags.contains("exam")`
+        #[arg(long)]
+        filter: Option<EquationFilter>,
+        /// Scrub dates and IDs from the outputs, render everything again to
+        /// check the bytes match, and write their checksums to SHA256SUMS
+        #[arg(long)]
+        deterministic: bool,
+    },
+    /// Manage a `.simptui` project that renders many files together
+    Project {
+        #[command(subcommand)]
+        action: ProjectCommand,
+    },
+    /// Render one equation to PNG and put it on the clipboard, ready to
+    /// paste into chat or email
+    Copy {
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+        /// Name of the equation
+        name: String,
+        /// Hex color to fill in behind the equation [default: transparent]
+        #[arg(long)]
+        background: Option<Rgb>,
+        /// Resolution of the image [default: profile dpi]
+        #[arg(long)]
+        dpi: Option<u32>,
+    },
+    /// Open an equation's rendered output in the system viewer, or the
+    /// one `[viewer]` in the config names for its format
+    Open {
+        /// Name of the equation
+        name: String,
+        /// Source file of the equation, whose output directory is searched
+        /// [default: the output directory for the current directory]
+        #[arg(long, value_hint = ValueHint::FilePath)]
+        file: Option<PathBuf>,
+        /// Directory to search instead
+        #[arg(long, value_hint = ValueHint::DirPath, conflicts_with = "file")]
+        out: Option<PathBuf>,
+        /// svg, png, pdf or mathml [default: the first of these rendered]
+        #[arg(long)]
+        format: Option<OutputFormat>,
+    },
+    /// Repaint the equations of rendered SVGs in another color without
+    /// compiling them again
+    Recolor {
+        /// Output directory to recolor, subdirectories included [default:
+        /// the output directory for the current directory]
+        #[arg(short, long, value_hint = ValueHint::DirPath)]
+        out: Option<PathBuf>,
+        /// Hex color, or `auto` to contrast the terminal/theme background
+        #[arg(short, long)]
+        color: ColorSpec,
+        /// base16 theme file consulted by `--color auto`
+        #[arg(long, value_hint = ValueHint::FilePath)]
+        theme: Option<PathBuf>,
+        /// Color to replace [default: the most used one of each SVG]
+        #[arg(long)]
+        from: Option<Rgb>,
+    },
+    /// Find equations across all notes under the roots
+    Grep {
+        /// LaTeX snippet to look for (matched literally unless --regex)
+        query: String,
+        #[arg(short = 'e', long)]
+        regex: bool,
+        #[arg(short, long)]
+        ignore_case: bool,
+    },
+    /// Print the equations of each file, one per line, `[x]` marking the
+    /// active ones
+    List {
+        /// Files or quoted globs such as "notes/**/*.md"
+        #[arg(required = true, value_hint = ValueHint::FilePath)]
+        files: Vec<PathBuf>,
+        /// Only the equations matching this expression, e.g.
+        /// `tags.contains("exam") && len(body) < 200`
+        #[arg(long)]
+        filter: Option<EquationFilter>,
+    },
+    /// Summarize the equations of each file: counts, the longest and most
+    /// complex ones, command, environment and package usage
+    Stats {
+        /// Files or quoted globs such as "notes/**/*.md"
+        #[arg(required = true, value_hint = ValueHint::FilePath)]
+        files: Vec<PathBuf>,
+        /// Entries per ranking
+        #[arg(long, default_value_t = 5)]
+        top: usize,
+        /// Only count the equations matching this expression
+        #[arg(long)]
+        filter: Option<EquationFilter>,
+    },
+    /// Show how the last `render --status-file` into an output directory is
+    /// doing. Exits non-zero when it failed, was interrupted or died
+    Status {
+        #[arg(default_value = ".", value_hint = ValueHint::DirPath)]
+        dir: PathBuf,
+        /// Print the status file as it is
+        #[arg(long)]
+        json: bool,
+    },
+    /// Re-render a file's equations as SVG and compare them pixel by pixel
+    /// with a baseline directory, e.g. in CI
+    Verify {
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+        /// Directory with the expected `<name>.svg` files
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        baseline: PathBuf,
+        /// Percentage of differing pixels still accepted
+        #[arg(long, default_value_t = 0.0)]
+        tolerance: f64,
+        /// Copy the new renders into the baseline instead of failing
+        #[arg(long)]
+        update: bool,
+    },
+    /// Rename every equation of a markdown, csv or toml file after a pattern
+    Rename {
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+        /// e.g. `{file_stem}_{index:02}_{name}`; variables: file_stem,
+        /// heading, index (`{index:03}` pads it), hash and name
+        #[arg(short, long)]
+        pattern: NamePattern,
+        /// Only print the new names
+        #[arg(long)]
+        dry_run: bool,
+        /// Don't ask before rewriting the file
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Tidy the equation bodies of a markdown, csv or toml file: spacing,
+    /// `&`s lined up and nested braces indented
+    Format {
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+        /// Only list the equations that aren't formatted, failing if any
+        #[arg(long)]
+        check: bool,
+        /// Don't ask before rewriting the file
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Mark the equations of a markdown, csv or toml file active, all of
+    /// them or those a filter and/or section picks
+    Activate {
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+        /// Only the equations this expression selects, e.g.
+        /// `name.matches("^exam_")` or `tags.contains("exam")`
+        #[arg(long)]
+        filter: Option<EquationFilter>,
+        /// Only the equations under this heading, subsections included
+        #[arg(long)]
+        section: Option<String>,
+        /// Only print the equations that would change
+        #[arg(long)]
+        dry_run: bool,
+        /// Don't ask before rewriting the file
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Mark equations inactive, like `activate` picks them
+    Deactivate {
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+        #[arg(long)]
+        filter: Option<EquationFilter>,
+        #[arg(long)]
+        section: Option<String>,
+        #[arg(long)]
+        dry_run: bool,
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Link each rendered equation of a markdown file in right after its
+    /// block, as `![name](equations/name.svg)`, updating the links a
+    /// previous run added
+    Embed {
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+        /// Directory the equations were rendered into [default: the output
+        /// directory for the file]
+        #[arg(short, long, value_hint = ValueHint::DirPath)]
+        out: Option<PathBuf>,
+        /// svg, png, pdf or mathml [default: the first of these rendered]
+        #[arg(long)]
+        format: Option<OutputFormat>,
+        /// Only link the equations matching this expression
+        #[arg(long)]
+        filter: Option<EquationFilter>,
+        /// Only print the links that would change
+        #[arg(long)]
+        dry_run: bool,
+        /// Don't ask before rewriting the file
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Write a file's equations to a CSV table for review in a spreadsheet;
+    /// the table renders like any other csv input
+    ExportCsv {
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+        /// CSV file to write
+        #[arg(value_hint = ValueHint::FilePath)]
+        output: PathBuf,
+        /// Only write the equations matching this expression
+        #[arg(long)]
+        filter: Option<EquationFilter>,
+    },
+    /// Write a file's equations to an equations.toml file, which renders
+    /// like any other toml input
+    ExportToml {
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+        /// TOML file to write
+        #[arg(value_hint = ValueHint::FilePath)]
+        output: PathBuf,
+        /// Only write the equations matching this expression
+        #[arg(long)]
+        filter: Option<EquationFilter>,
+    },
+    /// Render a file's equations into the static files of a Hugo, Zola or
+    /// Jekyll site and print the shortcodes showing them
+    ExportSsg {
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+        /// hugo, zola or jekyll
+        #[arg(long)]
+        flavor: SiteFlavor,
+        /// Root of the site
+        #[arg(long, default_value = ".", value_hint = ValueHint::DirPath)]
+        site: PathBuf,
+        /// Folder of the equations among the site's static files
+        #[arg(long, default_value = "equations")]
+        dir: String,
+        /// Render the equations this expression selects instead of the
+        /// active ones, e.g. `active && tags.contains("exam")`
+        #[arg(long)]
+        filter: Option<EquationFilter>,
+    },
+    /// Print a shell completion script, e.g. `simptui completions bash`
+    Completions { shell: Shell },
+    /// Print the man page, or write one per subcommand into DIR
+    Man {
+        #[arg(value_hint = ValueHint::DirPath)]
+        dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProjectCommand {
+    /// Create a project file in the current directory
+    Init {
+        /// Output directory, relative to the project
+        #[arg(short, long, value_hint = ValueHint::DirPath)]
+        out: Option<PathBuf>,
+    },
+    /// Add source files to the nearest project
+    Add {
+        #[arg(required = true, value_hint = ValueHint::FilePath)]
+        files: Vec<PathBuf>,
+    },
+    /// Render all equations of the nearest project
+    Render {
+        #[arg(long)]
+        fail_fast: bool,
+    },
+}
 
 #[derive(Debug)]
 struct FileEntry {
@@ -23,141 +500,1710 @@ struct FileEntry {
 }
 
 struct App {
-    textarea: TextArea<'static>,  // Input field
-    is_valid: bool,               // Validity of the filename
-    file_content: Option<String>, // Content of the file or error message
-    scroll_offset: u16,           // Scroll position for file content
-    should_redraw: bool,          // Redraw flag
-    files: Vec<FileEntry>,        // List of files in the folder
-    content_height: u16,          // Track content height for scrolling
+    config: Config,                                  // Settings the session started with
+    parsers: Arc<ParserRegistry>,                    // Formats, markdown with the configured syntax
+    textarea: TextArea<'static>,                     // Input field
+    is_valid: bool,                                  // Validity of the filename
+    file_content: Option<String>,                    // Content of the file or error message
+    content_shown: usize,                            // Bytes of it in the view so far
+    scroll_offset: u16,                              // Scroll position for file content
+    should_redraw: bool,                             // Redraw flag
+    files: Vec<FileEntry>,                           // List of files in the folder
+    content_height: u16,                             // Track content height for scrolling
+    content_view: u16,                               // Lines of it on screen, as last drawn
+    files_changed: bool,                             // Index events since `is_valid` was set
+    scanning: bool,                                  // Initial scan still running
+    confirm: Option<(ConfirmDialog, PendingAction)>, // Open modal and what it guards
+    overwrite: Option<(OverwriteDialog, Vec<Equation>, Vec<ChangedOutput>)>, // Held-up render
+    render_requested: Option<Vec<Equation>>,         // To render on the next loop turn
+    profiles: Vec<String>,                           // Profile names from the config
+    profile: Option<String>,                         // Selected render profile
+    auto_color: OnceCell<Rgb>,                       // For `color = "auto"`, asked once
+    profile_picker: Option<ListPicker>,              // Open profile picker modal
+    search: Option<SearchScreen>,                    // Open vault search screen
+    bookmarks: Option<Bookmarks>,                    // None if the saved list can't be read
+    bookmark_screen: Option<BookmarkScreen>,         // Open bookmarks screen
+    bookmark_render: Option<Vec<Equation>>,          // Bookmarks to render on the next loop turn
+    document: Option<Document>,                      // The loaded equation file
+    selected: usize,                                 // Highlighted row of `view`
+    focus: Focus,                                    // Pane receiving plain keys
+    view: Vec<ViewRow>,                              // Sorted/filtered rows of the table
+    grouped: bool,                                   // Equations under their headings
+    headings: Vec<Heading>,                          // Headings of `source`, while grouped
+    sections: Vec<Option<usize>>,                    // Heading of each equation, while grouped
+    collapsed: HashSet<Option<usize>>,               // Folded sections
+    sort: SortOrder,                                 // Kept across files for the session
+    filter: Option<Regex>,                           // Body filter set with `/`
+    filter_input: Option<TextArea<'static>>,         // Open filter prompt
+    rename_input: Option<TextArea<'static>>,         // Open bulk-rename form
+    new_equation: Option<NewEquationForm>,           // Open new-equation form
+    snippets: Vec<Snippet>,                          // Library offered by the form
+    snippet_picker: Option<ListPicker>,              // Open snippet picker, over the form
+    routes: Vec<OutputRoute>,                        // Where renders of matching equations go
+    failures: Vec<RenderFailure>,                    // Shown after a render until dismissed
+    last_report: Option<RenderReport>,               // Last batch render of the loaded file
+    keymap: KeyMap,                                  // Shortcuts, also shown by help and hint bar
+    help: bool,                                      // Help overlay open
+    palette: Option<CommandPalette>,                 // Open command palette
+    order_note: Option<String>,                      // Outcome of the last table action
+    caps: Capabilities,                              // Colors and symbols the terminal shows
+    show_preview: bool,                              // Rendered image instead of the source
+    side_by_side: bool,                              // LaTeX body left, rendered image right
+    preview: PreviewState,                           // Zoom and pan of the image preview
+    gallery: GalleryState,                           // Thumbnails, while the gallery has focus
+    live: Option<LiveTemplate>,                      // Watcher of the profile's template
+    preview_render: PreviewRender,                   // Background render for the preview
+    preview_window: Option<PreviewWindow>,           // Native window following the selection
+    file_load: FileLoad,                             // Background read of the file opened
+    pending_jump: Option<(String, String)>,          // Name and body to select once it's read
+    preview_pending: Option<(String, Option<Instant>)>, // Selected equation, when to render it
+    clipboard_copy: ClipboardCopy,                   // Background render onto the clipboard
+    live_error: Option<String>,                      // TeX errors of the last preview render
+    tree: FileTree,                                  // `files` by directory
+    show_tree: bool,                                 // File tree beside the content
+    error: Option<ErrorScreen>,                      // Open error screen
+    session_log: SessionLog,                         // Events of the session, for the log pane
+    log: LogState,                                   // Scroll and search of the log pane
+    show_log: bool,                                  // Log pane under the content
+    log_drawn: usize,                                // Log entries when last drawn
+    backend_label: String,                           // Found per profile, not per frame
+}
+
+enum PendingAction {
+    Render(Vec<Equation>),
+    Rename(Vec<Equation>, Vec<String>), // Equations in document order, new names
+    Format(Vec<Equation>, Vec<String>), // Equations and their formatted bodies
+    SetActive(Vec<Equation>, bool),     // Equations to mark, and whether active
+    RenderBookmarks(Vec<Equation>),
 }
 
 impl App {
-    fn new() -> Self {
+    fn new(
+        config: &Config,
+        parsers: ParserRegistry,
+        roots: &[PathBuf],
+        profile: Option<String>,
+        caps: Capabilities,
+        events: Sender<AppEvent>,
+        session_log: SessionLog,
+    ) -> Self {
         let mut textarea = TextArea::default();
         textarea.set_cursor_line_style(Style::default());
         textarea.set_placeholder_text("Enter a filename in this folder or any subfolder");
 
-        let files = files_in_folder("./").unwrap_or_default();
+        let indexer = FileIndexer::spawn(config.scan_roots(roots), config.scan.clone());
+        // The indexer lives on in this thread, watching until the app exits
+        let index_events = events.clone();
+        thread::spawn(move || {
+            while let Some(event) = indexer.recv() {
+                if index_events.send(AppEvent::FsChange(event)).is_err() {
+                    break;
+                }
+            }
+        });
+        let files = Vec::new();
         let is_valid = validate(&mut textarea, &files);
 
-        Self {
+        let mut app = Self {
+            config: config.clone(),
+            parsers: Arc::new(parsers),
             textarea,
             is_valid,
             file_content: None,
+            content_shown: 0,
             scroll_offset: 0,
             should_redraw: true,
             files,
             content_height: 0,
+            content_view: 0,
+            files_changed: false,
+            scanning: true,
+            confirm: None,
+            overwrite: None,
+            render_requested: None,
+            profiles: config.profile.keys().cloned().collect(),
+            profile,
+            profile_picker: None,
+            auto_color: OnceCell::new(),
+            search: None,
+            bookmarks: Bookmarks::load(&Paths::new().bookmarks_file())
+                .map_err(|e| warn!("Bookmarks unavailable: {}", e))
+                .ok(),
+            bookmark_screen: None,
+            bookmark_render: None,
+            document: None,
+            selected: 0,
+            focus: Focus::Input,
+            view: Vec::new(),
+            grouped: false,
+            headings: Vec::new(),
+            sections: Vec::new(),
+            collapsed: HashSet::new(),
+            sort: SortOrder::default(),
+            filter: None,
+            filter_input: None,
+            rename_input: None,
+            new_equation: None,
+            snippets: config.snippets(),
+            snippet_picker: None,
+            routes: config.routes().unwrap_or_default(), // Reported when rendering
+            failures: Vec::new(),
+            last_report: None,
+            keymap: KeyMap::default(),
+            help: false,
+            palette: None,
+            order_note: None,
+            caps,
+            show_preview: false,
+            side_by_side: false,
+            preview: PreviewState::default(),
+            gallery: GalleryState::default(),
+            live: None,
+            preview_render: PreviewRender::new(events.clone()),
+            preview_window: None,
+            file_load: FileLoad::new(events.clone()),
+            pending_jump: None,
+            preview_pending: None,
+            clipboard_copy: ClipboardCopy::new(events),
+            live_error: None,
+            tree: FileTree::new(config.scan_roots(roots)),
+            show_tree: true,
+            error: None,
+            session_log,
+            log: LogState::default(),
+            show_log: false,
+            log_drawn: 0,
+            backend_label: String::new(),
+        };
+        app.backend_label = app.describe_backend();
+        app.watch_template();
+        app
+    }
+
+    fn render_options(&self, out: PathBuf) -> io::Result<RenderOptions> {
+        // `auto` asks the terminal for its background, which only happens
+        // the first time; the answer stands for the session
+        let profile = self.profile.as_deref();
+        let spec = profile
+            .map(|name| self.config.profile(name))
+            .transpose()?
+            .and_then(|profile| profile.color.as_deref().map(str::parse::<ColorSpec>));
+        let color = match spec {
+            Some(Ok(ColorSpec::Auto)) => {
+                let rgb = match self.auto_color.get() {
+                    Some(rgb) => *rgb,
+                    None => {
+                        let hex = resolve_color(&ColorSpec::Auto, None)?;
+                        let rgb = Rgb::from_hex(&hex).expect("resolved colors are hex codes");
+                        *self.auto_color.get_or_init(|| rgb)
+                    }
+                };
+                Some(ColorSpec::Hex(rgb))
+            }
+            _ => None,
+        };
+        let mut options = build_render_options(&self.config, profile, out, color, None)?;
+        if let Some(document) = &self.document {
+            options.macros = document.macros.clone();
         }
+        Ok(options)
     }
 
-    fn handle_input(&mut self, input: Input) -> bool {
-        match input {
-            Input { key: Key::Esc, .. } => true, // Exit on Esc
-            Input {
-                key: Key::Enter, ..
-            } if self.is_valid => {
-                let input = self.textarea.lines()[0].trim();
-                if let Some(entry) = self.files.iter().find(|file| file.file_name == input) {
-                    match fs::read_to_string(&entry.full_path) {
-                        Ok(content) => {
-                            match detect_file_type(&entry.full_path) {
-                                "markdown" => {
-                                    let equations = parse_markdown(&content);
-                                    let mut table = Table::new();
-
-                                    table.add_row(row!["Active", "Name", "Equation"]);
-
-                                    for eq in &equations {
-                                        table.add_row(row![
-                                            if eq.active { "Yes" } else { "No" },
-                                            eq.name,
-                                            eq.body
-                                        ]);
-                                    }
-                                    self.file_content = Some(table.to_string());
-                                    self.scroll_offset = 0; // Reset scroll position
-                                    self.content_height = self
-                                        .file_content
-                                        .as_ref()
-                                        .map_or(0, |content| content.lines().count() as u16);
-                                }
-                                "csv" => {
-                                    match Table::from_csv_file(&entry.full_path) {
-                                        Ok(table) => {
-                                            self.file_content = Some(table.to_string());
-                                            self.scroll_offset = 0; // Reset scroll position
-                                            self.content_height =
-                                                self.file_content.as_ref().map_or(0, |content| {
-                                                    content.lines().count() as u16
-                                                });
-                                        }
-                                        Err(e) => {
-                                            self.file_content =
-                                                Some(format!("Error reading csv file: {} ", e))
-                                        }
-                                    }
-                                }
-                                "unknown" => {
-                                    self.file_content = Some(content);
-                                    self.scroll_offset = 0; // Reset scroll position
-                                    self.content_height = self
-                                        .file_content
-                                        .as_ref()
-                                        .map_or(0, |content| content.lines().count() as u16);
-                                }
-                                _ => {
-                                    self.file_content =
-                                        Some("Error detecting file type:".to_string())
-                                }
-                            }
+    // What the status line says renders go through under the profile
+    fn describe_backend(&self) -> String {
+        match self.render_options(PathBuf::new()) {
+            Ok(options) if options.remote.is_some() && options.format != OutputFormat::MathML => {
+                format!("remote → {}", options.format.extension())
+            }
+            Ok(options) => format!("{} → {}", options.engine, options.format.extension()),
+            Err(_) => "invalid profile".to_string(),
+        }
+    }
+
+    // Watches the template of the selected profile, if it has one
+    fn watch_template(&mut self) {
+        self.live = self
+            .render_options(PathBuf::new())
+            .ok()
+            .and_then(|options| options.template)
+            .and_then(|template| LiveTemplate::watch(&template));
+        self.live_error = None;
+    }
+
+    // Opens the selected equation's output, preferring the profile's format
+    fn open_viewer(&mut self) {
+        let (Some(source), Some(equation)) = (self.source_path(), self.selected_equation()) else {
+            return;
+        };
+        let dir = self.config.output_dir(source);
+        let format = self
+            .render_options(PathBuf::new())
+            .ok()
+            .map(|options| options.format);
+        let found = find_rendered(&dir, equation.file_stem(), format)
+            .or_else(|| find_rendered(&dir, equation.file_stem(), None));
+        self.order_note = Some(match found {
+            Some(path) => match open_in_viewer(&path, &self.config.viewer) {
+                Ok(()) => format!("opened {}", path.display()),
+                Err(e) => e.to_string(),
+            },
+            None => format!("{} isn't rendered yet, Ctrl-R renders it", equation.name),
+        });
+        self.should_redraw = true;
+    }
+
+    // Renders the selected equation onto the clipboard in the background
+    fn copy_image(&mut self) {
+        if self.clipboard_copy.is_running() {
+            return;
+        }
+        let Some(equation) = self.selected_equation().cloned() else {
+            return;
+        };
+        match self.render_options(PathBuf::new()) {
+            Ok(options) => {
+                self.clipboard_copy.start(&equation, options);
+                self.order_note = Some(format!("copying {}...", equation.name));
+            }
+            Err(e) => self.order_note = Some(e.to_string()),
+        }
+        self.should_redraw = true;
+    }
+
+    fn poll_copy(&mut self) {
+        if let Some(result) = self.clipboard_copy.finished() {
+            self.order_note = Some(match result {
+                Ok(name) => format!("copied {} to the clipboard", name),
+                Err(e) => {
+                    warn!("Copying to the clipboard failed: {}", e);
+                    format!("copy failed: {}", e)
+                }
+            });
+            self.should_redraw = true;
+        }
+    }
+
+    // Picks up the outcome of a preview render, and starts one for the
+    // selected equation after a template save or once it has been selected
+    // for a moment without an up-to-date output
+    fn poll_live(&mut self) {
+        self.poll_selection();
+        self.update_preview_window();
+        if let Some(result) = self.preview_render.finished() {
+            if let Err(e) = &result {
+                warn!("Preview render failed:\n{}", e.trim_end());
+            }
+            self.live_error = result.err();
+            self.show_preview = true;
+            self.should_redraw = true;
+        }
+        let Some(live) = self.live.as_ref() else {
+            return;
+        };
+        if !live.changed() || self.preview_render.is_running() {
+            return;
+        }
+        if let Some(equation) = self.selected_equation().cloned() {
+            self.render_preview(&equation);
+        }
+    }
+
+    fn poll_selection(&mut self) {
+        let window = self
+            .preview_window
+            .as_ref()
+            .is_some_and(PreviewWindow::is_open);
+        let shown =
+            (self.show_preview || self.side_by_side || window) && self.focus != Focus::Gallery;
+        let selected = self
+            .selected_equation()
+            .filter(|_| shown)
+            .map(|equation| equation.name.clone());
+        let tracked = self.preview_pending.as_ref().map(|(name, _)| name);
+        if selected.as_ref() != tracked {
+            // Whatever is rendering was for the row left behind
+            if tracked.is_some() {
+                self.preview_render.cancel();
+                self.live_error = None;
+            }
+            self.preview_pending =
+                selected.map(|name| (name, Some(Instant::now() + PREVIEW_DELAY)));
+            return;
+        }
+        let Some((_, due)) = self.preview_pending.as_mut() else {
+            return;
+        };
+        if due.is_none_or(|due| Instant::now() < due) || self.preview_render.is_running() {
+            return;
+        }
+        *due = None; // Once per selection
+        if let Some(equation) = self.selected_equation().cloned() {
+            if self.preview_outdated(&equation) {
+                self.render_preview(&equation);
+            }
+        }
+    }
+
+    // Keeps the preview window on the selected equation's latest render
+    fn update_preview_window(&mut self) {
+        let svg = self.preview_path();
+        let name = self
+            .selected_equation()
+            .map(|equation| equation.name.clone());
+        let (Some(window), Some(svg), Some(name)) = (self.preview_window.as_mut(), svg, name)
+        else {
+            return;
+        };
+        if let Err(e) = window.show(&name, &svg) {
+            warn!("Preview window not updated: {}", e);
+        }
+    }
+
+    // Whether the SVG of `equation` is missing or older than the loaded file
+    fn preview_outdated(&self, equation: &Equation) -> bool {
+        let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+        let Some(svg) = self.svg_path(equation) else {
+            return false;
+        };
+        match (
+            modified(&svg),
+            self.source_path().and_then(|path| modified(path)),
+        ) {
+            (None, _) => true,
+            (Some(svg), Some(source)) => svg < source,
+            (Some(_), None) => false,
+        }
+    }
+
+    // Renders `equation` of the loaded file in the background, into the
+    // file the preview shows whatever the naming scheme
+    fn render_preview(&mut self, equation: &Equation) {
+        let Some(path) = self.source_path() else {
+            return;
+        };
+        let svg = self.svg_path(equation);
+        let mut equation = equation.clone();
+        if let Some(stem) = svg.as_ref().and_then(|path| path.file_stem()) {
+            equation.name = stem.to_string_lossy().into_owned();
+        }
+        let output_dir = svg
+            .as_ref()
+            .and_then(|path| path.parent())
+            .map_or_else(|| self.config.output_dir(path), Path::to_path_buf);
+        // Per-equation SVG, which is what the preview shows
+        let options = self.render_options(output_dir).map(|mut options| {
+            options.format = OutputFormat::Svg;
+            options.layout = OutputLayout::PerEquation;
+            options
+        });
+        match options {
+            Ok(options) => self.preview_render.render(&equation, options),
+            Err(e) => self.live_error = Some(e.to_string()),
+        }
+        self.should_redraw = true;
+    }
+
+    fn index_event(&mut self, event: IndexEvent) {
+        match event {
+            IndexEvent::Found(path) | IndexEvent::Created(path) => {
+                self.tree.insert(path.clone());
+                if !self.files.iter().any(|file| file.full_path == path) {
+                    if let Some(entry) = FileEntry::from_path(path) {
+                        self.files.push(entry);
+                    }
+                }
+            }
+            IndexEvent::Removed(path) => {
+                self.tree.remove(&path);
+                self.files.retain(|file| file.full_path != path);
+            }
+            IndexEvent::ScanFinished { truncated } => {
+                self.scanning = false;
+                if truncated && self.file_content.is_none() {
+                    self.file_content = Some(format!(
+                        "File scan stopped after {} files; narrow the roots or raise scan.max_files.",
+                        self.config.scan.max_files
+                    ));
+                    self.content_shown = usize::MAX;
+                }
+            }
+        }
+        self.files_changed = true;
+        self.should_redraw = true;
+    }
+
+    // Checks the typed name against the files once per frame rather than
+    // once per index event, as the initial scan sends thousands
+    fn revalidate(&mut self) {
+        if self.files_changed {
+            self.is_valid = validate(&mut self.textarea, &self.files);
+            self.files_changed = false;
+        }
+    }
+
+    // Puts the file's name into the input field and loads it, as if typed
+    fn open_path(&mut self, path: PathBuf) {
+        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+            self.textarea.select_all();
+            self.textarea.cut();
+            self.textarea.insert_str(name);
+            self.is_valid = validate(&mut self.textarea, &self.files);
+        }
+        self.start_load(path);
+    }
+
+    // Reloads after an edit, which want the result at once
+    fn load_file(&mut self, path: PathBuf) {
+        // A background load still running would show older content
+        self.file_load.cancel();
+        self.clear_file(&path);
+        let loaded = read_file(&self.parsers, &path);
+        self.show_file(path, loaded);
+    }
+
+    // Opening a file reads it in the background; `poll_load` shows it
+    fn start_load(&mut self, path: PathBuf) {
+        self.pending_jump = None; // Meant for the file loading before
+        self.clear_file(&path);
+        self.file_load.start(path, Arc::clone(&self.parsers));
+        self.should_redraw = true;
+    }
+
+    fn poll_load(&mut self) {
+        if let Some((path, loaded)) = self.file_load.finished() {
+            self.show_file(path, loaded);
+            self.should_redraw = true;
+        } else if self.file_load.loading().is_some() {
+            self.should_redraw = true; // Turns the spinner
+        }
+    }
+
+    fn clear_file(&mut self, path: &Path) {
+        if self.source_path().map(PathBuf::as_path) != Some(path) {
+            self.last_report = None; // Reloads keep the stats of the file
+            self.collapsed.clear();
+        }
+        self.document = None;
+        self.file_content = None;
+        self.selected = 0;
+        self.order_note = None;
+        self.scroll_offset = 0; // Reset scroll position
+        self.content_height = 0;
+        self.content_shown = 0;
+    }
+
+    fn show_file(&mut self, path: PathBuf, loaded: io::Result<LoadedFile>) {
+        match loaded {
+            Ok(LoadedFile::Document(mut document)) => {
+                if let Some(project) = project_of(&path) {
+                    if let Some(order) = project.order_of(&path) {
+                        apply_order(&mut document.equations, order);
+                    }
+                }
+                info!(
+                    "Loaded {} equation(s) from {}",
+                    document.equations.len(),
+                    path.display()
+                );
+                if let Ok(options) = self.render_options(PathBuf::new()) {
+                    warn_unsupported(&document.equations, &options);
+                }
+                self.document = Some(document);
+            }
+            Ok(LoadedFile::Text(content)) => {
+                self.file_content = Some(content);
+                self.show_more_content();
+            }
+            Err(e) => self.show_error(ErrorReport::io(&path.display().to_string(), &e)),
+        }
+        self.refresh_view();
+        if self.document.is_some() {
+            self.focus = Focus::Table;
+        }
+        if let Some((name, body)) = self.pending_jump.take() {
+            self.select_named(&name, &body);
+        }
+    }
+
+    // Selects the equation called `name`, or else the one with `body` in
+    // case it was renamed, if it has a row
+    fn select_named(&mut self, name: &str, body: &str) {
+        let equations = self.equations();
+        let row_of = |found: &dyn Fn(&Equation) -> bool| {
+            self.view
+                .iter()
+                .position(|row| matches!(row, ViewRow::Equation(i) if found(&equations[*i])))
+        };
+        let row = row_of(&|equation| equation.name == name)
+            .or_else(|| row_of(&|equation| equation.body == body));
+        if let Some(row) = row {
+            self.selected = row;
+        }
+    }
+
+    // Brings the next chunk of a plain file into the view, up to as many
+    // lines as the scroll offset can reach
+    fn show_more_content(&mut self) {
+        let Some(content) = &self.file_content else {
+            return;
+        };
+        let rest = &content[self.content_shown.min(content.len())..];
+        let room = (u16::MAX - self.content_height).min(PREVIEW_CHUNK_LINES);
+        for line in rest.split_inclusive('\n').take(room as usize) {
+            self.content_shown += line.len();
+            self.content_height += 1;
+        }
+    }
+
+    // Whether part of the plain file is still out of the view
+    fn more_content(&self) -> bool {
+        self.file_content
+            .as_ref()
+            .is_some_and(|content| self.content_shown < content.len())
+    }
+
+    fn source_path(&self) -> Option<&PathBuf> {
+        self.document.as_ref().map(|document| &document.path)
+    }
+
+    fn equations(&self) -> &[Equation] {
+        self.document
+            .as_ref()
+            .map_or(&[], |document| &document.equations)
+    }
+
+    fn refresh_view(&mut self) {
+        let view = sorted_view(self.equations(), self.sort, self.filter.as_ref());
+        match (&self.document, self.grouped) {
+            (Some(document), true) => {
+                (self.headings, self.sections) =
+                    equation_sections(&document.source, &document.equations);
+                self.view = grouped_view(&view, &self.sections, &self.collapsed);
+            }
+            _ => self.view = view.into_iter().map(ViewRow::Equation).collect(),
+        }
+        self.selected = self.selected.min(self.view.len().saturating_sub(1));
+        self.should_redraw = true;
+    }
+
+    fn selected_equation(&self) -> Option<&Equation> {
+        match self.view.get(self.selected)? {
+            ViewRow::Equation(i) => Some(&self.equations()[*i]),
+            ViewRow::Section(_) => None,
+        }
+    }
+
+    // The section of the selected row, while grouped
+    fn selected_section(&self) -> Option<Option<usize>> {
+        match *self.view.get(self.selected)? {
+            ViewRow::Section(section) => Some(section),
+            ViewRow::Equation(i) => self.sections.get(i).copied(),
+        }
+    }
+
+    // Folds the selected section, or unfolds it, keeping its row selected
+    fn toggle_section(&mut self) {
+        let Some(section) = self.selected_section().filter(|_| self.grouped) else {
+            return;
+        };
+        if !self.collapsed.remove(&section) {
+            self.collapsed.insert(section);
+        }
+        self.refresh_view();
+        if let Some(row) = self
+            .view
+            .iter()
+            .position(|row| *row == ViewRow::Section(section))
+        {
+            self.selected = row;
+        }
+    }
+
+    // Asks to render the active equations of the selected section
+    fn render_section(&mut self) {
+        let (Some(section), Some(path)) = (
+            self.selected_section().filter(|_| self.grouped),
+            self.source_path(),
+        ) else {
+            self.order_note = Some("group by section (g) to render one".to_string());
+            return;
+        };
+        let equations: Vec<Equation> = self
+            .equations()
+            .iter()
+            .zip(&self.sections)
+            .filter(|(_, of)| **of == section)
+            .map(|(equation, _)| equation.clone())
+            .collect();
+        let active = equations.iter().filter(|eq| eq.active).count();
+        if active == 0 {
+            self.order_note = Some("no active equations in this section".to_string());
+            return;
+        }
+        let title = section
+            .and_then(|i| self.headings.get(i))
+            .map_or("(before the first heading)", |heading| {
+                heading.title.as_str()
+            });
+        let message = format!(
+            "Render {} active equation(s) of {} into {}/ with profile {}?",
+            active,
+            title,
+            self.config.output_dir(path).display(),
+            self.profile.as_deref().unwrap_or("(none)")
+        );
+        self.confirm = Some((
+            ConfirmDialog::new("Render section", &message),
+            PendingAction::Render(equations),
+        ));
+    }
+
+    // Renders `equations` on the next loop turn, unless that would replace
+    // outputs changed since they were rendered; then asks first
+    fn request_render(&mut self, equations: Vec<Equation>) {
+        let Some(path) = self.source_path() else {
+            return;
+        };
+        let output_dir = self.config.output_dir(path);
+        let changed = self
+            .render_options(output_dir.clone())
+            .map(|options| changed_outputs(&equations, &options))
+            .unwrap_or_default(); // The render reports a broken profile
+        if changed.is_empty() {
+            self.render_requested = Some(equations);
+            return;
+        }
+        let files = changed
+            .iter()
+            .map(|output| {
+                let file = output
+                    .path
+                    .strip_prefix(&output_dir)
+                    .unwrap_or(&output.path);
+                file.display().to_string()
+            })
+            .collect();
+        self.overwrite = Some((OverwriteDialog::new(files), equations, changed));
+    }
+
+    fn resolve_overwrite(
+        &mut self,
+        outcome: OverwriteOutcome,
+        mut equations: Vec<Equation>,
+        changed: &[ChangedOutput],
+    ) {
+        match outcome {
+            OverwriteOutcome::Skip => {
+                for equation in &mut equations {
+                    if changed.iter().any(|output| output.name == equation.name) {
+                        equation.active = false;
+                    }
+                }
+            }
+            OverwriteOutcome::Backup => {
+                for output in changed {
+                    if let Err(e) = back_up(&output.path) {
+                        self.show_error(ErrorReport::io(&output.path.display().to_string(), &e));
+                        return;
+                    }
+                }
+            }
+            _ => {}
+        }
+        self.render_requested = Some(equations);
+    }
+
+    // Where the last render put the selected equation's SVG
+    fn preview_path(&self) -> Option<PathBuf> {
+        self.svg_path(self.selected_equation()?)
+    }
+
+    fn svg_path(&self, equation: &Equation) -> Option<PathBuf> {
+        let dir = self.config.output_dir(self.source_path()?);
+        Some(svg_in(&dir, &read_manifest(&dir), equation, &self.routes))
+    }
+
+    // Every equation row of the view with its SVG, in view order
+    fn gallery_items(&self) -> Vec<GalleryItem> {
+        let dir = self.source_path().map(|path| self.config.output_dir(path));
+        let manifest = dir.as_deref().map(read_manifest).unwrap_or_default();
+        self.view
+            .iter()
+            .filter_map(|row| match row {
+                ViewRow::Equation(i) => Some(&self.equations()[*i]),
+                ViewRow::Section(_) => None,
+            })
+            .map(|equation| GalleryItem {
+                name: equation.name.clone(),
+                svg: dir
+                    .as_ref()
+                    .map(|dir| svg_in(dir, &manifest, equation, &self.routes)),
+            })
+            .collect()
+    }
+
+    // The rows of `view` the gallery shows; section rows have no thumbnail
+    fn gallery_rows(&self) -> Vec<usize> {
+        (0..self.view.len())
+            .filter(|&row| matches!(self.view[row], ViewRow::Equation(_)))
+            .collect()
+    }
+
+    // Moves the selection by `delta` thumbnails, skipping section rows
+    fn move_in_gallery(&mut self, delta: isize) {
+        let rows = self.gallery_rows();
+        let Some(last) = rows.len().checked_sub(1) else {
+            return;
+        };
+        let at = rows
+            .iter()
+            .position(|&row| row >= self.selected)
+            .unwrap_or(last);
+        self.selected = rows[at.saturating_add_signed(delta).min(last)];
+        self.should_redraw = true;
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let last = self.view.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+        self.should_redraw = true;
+    }
+
+    // Swaps the selected equation with its neighbour in the table and saves
+    // the new order. Only the unsorted view reflects the order, so other
+    // sorts leave it alone.
+    fn move_equation(&mut self, delta: isize) {
+        if self.sort != SortOrder::default() {
+            self.order_note = Some("sort by document order to move".to_string());
+            self.should_redraw = true;
+            return;
+        }
+        let Some(target) = self.selected.checked_add_signed(delta) else {
+            return;
+        };
+        let (Some(&ViewRow::Equation(from)), Some(&ViewRow::Equation(to))) =
+            (self.view.get(self.selected), self.view.get(target))
+        else {
+            return;
+        };
+        if let Some(document) = &mut self.document {
+            document.equations.swap(from, to);
+        }
+        self.selected = target;
+        self.order_note = Some(match self.save_order() {
+            Ok(note) => note,
+            Err(e) => format!("order not saved: {}", e),
+        });
+        self.refresh_view();
+    }
+
+    // CSV and TOML files carry their own order; other sources keep it in the
+    // project
+    fn save_order(&mut self) -> io::Result<String> {
+        let Some(document) = &mut self.document else {
+            return Ok(String::new());
+        };
+        let path = document.path.clone();
+        if matches!(document.format, "csv" | "toml") {
+            let reread: fn(&str) -> Vec<Equation> = if document.format == "csv" {
+                reorder_csv_file(&path, &document.equations)?;
+                parse_csv
+            } else {
+                reorder_toml_file(&path, &document.equations)?;
+                parse_toml
+            };
+            // Re-read so the spans point at the moved rows again
+            let content = load_source(&path)?;
+            document.equations = reread(&content);
+            document.source = content;
+            return Ok(format!("order saved to {}", path.display()));
+        }
+        let Some(mut project) = project_of(&path) else {
+            return Ok(format!(
+                "order not saved: no {} project, see `simptui project init`",
+                PROJECT_FILE_NAME
+            ));
+        };
+        let names = document
+            .equations
+            .iter()
+            .map(|eq| eq.name.clone())
+            .collect();
+        project.set_order(&path, names)?;
+        project.save()?;
+        Ok(format!("order saved to {}", PROJECT_FILE_NAME))
+    }
+
+    fn open_filter_prompt(&mut self) {
+        let mut input = TextArea::default();
+        input.set_cursor_line_style(Style::default());
+        input.set_placeholder_text("Regex over equation bodies (empty clears)");
+        if let Some(filter) = &self.filter {
+            input.insert_str(filter.as_str());
+        }
+        input.set_block(Block::default().borders(Borders::ALL).title("Filter"));
+        self.filter_input = Some(input);
+        self.should_redraw = true;
+    }
+
+    fn handle_filter_input(&mut self, input: Input) {
+        let Some(prompt) = self.filter_input.as_mut() else {
+            return;
+        };
+        match input.key {
+            Key::Esc => self.filter_input = None,
+            Key::Enter => {
+                let pattern = prompt.lines()[0].trim().to_string();
+                if pattern.is_empty() {
+                    self.filter = None;
+                } else {
+                    match Regex::new(&pattern) {
+                        Ok(re) => self.filter = Some(re),
+                        Err(_) => {
+                            prompt.set_block(
+                                Block::default()
+                                    .borders(Borders::ALL)
+                                    .border_style(Style::default().fg(Color::LightRed))
+                                    .title("Filter: invalid regex"),
+                            );
+                            self.should_redraw = true;
+                            return;
                         }
-                        Err(e) => self.file_content = Some(format!("Error reading file: {}", e)),
                     }
+                }
+                self.filter_input = None;
+                self.selected = 0;
+                self.refresh_view();
+            }
+            _ => {
+                prompt.input(input);
+            }
+        }
+        self.should_redraw = true;
+    }
+
+    fn open_rename_form(&mut self) {
+        let mut input = TextArea::default();
+        input.set_cursor_line_style(Style::default());
+        input.set_placeholder_text("{file_stem}_{index:02}_{name}, also {heading} and {hash}");
+        input.set_block(Block::default().borders(Borders::ALL).title("Rename all"));
+        self.rename_input = Some(input);
+        self.should_redraw = true;
+    }
+
+    // New names for the loaded file in document order, which `{index}` counts
+    fn rename_plan(&self, pattern: &str) -> Result<(Vec<Equation>, Vec<String>), String> {
+        let Some(document) = &self.document else {
+            return Err("no file loaded".to_string());
+        };
+        let pattern: NamePattern = pattern.parse()?;
+        let mut equations = document.equations.clone();
+        equations.sort_by_key(|eq| eq.span.map(|span| span.start_line));
+        let names = pattern
+            .apply(&equations, &document.path, &document.source)
+            .map_err(|e| e.to_string())?;
+        Ok((equations, names))
+    }
+
+    // Keeps the form's title on the selected equation's new name, or the
+    // reason there is none
+    fn handle_rename_input(&mut self, input: Input) {
+        let Some(form) = self.rename_input.as_mut() else {
+            return;
+        };
+        match input.key {
+            Key::Esc => self.rename_input = None,
+            Key::Enter => {
+                let pattern = form.lines()[0].trim().to_string();
+                if pattern.is_empty() {
+                    self.rename_input = None;
+                    self.should_redraw = true;
+                    return;
+                }
+                // An invalid pattern keeps the form open on the error in its title
+                let Ok((equations, names)) = self.rename_plan(&pattern) else {
+                    self.update_rename_title();
+                    self.should_redraw = true;
+                    return;
+                };
+                self.rename_input = None;
+                let changed = equations
+                    .iter()
+                    .zip(&names)
+                    .filter(|(eq, name)| eq.name != **name)
+                    .count();
+                if changed > 0 {
+                    let file_name = self
+                        .source_path()
+                        .and_then(|path| path.file_name())
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    let message = format!(
+                        "Rename {} equation(s) in {}, e.g. {} -> {}?",
+                        changed, file_name, equations[0].name, names[0]
+                    );
+                    self.confirm = Some((
+                        ConfirmDialog::new("Rename", &message),
+                        PendingAction::Rename(equations, names),
+                    ));
                 } else {
-                    self.file_content = Some("File not found!".to_string());
+                    self.order_note = Some("names already match".to_string());
+                }
+            }
+            _ => {
+                form.input(input);
+                self.update_rename_title();
+            }
+        }
+        self.should_redraw = true;
+    }
+
+    fn update_rename_title(&mut self) {
+        let Some(pattern) = self
+            .rename_input
+            .as_ref()
+            .map(|form| form.lines()[0].clone())
+        else {
+            return;
+        };
+        let selected = self.selected_equation().map(|eq| eq.name.clone());
+        let (title, color) = match self.rename_plan(pattern.trim()) {
+            Ok((equations, names)) => {
+                let i = equations
+                    .iter()
+                    .position(|eq| Some(&eq.name) == selected.as_ref())
+                    .unwrap_or(0);
+                let example = names
+                    .get(i)
+                    .map(|name| format!(": {} -> {}", equations[i].name, name));
+                (
+                    format!("Rename all{}", example.unwrap_or_default()),
+                    Color::Reset,
+                )
+            }
+            Err(_) if pattern.trim().is_empty() => ("Rename all".to_string(), Color::Reset),
+            Err(e) => (format!("Rename all: {}", e), Color::LightRed),
+        };
+        if let Some(form) = self.rename_input.as_mut() {
+            form.set_block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(color))
+                    .title(title),
+            );
+        }
+    }
+
+    // Applies a confirmed bulk rename and reloads the file to show it
+    fn rename_all(&mut self, equations: &[Equation], names: &[String]) {
+        let Some(path) = self.source_path().cloned() else {
+            return;
+        };
+        let changed = equations
+            .iter()
+            .zip(names)
+            .filter(|(eq, name)| eq.name != **name)
+            .count();
+        let result = rename_source(&path, equations, names);
+        self.load_file(path);
+        self.order_note = Some(match result {
+            Ok(()) => format!("renamed {} equation(s)", changed),
+            Err(e) => format!("not renamed: {}", e),
+        });
+    }
+
+    fn confirm_format(&mut self) {
+        let Some(path) = self.source_path() else {
+            return;
+        };
+        if !matches!(detect_file_type(path), "markdown" | "csv" | "toml") {
+            self.order_note =
+                Some("only markdown, csv and toml bodies can be rewritten".to_string());
+            return;
+        }
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let equations = self.equations().to_vec();
+        let bodies: Vec<String> = equations.iter().map(|eq| format_body(&eq.body)).collect();
+        let changed = equations
+            .iter()
+            .zip(&bodies)
+            .filter(|(eq, body)| eq.body != **body)
+            .count();
+        if changed == 0 {
+            self.order_note = Some("bodies already formatted".to_string());
+            return;
+        }
+        let message = format!("Format {} equation(s) in {}?", changed, file_name);
+        self.confirm = Some((
+            ConfirmDialog::new("Format", &message),
+            PendingAction::Format(equations, bodies),
+        ));
+    }
+
+    fn format_all(&mut self, equations: &[Equation], bodies: &[String]) {
+        let Some(path) = self.source_path().cloned() else {
+            return;
+        };
+        let changed = equations
+            .iter()
+            .zip(bodies)
+            .filter(|(eq, body)| eq.body != **body)
+            .count();
+        let result = rewrite_bodies(&path, equations, bodies);
+        self.load_file(path);
+        self.order_note = Some(match result {
+            Ok(()) => format!("formatted {} equation(s)", changed),
+            Err(e) => format!("not formatted: {}", e),
+        });
+    }
+
+    // Asks to mark the equations the filter shows, only those of the
+    // selected section while grouped, active or inactive
+    fn confirm_set_active(&mut self, active: bool) {
+        let Some(path) = self.source_path() else {
+            return;
+        };
+        if !matches!(detect_file_type(path), "markdown" | "csv" | "toml") {
+            self.order_note =
+                Some("only markdown, csv and toml equations can be switched".to_string());
+            return;
+        }
+        let section = self.selected_section().filter(|_| self.grouped);
+        let shown = sorted_view(self.equations(), self.sort, self.filter.as_ref());
+        let equations: Vec<Equation> = shown
+            .into_iter()
+            .filter(|&i| section.is_none_or(|section| self.sections.get(i) == Some(&section)))
+            .map(|i| &self.equations()[i])
+            .filter(|eq| eq.active != active)
+            .cloned()
+            .collect();
+        let state = if active { "active" } else { "inactive" };
+        if equations.is_empty() {
+            self.order_note = Some(format!("shown equations already {}", state));
+            return;
+        }
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let scope = match section {
+            Some(section) => format!(
+                " of {}",
+                section
+                    .and_then(|i| self.headings.get(i))
+                    .map_or("(before the first heading)", |heading| {
+                        heading.title.as_str()
+                    })
+            ),
+            None => String::new(),
+        };
+        let message = format!(
+            "Mark {} equation(s){} in {} {}?",
+            equations.len(),
+            scope,
+            file_name,
+            state
+        );
+        let title = if active { "Activate" } else { "Deactivate" };
+        self.confirm = Some((
+            ConfirmDialog::new(title, &message),
+            PendingAction::SetActive(equations, active),
+        ));
+    }
+
+    fn set_active_all(&mut self, equations: &[Equation], active: bool) {
+        let Some(path) = self.source_path().cloned() else {
+            return;
+        };
+        let result = set_active_in_source(&path, equations, active);
+        self.load_file(path);
+        self.order_note = Some(match result {
+            Ok(()) => format!(
+                "marked {} equation(s) {}",
+                equations.len(),
+                if active { "active" } else { "inactive" }
+            ),
+            Err(e) => format!("not changed: {}", e),
+        });
+    }
+
+    // Bookmarks the selected equation, or takes its bookmark away
+    fn toggle_bookmark(&mut self) {
+        let (Some(path), Some(equation)) = (
+            self.source_path().cloned(),
+            self.selected_equation().cloned(),
+        ) else {
+            return;
+        };
+        let Some(bookmarks) = self.bookmarks.as_mut() else {
+            self.order_note = Some("bookmarks can't be read; see the log".to_string());
+            return;
+        };
+        self.order_note = Some(match bookmarks.toggle(&path, &equation) {
+            Ok(true) => format!("bookmarked {}", equation.name),
+            Ok(false) => format!("removed the bookmark of {}", equation.name),
+            Err(e) => format!("not bookmarked: {}", e),
+        });
+    }
+
+    // Asks to render every bookmark into `BOOKMARKS_DIR`
+    fn confirm_render_bookmarks(&mut self) {
+        let Some(bookmarks) = &self.bookmarks else {
+            return;
+        };
+        let (equations, missing) = bookmarks.equations(&self.parsers);
+        for bookmark in &missing {
+            warn!("Bookmarked equation not found: {}", bookmark);
+        }
+        if let Ok(options) = self.render_options(PathBuf::new()) {
+            warn_unsupported(&equations, &options);
+        }
+        if equations.is_empty() {
+            self.order_note = Some("none of the bookmarked equations were found".to_string());
+            return;
+        }
+        let mut message = format!(
+            "Render {} bookmarked equation(s) into {}/ with profile {}?",
+            equations.len(),
+            BOOKMARKS_DIR,
+            self.profile.as_deref().unwrap_or("(none)")
+        );
+        if !missing.is_empty() {
+            message.push_str(&format!(
+                " {} can't be found any more: {}.",
+                missing.len(),
+                missing.join(", ")
+            ));
+        }
+        self.confirm = Some((
+            ConfirmDialog::new("Render bookmarks", &message),
+            PendingAction::RenderBookmarks(equations),
+        ));
+    }
+
+    fn open_new_equation_form(&mut self) {
+        let Some(path) = self.source_path() else {
+            return;
+        };
+        if !matches!(detect_file_type(path), "markdown" | "csv" | "toml") {
+            self.order_note =
+                Some("only markdown, csv and toml files take new equations".to_string());
+            return;
+        }
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        self.new_equation = Some(NewEquationForm::new(&file_name));
+    }
+
+    fn handle_new_equation_input(&mut self, input: Input) {
+        let Some(form) = self.new_equation.as_mut() else {
+            return;
+        };
+        match form.handle_input(input) {
+            NewEquationOutcome::Open => {}
+            NewEquationOutcome::Cancel => self.new_equation = None,
+            NewEquationOutcome::PickSnippet => {
+                let items = self
+                    .snippets
+                    .iter()
+                    .map(|snippet| {
+                        let body = snippet.body.replace('\n', " ");
+                        match body.char_indices().nth(40) {
+                            Some((end, _)) => format!("{}: {}…", snippet.name, &body[..end]),
+                            None => format!("{}: {}", snippet.name, body),
+                        }
+                    })
+                    .collect();
+                self.snippet_picker = Some(ListPicker::new("Insert snippet", items, 0));
+            }
+            NewEquationOutcome::Submit { name, body } => match self.add_equation(&name, &body) {
+                Ok(()) => self.new_equation = None,
+                Err(e) => {
+                    if let Some(form) = self.new_equation.as_mut() {
+                        form.set_error(Some(e));
+                    }
+                }
+            },
+        }
+        self.should_redraw = true;
+    }
+
+    // Appends a checked equation to the loaded file, then selects it and
+    // renders its preview
+    fn add_equation(&mut self, name: &str, body: &str) -> Result<(), String> {
+        let Some(path) = self.source_path().cloned() else {
+            return Err("no file loaded".to_string());
+        };
+        check_new_equation(&path, self.equations(), name, body)?;
+        append_equation(&path, name, body).map_err(|e| e.to_string())?;
+        self.load_file(path);
+        // A filter could hide the new row
+        self.filter = None;
+        self.refresh_view();
+        if let Some(row) = self.view.iter().position(
+            |row| matches!(row, ViewRow::Equation(i) if self.equations()[*i].name == name),
+        ) {
+            self.selected = row;
+        }
+        self.order_note = Some(format!("added {}", name));
+        if let Some(equation) = self.selected_equation().cloned() {
+            self.show_preview = true;
+            self.preview.reset();
+            self.render_preview(&equation);
+        }
+        Ok(())
+    }
+
+    fn show_error(&mut self, report: ErrorReport) {
+        error!("{}: {}: {}", report.kind, report.subject, report.message);
+        let copy_key = self.keymap.label(Action::CopyError).unwrap_or_default();
+        self.error = Some(ErrorScreen::new(report, copy_key));
+        self.should_redraw = true;
+    }
+
+    // Scrolls or copies the open error report; other keys close it
+    fn handle_error_input(&mut self, input: Input) {
+        let action = self.keymap.action(&input, self.focus);
+        let Some(screen) = self.error.as_mut() else {
+            return;
+        };
+        match action {
+            Some(Action::CopyError) => {
+                screen.set_note(match copy_text(&screen.report.full_text()) {
+                    Ok(tool) => format!("Copied to the clipboard with {}", tool),
+                    Err(e) => format!("Copying failed: {}", e),
+                })
+            }
+            Some(Action::Up) => screen.scroll_by(-1),
+            Some(Action::Down) => screen.scroll_by(1),
+            Some(Action::PageUp) => screen.scroll_by(-5),
+            Some(Action::PageDown) => screen.scroll_by(5),
+            _ => self.error = None,
+        }
+        self.should_redraw = true;
+    }
+
+    fn handle_input(&mut self, input: Input) -> bool {
+        if self.error.is_some() {
+            self.handle_error_input(input);
+            return false;
+        }
+        if !self.failures.is_empty() {
+            if input.key == Key::Enter {
+                if let Some(path) = self.source_path() {
+                    // Next to the outputs of its route, where it was rendered
+                    let failure = &self.failures[0];
+                    let route = self
+                        .equations()
+                        .iter()
+                        .find(|equation| equation.name == failure.name)
+                        .and_then(|equation| route_of(&self.routes, equation));
+                    let failed_dir = self
+                        .config
+                        .output_dir(path)
+                        .join(route.unwrap_or(Path::new("")))
+                        .join("failed");
+                    let report = ErrorReport::render_failure(failure, &failed_dir);
+                    self.show_error(report);
+                }
+            }
+            self.failures.clear();
+            self.should_redraw = true;
+            return false;
+        }
+        if self.help {
+            self.help = false;
+            self.should_redraw = true;
+            return false;
+        }
+
+        if let Some((dialog, ..)) = self.overwrite.as_mut() {
+            match dialog.handle_input(input) {
+                OverwriteOutcome::Open => {}
+                OverwriteOutcome::Cancelled => self.overwrite = None,
+                outcome => {
+                    let (_, equations, changed) = self.overwrite.take().unwrap();
+                    self.resolve_overwrite(outcome, equations, &changed);
+                }
+            }
+            self.should_redraw = true;
+            return false;
+        }
+
+        if let Some((dialog, _)) = self.confirm.as_mut() {
+            if let Some(confirmed) = dialog.handle_input(input) {
+                let (_, action) = self.confirm.take().unwrap();
+                if confirmed {
+                    match action {
+                        PendingAction::Render(equations) => self.request_render(equations),
+                        PendingAction::Rename(equations, names) => {
+                            self.rename_all(&equations, &names)
+                        }
+                        PendingAction::Format(equations, bodies) => {
+                            self.format_all(&equations, &bodies)
+                        }
+                        PendingAction::SetActive(equations, active) => {
+                            self.set_active_all(&equations, active)
+                        }
+                        PendingAction::RenderBookmarks(equations) => {
+                            self.bookmark_render = Some(equations)
+                        }
+                    }
+                }
+            }
+            self.should_redraw = true;
+            return false;
+        }
+
+        if let Some(search) = self.search.as_mut() {
+            match search.handle_input(input) {
+                SearchOutcome::Open => {}
+                SearchOutcome::Run(query) => {
+                    let pattern = match query.strip_prefix("re:") {
+                        Some(regex) => search_pattern(regex, true, false),
+                        None => search_pattern(&query, false, false),
+                    };
+                    let files: Vec<PathBuf> =
+                        self.files.iter().map(|f| f.full_path.clone()).collect();
+                    let result =
+                        pattern.map(|pattern| search_equations(&files, &self.parsers, &pattern));
+                    search.set_results(query, result);
+                }
+                SearchOutcome::Jump(hit) => {
+                    self.search = None;
+                    self.open_path(hit.path);
+                }
+                SearchOutcome::Close => self.search = None,
+            }
+            self.should_redraw = true;
+            return false;
+        }
+
+        if let Some(screen) = self.bookmark_screen.as_mut() {
+            match screen.handle_input(input) {
+                BookmarkOutcome::Open => {}
+                BookmarkOutcome::Jump(i) => {
+                    let bookmark = self
+                        .bookmarks
+                        .as_ref()
+                        .and_then(|bookmarks| bookmarks.list().get(i))
+                        .cloned();
+                    if let Some(bookmark) = bookmark {
+                        self.bookmark_screen = None;
+                        self.open_path(bookmark.file);
+                        self.pending_jump = Some((bookmark.name, bookmark.body));
+                    }
+                }
+                BookmarkOutcome::Remove(i) => {
+                    if let Some(bookmarks) = self.bookmarks.as_mut() {
+                        let removed = bookmarks.remove(i);
+                        screen.set_bookmarks(bookmarks.list());
+                        if let Err(e) = removed {
+                            self.show_error(ErrorReport::io("bookmarks", &e));
+                        }
+                    }
+                }
+                BookmarkOutcome::RenderAll => {
+                    self.bookmark_screen = None;
+                    self.confirm_render_bookmarks();
+                }
+                BookmarkOutcome::Close => self.bookmark_screen = None,
+            }
+            self.should_redraw = true;
+            return false;
+        }
+
+        if let Some(picker) = self.profile_picker.as_mut() {
+            match picker.handle_input(input) {
+                PickerOutcome::Open => {}
+                PickerOutcome::Picked(i) => {
+                    // Index 0 is "(none)"
+                    self.profile = i.checked_sub(1).and_then(|i| self.profiles.get(i).cloned());
+                    self.profile_picker = None;
+                    self.backend_label = self.describe_backend();
+                    self.watch_template();
+                }
+                PickerOutcome::Cancelled => self.profile_picker = None,
+            }
+            self.should_redraw = true;
+            return false;
+        }
+
+        if self.filter_input.is_some() {
+            self.handle_filter_input(input);
+            return false;
+        }
+
+        if self.rename_input.is_some() {
+            self.handle_rename_input(input);
+            return false;
+        }
+
+        if let Some(picker) = self.snippet_picker.as_mut() {
+            match picker.handle_input(input) {
+                PickerOutcome::Open => {}
+                PickerOutcome::Picked(i) => {
+                    if let (Some(form), Some(snippet)) =
+                        (self.new_equation.as_mut(), self.snippets.get(i))
+                    {
+                        form.insert_snippet(&snippet.body);
+                    }
+                    self.snippet_picker = None;
+                }
+                PickerOutcome::Cancelled => self.snippet_picker = None,
+            }
+            self.should_redraw = true;
+            return false;
+        }
+
+        if self.new_equation.is_some() {
+            self.handle_new_equation_input(input);
+            return false;
+        }
+
+        if self.log.searching() {
+            self.log.handle_search_input(input);
+            self.should_redraw = true;
+            return false;
+        }
+
+        if let Some(palette) = self.palette.as_mut() {
+            self.should_redraw = true;
+            match palette.handle_input(input) {
+                PaletteOutcome::Open => {}
+                PaletteOutcome::Close => self.palette = None,
+                PaletteOutcome::Run(action) => {
+                    self.palette = None;
+                    return self.run_action(action);
+                }
+            }
+            return false;
+        }
+
+        let Some(action) = self.keymap.action(&input, self.focus) else {
+            // Plain keys don't reach the filename field while the table has focus
+            if self.focus == Focus::Input && self.textarea.input(input) {
+                self.is_valid = validate(&mut self.textarea, &self.files);
+                self.should_redraw = true;
+            }
+            return false;
+        };
+        self.should_redraw = true;
+        self.run_action(action)
+    }
+
+    // Returns true when the app should exit
+    // Whether the command palette lists `action`: only what would do
+    // something from where the user is
+    fn offers(&self, action: Action) -> bool {
+        let previewing = self.show_preview || self.side_by_side;
+        match action {
+            Action::LoadFile => self.is_valid,
+            Action::FocusTable => {
+                self.focus != Focus::Table && (self.document.is_some() || self.show_tree)
+            }
+            Action::FocusInput => self.focus != Focus::Input,
+            Action::TreeOpen => self.show_tree && self.focus == Focus::Tree,
+            Action::ShowBookmarks => self.bookmarks.is_some(),
+            Action::Bookmark | Action::CopyImage | Action::OpenViewer => {
+                self.selected_equation().is_some()
+            }
+            Action::ToggleSection | Action::RenderSection => {
+                self.document.is_some() && self.grouped
+            }
+            Action::Render
+            | Action::Filter
+            | Action::CycleSort
+            | Action::ReverseSort
+            | Action::GroupSections
+            | Action::MoveUp
+            | Action::MoveDown
+            | Action::RenameAll
+            | Action::FormatBodies
+            | Action::ActivateShown
+            | Action::DeactivateShown
+            | Action::NewEquation
+            | Action::TogglePreview
+            | Action::SideBySide
+            | Action::Gallery => self.document.is_some(),
+            Action::FocusPreview => previewing && self.focus != Focus::Preview,
+            Action::ZoomIn | Action::ZoomOut | Action::ZoomReset => previewing,
+            _ => true,
+        }
+    }
+
+    fn run_action(&mut self, action: Action) -> bool {
+        match action {
+            Action::Quit => return true,
+            Action::Help => self.help = true,
+            Action::Palette => {
+                let palette = CommandPalette::new(&self.keymap, |action| self.offers(action));
+                self.palette = Some(palette);
+            }
+            Action::Search => self.search = Some(SearchScreen::new()),
+            Action::Bookmark => self.toggle_bookmark(),
+            Action::ShowBookmarks => match &self.bookmarks {
+                Some(bookmarks) => {
+                    self.bookmark_screen = Some(BookmarkScreen::new(bookmarks.list()))
                 }
-                self.should_redraw = true;
-                false
+                None => self.order_note = Some("bookmarks can't be read; see the log".to_string()),
+            },
+            Action::PickProfile => {
+                let items: Vec<String> = std::iter::once("(none)".to_string())
+                    .chain(self.profiles.iter().cloned())
+                    .collect();
+                let selected = self
+                    .profile
+                    .as_ref()
+                    .and_then(|p| self.profiles.iter().position(|name| name == p))
+                    .map_or(0, |i| i + 1);
+                self.profile_picker = Some(ListPicker::new("Render profile", items, selected));
             }
-            Input { key: Key::Up, .. } => {
-                if self.scroll_offset > 0 {
-                    self.scroll_offset -= 1;
-                    self.should_redraw = true;
+            Action::Render => {
+                let active = self.equations().iter().filter(|eq| eq.active).count();
+                if let (true, Some(path)) = (active > 0, self.source_path()) {
+                    let message = format!(
+                        "Render {} active equation(s) into {}/ with profile {}?",
+                        active,
+                        self.config.output_dir(path).display(),
+                        self.profile.as_deref().unwrap_or("(none)")
+                    );
+                    self.confirm = Some((
+                        ConfirmDialog::new("Render", &message),
+                        PendingAction::Render(self.equations().to_vec()),
+                    ));
                 }
-                false
             }
-            Input { key: Key::Down, .. } => {
-                if self.scroll_offset < self.content_height.saturating_sub(1) {
-                    self.scroll_offset += 1;
-                    self.should_redraw = true;
+            Action::LoadFile if self.is_valid => {
+                let input = self.textarea.lines()[0].trim();
+                match self.files.iter().find(|file| file.file_name == input) {
+                    Some(entry) => self.start_load(entry.full_path.clone()),
+                    None => self.show_error(ErrorReport::unknown_file(input, self.scanning)),
                 }
-                false
             }
-            Input {
-                key: Key::PageUp, ..
-            } => {
-                self.scroll_offset = self.scroll_offset.saturating_sub(5); // Scroll up by 5 lines
-                self.should_redraw = true;
-                false
+            Action::LoadFile => {}
+            Action::FocusTable if self.document.is_some() => self.focus = Focus::Table,
+            Action::FocusTable if self.show_tree => self.focus = Focus::Tree,
+            Action::FocusTable => {}
+            Action::ToggleTree if !self.show_tree || self.focus != Focus::Tree => {
+                self.show_tree = true;
+                self.focus = Focus::Tree;
             }
-            Input {
-                key: Key::PageDown, ..
-            } => {
-                self.scroll_offset =
-                    (self.scroll_offset + 5).min(self.content_height.saturating_sub(1)); // Scroll down by 5 lines
-                self.should_redraw = true;
-                false
+            Action::ToggleTree => {
+                self.show_tree = false;
+                self.focus = Focus::Input;
             }
-            input => {
-                if self.textarea.input(input) {
-                    self.is_valid = validate(&mut self.textarea, &self.files);
-                    self.should_redraw = true;
+            Action::TreeOpen => {
+                if let Some(path) = self.tree.activate() {
+                    self.open_path(path);
+                }
+            }
+            Action::TreeExpand => self.tree.expand(),
+            Action::TreeCollapse => self.tree.collapse(),
+            Action::FocusInput => self.focus = Focus::Input,
+            Action::TogglePreview => {
+                self.show_preview = !self.show_preview;
+                self.preview.reset();
+            }
+            Action::SideBySide => {
+                self.side_by_side = !self.side_by_side;
+                self.preview.reset();
+            }
+            Action::FocusPreview if self.show_preview || self.side_by_side => {
+                self.focus = Focus::Preview
+            }
+            Action::FocusPreview => {}
+            Action::Gallery if self.document.is_some() => {
+                self.focus = Focus::Gallery;
+                // Onto an equation, should a section row be selected
+                self.move_in_gallery(0);
+            }
+            Action::Gallery => {}
+            Action::GalleryLeft => self.move_in_gallery(-1),
+            Action::GalleryRight => self.move_in_gallery(1),
+            Action::ZoomIn => self.preview.zoom_in(),
+            Action::ZoomOut => self.preview.zoom_out(),
+            Action::ZoomReset => self.preview.reset(),
+            Action::PanUp => self.preview.pan(0, -2),
+            Action::PanDown => self.preview.pan(0, 2),
+            Action::PanLeft => self.preview.pan(-4, 0),
+            Action::PanRight => self.preview.pan(4, 0),
+            Action::Filter => self.open_filter_prompt(),
+            Action::CycleSort => {
+                self.sort.key = self.sort.key.next();
+                self.refresh_view();
+            }
+            Action::ReverseSort => {
+                self.sort.descending = !self.sort.descending;
+                self.refresh_view();
+            }
+            Action::RenameAll => self.open_rename_form(),
+            Action::FormatBodies => self.confirm_format(),
+            Action::ActivateShown => self.confirm_set_active(true),
+            Action::DeactivateShown => self.confirm_set_active(false),
+            Action::NewEquation => self.open_new_equation_form(),
+            Action::CopyImage => self.copy_image(),
+            Action::OpenViewer => self.open_viewer(),
+            Action::GroupSections => {
+                self.grouped = !self.grouped;
+                self.refresh_view();
+            }
+            Action::ToggleSection => self.toggle_section(),
+            Action::RenderSection => self.render_section(),
+            Action::CopyError => {} // Only while the error screen is open
+            Action::ToggleLog if self.show_log && self.focus == Focus::Log => {
+                self.show_log = false;
+                self.focus = Focus::Input;
+            }
+            Action::ToggleLog => {
+                self.show_log = true;
+                self.focus = Focus::Log;
+            }
+            // From the palette the log may be hidden; its prompt has to show
+            Action::SearchLog => {
+                self.show_log = true;
+                self.focus = Focus::Log;
+                self.log.open_search();
+            }
+            Action::ExportLog => {
+                self.show_log = true;
+                self.focus = Focus::Log;
+                let path = Path::new(SESSION_LOG);
+                let note = match self.session_log.export(path) {
+                    Ok(()) => format!("written to {}", path.display()),
+                    Err(e) => format!("export failed: {}", e),
+                };
+                self.log.set_note(note);
+            }
+            Action::MoveUp => self.move_equation(-1),
+            Action::MoveDown => self.move_equation(1),
+            Action::Up | Action::Down | Action::PageUp | Action::PageDown => {
+                let delta = match action {
+                    Action::Up => -1,
+                    Action::Down => 1,
+                    Action::PageUp => -5,
+                    _ => 5,
+                };
+                if self.focus == Focus::Tree {
+                    self.tree.move_selection(delta);
+                } else if self.focus == Focus::Log {
+                    self.log.scroll(delta);
+                } else if self.focus == Focus::Gallery {
+                    self.move_in_gallery(delta * self.gallery.columns() as isize);
+                } else if self.document.is_some() {
+                    self.move_selection(delta);
+                } else {
+                    // Scroll the plain file view, reading on past its end
+                    let offset = self.scroll_offset.saturating_add_signed(delta as i16);
+                    if offset > self.max_scroll() {
+                        self.show_more_content();
+                    }
+                    self.scroll_offset = offset.min(self.max_scroll());
                 }
-                false
             }
         }
+        false
+    }
+
+    // Far enough for the last line of the plain file view to reach the bottom
+    fn max_scroll(&self) -> u16 {
+        self.content_height.saturating_sub(self.content_view)
     }
 
     fn draw(&mut self, term: &mut Terminal<CrosstermBackend<io::Stdout>>) -> io::Result<()> {
@@ -168,22 +2214,284 @@ impl App {
             .constraints([
                 Constraint::Length(3), // Input area
                 Constraint::Min(1),    // File content area
+                Constraint::Length(1), // Status line
+                Constraint::Length(1), // Hint bar
             ])
             .split(rect);
+        // Too narrow for panes side by side: only the focused one is shown
+        let narrow = rect.width < NARROW_WIDTH;
+        let span = self.selected_equation().and_then(|eq| eq.span);
+        let output_dir = self.source_path().map(|path| self.config.output_dir(path));
+        let backend = self.backend_label.clone();
+        if self.show_preview || self.side_by_side {
+            self.preview.show(self.preview_path());
+        }
+        let (gallery_items, gallery_selected) = if self.focus == Focus::Gallery {
+            let selected = self
+                .gallery_rows()
+                .partition_point(|&row| row < self.selected);
+            (self.gallery_items(), selected)
+        } else {
+            (Vec::new(), 0)
+        };
+
+        let log_entries = if self.show_log {
+            self.session_log.entries()
+        } else {
+            Vec::new()
+        };
+        self.log_drawn = self.session_log.logged();
 
         term.draw(|f| {
             // Input area
             f.render_widget(&self.textarea, layout[0]);
 
+            // Session log along the bottom of the content area
+            let main = if self.show_log {
+                let panes = Layout::default()
+                    .constraints([
+                        Constraint::Min(1),
+                        Constraint::Length((layout[1].height / 3).max(6)),
+                    ])
+                    .split(layout[1]);
+                let pane = LogPane {
+                    entries: &log_entries,
+                    focused: self.focus == Focus::Log,
+                };
+                f.render_stateful_widget(pane, panes[1], &mut self.log);
+                panes[0]
+            } else {
+                layout[1]
+            };
+
+            // File tree to the left of everything else, or in place of it
+            let view = FileTreeView {
+                focused: self.focus == Focus::Tree,
+            };
+            let content = if self.show_tree && narrow && self.focus == Focus::Tree {
+                f.render_stateful_widget(view, main, &mut self.tree);
+                None
+            } else if self.show_tree && !narrow {
+                let columns = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([
+                        Constraint::Length(32.min(main.width / 3)),
+                        Constraint::Min(1),
+                    ])
+                    .split(main);
+                f.render_stateful_widget(view, columns[0], &mut self.tree);
+                Some(columns[1])
+            } else {
+                Some(main)
+            };
+
+            // Equation table with the source context of the selected row
+            let preview_focused =
+                self.focus == Focus::Preview && (self.show_preview || self.side_by_side);
+            if let (Some(_), Some(content), Focus::Gallery) = (&self.document, content, self.focus)
+            {
+                let grid = ThumbnailGrid {
+                    items: &gallery_items,
+                    selected: gallery_selected,
+                };
+                f.render_stateful_widget(grid, content, &mut self.gallery);
+            } else if let (Some(document), Some(content)) = (&self.document, content) {
+                let (table_area, detail_area) = if !narrow {
+                    let panes = Layout::default()
+                        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                        .split(content);
+                    (Some(panes[0]), Some(panes[1]))
+                } else if preview_focused {
+                    (None, Some(content))
+                } else {
+                    (Some(content), None)
+                };
+                let rows: Vec<TableRow> = self
+                    .view
+                    .iter()
+                    .map(|row| match *row {
+                        ViewRow::Equation(i) => TableRow::Equation(&document.equations[i]),
+                        ViewRow::Section(section) => TableRow::Section {
+                            heading: section.and_then(|i| self.headings.get(i)),
+                            equations: self.sections.iter().filter(|of| **of == section).count(),
+                            collapsed: self.collapsed.contains(&section),
+                        },
+                    })
+                    .collect();
+                let mut state = TableState::default().with_selected(Some(self.selected));
+                let table_width = table_area.map_or(content.width, |area| area.width);
+                let mut table = equation_table(&rows, self.sort, table_width);
+                if self.focus == Focus::Table {
+                    table = table.block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_style(Style::default().fg(Color::Cyan))
+                            .title(table_title(
+                                self.filter.as_ref(),
+                                self.order_note.as_deref(),
+                            )),
+                    );
+                }
+                if let Some(area) = table_area {
+                    f.render_stateful_widget(table, area, &mut state);
+                }
+                match detail_area {
+                    Some(area) if self.side_by_side => {
+                        let body = match self.view.get(self.selected) {
+                            Some(ViewRow::Equation(i)) => document.equations[*i].body.as_str(),
+                            _ => "",
+                        };
+                        let approximation = unicode_approximation(body);
+                        // The source half goes first when there is no room
+                        let area = if narrow {
+                            area
+                        } else {
+                            let halves = Layout::default()
+                                .direction(Direction::Horizontal)
+                                .constraints([
+                                    Constraint::Percentage(50),
+                                    Constraint::Percentage(50),
+                                ])
+                                .split(area);
+                            f.render_widget(latex_source(body), halves[0]);
+                            halves[1]
+                        };
+                        let pane = PreviewPane {
+                            focused: self.focus == Focus::Preview,
+                            live: self.live.is_some(),
+                            error: self.live_error.as_deref(),
+                            fallback: Some(&approximation),
+                        };
+                        f.render_stateful_widget(pane, area, &mut self.preview);
+                    }
+                    Some(area) if self.show_preview => {
+                        let pane = PreviewPane {
+                            focused: self.focus == Focus::Preview,
+                            live: self.live.is_some(),
+                            error: self.live_error.as_deref(),
+                            fallback: None,
+                        };
+                        f.render_stateful_widget(pane, area, &mut self.preview);
+                    }
+                    Some(area) => {
+                        // A body cut short in the table shows in full above its context
+                        let body = match self.view.get(self.selected) {
+                            Some(ViewRow::Equation(i)) => document.equations[*i].body.as_str(),
+                            _ => "",
+                        };
+                        let area = if body_truncated(body, table_width) {
+                            let inner = area.width.saturating_sub(2).max(1) as usize;
+                            let lines: usize = body
+                                .lines()
+                                .map(|line| line.chars().count().div_ceil(inner).max(1))
+                                .sum();
+                            let lines = lines as u16 + 2;
+                            let panes = Layout::default()
+                                .constraints([
+                                    Constraint::Length(lines.min(area.height / 2)),
+                                    Constraint::Min(0),
+                                ])
+                                .split(area);
+                            f.render_widget(latex_source(body), panes[0]);
+                            panes[1]
+                        } else {
+                            area
+                        };
+                        f.render_widget(source_context(&document.source, span), area);
+                    }
+                    None => {}
+                }
+
+                let prompt = self.filter_input.as_ref().or(self.rename_input.as_ref());
+                if let (Some(prompt), Some(table_area)) = (prompt, table_area) {
+                    let area = Rect::new(
+                        table_area.x,
+                        table_area.bottom().saturating_sub(3),
+                        table_area.width,
+                        3.min(table_area.height),
+                    );
+                    f.render_widget(ratatui::widgets::Clear, area);
+                    f.render_widget(prompt, area);
+                }
+            }
+
             // File content area
-            let file_content = self
-                .file_content
-                .as_deref()
-                .unwrap_or("No file content loaded.");
-            let paragraph = Paragraph::new(file_content)
-                .block(Block::default().borders(Borders::ALL).title("File Content"))
-                .scroll((self.scroll_offset, 0)); // Apply vertical scroll offset
-            f.render_widget(paragraph, layout[1]);
+            let status = if let Some((path, started)) = self.file_load.loading() {
+                let frame = started.elapsed().as_millis() / 100 % SPINNER.len() as u128;
+                format!("{} Loading {}...", SPINNER[frame as usize], path.display())
+            } else if self.scanning {
+                format!("Scanning files... ({} found)", self.files.len())
+            } else {
+                "No file content loaded.".to_string()
+            };
+            if let (None, Some(content)) = (&self.document, content) {
+                // A resize may have left the offset past the new bottom
+                self.content_view = content.height.saturating_sub(2);
+                self.scroll_offset = self.scroll_offset.min(self.max_scroll());
+                let file_content = match &self.file_content {
+                    Some(content) => &content[..self.content_shown.min(content.len())],
+                    None => &status,
+                };
+                let title = match self.more_content() {
+                    true => format!("File Content (first {} lines)", self.content_height),
+                    false => "File Content".to_string(),
+                };
+                let paragraph = Paragraph::new(file_content)
+                    .block(Block::default().borders(Borders::ALL).title(title))
+                    .scroll((self.scroll_offset, 0)); // Apply vertical scroll offset
+                f.render_widget(paragraph, content);
+            }
+
+            let status = StatusLine {
+                document: self.document.as_ref(),
+                report: self.last_report.as_ref(),
+                backend: &backend,
+                output_dir: output_dir.as_deref(),
+                rendering: self.preview_render.is_running(),
+            };
+            f.render_widget(status, layout[2]);
+            f.render_widget(hint_bar(&self.keymap, self.focus), layout[3]);
+
+            if let Some(form) = &self.new_equation {
+                f.render_widget(form, f.area());
+            }
+            if let Some(picker) = &self.snippet_picker {
+                f.render_widget(picker, f.area());
+            }
+            if let Some(search) = &self.search {
+                f.render_widget(search, f.area());
+            }
+            if let Some(screen) = &self.bookmark_screen {
+                f.render_widget(screen, f.area());
+            }
+            if let Some(picker) = &self.profile_picker {
+                f.render_widget(picker, f.area());
+            }
+            if let Some((dialog, _)) = &self.confirm {
+                f.render_widget(dialog, f.area());
+            }
+            if let Some((dialog, ..)) = &self.overwrite {
+                f.render_widget(dialog, f.area());
+            }
+            if !self.failures.is_empty() {
+                let panel = FailuresPanel {
+                    failures: &self.failures,
+                };
+                f.render_widget(panel, f.area());
+            }
+            if let Some(error) = &self.error {
+                f.render_widget(error, f.area());
+            }
+            if let Some(palette) = &self.palette {
+                f.render_widget(palette, f.area());
+            }
+            if self.help {
+                let overlay = HelpOverlay {
+                    keymap: &self.keymap,
+                };
+                f.render_widget(overlay, f.area());
+            }
+            self.caps.degrade(f.buffer_mut());
         })?;
 
         self.should_redraw = false;
@@ -191,19 +2499,14 @@ impl App {
     }
 }
 
-fn files_in_folder(dir_path: &str) -> io::Result<Vec<FileEntry>> {
-    let mut files = Vec::new();
-    for entry in WalkDir::new(dir_path).into_iter().filter_map(|e| e.ok()) {
-        if entry.path().is_file() {
-            if let Some(file_name) = entry.file_name().to_str() {
-                files.push(FileEntry {
-                    full_path: entry.path().to_path_buf(),
-                    file_name: file_name.to_string(),
-                });
-            }
-        }
+impl FileEntry {
+    fn from_path(path: PathBuf) -> Option<Self> {
+        let file_name = path.file_name()?.to_str()?.to_string();
+        Some(FileEntry {
+            full_path: path,
+            file_name,
+        })
     }
-    Ok(files)
 }
 
 fn validate(textarea: &mut TextArea, files: &[FileEntry]) -> bool {
@@ -237,6 +2540,39 @@ fn setup_terminal() -> io::Result<Terminal<CrosstermBackend<io::Stdout>>> {
     Terminal::new(backend)
 }
 
+// Hands the terminal back to the shell while a batch renders with its
+// progress bar, then re-enters the TUI. Returns the batch's outcome; only
+// terminal errors are the outer error.
+fn render_suspended(
+    term: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    equations: &[Equation],
+    config: &Config,
+    profile: Option<&str>,
+    out: PathBuf,
+    source: Option<&Path>,
+) -> io::Result<io::Result<RenderReport>> {
+    restore_terminal(term)?;
+    let result = build_render_options(config, profile, out, None, None).and_then(|mut options| {
+        options.source_name = source
+            .and_then(Path::file_stem)
+            .map(|stem| stem.to_string_lossy().into_owned());
+        options.source_file = source.map(Path::to_path_buf);
+        // Ctrl-C stops the batch and its engine, not the session
+        let _interrupts = catch_interrupts();
+        render_equations(equations, &options)
+    });
+    if let Err(e) = &result {
+        eprintln!("Rendering failed: {}", e);
+    }
+    println!("Press Enter to return to simptui.");
+    io::stdin().read_line(&mut String::new())?;
+
+    enable_raw_mode()?;
+    crossterm::execute!(term.backend_mut(), EnterAlternateScreen, EnableMouseCapture)?;
+    term.clear()?;
+    Ok(result)
+}
+
 fn restore_terminal(term: &mut Terminal<CrosstermBackend<io::Stdout>>) -> io::Result<()> {
     disable_raw_mode()?;
     crossterm::execute!(
@@ -248,28 +2584,1189 @@ fn restore_terminal(term: &mut Terminal<CrosstermBackend<io::Stdout>>) -> io::Re
     Ok(())
 }
 
-fn main() -> io::Result<()> {
-    let mut term = setup_terminal()?;
-    let mut app = App::new();
+// Scrubs every output and has TeX stamp the PDFs with a fixed date, that of
+// SOURCE_DATE_EPOCH when the caller set one
+fn make_deterministic(options: &mut RenderOptions) -> io::Result<()> {
+    options.deterministic = true;
+    options.pipeline.insert_after("post-process", Scrub)
+}
 
-    loop {
-        if app.should_redraw {
-            app.draw(&mut term)?;
+// Renders the batch again to compare, then records the checksums
+fn check_reproducible(equations: &[Equation], options: &RenderOptions) -> io::Result<()> {
+    let differing = verify_reproducible(equations, options)?;
+    if !differing.is_empty() {
+        return Err(io::Error::other(format!(
+            "Rendering {} again gave different bytes for: {}",
+            options.output_dir.display(),
+            differing.join(", ")
+        )));
+    }
+    let checksums = write_checksums(&options.output_dir)?;
+    println!("Reproducible; checksums in {}", checksums.display());
+    Ok(())
+}
+
+// Explicit flags win over the profile, which wins over the defaults
+fn build_render_options(
+    config: &Config,
+    profile: Option<&str>,
+    out: PathBuf,
+    color: Option<ColorSpec>,
+    theme: Option<&Path>,
+) -> io::Result<RenderOptions> {
+    let profile = profile.map(|name| config.profile(name)).transpose()?;
+    let color = match (color, profile.and_then(|p| p.color.as_deref())) {
+        (Some(spec), _) => spec,
+        (None, Some(spec)) => spec
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?,
+        (None, None) => ColorSpec::Hex(Rgb::from_hex(DEFAULT_COLOR).unwrap()),
+    };
+
+    let mut options = RenderOptions::new(out, &resolve_color(&color, theme)?);
+    options.cache_dir = Some(Paths::new().render_cache_dir());
+    options.naming = config.output.naming;
+    options.remote = config.remote_backend();
+    options.routes = config.routes()?;
+    if let Some(profile) = profile {
+        profile.apply(&mut options);
+    }
+    config.apply_hooks(&mut options)?;
+    Ok(options)
+}
+
+fn main() -> io::Result<()> {
+    let cli = Cli::parse();
+    let caps = Capabilities::detect();
+    let tui = cli.command.is_none() && !cli.no_tui && caps.fullscreen && caps.interactive;
+    let verbosity = Verbosity::new(cli.quiet, cli.verbose);
+    let session = tui.then(SessionLog::new);
+    let warnings = logging::init(verbosity, cli.log_file.as_deref(), session.clone())?;
+    let result = run(cli, caps, session);
+    if verbosity != Verbosity::Quiet && !tui {
+        warnings.print_summary();
+    }
+    // A batch Ctrl-C stopped exits like one it killed: 128 + SIGINT
+    if let Err(e) = &result {
+        if e.kind() == io::ErrorKind::Interrupted {
+            eprintln!("Error: {:?}", e);
+            process::exit(130);
         }
+    }
+    result
+}
 
-        match crossterm::event::read()? {
-            Event::Key(key) => {
-                let input = Input::from(key);
-                if app.handle_input(input) {
+fn run(cli: Cli, caps: Capabilities, session: Option<SessionLog>) -> io::Result<()> {
+    let config = Config::load()?;
+
+    match cli.command {
+        Some(Command::Render {
+            files,
+            out,
+            color,
+            theme,
+            background,
+            fix_contrast,
+            fill,
+            padding,
+            corner_radius,
+            keep_intermediates,
+            retention,
+            engine,
+            font,
+            size,
+            format,
+            layout,
+            optimize_svg,
+            baseline_align,
+            split_lines,
+            normalize_styles,
+            keep_labels,
+            strip_colors,
+            fail_fast,
+            timeout,
+            hash_names,
+            source_map,
+            naming,
+            no_cache,
+            restart,
+            status_file,
+            filter,
+            deterministic,
+        }) => {
+            let inputs = expand_inputs(&files, &config.scan)?;
+            let mut options = build_render_options(
+                &config,
+                cli.profile.as_deref(),
+                PathBuf::new(), // Set per file below
+                color,
+                theme.as_deref(),
+            )?;
+            if let Some(format) = format {
+                options.format = format;
+            }
+            if let Some(engine) = engine {
+                options.engine = engine;
+            }
+            if let Some(font) = font {
+                options.font = font;
+            }
+            if let Some(size) = size {
+                options.size = size;
+            }
+            if let Some(fill) = fill {
+                options.fill = fill;
+            }
+            if let Some(padding) = padding {
+                options.padding = padding;
+            }
+            if let Some(corner_radius) = corner_radius {
+                options.corner_radius = corner_radius;
+            }
+            // The fill is what the equation will be seen on, unless told otherwise
+            let background = match (background, options.fill) {
+                (Some(background), _) | (None, Fill::Color(background)) => Some(background),
+                (None, Fill::Transparent) => None,
+            };
+            if let Some(background) = background {
+                check_contrast(&mut options, background, fix_contrast);
+            }
+            if keep_intermediates {
+                options.retention = Retention::KeepAll;
+            } else if let Some(retention) = retention {
+                options.retention = retention;
+            }
+            options.layout = layout;
+            options.optimize_svg = optimize_svg;
+            options.baseline_align = baseline_align;
+            options.split_lines |= split_lines;
+            options.normalize_styles |= normalize_styles;
+            options.keep_labels |= keep_labels;
+            options.keep_colors &= !strip_colors;
+            options.fail_fast = fail_fast;
+            if let Some(timeout) = timeout {
+                options.timeout = (timeout > 0).then(|| Duration::from_secs(timeout));
+            }
+            options.hash_names = hash_names;
+            options.source_map |= source_map;
+            if let Some(naming) = naming {
+                options.naming = naming;
+            }
+            if no_cache {
+                options.cache_dir = None;
+            }
+            options.resume = !restart;
+            options.status_file = status_file;
+            if deterministic {
+                make_deterministic(&mut options)?;
+            }
+
+            // A single file named as such renders straight into the output
+            // directory, as it always has
+            let namespaced = !(files.len() == 1 && inputs.len() == 1 && inputs[0].path == files[0]);
+            let parsers = config.parsers()?;
+            let mut total = RenderReport::default();
+            let _interrupts = catch_interrupts(); // Until the last file is done
+            for input in &inputs {
+                options.output_dir = match (&out, namespaced) {
+                    (Some(out), true) => out.join(input.relative.with_extension("")),
+                    (Some(out), false) => out.clone(),
+                    (None, true) => config
+                        .output_dir(&input.path)
+                        .join(input.path.file_stem().unwrap_or_default()),
+                    (None, false) => config.output_dir(&input.path),
+                };
+                options.source_name = input
+                    .path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned());
+                options.source_file = Some(input.path.clone());
+                let mut document = parsers.load_document(&input.path)?;
+                select(&mut document.equations, filter.as_ref());
+                options.macros = document.macros;
+                warn_unsupported(&document.equations, &options);
+                let report = render_equations(&document.equations, &options)?;
+                if namespaced {
+                    println!("{}: {}", input.path.display(), report.summary());
+                }
+                let stop = report.is_interrupted() || report.is_failure(fail_fast);
+                if deterministic && !stop {
+                    check_reproducible(&document.equations, &options)?;
+                }
+                total.merge(report);
+                if stop {
                     break;
                 }
             }
-            Event::Mouse(_) => {} // Ignore mouse events
-            _ => {}
+            if inputs.len() > 1 {
+                println!("Total: {}", total.summary());
+            }
+            check_report(&total, fail_fast)
+        }
+        Some(Command::Project { action }) => run_project(&config, cli.profile.as_deref(), action),
+        Some(Command::Open {
+            name,
+            file,
+            out,
+            format,
+        }) => {
+            let dir = match (out, file) {
+                (Some(out), _) => out,
+                (None, Some(file)) => config.output_dir(&file),
+                (None, None) => config.output_dir(Path::new(".")),
+            };
+            let path = find_rendered(&dir, &name, format).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("No rendered output of '{}' in {}", name, dir.display()),
+                )
+            })?;
+            open_in_viewer(&path, &config.viewer)?;
+            println!("Opened {}", path.display());
+            Ok(())
+        }
+        Some(Command::Recolor {
+            out,
+            color,
+            theme,
+            from,
+        }) => {
+            let dir = out.unwrap_or_else(|| config.output_dir(Path::new(".")));
+            let color = resolve_color(&color, theme.as_deref())?;
+            let to = Rgb::from_hex(&color).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Not a hex color: {}", color),
+                )
+            })?;
+            let report = recolor_dir(&dir, from, to)?;
+            for path in &report.recolored {
+                println!("{}", path.display());
+            }
+            println!(
+                "Recolored {} SVG(s) in {} to {}, {} left as they were",
+                report.recolored.len(),
+                dir.display(),
+                to.to_hex(),
+                report.unchanged
+            );
+            Ok(())
+        }
+        Some(Command::Copy {
+            file,
+            name,
+            background,
+            dpi,
+        }) => {
+            let document = config.parsers()?.load_document(&file)?;
+            let equation = document.equation(&name).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("No equation named '{}' in {}", name, file.display()),
+                )
+            })?;
+            let mut options =
+                build_render_options(&config, cli.profile.as_deref(), PathBuf::new(), None, None)?;
+            if let Some(dpi) = dpi {
+                options.dpi = dpi;
+            }
+            options.macros = document.macros.clone();
+            let png = render_png(equation, &options, options.backend(), background)?;
+            let tool = copy_png(&png)?;
+            println!(
+                "Copied {} to the clipboard ({} KiB PNG, via {})",
+                name,
+                png.len().div_ceil(1024),
+                tool
+            );
+            Ok(())
+        }
+        Some(Command::Grep {
+            query,
+            regex,
+            ignore_case,
+        }) => {
+            let pattern = search_pattern(&query, regex, ignore_case)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            let files = scan_files(&config.scan_roots(&cli.roots), &config.scan);
+            for hit in search_equations(&files, &config.parsers()?, &pattern) {
+                let line = hit.equation.span.map_or(0, |span| span.start_line);
+                println!(
+                    "{}:{}: {}: {}",
+                    hit.path.display(),
+                    line,
+                    hit.equation.name,
+                    hit.equation.body.replace('\n', " ")
+                );
+            }
+            Ok(())
+        }
+        Some(Command::List { files, filter }) => {
+            let parsers = config.parsers()?;
+            let inputs = expand_inputs(&files, &config.scan)?;
+            for input in &inputs {
+                let mut equations = parsers.load(&input.path)?;
+                keep_matching(&mut equations, filter.as_ref());
+                for eq in &equations {
+                    let mark = if eq.active { "x" } else { " " };
+                    let body = eq.body.trim().replace('\n', " ");
+                    match inputs.len() {
+                        1 => println!("[{}] {}: {}", mark, eq.name, body),
+                        _ => println!("{}: [{}] {}: {}", input.path.display(), mark, eq.name, body),
+                    }
+                }
+            }
+            Ok(())
+        }
+        Some(Command::Stats { files, top, filter }) => {
+            let parsers = config.parsers()?;
+            for input in expand_inputs(&files, &config.scan)? {
+                let mut equations = parsers.load(&input.path)?;
+                keep_matching(&mut equations, filter.as_ref());
+                print_stats(&input.path, &EquationStats::of(&equations, top));
+            }
+            Ok(())
+        }
+        Some(Command::Status { dir, json }) => {
+            let Some(status) = read_status(&dir)? else {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("No render status in {}", dir.display()),
+                ));
+            };
+            if json {
+                println!(
+                    "{}",
+                    fs::read_to_string(dir.join(STATUS_FILE_NAME))?.trim_end()
+                );
+            } else {
+                print_status(&status);
+            }
+            if status.is_stale() {
+                return Err(io::Error::other(format!(
+                    "The batch (pid {}) is gone without finishing",
+                    status.pid
+                )));
+            }
+            match status.state {
+                JobState::Failed | JobState::Interrupted => Err(io::Error::other(
+                    status
+                        .summary
+                        .unwrap_or_else(|| "The batch failed".to_string()),
+                )),
+                JobState::Running | JobState::Finished => Ok(()),
+            }
+        }
+        Some(Command::Verify {
+            file,
+            baseline,
+            tolerance,
+            update,
+        }) => {
+            let Document {
+                mut equations,
+                macros,
+                ..
+            } = config.parsers()?.load_document(&file)?;
+            name_by_file(&mut equations);
+            let out = tempfile::tempdir()?;
+            let mut options = build_render_options(
+                &config,
+                cli.profile.as_deref(),
+                out.path().to_path_buf(),
+                None,
+                None,
+            )?;
+            options.format = OutputFormat::Svg;
+            options.layout = OutputLayout::PerEquation;
+            options.hash_names = false;
+            options.naming = OutputNaming::Name; // Compared by equation name
+            options.routes.clear();
+            // A cached render would only be compared with itself
+            options.cache_dir = None;
+            // Nor should its outputs be uploaded or post-processed by hooks,
+            // or the scratch batch announced
+            options.pipeline = RenderPipeline::default();
+            options.hooks = BatchHooks::default();
+            options.macros = macros;
+            warn_unsupported(&equations, &options);
+            let report = {
+                let _interrupts = catch_interrupts();
+                render_equations(&equations, &options)?
+            };
+            check_report(&report, true)?;
+
+            let results = verify_renders(&report.rendered, out.path(), &baseline, tolerance)?;
+            let mut differing = 0;
+            for result in &results {
+                let status = match result.verdict {
+                    Verdict::Unchanged => continue,
+                    Verdict::Changed(percent) => format!("changed {:.2}%", percent),
+                    Verdict::NoBaseline => "no baseline".to_string(),
+                };
+                println!("{}: {}", result.name, status);
+                differing += 1;
+                if update {
+                    fs::create_dir_all(&baseline)?;
+                    let file_name = format!("{}.svg", result.name);
+                    fs::copy(out.path().join(&file_name), baseline.join(&file_name))?;
+                }
+            }
+            println!(
+                "{} unchanged, {} changed or new",
+                results.len() - differing,
+                differing
+            );
+            if differing > 0 && !update {
+                return Err(io::Error::other(format!(
+                    "{} equation(s) differ from {}, rerun with --update to accept them",
+                    differing,
+                    baseline.display()
+                )));
+            }
+            Ok(())
+        }
+        Some(Command::Rename {
+            file,
+            pattern,
+            dry_run,
+            yes,
+        }) => {
+            let Document {
+                equations, source, ..
+            } = config.parsers()?.load_document(&file)?;
+            let names = pattern.apply(&equations, &file, &source)?;
+            let mut changed = 0;
+            for (equation, name) in equations.iter().zip(&names) {
+                if equation.name != *name {
+                    println!("{} -> {}", equation.name, name);
+                    changed += 1;
+                }
+            }
+            if changed == 0 {
+                println!("All names already match the pattern.");
+                return Ok(());
+            }
+            let question = format!("Rename {} equation(s) in {}?", changed, file.display());
+            if dry_run || !(yes || ask_confirmation(&question)) {
+                return Ok(());
+            }
+            rename_source(&file, &equations, &names)?;
+            println!("Renamed {} equation(s).", changed);
+            Ok(())
+        }
+        Some(Command::Format { file, check, yes }) => {
+            let equations = config.parsers()?.load(&file)?;
+            let bodies: Vec<String> = equations.iter().map(|eq| format_body(&eq.body)).collect();
+            let mut changed = 0;
+            for (equation, body) in equations.iter().zip(&bodies) {
+                if equation.body != *body {
+                    println!("{}", equation.name);
+                    changed += 1;
+                }
+            }
+            if changed == 0 {
+                println!("All bodies are already formatted.");
+                return Ok(());
+            }
+            if check {
+                return Err(io::Error::other(format!(
+                    "{} equation(s) need formatting",
+                    changed
+                )));
+            }
+            let question = format!("Format {} equation(s) in {}?", changed, file.display());
+            if !(yes || ask_confirmation(&question)) {
+                return Ok(());
+            }
+            rewrite_bodies(&file, &equations, &bodies)?;
+            println!("Formatted {} equation(s).", changed);
+            Ok(())
+        }
+        Some(Command::Activate {
+            file,
+            filter,
+            section,
+            dry_run,
+            yes,
+        }) => set_active(
+            &config,
+            &file,
+            filter.as_ref(),
+            section.as_deref(),
+            true,
+            dry_run,
+            yes,
+        ),
+        Some(Command::Deactivate {
+            file,
+            filter,
+            section,
+            dry_run,
+            yes,
+        }) => set_active(
+            &config,
+            &file,
+            filter.as_ref(),
+            section.as_deref(),
+            false,
+            dry_run,
+            yes,
+        ),
+        Some(Command::Embed {
+            file,
+            out,
+            format,
+            filter,
+            dry_run,
+            yes,
+        }) => embed(&config, &file, out, format, filter.as_ref(), dry_run, yes),
+        Some(Command::ExportCsv {
+            file,
+            output,
+            filter,
+        }) => {
+            let mut equations = config.parsers()?.load(&file)?;
+            keep_matching(&mut equations, filter.as_ref());
+            write_csv_file(&output, &equations)?;
+            println!(
+                "Wrote {} equation(s) to {}",
+                equations.len(),
+                output.display()
+            );
+            Ok(())
+        }
+        Some(Command::ExportToml {
+            file,
+            output,
+            filter,
+        }) => {
+            let mut equations = config.parsers()?.load(&file)?;
+            keep_matching(&mut equations, filter.as_ref());
+            write_toml_file(&output, &equations)?;
+            println!(
+                "Wrote {} equation(s) to {}",
+                equations.len(),
+                output.display()
+            );
+            Ok(())
+        }
+        Some(Command::ExportSsg {
+            file,
+            flavor,
+            site,
+            dir,
+            filter,
+        }) => {
+            let Document {
+                mut equations,
+                macros,
+                ..
+            } = config.parsers()?.load_document(&file)?;
+            select(&mut equations, filter.as_ref());
+            name_by_file(&mut equations);
+            let out = flavor.asset_dir(&site, &dir);
+            let mut options =
+                build_render_options(&config, cli.profile.as_deref(), out.clone(), None, None)?;
+            // One SVG per equation under its name, so shortcodes keep working
+            // across exports
+            options.format = OutputFormat::Svg;
+            options.layout = OutputLayout::PerEquation;
+            options.hash_names = false;
+            options.naming = OutputNaming::Name;
+            options.routes.clear();
+            options.source_file = Some(file.clone());
+            options.macros = macros;
+            warn_unsupported(&equations, &options);
+            let report = {
+                let _interrupts = catch_interrupts();
+                render_equations(&equations, &options)?
+            };
+            check_report(&report, false)?;
+
+            if let Some(shortcode) = flavor.write_shortcode(&site, &dir)? {
+                println!("Wrote {}", shortcode.display());
+            }
+            println!(
+                "Rendered {} equation(s) into {}:",
+                report.rendered.len(),
+                out.display()
+            );
+            for name in &report.rendered {
+                println!("{}", flavor.snippet(name));
+            }
+            Ok(())
+        }
+        Some(Command::Completions { shell }) => {
+            let mut command = cli_command(&config);
+            clap_complete::generate(shell, &mut command, "simptui", &mut io::stdout());
+            Ok(())
+        }
+        Some(Command::Man { dir: Some(dir) }) => {
+            fs::create_dir_all(&dir)?;
+            clap_mangen::generate_to(cli_command(&config), &dir)
+        }
+        Some(Command::Man { dir: None }) => {
+            clap_mangen::Man::new(cli_command(&config)).render(&mut io::stdout())
+        }
+        None => {
+            if !cli.no_tui && !caps.interactive {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "Output isn't a terminal, so there is no interface to start. Use a \
+                     subcommand such as `simptui render FILE` or `simptui list FILE` (see \
+                     `simptui --help`), or --no-tui for prompts",
+                ));
+            }
+            if cli.no_tui || !caps.fullscreen {
+                if !cli.no_tui {
+                    println!("This terminal can't run the full-screen interface, using prompts.");
+                }
+                return plain::run(&config, &cli.roots, cli.profile.as_deref());
+            }
+            let session = session.unwrap_or_else(SessionLog::new);
+            let window = cli.preview_window;
+            run_tui(&config, &cli.roots, cli.profile, caps, session, window)
+        }
+    }
+}
+
+// The CLI as clap sees it, plus the values of the options parsed through
+// `FromStr` so completions and man pages can list them
+fn cli_command(config: &Config) -> clap::Command {
+    let engines: Vec<&str> = std::iter::once(Engine::Auto)
+        .chain(Engine::CANDIDATES)
+        .map(Engine::program)
+        .collect();
+    let fonts: Vec<&str> = Font::ALL.iter().map(|font| font.name()).collect();
+    let profiles: Vec<String> = config.profile.keys().cloned().collect();
+    let values = |names: Vec<String>| PossibleValuesParser::new(names);
+    let strings = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+
+    Cli::command()
+        .mut_arg("profile", |arg| arg.value_parser(values(profiles)))
+        .mut_subcommand("render", |render| {
+            render
+                .mut_arg("engine", |arg| arg.value_parser(values(strings(&engines))))
+                .mut_arg("font", |arg| arg.value_parser(values(strings(&fonts))))
+                .mut_arg("format", |arg| {
+                    arg.value_parser(values(strings(&["svg", "png", "pdf", "mathml"])))
+                })
+                .mut_arg("layout", |arg| {
+                    arg.value_parser(values(strings(&["per-equation", "single-pdf"])))
+                })
+                .mut_arg("retention", |arg| {
+                    arg.value_parser(values(strings(&[
+                        "keep-all",
+                        "keep-pdf",
+                        "keep-tex-on-failure",
+                        "delete-all",
+                    ])))
+                })
+        })
+        .mut_subcommand("export-ssg", |export| {
+            export.mut_arg("flavor", |arg| {
+                arg.value_parser(values(strings(&["hugo", "zola", "jekyll"])))
+            })
+        })
+}
+
+// Failed equations were logged as warnings while rendering, which `-q`
+// hides; when they fail the run, their errors are repeated as errors
+fn check_report(report: &RenderReport, fail_fast: bool) -> io::Result<()> {
+    if report.is_interrupted() {
+        return Err(io::Error::new(
+            io::ErrorKind::Interrupted,
+            format!("Interrupted ({})", report.summary()),
+        ));
+    }
+    if report.is_failure(fail_fast) {
+        for failure in &report.failed {
+            error!("{}: {}", failure.name, failure.error);
+        }
+        return Err(io::Error::other(format!(
+            "Rendering failed ({})",
+            report.summary()
+        )));
+    }
+    Ok(())
+}
+
+fn print_stats(path: &Path, stats: &EquationStats) {
+    println!(
+        "{}: {} equations, {} active, {} distinct renders",
+        path.display(),
+        stats.equations,
+        stats.active,
+        stats.distinct
+    );
+    let ranked = |ranking: &[Ranked]| {
+        let entries: Vec<String> = ranking
+            .iter()
+            .map(|entry| format!("{} ({})", entry.name, entry.value))
+            .collect();
+        entries.join(", ")
+    };
+    let counted = |counts: &[(String, usize)]| {
+        let entries: Vec<String> = counts
+            .iter()
+            .map(|(name, count)| format!("{} ({})", name, count))
+            .collect();
+        entries.join(", ")
+    };
+    let rows = [
+        ("Longest", ranked(&stats.longest)),
+        ("Most complex", ranked(&stats.most_complex)),
+        ("Commands", counted(&stats.commands)),
+        ("Environments", counted(&stats.environments)),
+    ];
+    for (label, value) in rows.iter().filter(|(_, value)| !value.is_empty()) {
+        println!("  {:<13} {}", format!("{}:", label), value);
+    }
+    for (name, packages) in &stats.packages {
+        println!(
+            "  {:<13} {} needs {}",
+            "Packages:",
+            name,
+            packages.join(", ")
+        );
+    }
+}
+
+fn print_status(status: &JobStatus) {
+    let state = match status.state {
+        _ if status.is_stale() => "gone",
+        JobState::Running => "running",
+        JobState::Finished => "finished",
+        JobState::Failed => "failed",
+        JobState::Interrupted => "interrupted",
+    };
+    println!(
+        "{}: {}, {}/{} equations, {} failed (pid {})",
+        status.source.as_deref().unwrap_or("batch"),
+        state,
+        status.done,
+        status.total,
+        status.failed,
+        status.pid
+    );
+    if let Some(current) = &status.current {
+        println!("  Rendering:   {}", current);
+    }
+    if let Some(last) = &status.last {
+        match &last.error {
+            Some(error) => println!("  Last:        {} ({}): {}", last.name, last.status, error),
+            None => println!("  Last:        {} ({})", last.name, last.status),
+        }
+    }
+    if let Some(summary) = &status.summary {
+        println!("  Result:      {}", summary);
+    }
+}
+
+// With a filter, it alone decides which equations render
+fn select(equations: &mut [Equation], filter: Option<&EquationFilter>) {
+    if let Some(filter) = filter {
+        for equation in equations {
+            equation.active = filter.matches(equation);
+        }
+    }
+}
+
+// `activate`/`deactivate`: switches the equations of `file` that `filter`
+// and `section` pick and aren't `active` yet
+fn set_active(
+    config: &Config,
+    file: &Path,
+    filter: Option<&EquationFilter>,
+    section: Option<&str>,
+    active: bool,
+    dry_run: bool,
+    yes: bool,
+) -> io::Result<()> {
+    let Document {
+        equations, source, ..
+    } = config.parsers()?.load_document(file)?;
+    let members = section.map(|title| in_section(&source, &equations, title));
+    if let (Some(title), Some(members)) = (section, &members) {
+        if !members.contains(&true) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "{}: no equations under a heading '{}'",
+                    file.display(),
+                    title
+                ),
+            ));
+        }
+    }
+    let changes: Vec<Equation> = equations
+        .iter()
+        .enumerate()
+        .filter(|(i, equation)| {
+            equation.active != active
+                && filter.is_none_or(|filter| filter.matches(equation))
+                && members.as_ref().is_none_or(|members| members[*i])
+        })
+        .map(|(_, equation)| equation.clone())
+        .collect();
+    let state = if active { "active" } else { "inactive" };
+    if changes.is_empty() {
+        println!("All picked equations are already {}.", state);
+        return Ok(());
+    }
+    for equation in &changes {
+        println!("{}", equation.name);
+    }
+    let question = format!(
+        "Mark {} equation(s) in {} {}?",
+        changes.len(),
+        file.display(),
+        state
+    );
+    if dry_run || !(yes || ask_confirmation(&question)) {
+        return Ok(());
+    }
+    set_active_in_source(file, &changes, active)?;
+    println!("Marked {} equation(s) {}.", changes.len(), state);
+    Ok(())
+}
+
+fn embed(
+    config: &Config,
+    file: &Path,
+    out: Option<PathBuf>,
+    format: Option<OutputFormat>,
+    filter: Option<&EquationFilter>,
+    dry_run: bool,
+    yes: bool,
+) -> io::Result<()> {
+    // Before anything is looked up for links that can't go in
+    if detect_file_type(file) != "markdown" {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "{}: image links can only be embedded in markdown",
+                file.display()
+            ),
+        ));
+    }
+    let mut equations = config.parsers()?.load(file)?;
+    keep_matching(&mut equations, filter);
+    let dir = out.unwrap_or_else(|| config.output_dir(file));
+    let routes = config.routes()?;
+    let mut links = Vec::new();
+    let mut unrendered = Vec::new();
+    for equation in &equations {
+        // Routed equations sit in a subdirectory, unless the manifest
+        // knows them
+        let image = find_rendered(&dir, equation.file_stem(), format).or_else(|| {
+            let route = route_of(&routes, equation)?;
+            find_rendered(&dir.join(route), equation.file_stem(), format)
+        });
+        match image {
+            Some(image) => links.push((equation, link_target(file, &image))),
+            None => unrendered.push(equation.name.as_str()),
+        }
+    }
+    if !unrendered.is_empty() {
+        println!(
+            "Not rendered into {}: {}",
+            dir.display(),
+            unrendered.join(", ")
+        );
+    }
+
+    let (_, report) = embed_links(&load_source(file)?, &links);
+    let changed = report.added.len() + report.updated.len();
+    if changed == 0 {
+        println!("All image links are up to date.");
+        return Ok(());
+    }
+    for (equation, target) in &links {
+        if report.added.contains(&equation.name) || report.updated.contains(&equation.name) {
+            println!("![{}]({})", equation.name, target);
+        }
+    }
+    let question = format!("Put {} image link(s) into {}?", changed, file.display());
+    if dry_run || !(yes || ask_confirmation(&question)) {
+        return Ok(());
+    }
+    let report = embed_images(file, &links)?;
+    println!(
+        "Added {} image link(s), updated {}.",
+        report.added.len(),
+        report.updated.len()
+    );
+    Ok(())
+}
+
+fn keep_matching(equations: &mut Vec<Equation>, filter: Option<&EquationFilter>) {
+    if let Some(filter) = filter {
+        equations.retain(|equation| filter.matches(equation));
+    }
+}
+
+// Warns about (or fixes) a color that would be hard to see on `background`
+fn check_contrast(options: &mut RenderOptions, background: Rgb, fix: bool) {
+    let Some(color) = Rgb::from_hex(&options.color) else {
+        return;
+    };
+    let ratio = color.contrast(background);
+    if ratio >= MIN_CONTRAST {
+        return;
+    }
+    let warning = format!(
+        "{} on {} has a contrast of {:.2}:1, below {}:1",
+        color, background, ratio, MIN_CONTRAST
+    );
+    if fix {
+        let adjusted = adjust_contrast(color, background, MIN_CONTRAST);
+        warn!("{}, using {} instead", warning, adjusted);
+        options.color = adjusted.to_hex();
+    } else {
+        warn!("{}", warning);
+    }
+}
+
+fn table_title(filter: Option<&Regex>, note: Option<&str>) -> String {
+    let details: Vec<String> = filter
+        .map(|re| format!("filter: {}", re))
+        .into_iter()
+        .chain(note.map(str::to_string))
+        .collect();
+    if details.is_empty() {
+        "Equations".to_string()
+    } else {
+        format!("Equations ({})", details.join(", "))
+    }
+}
+
+// Gives equations that share a name, told apart by their file names under
+// hash naming, those names, where outputs are listed by equation name
+fn name_by_file(equations: &mut [Equation]) {
+    for equation in equations {
+        if let Some(stem) = equation.stem.take() {
+            equation.name = stem;
+        }
+    }
+}
+
+// The SVG of `equation` in `dir`: as listed in the manifest when the naming
+// scheme or a route wrote one, else under its name in its route's directory
+fn svg_in(dir: &Path, manifest: &Manifest, equation: &Equation, routes: &[OutputRoute]) -> PathBuf {
+    match manifest
+        .get(equation.file_stem())
+        .filter(|file| file.ends_with(".svg"))
+    {
+        Some(file) => dir.join(file),
+        None => dir
+            .join(route_of(routes, equation).unwrap_or(Path::new("")))
+            .join(format!("{}.svg", equation.file_stem())),
+    }
+}
+
+// Rewrites the names in `path` and moves the project's saved order and
+// overrides along with them
+fn rename_source(path: &Path, equations: &[Equation], names: &[String]) -> io::Result<()> {
+    rename_in_source(path, equations, names)?;
+    let renames: Vec<(String, String)> = equations
+        .iter()
+        .zip(names)
+        .filter(|(eq, name)| eq.name != **name)
+        .map(|(eq, name)| (eq.name.clone(), name.clone()))
+        .collect();
+    if let Some(mut project) = project_of(path) {
+        project.rename_equations(path, &renames)?;
+        project.save()?;
+    }
+    Ok(())
+}
+
+// The project `file` belongs to, if it sits inside one
+fn project_of(file: &Path) -> Option<Project> {
+    let dir = file.canonicalize().ok()?.parent()?.to_path_buf();
+    Project::load(&Project::find(&dir)?).ok()
+}
+
+fn find_project() -> io::Result<Project> {
+    let path = Project::find(&std::env::current_dir()?).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No {} found, run `simptui project init`", PROJECT_FILE_NAME),
+        )
+    })?;
+    Project::load(&path)
+}
+
+fn run_project(config: &Config, profile: Option<&str>, action: ProjectCommand) -> io::Result<()> {
+    match action {
+        ProjectCommand::Init { out } => {
+            if Path::new(PROJECT_FILE_NAME).exists() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} already exists", PROJECT_FILE_NAME),
+                ));
+            }
+            let mut project = Project::new(Path::new("."));
+            if let Some(out) = out {
+                project.output.dir = out;
+            }
+            project.output.profile = profile.map(str::to_string);
+            project.save()?;
+            println!("Created {}", PROJECT_FILE_NAME);
+            Ok(())
+        }
+        ProjectCommand::Add { files } => {
+            let mut project = find_project()?;
+            for file in &files {
+                if !project.add_source(file)? {
+                    println!("{} is already part of the project", file.display());
+                }
+            }
+            project.save()
+        }
+        ProjectCommand::Render { fail_fast } => {
+            let project = find_project()?;
+            let equations = project.equations(&config.parsers()?)?;
+            let color = project
+                .output
+                .color
+                .as_deref()
+                .map(str::parse)
+                .transpose()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            // The command line profile wins over the one recorded in the project
+            let profile = profile.or(project.output.profile.as_deref());
+            let mut options =
+                build_render_options(config, profile, project.output_dir(), color, None)?;
+            if let Some(format) = project.output.format {
+                options.format = format;
+            }
+            options.fail_fast = fail_fast;
+            options.cache_dir = Some(project.cache_dir());
+            warn_unsupported(&equations, &options);
+            let _interrupts = catch_interrupts();
+            check_report(&render_equations(&equations, &options)?, fail_fast)
         }
     }
+}
 
+fn run_tui(
+    config: &Config,
+    roots: &[PathBuf],
+    profile: Option<String>,
+    caps: Capabilities,
+    session: SessionLog,
+    preview_window: bool,
+) -> io::Result<()> {
+    if let Some(name) = &profile {
+        config.profile(name)?;
+    }
+    let parsers = config.parsers()?;
+    // Before the terminal is taken over, so a window that can't open is an
+    // ordinary error
+    let window = preview_window.then(PreviewWindow::new).transpose()?;
+    let mut term = setup_terminal()?;
+    let mut events = Events::new(Duration::from_millis(1000 / MAX_FPS));
+    let mut app = App::new(
+        config,
+        parsers,
+        roots,
+        profile,
+        caps,
+        events.sender(),
+        session,
+    );
+    app.preview_window = window;
+    // The terminal goes back to the shell whatever ends the loop
+    let result = event_loop(&mut app, &mut term, &mut events, config);
     restore_terminal(&mut term)?;
+    result?;
     println!("Input: {:?}", app.textarea.lines()[0]);
     Ok(())
 }
+
+fn event_loop(
+    app: &mut App,
+    term: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    events: &mut Events,
+    config: &Config,
+) -> io::Result<()> {
+    let mut last_draw: Option<Instant> = None;
+
+    loop {
+        match events.next()? {
+            AppEvent::Key(input) => {
+                if app.handle_input(input) {
+                    break;
+                }
+            }
+            AppEvent::Resize => app.should_redraw = true,
+            AppEvent::FsChange(event) => app.index_event(event),
+            AppEvent::RenderProgress => {
+                app.poll_live();
+                app.poll_copy();
+                app.poll_load();
+            }
+            AppEvent::Tick => {
+                app.poll_live(); // Template saves
+                app.poll_load();
+                app.should_redraw |= app.tree.poll_types();
+            }
+        }
+        if let Some(equations) = app.render_requested.take() {
+            if let Some(path) = app.source_path().cloned() {
+                let out = config.output_dir(&path);
+                let profile = app.profile.as_deref();
+                let result = render_suspended(term, &equations, config, profile, out, Some(&path))?;
+                match result {
+                    Ok(report) => {
+                        info!(
+                            "Rendered {} equation(s) of {}, {} failed",
+                            report.rendered.len(),
+                            path.display(),
+                            report.failed.len()
+                        );
+                        app.failures = report.failed.clone();
+                        app.last_report = Some(report);
+                    }
+                    Err(e) => {
+                        app.last_report = None;
+                        app.show_error(ErrorReport::io(&path.display().to_string(), &e));
+                    }
+                }
+            }
+            app.should_redraw = true;
+        }
+        if let Some(equations) = app.bookmark_render.take() {
+            let out = PathBuf::from(BOOKMARKS_DIR);
+            let profile = app.profile.as_deref();
+            match render_suspended(term, &equations, config, profile, out, None)? {
+                Ok(report) => {
+                    info!(
+                        "Rendered {} bookmarked equation(s) into {}, {} failed",
+                        report.rendered.len(),
+                        BOOKMARKS_DIR,
+                        report.failed.len()
+                    );
+                    app.failures = report.failed;
+                }
+                Err(e) => app.show_error(ErrorReport::io(BOOKMARKS_DIR, &e)),
+            }
+            app.should_redraw = true;
+        }
+        // Changes within a frame of the last draw wait for a later event,
+        // at most a tick away
+        if app.show_log && app.session_log.logged() != app.log_drawn {
+            app.should_redraw = true;
+        }
+        let frame_due = last_draw.is_none_or(|at| at.elapsed() >= events.tick_rate());
+        if app.should_redraw && frame_due {
+            app.revalidate();
+            app.draw(term)?;
+            last_draw = Some(Instant::now());
+        }
+    }
+    Ok(())
+}
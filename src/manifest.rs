@@ -0,0 +1,60 @@
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub const MANIFEST_NAME: &str = "manifest.json";
+const HASH_LEN: usize = 6;
+
+/// Maps logical equation names to the hashed file names last rendered for
+/// them, e.g. `"energy": "energy-3fa2c1.svg"`.
+pub type Manifest = BTreeMap<String, String>;
+
+/// Short hash used in file names.
+pub(crate) fn content_hash(bytes: &[u8]) -> String {
+    sha256_hex(bytes)[..HASH_LEN].to_string()
+}
+
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Renames `<dir>/<name>.<ext>` to `<dir>/<name>-<hash>.<ext>` and returns
+/// the new file name. Identical content always maps to the same name.
+pub(crate) fn hash_output_file(
+    output_dir: &Path,
+    name: &str,
+    extension: &str,
+) -> io::Result<String> {
+    let file = output_dir.join(format!("{}.{}", name, extension));
+    let hashed = format!("{}-{}.{}", name, content_hash(&fs::read(&file)?), extension);
+    fs::rename(&file, output_dir.join(&hashed))?;
+    Ok(hashed)
+}
+
+pub fn read_manifest(output_dir: &Path) -> Manifest {
+    fs::read_to_string(output_dir.join(MANIFEST_NAME))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Merges `rendered` into the manifest in `output_dir`. Entries for equations
+/// that weren't rendered this time are kept; hashed files that an entry no
+/// longer points to are deleted.
+pub fn update_manifest(output_dir: &Path, rendered: Manifest) -> io::Result<()> {
+    let mut manifest = read_manifest(output_dir);
+    for (name, file) in rendered {
+        if let Some(old) = manifest.insert(name, file.clone()) {
+            if old != file {
+                fs::remove_file(output_dir.join(old)).ok();
+            }
+        }
+    }
+    let json = serde_json::to_string_pretty(&manifest).map_err(io::Error::other)?;
+    fs::write(output_dir.join(MANIFEST_NAME), json + "\n")
+}
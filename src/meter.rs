@@ -0,0 +1,178 @@
+use crate::RenderReport;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::VecDeque;
+use std::io::{self, IsTerminal};
+use std::time::{Duration, Instant};
+
+// Compiles the time left is estimated from
+const WINDOW: usize = 50;
+// Least time between two messages on the bar
+const REDRAW: Duration = Duration::from_millis(100);
+// Equations between two lines without a terminal, in batches bigger than
+// this; smaller ones get a line per equation
+const LINE_EVERY: usize = 100;
+
+/// How far a batch got, on stderr. On a terminal, a bar whose message
+/// changes at most every `REDRAW` however fast cached equations go by.
+/// When stdout or stderr goes to a pipe or a CI log instead, where the bar
+/// would come out as control codes, plain lines: one per equation, or per
+/// `LINE_EVERY` of them in big batches. The time left comes from the last
+/// compiles, cache hits aside.
+pub(crate) struct BatchMeter {
+    bar: Option<ProgressBar>, // None without a terminal
+    len: usize,
+    done: usize,
+    failed: usize,                // When the last line was printed
+    current: String,              // The equation under way
+    compiles: VecDeque<Duration>, // The last `WINDOW`
+    started: Instant,             // The equation under way
+    shown: Option<Instant>,       // The bar's message
+}
+
+impl BatchMeter {
+    pub fn new(len: usize) -> Self {
+        let terminal = io::stdout().is_terminal() && io::stderr().is_terminal();
+        let bar = terminal.then(|| {
+            let bar = ProgressBar::new(len as u64);
+            bar.set_style(
+                ProgressStyle::default_bar()
+                    .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} {msg}")
+                    .expect("Error setting template")
+                    .progress_chars("#>-"),
+            );
+            bar
+        });
+        BatchMeter {
+            bar,
+            len,
+            done: 0,
+            failed: 0,
+            current: String::new(),
+            compiles: VecDeque::with_capacity(WINDOW),
+            started: Instant::now(),
+            shown: None,
+        }
+    }
+
+    pub fn begin(&mut self, name: &str) {
+        self.started = Instant::now();
+        let Some(bar) = &self.bar else {
+            self.current = name.to_string();
+            return;
+        };
+        if self.shown.is_some_and(|shown| shown.elapsed() < REDRAW) {
+            return;
+        }
+        self.shown = Some(Instant::now());
+        match self.time_left() {
+            Some(left) => bar.set_message(format!(
+                "Rendering: {} ({} left)",
+                name,
+                format_duration(left)
+            )),
+            None => bar.set_message(format!("Rendering: {}", name)),
+        }
+    }
+
+    /// Counts the equation begun last; `compiled` if it went through TeX
+    /// rather than the cache or an earlier run.
+    pub fn finish(&mut self, compiled: bool, report: &RenderReport) {
+        self.done += 1;
+        if compiled {
+            if self.compiles.len() == WINDOW {
+                self.compiles.pop_front();
+            }
+            self.compiles.push_back(self.started.elapsed());
+        }
+        let failed = report.failed.len() > self.failed;
+        self.failed = report.failed.len();
+        match &self.bar {
+            Some(bar) => bar.inc(1),
+            None if self.len <= LINE_EVERY => {
+                let outcome = if failed { " failed" } else { "" };
+                eprintln!("[{}/{}] {}{}", self.done, self.len, self.current, outcome);
+            }
+            None if self.done.is_multiple_of(LINE_EVERY) && self.done < self.len => {
+                let left = self
+                    .time_left()
+                    .map(|left| format!(", about {} left", format_duration(left)))
+                    .unwrap_or_default();
+                eprintln!(
+                    "Rendered {}/{} ({} failed){}",
+                    self.done,
+                    self.len,
+                    report.failed.len(),
+                    left
+                );
+            }
+            None => {}
+        }
+    }
+
+    pub fn end(self, report: &RenderReport) {
+        let message = match report.is_interrupted() {
+            true => format!("Interrupted: {}", report.summary()),
+            false => format!("Rendering complete: {}", report.summary()),
+        };
+        match self.bar {
+            Some(bar) if report.is_interrupted() => bar.abandon_with_message(message),
+            Some(bar) => bar.finish_with_message(message),
+            None => eprintln!("{}", message),
+        }
+    }
+
+    fn time_left(&self) -> Option<Duration> {
+        time_left(
+            self.compiles.iter().copied(),
+            self.len.saturating_sub(self.done),
+        )
+    }
+}
+
+// The mean of the `recent` compile times for each of the `remaining`
+// equations, or None before anything was compiled
+fn time_left(recent: impl IntoIterator<Item = Duration>, remaining: usize) -> Option<Duration> {
+    let (total, count) = recent
+        .into_iter()
+        .fold((Duration::ZERO, 0u32), |(total, count), compile| {
+            (total + compile, count.saturating_add(1))
+        });
+    let mean = total.checked_div(count)?;
+    Some(mean.saturating_mul(u32::try_from(remaining).unwrap_or(u32::MAX)))
+}
+
+// `2h 05m`, `4m 10s`, `12s`
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..60 => format!("{}s", seconds),
+        60..3600 => format!("{}m {:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_duration, time_left};
+    use std::time::Duration;
+
+    #[test]
+    fn durations_read_at_a_glance() {
+        let secs = Duration::from_secs;
+        assert_eq!(format_duration(Duration::from_millis(900)), "0s");
+        assert_eq!(format_duration(secs(59)), "59s");
+        assert_eq!(format_duration(secs(60)), "1m 00s");
+        assert_eq!(format_duration(secs(250)), "4m 10s");
+        assert_eq!(format_duration(secs(3599)), "59m 59s");
+        assert_eq!(format_duration(secs(7500)), "2h 05m");
+    }
+
+    #[test]
+    fn time_left_is_the_mean_compile_per_equation() {
+        let secs = Duration::from_secs;
+        assert_eq!(time_left([], 10), None);
+        assert_eq!(time_left([secs(1), secs(3)], 10), Some(secs(20)));
+        assert_eq!(time_left([secs(2)], 0), Some(Duration::ZERO));
+        assert_eq!(time_left([secs(u64::MAX)], usize::MAX), Some(Duration::MAX));
+    }
+}
@@ -0,0 +1,60 @@
+use crate::{content_hash, normalize_body, Equation};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+/// How equations that share a name are told apart. `[output]
+/// duplicate_names` in the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum DuplicateNames {
+    #[default]
+    Counter, // `x`, `x_1`, `x_2`, ... in document order
+    Hash, // `x` kept, in files `x_<hash of the body>` unaffected by equations added before it
+}
+
+/// Tells apart the equations of `equations` that share a name. `Counter`
+/// renames them. `Hash` leaves the names as written and gives each such
+/// equation a `stem` for its files, with a suffix from its body ignoring
+/// whitespace, so its outputs keep their file names however the others
+/// move; only identical bodies under one name still need a counter.
+pub(crate) fn unique_names(equations: &mut [Equation], mode: DuplicateNames) {
+    match mode {
+        DuplicateNames::Counter => {
+            let mut name_count: HashMap<String, usize> = HashMap::new();
+            for equation in equations {
+                let count = name_count.entry(equation.name.clone()).or_insert(0);
+                if *count > 0 {
+                    equation.name = format!("{}_{}", equation.name, count);
+                }
+                *count += 1;
+            }
+        }
+        DuplicateNames::Hash => {
+            // First find the shared names, then give only those a stem
+            let mut name_count: HashMap<String, usize> = HashMap::new();
+            for equation in equations.iter() {
+                *name_count.entry(equation.name.clone()).or_insert(0) += 1;
+            }
+            let mut taken: HashSet<String> = name_count
+                .iter()
+                .filter(|(_, count)| **count == 1)
+                .map(|(name, _)| name.clone())
+                .collect();
+            for equation in equations {
+                if name_count[&equation.name] == 1 {
+                    continue;
+                }
+                let hash = content_hash(normalize_body(&equation.body, false).as_bytes());
+                let hashed = format!("{}_{}", equation.name, hash);
+                let mut stem = hashed.clone();
+                let mut count = 0;
+                while !taken.insert(stem.clone()) {
+                    count += 1;
+                    stem = format!("{}_{}", hashed, count);
+                }
+                equation.stem = Some(stem);
+            }
+        }
+    }
+}
@@ -0,0 +1,127 @@
+// Different spellings of the same symbol
+const ALIASES: [(&str, &str); 12] = [
+    ("le", r"\leq"),
+    ("ge", r"\geq"),
+    ("ne", r"\neq"),
+    ("to", r"\rightarrow"),
+    ("gets", r"\leftarrow"),
+    ("land", r"\wedge"),
+    ("lor", r"\vee"),
+    ("lnot", r"\neg"),
+    ("lbrace", r"\{"),
+    ("rbrace", r"\}"),
+    ("vert", "|"),
+    ("Vert", r"\|"),
+];
+
+// Commands that only force a display or text style; same glyphs otherwise
+const STYLE_ALIASES: [(&str, &str); 4] = [
+    ("dfrac", r"\frac"),
+    ("tfrac", r"\frac"),
+    ("dbinom", r"\binom"),
+    ("tbinom", r"\binom"),
+];
+
+// Arguments typeset as text, where spaces are kept
+pub(crate) const TEXT_COMMANDS: [&str; 7] = [
+    "text", "textrm", "textit", "textbf", "textsf", "texttt", "mbox",
+];
+
+/// `body` with `%` comments and whitespace TeX ignores in math mode removed
+/// and synonyms such as `\le`/`\leq` spelled one way, so equations that
+/// render the same compare equal. With `styles`, `\dfrac`/`\tfrac` also count as `\frac`
+/// (and the same for `\binom`), which does change the rendered size. Only
+/// meant for comparing; the original body is what gets rendered.
+pub fn normalize_body(body: &str, styles: bool) -> String {
+    let mut normalized = String::with_capacity(body.len());
+    let mut text_depth = None; // Brace depth where a text argument started
+    let mut depth = 0usize;
+    let mut pending_space = false;
+    let mut chars = body.trim().chars().peekable();
+
+    while let Some(c) = chars.next() {
+        // A comment goes with its line break, as in TeX; `\%` is a control
+        // symbol and never gets here. It still ends a command name, and in
+        // math whatever space there was around it
+        if c == '%' {
+            while chars.next_if(|&next| next != '\n').is_some() {}
+            chars.next();
+            pending_space |= text_depth.is_none() || ends_with_command(&normalized);
+            continue;
+        }
+        if c.is_whitespace() {
+            pending_space = true;
+            continue;
+        }
+        // A space only matters between two letters or digits (`\alpha b`,
+        // `1 2`) or inside text
+        if pending_space {
+            let previous = normalized.chars().next_back();
+            if text_depth.is_some()
+                || (previous.is_some_and(|p| p.is_ascii_alphanumeric())
+                    && c.is_ascii_alphanumeric())
+            {
+                normalized.push(' ');
+            }
+            pending_space = false;
+        }
+
+        match c {
+            '\\' => {
+                let mut command = String::new();
+                while let Some(&next) = chars.peek().filter(|next| next.is_ascii_alphabetic()) {
+                    command.push(next);
+                    chars.next();
+                }
+                if command.is_empty() {
+                    // Control symbol such as `\{` or `\ `, kept as is
+                    normalized.push('\\');
+                    if let Some(symbol) = chars.next() {
+                        normalized.push(symbol);
+                    }
+                    continue;
+                }
+                // `\text {..}` is `\text{..}`
+                if text_depth.is_none()
+                    && TEXT_COMMANDS.contains(&command.as_str())
+                    && chars.clone().find(|next| !next.is_whitespace()) == Some('{')
+                {
+                    while chars.next_if(|next| next.is_whitespace()).is_some() {}
+                    text_depth = Some(depth);
+                }
+                let styles = STYLE_ALIASES.iter().filter(|_| styles);
+                match ALIASES
+                    .iter()
+                    .chain(styles)
+                    .find(|(alias, _)| *alias == command)
+                {
+                    Some((_, canonical)) => normalized.push_str(canonical),
+                    None => {
+                        normalized.push('\\');
+                        normalized.push_str(&command);
+                    }
+                }
+            }
+            '{' => {
+                depth += 1;
+                normalized.push(c);
+            }
+            '}' => {
+                depth = depth.saturating_sub(1);
+                // The text argument closed; back to math
+                if text_depth.is_some_and(|start| depth <= start) {
+                    text_depth = None;
+                }
+                normalized.push(c);
+            }
+            _ => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+// Whether `normalized` ends in a command name such as `\alpha`
+fn ends_with_command(normalized: &str) -> bool {
+    let name = normalized.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    name.len() < normalized.len() && name.ends_with('\\')
+}
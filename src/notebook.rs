@@ -0,0 +1,20 @@
+use serde_json::Value;
+
+/// The text of the markdown cells of a Jupyter notebook, separated by blank
+/// lines, or `None` when `content` isn't a notebook.
+pub(crate) fn notebook_markdown(content: &str) -> Option<String> {
+    let notebook: Value = serde_json::from_str(content).ok()?;
+    notebook.get("nbformat")?;
+    let cells = notebook.get("cells")?.as_array()?;
+    let markdown: Vec<String> = cells
+        .iter()
+        .filter(|cell| cell.get("cell_type").and_then(Value::as_str) == Some("markdown"))
+        .filter_map(|cell| match cell.get("source")? {
+            // Either one string or a list of lines that keep their `\n`
+            Value::String(source) => Some(source.clone()),
+            Value::Array(lines) => Some(lines.iter().filter_map(Value::as_str).collect()),
+            _ => None,
+        })
+        .collect();
+    Some(markdown.join("\n\n"))
+}
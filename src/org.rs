@@ -0,0 +1,53 @@
+use crate::{Equation, ExtractRule, Extractor, Group};
+use std::collections::BTreeMap;
+
+// `#+NAME: x` on the line before a block names it
+const NAME_LINE: &str = r"(?mi)^[ \t]*#\+name:[ \t]*(?P<name>\S+)[ \t]*\n[ \t]*";
+const ENVIRONMENTS: &str = "equation|align|flalign|alignat|gather|multline|eqnarray";
+
+/// Equations in Org documents: `\[...\]`, `$$...$$` and the amsmath
+/// environments, named by a `#+NAME:` line right above them.
+pub fn parse_org(content: &str) -> Vec<Equation> {
+    org_extractor().parse(content)
+}
+
+// The equations with their names as written, repeats and all
+pub(crate) fn org_equations(content: &str) -> Vec<Equation> {
+    org_extractor().extract(content)
+}
+
+fn org_extractor() -> Extractor {
+    let rule = |pattern: String| ExtractRule {
+        pattern,
+        body: Group::Name("body".to_string()),
+        name: Some(Group::Name("name".to_string())),
+        active: None,
+        options: None,
+        environment: Some(Group::Name("environment".to_string())),
+        skip_code: false,
+    };
+    let environment = format!(
+        r"\\begin\{{(?P<environment>(?:{0})\*?)\}}(?P<body>(?s:.*?))\\end\{{(?:{0})\*?\}}",
+        ENVIRONMENTS
+    );
+    let rules = BTreeMap::from([
+        (
+            "named_bracket".to_string(),
+            rule(format!(r"{}\\\[(?P<body>(?s:.*?))\\\]", NAME_LINE)),
+        ),
+        (
+            "named_dollars".to_string(),
+            rule(format!(r"{}\$\$(?P<body>(?s:.*?))\$\$", NAME_LINE)),
+        ),
+        (
+            "named_environment".to_string(),
+            rule(format!("{}{}", NAME_LINE, environment)),
+        ),
+        (
+            "bracket".to_string(),
+            rule(r"\\\[(?P<body>(?s:.*?))\\\]".to_string()),
+        ),
+    ]);
+    // The built-in rules cover unnamed `$$` blocks and environments
+    Extractor::new(&rules).expect("built-in Org patterns are valid")
+}
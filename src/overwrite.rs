@@ -0,0 +1,89 @@
+use crate::{
+    optimize_svg, read_manifest, route_of, strip_svg_source_map, Engine, Equation, OutputFormat,
+    RenderOptions,
+};
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// An existing output that no longer matches what was rendered for it,
+/// most likely touched up by hand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedOutput {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// The outputs of the active `equations` that a render with `options` would
+/// overwrite although they differ from the render cache's copy of the same
+/// source. Outputs with no cached copy can't be told apart from stale ones,
+/// so they aren't listed, nor is anything without a `cache_dir`.
+pub fn changed_outputs(equations: &[Equation], options: &RenderOptions) -> Vec<ChangedOutput> {
+    if options.cache_dir.is_none() || options.format == OutputFormat::MathML {
+        return Vec::new();
+    }
+    // Resolved as the render would, or the cache keys won't match
+    let engine = match options.engine {
+        Engine::Auto if options.remote.is_none() => match Engine::Auto.resolve() {
+            Ok(engine) => engine,
+            Err(_) => return Vec::new(),
+        },
+        engine => engine,
+    };
+    let manifest = read_manifest(&options.output_dir);
+
+    let mut changed = Vec::new();
+    for equation in equations.iter().filter(|equation| equation.active) {
+        let path = match manifest.get(equation.file_stem()) {
+            Some(file) => options.output_dir.join(file),
+            None => options
+                .output_dir
+                .join(route_of(&options.routes, equation).unwrap_or(Path::new("")))
+                .join(format!(
+                    "{}.{}",
+                    equation.file_stem(),
+                    options.format.extension()
+                )),
+        };
+        let Ok(mut output) = fs::read(&path) else {
+            continue;
+        };
+        // The cache's copy has no source map
+        if options.format == OutputFormat::Svg {
+            output = strip_svg_source_map(&String::from_utf8_lossy(&output)).into_bytes();
+        }
+        let equation_options = RenderOptions {
+            engine: equation.engine.unwrap_or(engine),
+            ..options.clone()
+        };
+        let cached = match equation.cache_file(&equation_options) {
+            Ok(Some(cached)) => cached,
+            _ => continue,
+        };
+        let Ok(mut rendered) = fs::read(&cached) else {
+            continue;
+        };
+        // Optimized after leaving the cache
+        if options.optimize_svg && options.format == OutputFormat::Svg {
+            rendered = optimize_svg(&String::from_utf8_lossy(&rendered)).into_bytes();
+        }
+        if output != rendered {
+            changed.push(ChangedOutput {
+                name: equation.name.clone(),
+                path,
+            });
+        }
+    }
+    changed
+}
+
+/// Moves `path` aside to `<path>.bak`, replacing an older backup. Returns
+/// the backup's path.
+pub fn back_up(path: &Path) -> io::Result<PathBuf> {
+    let mut backup = OsString::from(path.as_os_str());
+    backup.push(".bak");
+    let backup = PathBuf::from(backup);
+    fs::rename(path, &backup)?;
+    Ok(backup)
+}
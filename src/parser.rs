@@ -0,0 +1,468 @@
+use crate::{
+    csv_equations, csv_fields, equation_macros, html_equations, load_source, notebook_markdown,
+    org_equations, org_keywords, toml_equations, unique_names, wiki_equations, yaml_frontmatter,
+    Document, DuplicateNames, Equation, Extractor,
+};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use tracing::debug;
+
+/// A document format equations can be read from. Register implementations
+/// with a `ParserRegistry` to teach every loader (render, search, the TUI)
+/// a new format.
+pub trait Parser: Send + Sync {
+    /// Short name of the format, as `detect_file_type` reports it.
+    fn name(&self) -> &'static str;
+
+    /// File extensions it claims, without the dot, lowercase.
+    fn extensions(&self) -> &[&'static str];
+
+    /// How sure, from 0 to 1, that `content` is in this format.
+    fn sniff(&self, _content: &str) -> f32 {
+        0.0
+    }
+
+    /// The equations in `content`. Names may repeat; the registry tells
+    /// such equations apart.
+    fn parse(&self, content: &str) -> Vec<Equation>;
+
+    /// What the document says about itself, like its title, keyed in
+    /// lowercase.
+    fn frontmatter(&self, _content: &str) -> BTreeMap<String, String> {
+        BTreeMap::new()
+    }
+}
+
+/// `$$` blocks and whatever else the extraction rules match.
+pub struct MarkdownParser(pub Extractor);
+
+impl Parser for MarkdownParser {
+    fn name(&self) -> &'static str {
+        "markdown"
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["md", "markdown"]
+    }
+
+    fn sniff(&self, content: &str) -> f32 {
+        let blocks = content.matches("$$").count() >= 2;
+        let environments = content.contains(r"\begin{") && content.contains(r"\end{");
+        match (blocks || environments, content.contains("%%")) {
+            (true, true) => 0.8, // `%%name%%` is this tool's own syntax
+            (true, false) => 0.6,
+            (false, _) if content.contains("$$") => 0.2,
+            (false, _) => 0.0,
+        }
+    }
+
+    fn parse(&self, content: &str) -> Vec<Equation> {
+        self.0.extract(content)
+    }
+
+    fn frontmatter(&self, content: &str) -> BTreeMap<String, String> {
+        yaml_frontmatter(content)
+    }
+}
+
+/// `Active,Body,Name` tables.
+pub struct CsvParser;
+
+impl Parser for CsvParser {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["csv"]
+    }
+
+    fn sniff(&self, content: &str) -> f32 {
+        let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+        let Some(first) = lines.next() else {
+            return 0.0;
+        };
+        // `export-csv` adds Options and Line columns
+        if first
+            .trim()
+            .to_ascii_lowercase()
+            .starts_with("active,body,name")
+        {
+            return 1.0;
+        }
+        // Headerless rows still start with the active flag
+        let row = |line: &str| {
+            let fields = csv_fields(line);
+            matches!(
+                fields[0].to_ascii_lowercase().as_str(),
+                "yes" | "no" | "true" | "false"
+            ) && fields.len() >= 3
+        };
+        if row(first) && lines.take(20).all(row) {
+            0.7
+        } else {
+            0.0
+        }
+    }
+
+    fn parse(&self, content: &str) -> Vec<Equation> {
+        csv_equations(content)
+    }
+}
+
+/// `[[equation]]` tables in TOML.
+pub struct TomlParser;
+
+impl Parser for TomlParser {
+    fn name(&self) -> &'static str {
+        "toml"
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["toml"]
+    }
+
+    fn sniff(&self, content: &str) -> f32 {
+        let header = Regex::new(r"(?m)^[ \t]*\[\[[ \t]*equation[ \t]*\]\]").unwrap();
+        if header.is_match(content) {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    fn parse(&self, content: &str) -> Vec<Equation> {
+        toml_equations(content)
+    }
+}
+
+/// Exported HTML notes.
+pub struct HtmlParser;
+
+impl Parser for HtmlParser {
+    fn name(&self) -> &'static str {
+        "html"
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["html", "htm"]
+    }
+
+    fn sniff(&self, content: &str) -> f32 {
+        let start: String = content.trim_start().chars().take(15).collect();
+        let start = start.to_ascii_lowercase();
+        if start.starts_with("<!doctype html") || start.starts_with("<html") {
+            return 1.0;
+        }
+        // A fragment; markdown notes carry tags too, so only a weak hint
+        let lower = content.to_ascii_lowercase();
+        if ["<body", "<math", "<div", "<p>"]
+            .iter()
+            .any(|tag| lower.contains(tag))
+        {
+            0.5
+        } else {
+            0.0
+        }
+    }
+
+    fn parse(&self, content: &str) -> Vec<Equation> {
+        html_equations(content)
+    }
+}
+
+/// Org documents, with `#+TITLE:`-style keyword lines.
+pub struct OrgParser;
+
+impl Parser for OrgParser {
+    fn name(&self) -> &'static str {
+        "org"
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["org"]
+    }
+
+    fn sniff(&self, content: &str) -> f32 {
+        let keyword = |line: &str| {
+            let line = line.trim_start().to_ascii_lowercase();
+            line.strip_prefix("#+").is_some_and(|rest| {
+                rest.starts_with("begin_")
+                    || rest
+                        .split_once(':')
+                        .is_some_and(|(key, _)| !key.is_empty() && !key.contains(' '))
+            })
+        };
+        if content.lines().any(keyword) {
+            0.8
+        } else {
+            0.0
+        }
+    }
+
+    fn parse(&self, content: &str) -> Vec<Equation> {
+        org_equations(content)
+    }
+
+    fn frontmatter(&self, content: &str) -> BTreeMap<String, String> {
+        org_keywords(content)
+    }
+}
+
+/// Jupyter notebooks, whose markdown cells are read like markdown notes.
+/// Equations get no source span, as lines of the JSON don't match lines of
+/// the cells.
+pub struct NotebookParser(pub Extractor);
+
+impl Parser for NotebookParser {
+    fn name(&self) -> &'static str {
+        "notebook"
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["ipynb"]
+    }
+
+    fn sniff(&self, content: &str) -> f32 {
+        let json = content.trim_start().starts_with('{');
+        if json && notebook_markdown(content).is_some() {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    fn parse(&self, content: &str) -> Vec<Equation> {
+        let Some(markdown) = notebook_markdown(content) else {
+            return Vec::new();
+        };
+        let mut equations = self.0.extract(&markdown);
+        for equation in &mut equations {
+            equation.span = None;
+        }
+        equations
+    }
+}
+
+/// MediaWiki markup, as saved from an edit box or exported in an XML dump.
+pub struct WikiParser;
+
+impl Parser for WikiParser {
+    fn name(&self) -> &'static str {
+        "wiki"
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["wiki", "mediawiki", "wikitext"]
+    }
+
+    fn sniff(&self, content: &str) -> f32 {
+        if content.contains("<mediawiki") && content.contains("<page>") {
+            return 1.0;
+        }
+        let heading = content.lines().any(|line| {
+            // `== Title ==`, not a markdown `=====` underline
+            let line = line.trim_end();
+            let title = line.trim_start_matches('=').trim_end_matches('=');
+            line.starts_with("==") && line.ends_with("==") && !title.trim().is_empty()
+        });
+        // Above HTML, which also claims `<math`
+        match (content.contains("<math"), heading) {
+            (true, true) => 0.8,
+            (true, false) if content.contains("'''") => 0.6,
+            (false, true) => 0.3,
+            _ => 0.0,
+        }
+    }
+
+    fn parse(&self, content: &str) -> Vec<Equation> {
+        wiki_equations(content)
+    }
+}
+
+// What a matching extension adds to a parser's confidence: enough to settle
+// close calls, not enough to overrule content that is clearly another format
+const EXTENSION_WEIGHT: f32 = 0.5;
+
+/// The parser `ParserRegistry::detect` picked and how sure it is, from 0 to
+/// 1 before the extension is counted.
+#[derive(Clone, Copy)]
+pub struct Detection<'a> {
+    pub parser: &'a dyn Parser,
+    pub confidence: f32,
+    pub by_extension: bool, // The parser claims the file's extension
+}
+
+/// The formats files are read as, picked by sniffing the content, with the
+/// extension as a tiebreaker. Parsers registered later win over earlier
+/// ones, so a downstream crate can replace a built-in format as well as add
+/// one.
+pub struct ParserRegistry {
+    parsers: Vec<Box<dyn Parser>>,
+    duplicate_names: DuplicateNames,
+    ignore: Vec<Regex>, // Equations whose body matches one are dropped
+}
+
+impl Default for ParserRegistry {
+    fn default() -> Self {
+        ParserRegistry::with_extractor(Extractor::default())
+    }
+}
+
+impl ParserRegistry {
+    /// No formats at all.
+    pub fn empty() -> Self {
+        ParserRegistry {
+            parsers: Vec::new(),
+            duplicate_names: DuplicateNames::default(),
+            ignore: Vec::new(),
+        }
+    }
+
+    /// The built-in formats, with markdown read through `extractor`.
+    pub fn with_extractor(extractor: Extractor) -> Self {
+        let mut registry = ParserRegistry::empty();
+        registry.register(MarkdownParser(extractor.clone()));
+        registry.register(CsvParser);
+        registry.register(HtmlParser);
+        registry.register(OrgParser);
+        registry.register(NotebookParser(extractor));
+        registry.register(WikiParser);
+        registry.register(TomlParser);
+        registry
+    }
+
+    pub fn register(&mut self, parser: impl Parser + 'static) {
+        self.parsers.push(Box::new(parser));
+    }
+
+    pub fn duplicate_names(&self) -> DuplicateNames {
+        self.duplicate_names
+    }
+
+    pub fn set_duplicate_names(&mut self, mode: DuplicateNames) {
+        self.duplicate_names = mode;
+    }
+
+    /// Drops every equation whose body matches one of `patterns`, e.g. `$$`
+    /// pairs around prices rather than math.
+    pub fn set_ignore(&mut self, patterns: Vec<Regex>) {
+        self.ignore = patterns;
+    }
+
+    /// The parser claiming the extension of `path`.
+    pub fn for_path(&self, path: &Path) -> Option<&dyn Parser> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        self.parsers
+            .iter()
+            .rev()
+            .find(|parser| parser.extensions().contains(&extension.as_str()))
+            .map(|parser| parser.as_ref())
+    }
+
+    /// The parser most sure of `content`, counting a matching extension in
+    /// its favor. Without any match, or any confidence, there is none.
+    pub fn detect(&self, path: &Path, content: &str) -> Option<Detection<'_>> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        let mut best: Option<(Detection, f32)> = None;
+        for parser in self.parsers.iter().rev() {
+            let confidence = parser.sniff(content).clamp(0.0, 1.0);
+            let by_extension = extension
+                .as_deref()
+                .is_some_and(|extension| parser.extensions().contains(&extension));
+            if confidence == 0.0 && !by_extension {
+                continue;
+            }
+            let score = confidence + if by_extension { EXTENSION_WEIGHT } else { 0.0 };
+            // On a tie the extension decides
+            let better = best.as_ref().is_none_or(|(best, best_score)| {
+                score > *best_score || (score == *best_score && by_extension && !best.by_extension)
+            });
+            if better {
+                let detection = Detection {
+                    parser: parser.as_ref(),
+                    confidence,
+                    by_extension,
+                };
+                best = Some((detection, score));
+            }
+        }
+        best.map(|(detection, _)| detection)
+    }
+
+    /// The equations in `content` read from `path`, with unique names and
+    /// without the ignored ones, or `None` when no parser takes it.
+    pub fn parse(&self, path: &Path, content: &str) -> Option<Vec<Equation>> {
+        self.read(path, content).map(|(_, equations)| equations)
+    }
+
+    /// Like `parse`, along with the frontmatter of `content` and the macros
+    /// its equations define.
+    pub fn parse_document(&self, path: &Path, content: &str) -> Option<Document> {
+        let (parser, equations) = self.read(path, content)?;
+        let macros = equation_macros(&equations);
+        Some(Document {
+            path: path.to_path_buf(),
+            format: parser.name(),
+            source: content.to_string(),
+            equations,
+            frontmatter: parser.frontmatter(content),
+            macros,
+        })
+    }
+
+    fn read(&self, path: &Path, content: &str) -> Option<(&dyn Parser, Vec<Equation>)> {
+        let Detection {
+            parser,
+            confidence,
+            by_extension,
+        } = self.detect(path, content)?;
+        debug!(
+            "{}: reading as {} (confidence {:.1}{})",
+            path.display(),
+            parser.name(),
+            confidence,
+            if by_extension { ", by extension" } else { "" }
+        );
+        let mut equations = parser.parse(content);
+        equations.retain(|equation| {
+            let ignored = self.ignore.iter().find(|re| re.is_match(&equation.body));
+            if let Some(re) = ignored {
+                debug!(
+                    "{}: ignoring {} (matches {})",
+                    path.display(),
+                    equation.name,
+                    re
+                );
+            }
+            ignored.is_none()
+        });
+        unique_names(&mut equations, self.duplicate_names);
+        Some((parser, equations))
+    }
+
+    pub fn load(&self, path: &Path) -> io::Result<Vec<Equation>> {
+        Ok(self.load_document(path)?.equations)
+    }
+
+    pub fn load_document(&self, path: &Path) -> io::Result<Document> {
+        let content = load_source(path)?;
+        let document = self.parse_document(path, &content).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unsupported file type: {}", path.display()),
+            )
+        })?;
+        debug!(
+            "{}: {} equation(s)",
+            path.display(),
+            document.equations.len()
+        );
+        Ok(document)
+    }
+}
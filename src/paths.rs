@@ -0,0 +1,70 @@
+use directories::ProjectDirs;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Per-user locations for everything simptui keeps outside the output
+/// directory. Follows XDG on Linux (honoring `XDG_*_HOME`) and the platform
+/// conventions elsewhere.
+#[derive(Debug, Clone)]
+pub struct Paths {
+    pub config_dir: PathBuf,
+    pub cache_dir: PathBuf, // Rebuildable render artifacts
+    pub state_dir: PathBuf, // Data worth keeping between sessions
+    pub log_dir: PathBuf,
+}
+
+impl Paths {
+    pub fn new() -> Self {
+        let Some(dirs) = ProjectDirs::from("", "", "simptui") else {
+            // No home directory to anchor to, keep everything local
+            let base = PathBuf::from(".simptui");
+            return Paths {
+                config_dir: base.clone(),
+                cache_dir: base.join("cache"),
+                state_dir: base.join("state"),
+                log_dir: base.join("logs"),
+            };
+        };
+        // Only Linux has a state dir; elsewhere it lives with local data
+        let state_dir = dirs.state_dir().unwrap_or(dirs.data_local_dir());
+        Paths {
+            config_dir: dirs.config_dir().to_path_buf(),
+            cache_dir: dirs.cache_dir().to_path_buf(),
+            state_dir: state_dir.to_path_buf(),
+            log_dir: state_dir.join("logs"),
+        }
+    }
+
+    pub fn config_file(&self) -> PathBuf {
+        self.config_dir.join("config.toml")
+    }
+
+    /// Snippet files, `*.toml`, merged into the snippet library.
+    pub fn snippets_dir(&self) -> PathBuf {
+        self.config_dir.join("snippets")
+    }
+
+    /// Equations bookmarked in the TUI, from whatever file.
+    pub fn bookmarks_file(&self) -> PathBuf {
+        self.state_dir.join("bookmarks.json")
+    }
+
+    /// Rendered outputs keyed by their LaTeX source, shared by all projects.
+    pub fn render_cache_dir(&self) -> PathBuf {
+        self.cache_dir.join("renders")
+    }
+}
+
+impl Default for Paths {
+    fn default() -> Self {
+        Paths::new()
+    }
+}
+
+/// Creates `dir` (and its parents) if needed and hands it back, so callers can
+/// write `ensure_dir(&paths.cache_dir)?.join(..)`.
+pub fn ensure_dir(dir: &Path) -> io::Result<&Path> {
+    fs::create_dir_all(dir)?;
+    Ok(dir)
+}
@@ -0,0 +1,345 @@
+use crate::{
+    compile_timeout, shell_command, Equation, OutputFormat, RenderBackend, RenderOptions, SourceMap,
+};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::debug;
+
+const BUILT_IN: [&str; 5] = [
+    "generate-tex",
+    "compile",
+    "convert",
+    "post-process",
+    "cleanup",
+];
+
+/// What the stages of one equation's render work on. Stages hand results to
+/// each other through the files named here.
+pub struct StageContext<'a> {
+    pub equation: &'a Equation,
+    pub options: &'a RenderOptions,
+    pub backend: &'a dyn RenderBackend,
+    pub tex_file: PathBuf,
+    pub pdf_file: PathBuf,
+    pub output_file: PathBuf, // In the format asked for, once converted
+}
+
+impl StageContext<'_> {
+    pub fn output_dir(&self) -> &Path {
+        &self.options.output_dir
+    }
+}
+
+/// One step of rendering an equation. The built-in ones are `generate-tex`,
+/// `compile`, `convert`, `post-process` and `cleanup`; custom stages go
+/// anywhere between them through `RenderPipeline`.
+pub trait RenderStage: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn run(&self, context: &StageContext) -> io::Result<()>;
+
+    /// Whether to run even when the output comes from the render cache, for
+    /// stages whose effect lies outside the output file, such as an upload.
+    /// Other stages only run on a fresh render, and their names are part of
+    /// the cache key.
+    fn on_cache_hit(&self) -> bool {
+        false
+    }
+
+    /// Whether the stage is a command of the user's, which renders made only
+    /// to be compared leave out.
+    fn is_hook(&self) -> bool {
+        false
+    }
+
+    /// What stands for the stage in the cache key: its name, unless the
+    /// stage can change under the same name.
+    fn cache_key(&self) -> String {
+        self.name().to_string()
+    }
+}
+
+/// Writes the `.tex` source.
+pub struct GenerateTex;
+
+impl RenderStage for GenerateTex {
+    fn name(&self) -> &str {
+        "generate-tex"
+    }
+
+    fn run(&self, context: &StageContext) -> io::Result<()> {
+        let source = context.equation.generate_latex(context.options)?;
+        // Not part of the cache key, which `generate_latex` alone makes
+        let map = match context.options.source_map {
+            true => SourceMap::of(context.equation, context.options).tex_comment(),
+            false => String::new(),
+        };
+        fs::write(&context.tex_file, map + &source)
+    }
+}
+
+/// Runs TeX on the source, into a PDF.
+pub struct Compile;
+
+impl RenderStage for Compile {
+    fn name(&self) -> &str {
+        "compile"
+    }
+
+    fn run(&self, context: &StageContext) -> io::Result<()> {
+        let (equation, options) = (context.equation, context.options);
+        debug!("Compiling {} with {}", equation.name, options.engine);
+        // Keep the log on failure so the quarantined copy explains it
+        if context.backend.compile(
+            options.engine,
+            &context.tex_file,
+            context.output_dir(),
+            true,
+            options
+                .timeout
+                .map(|base| compile_timeout(&equation.body, base)),
+        )? {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!(
+                "LaTeX compilation failed for {}",
+                equation.name
+            )))
+        }
+    }
+}
+
+/// Turns the PDF into an SVG or PNG; PDF output is already done.
+pub struct Convert;
+
+impl RenderStage for Convert {
+    fn name(&self) -> &str {
+        "convert"
+    }
+
+    fn run(&self, context: &StageContext) -> io::Result<()> {
+        let options = context.options;
+        if !matches!(options.format, OutputFormat::Svg | OutputFormat::Png) {
+            return Ok(());
+        }
+        debug!(
+            "Converting {} to {}",
+            context.equation.name,
+            options.format.extension()
+        );
+        context.backend.convert(
+            &context.pdf_file,
+            &context.output_file,
+            options.format,
+            options.dpi,
+        )
+    }
+}
+
+/// Adjusts the output: baseline alignment of SVGs, when asked for.
+pub struct PostProcess;
+
+impl RenderStage for PostProcess {
+    fn name(&self) -> &str {
+        "post-process"
+    }
+
+    fn run(&self, context: &StageContext) -> io::Result<()> {
+        if context.options.baseline_align && context.options.format == OutputFormat::Svg {
+            context.equation.align_svg_to_baseline(context.options)?;
+        }
+        Ok(())
+    }
+}
+
+/// Removes the `.tex`, `.log` and `.pdf` files the retention policy doesn't
+/// keep.
+pub struct Cleanup;
+
+impl RenderStage for Cleanup {
+    fn name(&self) -> &str {
+        "cleanup"
+    }
+
+    fn run(&self, context: &StageContext) -> io::Result<()> {
+        let options = context.options;
+        context.equation.cleanup_intermediate_files(
+            context.output_dir(),
+            options.format,
+            options.retention,
+        )
+    }
+}
+
+/// A shell command as a stage, from a `[[hook]]` table of the config. It
+/// runs through `sh -c` (`cmd /C` on Windows) in the output directory, and
+/// finds the files in `SIMPTUI_OUTPUT`, `SIMPTUI_TEX` and `SIMPTUI_PDF`,
+/// the equation in `SIMPTUI_NAME`.
+pub struct ShellHook {
+    pub name: String,
+    pub command: String,
+    pub on_cache_hit: bool,
+}
+
+impl RenderStage for ShellHook {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn run(&self, context: &StageContext) -> io::Result<()> {
+        let status = shell_command(&self.command)
+            .current_dir(context.output_dir())
+            .env("SIMPTUI_NAME", &context.equation.name)
+            .env("SIMPTUI_OUTPUT", &context.output_file)
+            .env("SIMPTUI_TEX", &context.tex_file)
+            .env("SIMPTUI_PDF", &context.pdf_file)
+            .status()?;
+        if status.success() {
+            return Ok(());
+        }
+        Err(io::Error::other(format!(
+            "Hook '{}' failed for {} ({})",
+            self.name, context.equation.name, status
+        )))
+    }
+
+    fn on_cache_hit(&self) -> bool {
+        self.on_cache_hit
+    }
+
+    fn is_hook(&self) -> bool {
+        true
+    }
+
+    // A new command makes for new outputs
+    fn cache_key(&self) -> String {
+        format!("{}={}", self.name, self.command)
+    }
+}
+
+/// The stages an equation goes through, in order. Cheap to clone, as stages
+/// are shared.
+#[derive(Clone)]
+pub struct RenderPipeline {
+    stages: Vec<Arc<dyn RenderStage>>,
+}
+
+impl Default for RenderPipeline {
+    fn default() -> Self {
+        RenderPipeline {
+            stages: vec![
+                Arc::new(GenerateTex),
+                Arc::new(Compile),
+                Arc::new(Convert),
+                Arc::new(PostProcess),
+                Arc::new(Cleanup),
+            ],
+        }
+    }
+}
+
+impl fmt::Debug for RenderPipeline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+impl RenderPipeline {
+    pub fn names(&self) -> Vec<&str> {
+        self.stages.iter().map(|stage| stage.name()).collect()
+    }
+
+    /// Appends `stage`, after the cleanup.
+    pub fn push(&mut self, stage: impl RenderStage + 'static) {
+        self.stages.push(Arc::new(stage));
+    }
+
+    /// Puts `stage` right after the stage called `after`.
+    pub fn insert_after(
+        &mut self,
+        after: &str,
+        stage: impl RenderStage + 'static,
+    ) -> io::Result<()> {
+        let at = self.position(after)? + 1;
+        self.stages.insert(at, Arc::new(stage));
+        Ok(())
+    }
+
+    /// Puts `stage` right before the stage called `before`.
+    pub fn insert_before(
+        &mut self,
+        before: &str,
+        stage: impl RenderStage + 'static,
+    ) -> io::Result<()> {
+        let at = self.position(before)?;
+        self.stages.insert(at, Arc::new(stage));
+        Ok(())
+    }
+
+    /// The same stages, hooks aside.
+    pub fn without_hooks(&self) -> RenderPipeline {
+        RenderPipeline {
+            stages: self
+                .stages
+                .iter()
+                .filter(|stage| !stage.is_hook())
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Takes the stage called `name` out, e.g. `cleanup` to keep every file.
+    pub fn remove(&mut self, name: &str) -> io::Result<()> {
+        let at = self.position(name)?;
+        self.stages.remove(at);
+        Ok(())
+    }
+
+    /// The stages that aren't built in, which change what a cached output
+    /// would be, one per line.
+    pub(crate) fn cache_key(&self) -> String {
+        self.stages
+            .iter()
+            .filter(|stage| !BUILT_IN.contains(&stage.name()))
+            .map(|stage| stage.cache_key())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Runs every stage in order, stopping at the first error.
+    pub fn run(&self, context: &StageContext) -> io::Result<()> {
+        for stage in &self.stages {
+            debug!("{}: {}", context.equation.name, stage.name());
+            stage.run(context)?;
+        }
+        Ok(())
+    }
+
+    /// Runs the stages that want to see outputs restored from the cache.
+    pub fn run_on_cache_hit(&self, context: &StageContext) -> io::Result<()> {
+        for stage in self.stages.iter().filter(|stage| stage.on_cache_hit()) {
+            debug!("{}: {} (cached)", context.equation.name, stage.name());
+            stage.run(context)?;
+        }
+        Ok(())
+    }
+
+    fn position(&self, name: &str) -> io::Result<usize> {
+        self.stages
+            .iter()
+            .position(|stage| stage.name() == name)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "No render stage '{}' (stages: {})",
+                        name,
+                        self.names().join(", ")
+                    ),
+                )
+            })
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{build_render_options, check_report};
+use simptui::{catch_interrupts, render_equations, scan_files, warn_unsupported, Config, Equation};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// Line-based stand-in for the TUI, for terminals that can't run it and for
+/// `--no-tui`: pick a file, review its equations, render them.
+pub fn run(config: &Config, roots: &[PathBuf], profile: Option<&str>) -> io::Result<()> {
+    if let Some(name) = profile {
+        config.profile(name)?;
+    }
+    let parsers = config.parsers()?;
+    println!("Scanning files...");
+    let files: Vec<PathBuf> = scan_files(&config.scan_roots(roots), &config.scan)
+        .into_iter()
+        .filter(|path| parsers.for_path(path).is_some())
+        .collect();
+    println!("{} note(s) found.", files.len());
+
+    while let Some(answer) = prompt("File (name or number, `?` lists them, empty quits)")? {
+        let path = match answer.as_str() {
+            "" => break,
+            "?" => {
+                list_files(&files, "");
+                continue;
+            }
+            _ => match pick_file(&files, &answer) {
+                Some(path) => path,
+                None => {
+                    list_files(&files, &answer);
+                    continue;
+                }
+            },
+        };
+
+        let equations = match parsers.load(path) {
+            Ok(equations) => equations,
+            Err(e) => {
+                eprintln!("Error reading {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        print_equations(&equations);
+        if let Ok(options) = build_render_options(config, profile, PathBuf::new(), None, None) {
+            warn_unsupported(&equations, &options);
+        }
+        let active = equations.iter().filter(|eq| eq.active).count();
+        if active == 0 {
+            println!("No active equations in {}.", path.display());
+            continue;
+        }
+
+        let out = config.output_dir(path);
+        let question = format!(
+            "Render {} active equation(s) into {}/ with profile {}? (y/n)",
+            active,
+            out.display(),
+            profile.unwrap_or("(none)")
+        );
+        if !prompt(&question)?.is_some_and(|a| matches!(a.to_lowercase().as_str(), "y" | "yes")) {
+            continue;
+        }
+        let result = build_render_options(config, profile, out, None, None)
+            .and_then(|options| {
+                // Ctrl-C stops the batch, not the prompts around it
+                let _interrupts = catch_interrupts();
+                render_equations(&equations, &options)
+            })
+            .and_then(|report| {
+                check_report(&report, false)?;
+                println!("{}", report.summary());
+                Ok(())
+            });
+        if let Err(e) = result {
+            eprintln!("{}", e);
+        }
+    }
+    Ok(())
+}
+
+// `None` once stdin is closed
+fn prompt(text: &str) -> io::Result<Option<String>> {
+    print!("{}: ", text);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    if io::stdin().read_line(&mut line)? == 0 {
+        println!();
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_string()))
+}
+
+// A 1-based number from the listing or an exact file name
+fn pick_file<'a>(files: &'a [PathBuf], answer: &str) -> Option<&'a PathBuf> {
+    if let Ok(number) = answer.parse::<usize>() {
+        return number.checked_sub(1).and_then(|i| files.get(i));
+    }
+    files
+        .iter()
+        .find(|path| path.file_name().is_some_and(|name| name == answer))
+}
+
+fn list_files(files: &[PathBuf], filter: &str) {
+    let mut shown = 0;
+    for (i, path) in files.iter().enumerate() {
+        if path.to_string_lossy().contains(filter) {
+            println!("{:>4}  {}", i + 1, path.display());
+            shown += 1;
+        }
+    }
+    if shown == 0 {
+        println!("No notes match '{}'.", filter);
+    }
+}
+
+fn print_equations(equations: &[Equation]) {
+    for eq in equations {
+        let mark = if eq.active { "x" } else { " " };
+        println!("[{}] {}: {}", mark, eq.name, eq.body.replace('\n', " "));
+    }
+}
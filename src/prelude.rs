@@ -0,0 +1,20 @@
+//! What most users of the library need, for a single glob import:
+//!
+//! ```no_run
+//! use simptui::prelude::*;
+//!
+//! let equations = load_equations("notes.md".as_ref())?;
+//! let options = RenderOptions::new("equations", "#000000");
+//! let report = render_equations(&equations, &options)?;
+//! println!("{}", report.summary());
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! Items stay here across minor versions; additions come as new names.
+
+pub use crate::{
+    detect_file_type, load_document, load_equations, parse_markdown, render_equations,
+    render_equations_with, Config, Document, Engine, Equation, EquationFilter, Fill, Font,
+    FontSize, OutputFormat, OutputLayout, OutputNaming, ParserRegistry, Project, RenderBackend,
+    RenderFailure, RenderOptions, RenderPipeline, RenderReport, Retention, SourceSpan,
+};
@@ -0,0 +1,72 @@
+use crate::sha256_hex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// An equation a batch finished, with what it left in the output directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Completed {
+    pub key: String,         // Changes with anything that changes the output
+    pub file: String,        // Output file name
+    pub css: Option<String>, // Its baseline.css rule
+}
+
+/// Equations of a batch into one output directory that are already done,
+/// kept next to the render cache after every equation. A batch cut short by
+/// Ctrl-C or a crash skips them when run again; one that gets through
+/// without failures removes the record.
+#[derive(Debug, Default)]
+pub(crate) struct BatchProgress {
+    path: PathBuf,
+    done: BTreeMap<String, Completed>,
+}
+
+impl BatchProgress {
+    /// The record for `output_dir` under `cache_dir`, empty if there is
+    /// none or it can't be read.
+    pub fn load(cache_dir: &Path, output_dir: &Path) -> Self {
+        // Not canonicalized, as the first run may not have created it yet
+        let output_dir =
+            std::path::absolute(output_dir).unwrap_or_else(|_| output_dir.to_path_buf());
+        let id = sha256_hex(output_dir.to_string_lossy().as_bytes());
+        let path = cache_dir
+            .join("progress")
+            .join(format!("{}.json", &id[..16]));
+        let done = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        BatchProgress { path, done }
+    }
+
+    /// The finished render of `name`, if `key` still matches and its file is
+    /// still in `output_dir`.
+    pub fn completed(&self, name: &str, key: &str, output_dir: &Path) -> Option<&Completed> {
+        self.done
+            .get(name)
+            .filter(|done| done.key == key && output_dir.join(&done.file).exists())
+    }
+
+    pub fn record(&mut self, name: &str, completed: Completed) -> io::Result<()> {
+        self.done.insert(name.to_string(), completed);
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string(&self.done).map_err(io::Error::other)?;
+        fs::write(&self.path, json)
+    }
+
+    /// Forgets the batch, so the next one starts over.
+    pub fn clear(&mut self) -> io::Result<()> {
+        self.done.clear();
+        if self.path.as_os_str().is_empty() {
+            return Ok(()); // Not kept anywhere
+        }
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
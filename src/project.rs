@@ -0,0 +1,217 @@
+use crate::{unique_names, Equation, OutputFormat, ParserRegistry, Paths};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const PROJECT_FILE_NAME: &str = ".simptui";
+
+/// A set of source files rendered together, e.g. all chapters of a thesis.
+/// Stored as TOML in `.simptui` at the project root; all paths in it are
+/// relative to that directory.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct Project {
+    #[serde(skip)]
+    pub root: PathBuf, // Directory holding the project file
+    pub sources: Vec<PathBuf>,
+    pub output: ProjectOutput,
+    pub cache_dir: Option<PathBuf>, // Defaults to the user cache directory
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub equations: BTreeMap<String, EquationOverride>, // `[equations.<name>]` tables
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub order: BTreeMap<PathBuf, Vec<String>>, // Manual equation order per source
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct ProjectOutput {
+    pub dir: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<OutputFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+impl Default for ProjectOutput {
+    fn default() -> Self {
+        ProjectOutput {
+            dir: PathBuf::from("equations"),
+            profile: None,
+            format: None,
+            color: None,
+        }
+    }
+}
+
+/// Per-equation tweaks that win over what the source file says.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct EquationOverride {
+    pub active: Option<bool>,
+    pub body: Option<String>,
+    pub rename: Option<String>, // Output file name instead of the source name
+}
+
+impl Project {
+    pub fn new(root: &Path) -> Self {
+        Project {
+            root: root.to_path_buf(),
+            ..Project::default()
+        }
+    }
+
+    /// Walks up from `start` to the nearest directory with a project file.
+    pub fn find(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|dir| dir.join(PROJECT_FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let mut project: Project = toml::from_str(&content).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid project file {}: {}", path.display(), e),
+            )
+        })?;
+        project.root = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        Ok(project)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let content = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(self.root.join(PROJECT_FILE_NAME), content)
+    }
+
+    /// Records `file` as a source. Returns `false` if it was already listed.
+    pub fn add_source(&mut self, file: &Path) -> io::Result<bool> {
+        let relative = self.relative_path(file)?;
+        if self.sources.contains(&relative) {
+            return Ok(false);
+        }
+        self.sources.push(relative);
+        Ok(true)
+    }
+
+    /// Remembers the equation order of `file`, by name, in place of the
+    /// document order.
+    pub fn set_order(&mut self, file: &Path, names: Vec<String>) -> io::Result<()> {
+        let relative = self.relative_path(file)?;
+        self.order.insert(relative, names);
+        Ok(())
+    }
+
+    /// The manual order saved for `file`, if any.
+    pub fn order_of(&self, file: &Path) -> Option<&[String]> {
+        let relative = self.relative_path(file).ok()?;
+        self.order.get(&relative).map(Vec::as_slice)
+    }
+
+    /// Follows equations of `file` renamed in the source: its saved order and
+    /// `[equations.<name>]` overrides move to the new names.
+    pub fn rename_equations(
+        &mut self,
+        file: &Path,
+        renames: &[(String, String)],
+    ) -> io::Result<()> {
+        let relative = self.relative_path(file)?;
+        if let Some(order) = self.order.get_mut(&relative) {
+            for name in order.iter_mut() {
+                if let Some((_, new)) = renames.iter().find(|(old, _)| old == name) {
+                    *name = new.clone();
+                }
+            }
+        }
+        for (old, new) in renames {
+            if let Some(overrides) = self.equations.remove(old) {
+                self.equations.insert(new.clone(), overrides);
+            }
+        }
+        Ok(())
+    }
+
+    fn relative_path(&self, file: &Path) -> io::Result<PathBuf> {
+        let file = file.canonicalize()?;
+        let root = self.root.canonicalize()?;
+        let relative = file.strip_prefix(&root).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} is outside the project {}",
+                    file.display(),
+                    root.display()
+                ),
+            )
+        })?;
+        Ok(relative.to_path_buf())
+    }
+
+    pub fn output_dir(&self) -> PathBuf {
+        self.root.join(&self.output.dir)
+    }
+
+    pub fn cache_dir(&self) -> PathBuf {
+        match &self.cache_dir {
+            Some(dir) => self.root.join(dir),
+            None => Paths::new().render_cache_dir(),
+        }
+    }
+
+    /// Reads every source in order and applies the saved orders and overrides. Names that
+    /// repeat across files are told apart as within a single file.
+    pub fn equations(&self, parsers: &ParserRegistry) -> io::Result<Vec<Equation>> {
+        let mut equations = Vec::new();
+
+        for source in &self.sources {
+            let path = self.root.join(source);
+            let mut loaded = parsers
+                .load(&path)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+            if let Some(order) = self.order.get(source) {
+                apply_order(&mut loaded, order);
+            }
+            equations.extend(loaded);
+        }
+        unique_names(&mut equations, parsers.duplicate_names());
+        for equation in &mut equations {
+            if let Some(overrides) = self.equations.get(&equation.name) {
+                overrides.apply(equation);
+            }
+        }
+        Ok(equations)
+    }
+}
+
+impl EquationOverride {
+    fn apply(&self, equation: &mut Equation) {
+        if let Some(active) = self.active {
+            equation.active = active;
+        }
+        if let Some(body) = &self.body {
+            equation.body = body.clone();
+        }
+        if let Some(rename) = &self.rename {
+            equation.name = Equation::sanitize_filename(rename);
+        }
+    }
+}
+
+/// Sorts `equations` by their position in `order`. Names not listed keep
+/// their document order after the listed ones.
+pub fn apply_order(equations: &mut [Equation], order: &[String]) {
+    equations.sort_by_key(|eq| {
+        order
+            .iter()
+            .position(|name| *name == eq.name)
+            .unwrap_or(order.len())
+    });
+}
@@ -0,0 +1,159 @@
+use crate::{content_hash, read_manifest, update_manifest, Manifest, Rgb};
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// `fill`/`stroke` as attributes and inside `style`; `fill-rule` and the like
+// don't match
+const PAINT: &str = r#"\b(?P<property>fill|stroke)(?P<separator>="|:\s*)(?P<color>#[0-9a-fA-F]{6}\b|#[0-9a-fA-F]{3}\b|rgb\([^)]*\)|black\b|white\b)"#;
+
+/// How many files `recolor_dir` rewrote, and how many SVGs it left alone
+/// because they had no paint of the color to replace.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RecolorReport {
+    pub recolored: Vec<PathBuf>,
+    pub unchanged: usize,
+}
+
+/// The color of most `fill`s and `stroke`s in `svg`: the equation's own,
+/// rather than a background or a `\textcolor` part.
+pub fn dominant_color(svg: &str) -> Option<Rgb> {
+    let mut counts: HashMap<Rgb, usize> = HashMap::new();
+    for cap in Regex::new(PAINT).unwrap().captures_iter(svg) {
+        if let Some(color) = paint_color(&cap["color"]) {
+            *counts.entry(color).or_default() += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .max_by_key(|&(color, count)| (count, color.to_hex()))
+        .map(|(color, _)| color)
+}
+
+/// Repaints the `from` fills and strokes of `svg` in `to`, touching nothing
+/// else. Without `from`, the dominant color is repainted.
+pub fn recolor_svg(svg: &str, from: Option<Rgb>, to: Rgb) -> String {
+    let Some(from) = from.or_else(|| dominant_color(svg)) else {
+        return svg.to_string();
+    };
+    Regex::new(PAINT)
+        .unwrap()
+        .replace_all(svg, |cap: &Captures| {
+            if paint_color(&cap["color"]) == Some(from) {
+                format!("{}{}{}", &cap["property"], &cap["separator"], to.to_hex())
+            } else {
+                cap[0].to_string()
+            }
+        })
+        .into_owned()
+}
+
+/// Recolors every SVG under the output directory `dir` like `recolor_svg`.
+/// Hashed file names follow their new content, in the manifest too.
+pub fn recolor_dir(dir: &Path, from: Option<Rgb>, to: Rgb) -> io::Result<RecolorReport> {
+    let mut report = RecolorReport::default();
+    let mut renamed = HashMap::new();
+    for path in svg_files(dir)? {
+        let svg = fs::read_to_string(&path)?;
+        let recolored = recolor_svg(&svg, from, to);
+        if recolored == svg {
+            report.unchanged += 1;
+            continue;
+        }
+        fs::write(&path, &recolored)?;
+        let old_suffix = format!("-{}.svg", content_hash(svg.as_bytes()));
+        let hashed = path
+            .to_str()
+            .and_then(|path| path.strip_suffix(&old_suffix));
+        let path = match hashed {
+            Some(stem) => {
+                let new = PathBuf::from(format!(
+                    "{}-{}.svg",
+                    stem,
+                    content_hash(recolored.as_bytes())
+                ));
+                fs::rename(&path, &new)?;
+                let file_name = new.file_name().unwrap_or_default().to_string_lossy();
+                renamed.insert(path, file_name.into_owned());
+                new
+            }
+            None => path,
+        };
+        report.recolored.push(path);
+    }
+
+    if !renamed.is_empty() {
+        let moved: Manifest = read_manifest(dir)
+            .into_iter()
+            .filter_map(|(name, file)| {
+                let new = renamed.get(&dir.join(&file))?;
+                let new = match file.rsplit_once('/') {
+                    Some((subdir, _)) => format!("{}/{}", subdir, new),
+                    None => new.clone(),
+                };
+                Some((name, new))
+            })
+            .collect();
+        update_manifest(dir, moved)?;
+    }
+    Ok(report)
+}
+
+// `#rgb`, `#rrggbb`, `rgb(...)` in numbers or percentages, black and white
+fn paint_color(color: &str) -> Option<Rgb> {
+    match color {
+        "black" => return Some(Rgb(0, 0, 0)),
+        "white" => return Some(Rgb(255, 255, 255)),
+        _ => {}
+    }
+    if let Some(hex) = color.strip_prefix('#') {
+        if hex.len() == 3 {
+            let doubled: String = hex.chars().flat_map(|c| [c, c]).collect();
+            return Rgb::from_hex(&doubled);
+        }
+        return Rgb::from_hex(hex);
+    }
+    let channels: Vec<u8> = color
+        .strip_prefix("rgb(")?
+        .strip_suffix(')')?
+        .split(',')
+        .map(|channel| {
+            let channel = channel.trim();
+            match channel.strip_suffix('%') {
+                Some(percent) => percent
+                    .parse::<f64>()
+                    .ok()
+                    .map(|p| (p.clamp(0.0, 100.0) * 2.55).round() as u8),
+                None => channel.parse().ok(),
+            }
+        })
+        .collect::<Option<_>>()?;
+    match channels[..] {
+        [r, g, b] => Some(Rgb(r, g, b)),
+        _ => None,
+    }
+}
+
+// The SVGs in `dir` and its subdirectories, where routes put outputs, but
+// not in `failed/`, where failed renders are kept as they were
+fn svg_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let failed = dir.join("failed");
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                if path != failed {
+                    dirs.push(path);
+                }
+            } else if path.extension().is_some_and(|extension| extension == "svg") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
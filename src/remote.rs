@@ -0,0 +1,162 @@
+use crate::{Engine, OutputFormat, RenderBackend};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use tracing::debug;
+
+/// Version of `RenderRequest`/`RenderResponse`, sent along so a service can
+/// turn away clients it doesn't understand.
+pub const REMOTE_PROTOCOL_VERSION: u32 = 1;
+
+// A render is one TeX run; anything slower is treated as a dead service,
+// unless the equation was given longer
+const REMOTE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// What a client POSTs to the rendering service, as JSON: the complete
+/// `.tex` document the local engine would have compiled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RenderRequest {
+    pub version: u32,
+    pub document: String,
+    pub engine: Engine, // `auto` lets the service choose
+}
+
+/// The service's answer: the SVG on success, and the TeX log either way.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct RenderResponse {
+    pub ok: bool,
+    #[serde(default)]
+    pub svg: Option<String>,
+    #[serde(default)]
+    pub log: String,
+}
+
+/// Renders on a service speaking the `RenderRequest` protocol instead of a
+/// local TeX installation, so machines without TeX still produce SVGs. The
+/// token goes out as `Authorization: Bearer <token>`.
+#[derive(Clone)]
+pub struct RemoteBackend {
+    pub url: String,
+    pub token: Option<String>,
+}
+
+impl RemoteBackend {
+    pub fn new(url: &str, token: Option<&str>) -> Self {
+        RemoteBackend {
+            url: url.to_string(),
+            token: token.map(str::to_string),
+        }
+    }
+
+    /// Sends `request` and reads the service's response.
+    pub fn send(&self, request: &RenderRequest) -> io::Result<RenderResponse> {
+        self.send_within(request, REMOTE_TIMEOUT)
+    }
+
+    fn send_within(
+        &self,
+        request: &RenderRequest,
+        timeout: Duration,
+    ) -> io::Result<RenderResponse> {
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .timeout_global(Some(timeout))
+            .build()
+            .into();
+        let mut post = agent.post(&self.url);
+        if let Some(token) = &self.token {
+            post = post.header("Authorization", format!("Bearer {}", token));
+        }
+        debug!(
+            "Posting {} bytes of LaTeX to {}",
+            request.document.len(),
+            self.url
+        );
+        let remote_error = |e: ureq::Error| {
+            io::Error::other(format!("Remote render at {} failed: {}", self.url, e))
+        };
+        post.send_json(request)
+            .map_err(remote_error)?
+            .body_mut()
+            .read_json()
+            .map_err(remote_error)
+    }
+}
+
+// Keeps the token out of logs
+impl fmt::Debug for RemoteBackend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RemoteBackend")
+            .field("url", &self.url)
+            .field("token", &self.token.as_ref().map(|_| "***"))
+            .finish()
+    }
+}
+
+impl RenderBackend for RemoteBackend {
+    /// Posts the document and writes the SVG the service returns straight to
+    /// `<stem>.svg`; there is no PDF.
+    fn compile(
+        &self,
+        engine: Engine,
+        tex_file: &Path,
+        output_dir: &Path,
+        keep_logs: bool,
+        timeout: Option<Duration>,
+    ) -> io::Result<bool> {
+        let request = RenderRequest {
+            version: REMOTE_PROTOCOL_VERSION,
+            document: fs::read_to_string(tex_file)?,
+            engine,
+        };
+        let timeout = timeout.map_or(REMOTE_TIMEOUT, |timeout| timeout.max(REMOTE_TIMEOUT));
+        let response = self.send_within(&request, timeout)?;
+        let stem = tex_file.file_stem().unwrap_or_default().to_string_lossy();
+        let output = |ext: &str| output_dir.join(format!("{}.{}", stem, ext));
+
+        if keep_logs || !response.ok {
+            fs::write(output("log"), &response.log)?;
+        }
+        match (response.ok, response.svg) {
+            (true, Some(svg)) => fs::write(output("svg"), svg).map(|_| true),
+            (true, None) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} reported success but sent no SVG", self.url),
+            )),
+            (false, _) => Ok(false),
+        }
+    }
+
+    /// Moves the SVG `compile` wrote to `target`. Only SVG can be had.
+    fn convert(
+        &self,
+        pdf_file: &Path,
+        target: &Path,
+        format: OutputFormat,
+        _dpi: u32,
+    ) -> io::Result<()> {
+        match format {
+            OutputFormat::Svg => {
+                let svg = pdf_file.with_extension("svg");
+                if svg != target {
+                    fs::rename(svg, target)?;
+                }
+                Ok(())
+            }
+            OutputFormat::Png => Err(remote_format_error(format)),
+            OutputFormat::Pdf | OutputFormat::MathML => Ok(()),
+        }
+    }
+}
+
+pub(crate) fn remote_format_error(format: OutputFormat) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "The remote backend renders SVG only, not {}",
+            format.extension().to_uppercase()
+        ),
+    )
+}
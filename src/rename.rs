@@ -0,0 +1,356 @@
+use crate::{
+    balance, csv_columns, csv_field, csv_row, detect_file_type, export_toml, headings, load_source,
+    sha256_hex, Equation,
+};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use toml::Value;
+
+const HASH_LEN: usize = 8;
+
+/// Template for equation names, e.g. `{file_stem}_{index:02}_{name}`.
+/// Variables: `{file_stem}`, `{heading}` (slug of the nearest markdown
+/// heading above), `{index}` (1-based, `{index:03}` pads it), `{hash}` (of
+/// the body) and `{name}` (the current name).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamePattern {
+    source: String,
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    FileStem,
+    Heading,
+    Index(usize), // Zero-padded width
+    Hash,
+    Name,
+}
+
+impl NamePattern {
+    /// New names for `equations`, in the same order. `path` and `source` are
+    /// the file they were read from. Names are sanitized like source names
+    /// and stripped of leading/trailing `_` left by empty variables; a
+    /// pattern that gives two equations the same name is an error.
+    pub fn apply(
+        &self,
+        equations: &[Equation],
+        path: &Path,
+        source: &str,
+    ) -> io::Result<Vec<String>> {
+        let file_stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let headings = markdown_headings(path, source);
+
+        let mut names = Vec::new();
+        let mut seen: HashMap<String, &str> = HashMap::new();
+        for (i, equation) in equations.iter().enumerate() {
+            let line = equation.span.map_or(0, |span| span.start_line);
+            let heading = headings
+                .iter()
+                .take_while(|(heading_line, _)| *heading_line < line)
+                .last()
+                .map_or("", |(_, slug)| slug.as_str());
+
+            let mut name = String::new();
+            for part in &self.parts {
+                match part {
+                    Part::Text(text) => name.push_str(text),
+                    Part::FileStem => name.push_str(&file_stem),
+                    Part::Heading => name.push_str(heading),
+                    Part::Index(width) => name.push_str(&format!("{:0width$}", i + 1)),
+                    Part::Hash => name.push_str(&sha256_hex(equation.body.as_bytes())[..HASH_LEN]),
+                    Part::Name => name.push_str(&equation.name),
+                }
+            }
+            let name = Equation::sanitize_filename(name.trim_matches('_'));
+
+            if let Some(previous) = seen.insert(name.clone(), &equation.name) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "'{}' would name both {} and {}; add {{index}} or {{hash}} to the pattern",
+                        name, previous, equation.name
+                    ),
+                ));
+            }
+            names.push(name);
+        }
+        Ok(names)
+    }
+}
+
+impl fmt::Display for NamePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl FromStr for NamePattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut rest = s;
+        while let Some(open) = rest.find('{') {
+            if open > 0 {
+                parts.push(Part::Text(rest[..open].to_string()));
+            }
+            let close = rest[open..]
+                .find('}')
+                .ok_or_else(|| format!("unclosed '{{' in pattern '{}'", s))?;
+            let variable = &rest[open + 1..open + close];
+            parts.push(match variable.split_once(':') {
+                Some(("index", width)) => Part::Index(
+                    width
+                        .parse()
+                        .map_err(|_| format!("invalid width '{}' in {{{}}}", width, variable))?,
+                ),
+                Some(_) => {
+                    return Err(format!(
+                        "only {{index}} takes a width, not {{{}}}",
+                        variable
+                    ))
+                }
+                None => match variable {
+                    "file_stem" => Part::FileStem,
+                    "heading" => Part::Heading,
+                    "index" => Part::Index(0),
+                    "hash" => Part::Hash,
+                    "name" => Part::Name,
+                    _ => {
+                        return Err(format!(
+                        "unknown variable {{{}}}: expected file_stem, heading, index, hash or name",
+                        variable
+                    ))
+                    }
+                },
+            });
+            rest = &rest[open + close + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_string()));
+        }
+        if !parts.iter().any(|part| !matches!(part, Part::Text(_))) {
+            return Err(format!("pattern '{}' has no variables", s));
+        }
+        Ok(NamePattern {
+            source: s.to_string(),
+            parts,
+        })
+    }
+}
+
+// (1-based line, slug) of every `#` heading; empty for other file types
+fn markdown_headings(path: &Path, source: &str) -> Vec<(usize, String)> {
+    if detect_file_type(path) != "markdown" {
+        return Vec::new();
+    }
+    headings(source)
+        .into_iter()
+        .map(|heading| (heading.line, slug(&heading.title)))
+        .collect()
+}
+
+pub(crate) fn slug(title: &str) -> String {
+    title
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Writes `names` into the markdown, CSV or TOML file `path` in place of the
+/// names of `equations`, which must have been read from it with the built-in
+/// syntax. Markdown blocks without a `%%name%%` line and TOML tables without
+/// a `name` get one. The file is
+/// written back as UTF-8 with LF line endings.
+pub fn rename_in_source(path: &Path, equations: &[Equation], names: &[String]) -> io::Result<()> {
+    let content = load_source(path)?;
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let header_columns = lines.first().map_or(3, |header| csv_columns(header));
+    let file_type = detect_file_type(path);
+    let name_key = Regex::new(r"^[ \t]*name[ \t]*=").unwrap();
+
+    // Bottom up, so inserted lines don't shift the spans still to come
+    let mut renames: Vec<(&Equation, &String)> = equations.iter().zip(names).collect();
+    renames.sort_by_key(|(eq, _)| std::cmp::Reverse(eq.span.map(|span| span.start_line)));
+    for (equation, name) in renames {
+        let span = equation
+            .span
+            .filter(|span| span.end_line <= lines.len())
+            .ok_or_else(|| not_renamable(path, equation))?;
+        match file_type {
+            "csv" => {
+                let row = &mut lines[span.start_line - 1];
+                let (mut columns, _) = csv_row(row, header_columns);
+                if columns.len() < 3 {
+                    return Err(not_renamable(path, equation));
+                }
+                columns[2] = name.clone();
+                *row = columns
+                    .iter()
+                    .map(|column| csv_field(column))
+                    .collect::<Vec<_>>()
+                    .join(",");
+            }
+            "markdown" => {
+                let block = lines[span.start_line - 1..span.end_line].join("\n");
+                let close = match &equation.environment {
+                    Some(environment) => format!(r"\end{{{}}}", environment),
+                    None => "$$".to_string(),
+                };
+                let renamed = rename_block(&block, &close, name)
+                    .ok_or_else(|| not_renamable(path, equation))?;
+                lines.splice(
+                    span.start_line - 1..span.end_line,
+                    renamed.lines().map(str::to_string),
+                );
+            }
+            "toml" => {
+                let table = span.start_line - 1..span.end_line;
+                let entry = format!("name = {}", Value::from(name.as_str()));
+                match lines[table.clone()]
+                    .iter()
+                    .position(|line| name_key.is_match(line))
+                {
+                    Some(at) => lines[table.start + at] = entry,
+                    None => lines.insert(table.start + 1, entry),
+                }
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!(
+                        "{}: only markdown, csv and toml names can be rewritten",
+                        path.display()
+                    ),
+                ))
+            }
+        }
+    }
+    fs::write(path, lines.join("\n") + "\n")
+}
+
+// Replaces the `%%name%%` after the closing `$$` (or `\end{..}`), or adds
+// one: on its own line, or right after the close when text follows on the
+// same line
+fn rename_block(block: &str, close: &str, name: &str) -> Option<String> {
+    let close = block.rfind(close)? + close.len();
+    let (math, trailer) = block.split_at(close);
+    let renamed = match trailer.find("%%") {
+        Some(start) if trailer[..start].trim().is_empty() => {
+            let end = start + 2 + trailer[start + 2..].find("%%")? + 2;
+            format!(
+                "{}{}%%{}%%{}",
+                math,
+                &trailer[..start],
+                name,
+                &trailer[end..]
+            )
+        }
+        _ if trailer.is_empty() => format!("{}\n%%{}%%", math, name),
+        _ => format!("{}%%{}%%{}", math, name, trailer),
+    };
+    Some(renamed)
+}
+
+/// Why `name` and `body` can't be added to the file `path` next to its
+/// `existing` equations, if they can't: bad or taken names, an empty body,
+/// unbalanced braces, or text the file's syntax has no room for.
+pub fn check_new_equation(
+    path: &Path,
+    existing: &[Equation],
+    name: &str,
+    body: &str,
+) -> Result<(), String> {
+    let file_type = detect_file_type(path);
+    if !matches!(file_type, "markdown" | "csv" | "toml") {
+        return Err("only markdown, csv and toml files take new equations".to_string());
+    }
+    if name.is_empty() {
+        return Err("the name is empty".to_string());
+    }
+    if Equation::sanitize_filename(name) != name {
+        return Err("names may only use letters, digits, _ and .".to_string());
+    }
+    if existing.iter().any(|eq| eq.name == name) {
+        return Err(format!("there already is an equation named {}", name));
+    }
+    let body = body.trim();
+    if body.is_empty() {
+        return Err("the body is empty".to_string());
+    }
+    if let Some(problem) = balance(body).problem() {
+        return Err(format!("unbalanced braces or environments: {}", problem));
+    }
+    match file_type {
+        "csv" if body.contains('\n') => Err("csv bodies can't contain line breaks".to_string()),
+        "markdown" if body.contains("$$") || body.contains("%%") => {
+            Err("the body can't contain $$ or %%".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Appends an active equation to the markdown (as a `$$` block with a
+/// `%%name%%` line), CSV or TOML file `path`. Check it with `check_new_equation`
+/// first. The file is written back as UTF-8 with LF line endings.
+pub fn append_equation(path: &Path, name: &str, body: &str) -> io::Result<()> {
+    let mut content = load_source(path)?;
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    match detect_file_type(path) {
+        "markdown" => {
+            if !content.is_empty() && !content.ends_with("\n\n") {
+                content.push('\n');
+            }
+            content.push_str(&format!("$$\n{}\n$$\n%%{}%%\n", body.trim(), name));
+        }
+        "csv" => {
+            if content.is_empty() {
+                content.push_str("Active,Body,Name\n");
+            }
+            let row = ["yes", body.trim(), name].map(csv_field).join(",");
+            content.push_str(&row);
+            content.push('\n');
+        }
+        "toml" => {
+            if !content.is_empty() && !content.ends_with("\n\n") {
+                content.push('\n');
+            }
+            content.push_str(&export_toml(&[Equation::new(true, name, body.trim())]));
+        }
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "{}: only markdown, csv and toml files take new equations",
+                    path.display()
+                ),
+            ))
+        }
+    }
+    fs::write(path, content)
+}
+
+fn not_renamable(path: &Path, equation: &Equation) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!(
+            "{}: can't find where {} is named; was it read with a custom rule?",
+            path.display(),
+            equation.name
+        ),
+    )
+}
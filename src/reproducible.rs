@@ -0,0 +1,204 @@
+use crate::{
+    render_equations_with, sha256_hex, BatchHooks, Equation, RenderBackend, RenderOptions,
+    RenderStage, StageContext, LOCK_FILE_NAME, MANIFEST_NAME, STATUS_FILE_NAME,
+};
+use regex::bytes::{Captures, Regex};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where `write_checksums` puts the checksums, in `sha256sum` format.
+pub const CHECKSUMS_FILE: &str = "SHA256SUMS";
+
+// The epoch as a PDF date; digits past it (the time zone) become zeros
+const EPOCH_DIGITS: &[u8] = b"19700101000000";
+// Chunks pdftocairo and other encoders stamp with the time or themselves
+const PNG_METADATA: [&[u8; 4]; 4] = [b"tIME", b"tEXt", b"zTXt", b"iTXt"];
+
+/// The `scrub` stage: takes creation dates, document IDs and similar
+/// metadata out of the output, and the PDF when one is kept, so the same
+/// source renders to the same bytes. Goes after `post-process`.
+pub struct Scrub;
+
+impl RenderStage for Scrub {
+    fn name(&self) -> &str {
+        "scrub"
+    }
+
+    fn run(&self, context: &StageContext) -> io::Result<()> {
+        for path in [&context.output_file, &context.pdf_file] {
+            if path.exists() {
+                scrub_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Scrubs `path` in place by its extension: PDF, SVG or PNG. Other files
+/// are left alone.
+pub(crate) fn scrub_file(path: &Path) -> io::Result<()> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    let bytes = fs::read(path)?;
+    let scrubbed = match extension.as_deref() {
+        Some("pdf") => scrub_pdf(&bytes),
+        Some("png") => scrub_png(&bytes),
+        Some("svg") => scrub_svg(&String::from_utf8_lossy(&bytes)).into_bytes(),
+        _ => return Ok(()),
+    };
+    if scrubbed != bytes {
+        fs::write(path, scrubbed)?;
+    }
+    Ok(())
+}
+
+/// Sets the `/CreationDate` and `/ModDate` of a PDF to the epoch and zeroes
+/// its `/ID`. Every value keeps its length, so the cross-reference offsets
+/// stay valid.
+pub fn scrub_pdf(pdf: &[u8]) -> Vec<u8> {
+    let dates = Regex::new(r"/(?:CreationDate|ModDate)\s*\(D:(?P<date>[^)]*)\)").unwrap();
+    let ids = Regex::new(r"/ID\s*\[\s*<[0-9A-Fa-f]*>\s*<[0-9A-Fa-f]*>\s*\]").unwrap();
+    let pdf = dates.replace_all(pdf, |cap: &Captures| {
+        let date = cap.name("date").unwrap();
+        let mut whole = cap[0].to_vec();
+        let start = date.start() - cap.get(0).unwrap().start();
+        let mut epoch = EPOCH_DIGITS.iter();
+        for byte in &mut whole[start..start + date.len()] {
+            if byte.is_ascii_digit() {
+                *byte = *epoch.next().unwrap_or(&b'0');
+            }
+        }
+        whole
+    });
+    ids.replace_all(&pdf, |cap: &Captures| {
+        let mut whole = cap[0].to_vec();
+        let start = whole.iter().position(|&byte| byte == b'[').unwrap_or(0);
+        for byte in &mut whole[start..] {
+            if byte.is_ascii_hexdigit() {
+                *byte = b'0';
+            }
+        }
+        whole
+    })
+    .into_owned()
+}
+
+/// Drops the time and text chunks of a PNG. Anything that isn't a PNG comes
+/// back as it was.
+pub fn scrub_png(png: &[u8]) -> Vec<u8> {
+    const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    if !png.starts_with(SIGNATURE) {
+        return png.to_vec();
+    }
+    let mut scrubbed = SIGNATURE.to_vec();
+    let mut at = SIGNATURE.len();
+    // Length, type, data, CRC
+    while at + 12 <= png.len() {
+        let length = u32::from_be_bytes(png[at..at + 4].try_into().unwrap()) as usize;
+        let end = at + 12 + length;
+        if end > png.len() {
+            return png.to_vec();
+        }
+        let kind = &png[at + 4..at + 8];
+        if !PNG_METADATA.iter().any(|metadata| kind == *metadata) {
+            scrubbed.extend_from_slice(&png[at..end]);
+        }
+        at = end;
+    }
+    scrubbed
+}
+
+/// Drops the `<metadata>` elements and comments of an SVG.
+pub fn scrub_svg(svg: &str) -> String {
+    let metadata = regex::Regex::new(r"(?s)<metadata\b.*?</metadata>\s*|<!--.*?-->\s*").unwrap();
+    metadata.replace_all(svg, "").into_owned()
+}
+
+/// The SHA-256 of every output under `dir`, by path relative to it with `/`
+/// separators. TeX sources and logs, failure reports, the batch status and
+/// lock files and the checksums file itself aren't outputs.
+pub(crate) fn output_checksums(dir: &Path) -> io::Result<BTreeMap<String, String>> {
+    let mut checksums = BTreeMap::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(current) = dirs.pop() {
+        for entry in fs::read_dir(&current)? {
+            let path = entry?.path();
+            let relative = path.strip_prefix(dir).unwrap_or(&path);
+            let relative = relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            if path.is_dir() {
+                if relative != "failed" {
+                    dirs.push(path);
+                }
+                continue;
+            }
+            let intermediate = path
+                .extension()
+                .is_some_and(|extension| extension == "tex" || extension == "log");
+            let bookkeeping = [CHECKSUMS_FILE, STATUS_FILE_NAME, LOCK_FILE_NAME];
+            if intermediate || bookkeeping.contains(&relative.as_str()) {
+                continue;
+            }
+            checksums.insert(relative, sha256_hex(&fs::read(&path)?));
+        }
+    }
+    Ok(checksums)
+}
+
+/// Writes the `output_checksums` of `dir` to `<dir>/SHA256SUMS`, checkable
+/// with `sha256sum -c`. Returns its path.
+pub fn write_checksums(dir: &Path) -> io::Result<PathBuf> {
+    let mut text = String::new();
+    for (path, checksum) in output_checksums(dir)? {
+        writeln!(text, "{}  {}", checksum, path).ok();
+    }
+    let path = dir.join(CHECKSUMS_FILE);
+    fs::write(&path, text)?;
+    Ok(path)
+}
+
+/// Renders `equations` a second time, into a scratch directory and past the
+/// cache, and compares each output with its namesake in `options.output_dir`.
+/// `[[hook]]` commands aren't run again, so outputs they change come out
+/// different.
+/// Returns the outputs that came out different, or under a name the output
+/// directory doesn't have. The manifest is left out, as it keeps the entries
+/// of earlier batches.
+pub fn verify_reproducible(
+    equations: &[Equation],
+    options: &RenderOptions,
+) -> io::Result<Vec<String>> {
+    verify_reproducible_with(equations, options, options.backend())
+}
+
+pub fn verify_reproducible_with(
+    equations: &[Equation],
+    options: &RenderOptions,
+    backend: &dyn RenderBackend,
+) -> io::Result<Vec<String>> {
+    let scratch = tempfile::tempdir()?;
+    let again = RenderOptions {
+        output_dir: scratch.path().to_path_buf(),
+        cache_dir: None,
+        resume: false,
+        status_file: false,           // The scratch render isn't a batch to follow
+        hooks: BatchHooks::default(), // Once is enough for notifications
+        pipeline: options.pipeline.without_hooks(), // And for uploads
+        ..options.clone()
+    };
+    render_equations_with(equations, &again, backend)?;
+    let existing = output_checksums(&options.output_dir)?;
+    Ok(output_checksums(&again.output_dir)?
+        .into_iter()
+        .filter(|(path, checksum)| path != MANIFEST_NAME && existing.get(path) != Some(checksum))
+        .map(|(path, _)| path)
+        .collect())
+}
@@ -0,0 +1,74 @@
+use crate::Equation;
+use regex::Regex;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Sends matching equations into the subdirectory `dir` of the output
+/// directory: those tagged `tag` (`%%tags=appendix%%`) and whose name
+/// matches `name`. A route without either takes every equation.
+#[derive(Debug, Clone)]
+pub struct OutputRoute {
+    pub tag: Option<String>,
+    pub name: Option<Regex>,
+    pub dir: PathBuf, // Relative to the output directory
+}
+
+impl OutputRoute {
+    /// Fails for a `dir` that would leave the output directory.
+    pub fn new(tag: Option<&str>, name: Option<Regex>, dir: &Path) -> io::Result<Self> {
+        let inside = dir
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        if !inside {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Route directory {} must be relative to the output directory",
+                    dir.display()
+                ),
+            ));
+        }
+        Ok(OutputRoute {
+            tag: tag.map(str::to_string),
+            name,
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    pub fn matches(&self, equation: &Equation) -> bool {
+        self.tag
+            .as_ref()
+            .is_none_or(|tag| equation.tags.contains(tag))
+            && self
+                .name
+                .as_ref()
+                .is_none_or(|name| name.is_match(&equation.name))
+    }
+}
+
+/// The subdirectory of the first of `routes` that `equation` matches.
+pub fn route_of<'a>(routes: &'a [OutputRoute], equation: &Equation) -> Option<&'a Path> {
+    routes
+        .iter()
+        .find(|route| route.matches(equation))
+        .map(|route| route.dir.as_path())
+}
+
+/// `file` in the routed subdirectory `dir`, as the manifest records it.
+pub(crate) fn routed_file(dir: Option<&Path>, file: String) -> String {
+    match dir {
+        // Forward slashes on every platform, so manifests travel
+        Some(dir) => {
+            let mut parts: Vec<String> = dir
+                .components()
+                .filter_map(|component| match component {
+                    Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+                    _ => None,
+                })
+                .collect();
+            parts.push(file);
+            parts.join("/")
+        }
+        None => file,
+    }
+}
@@ -0,0 +1,263 @@
+use crate::ScanConfig;
+use globset::GlobBuilder;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::WalkBuilder;
+use notify::event::{EventKind, ModifyKind};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+const IGNORE_FILE_NAME: &str = ".simptuiignore";
+
+/// Collects the files below all `roots`, honoring `.gitignore` and
+/// `.simptuiignore`. Stops after `max_files` so huge trees can't stall startup;
+/// callers can compare the result length against the cap to detect that.
+pub fn scan_files(roots: &[PathBuf], config: &ScanConfig) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    walk_files(roots, config, |path| {
+        files.push(path);
+        true
+    });
+    files
+}
+
+/// A file named on the command line, directly or through a glob.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputFile {
+    pub path: PathBuf,
+    pub relative: PathBuf, // Below the glob's directory, or just the file name
+}
+
+/// Expands the glob patterns among `patterns` (`notes/**/*.md`) by scanning
+/// the directory before the first wildcard like `scan_files` does, so
+/// ignored files stay out. The `max_files` and `max_depth` caps, which keep
+/// the interface's startup quick, don't apply: every match is rendered.
+/// Other entries are taken as they are. A glob that matches nothing is an
+/// error; files matched twice are kept once.
+pub fn expand_inputs(patterns: &[PathBuf], config: &ScanConfig) -> io::Result<Vec<InputFile>> {
+    let uncapped = ScanConfig {
+        max_depth: usize::MAX,
+        max_files: usize::MAX,
+        ..config.clone()
+    };
+    let mut inputs: Vec<InputFile> = Vec::new();
+    for pattern in patterns {
+        let (base, glob) = split_glob(pattern);
+        let Some(glob) = glob else {
+            inputs.push(InputFile {
+                relative: pattern.file_name().map_or_else(PathBuf::new, PathBuf::from),
+                path: pattern.clone(),
+            });
+            continue;
+        };
+        let matcher = GlobBuilder::new(&glob)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?
+            .compile_matcher();
+        let root = if base.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            &base
+        };
+        let mut matches: Vec<InputFile> = scan_files(&[root.to_path_buf()], &uncapped)
+            .into_iter()
+            .filter_map(|path| {
+                let relative = path.strip_prefix(root).ok()?.to_path_buf();
+                matcher.is_match(&relative).then(|| InputFile {
+                    path: base.join(&relative),
+                    relative,
+                })
+            })
+            .collect();
+        if matches.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No files match {}", pattern.display()),
+            ));
+        }
+        matches.sort_by(|a, b| a.path.cmp(&b.path));
+        inputs.extend(matches);
+    }
+
+    let mut seen = HashSet::new();
+    inputs.retain(|input| seen.insert(input.path.clone()));
+    Ok(inputs)
+}
+
+// `notes/**/*.md` -> (`notes`, `**/*.md`); no glob when nothing is wild
+fn split_glob(pattern: &Path) -> (PathBuf, Option<String>) {
+    let is_wild = |component: &Component| {
+        component
+            .as_os_str()
+            .to_string_lossy()
+            .contains(['*', '?', '[', '{'])
+    };
+    let mut components = pattern.components();
+    let mut base = PathBuf::new();
+    while let Some(component) = components.clone().next() {
+        if is_wild(&component) {
+            let rest = components.as_path().to_string_lossy().into_owned();
+            return (base, Some(rest));
+        }
+        base.push(component);
+        components.next();
+    }
+    (base, None)
+}
+
+/// Walks the roots like `scan_files` but hands every file to `on_file` as soon
+/// as it is found. Returning `false` from the callback stops the walk.
+/// Returns `true` when the walk was cut short by `max_files`.
+fn walk_files(
+    roots: &[PathBuf],
+    config: &ScanConfig,
+    mut on_file: impl FnMut(PathBuf) -> bool,
+) -> bool {
+    let Some((first, rest)) = roots.split_first() else {
+        return false;
+    };
+
+    let mut builder = WalkBuilder::new(first);
+    for root in rest {
+        builder.add(root);
+    }
+    builder
+        .max_depth(Some(config.max_depth))
+        .git_ignore(config.respect_gitignore)
+        .git_global(config.respect_gitignore)
+        .git_exclude(config.respect_gitignore)
+        .require_git(false)
+        .add_custom_ignore_filename(IGNORE_FILE_NAME);
+
+    let mut count = 0;
+    for entry in builder.build().filter_map(|e| e.ok()) {
+        if count >= config.max_files {
+            return true;
+        }
+        if entry.file_type().is_some_and(|t| t.is_file()) {
+            count += 1;
+            if !on_file(entry.into_path()) {
+                break;
+            }
+        }
+    }
+    false
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexEvent {
+    Found(PathBuf),                   // Discovered by the initial scan
+    ScanFinished { truncated: bool }, // Initial scan done (or capped)
+    Created(PathBuf),                 // Appeared after the initial scan
+    Removed(PathBuf),                 // Deleted or renamed away
+}
+
+/// Handle to a background scan of the roots. Results arrive through
+/// `try_recv` or `recv`; with `scan.watch` enabled the index keeps receiving
+/// `Created`/`Removed` events until the indexer is dropped.
+pub struct FileIndexer {
+    receiver: Receiver<IndexEvent>,
+    _watcher: Option<RecommendedWatcher>,
+}
+
+impl FileIndexer {
+    pub fn spawn(roots: Vec<PathBuf>, config: ScanConfig) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        // Absolute roots keep scan results comparable with watcher paths
+        let roots: Vec<PathBuf> = roots
+            .iter()
+            .map(|root| root.canonicalize().unwrap_or_else(|_| root.clone()))
+            .collect();
+
+        let watcher = if config.watch {
+            watch_roots(&roots, &config, sender.clone())
+        } else {
+            None
+        };
+
+        thread::spawn(move || {
+            let truncated = walk_files(&roots, &config, |path| {
+                sender.send(IndexEvent::Found(path)).is_ok()
+            });
+            sender.send(IndexEvent::ScanFinished { truncated }).ok();
+        });
+
+        FileIndexer {
+            receiver,
+            _watcher: watcher,
+        }
+    }
+
+    pub fn try_recv(&self) -> Option<IndexEvent> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Waits for the next event; `None` once the scan is done and the roots
+    /// aren't watched.
+    pub fn recv(&self) -> Option<IndexEvent> {
+        self.receiver.recv().ok()
+    }
+}
+
+fn ignore_matcher(root: &Path, config: &ScanConfig) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(root);
+    if config.respect_gitignore {
+        builder.add(root.join(".gitignore"));
+    }
+    builder.add(root.join(IGNORE_FILE_NAME));
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
+fn is_ignored(path: &Path, matchers: &[(PathBuf, Gitignore)]) -> bool {
+    matchers.iter().any(|(root, matcher)| {
+        let Ok(relative) = path.strip_prefix(root) else {
+            return false;
+        };
+        let hidden = relative
+            .components()
+            .any(|c| c.as_os_str().to_str().is_some_and(|s| s.starts_with('.')));
+        hidden || matcher.matched_path_or_any_parents(path, false).is_ignore()
+    })
+}
+
+fn watch_roots(
+    roots: &[PathBuf],
+    config: &ScanConfig,
+    sender: Sender<IndexEvent>,
+) -> Option<RecommendedWatcher> {
+    let matchers: Vec<(PathBuf, Gitignore)> = roots
+        .iter()
+        .map(|root| (root.clone(), ignore_matcher(root, config)))
+        .collect();
+
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        let Ok(event) = res else {
+            return;
+        };
+        for path in event.paths {
+            if is_ignored(&path, &matchers) {
+                continue;
+            }
+            let index_event = match event.kind {
+                EventKind::Create(_) if path.is_file() => IndexEvent::Created(path),
+                EventKind::Remove(_) => IndexEvent::Removed(path),
+                EventKind::Modify(ModifyKind::Name(_)) if path.is_file() => {
+                    IndexEvent::Created(path)
+                }
+                EventKind::Modify(ModifyKind::Name(_)) => IndexEvent::Removed(path),
+                _ => continue,
+            };
+            sender.send(index_event).ok();
+        }
+    })
+    .ok()?;
+
+    for root in roots {
+        watcher.watch(root, RecursiveMode::Recursive).ok()?;
+    }
+    Some(watcher)
+}
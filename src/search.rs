@@ -0,0 +1,53 @@
+use crate::{Equation, ParserRegistry};
+use regex::Regex;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub path: PathBuf,
+    pub equation: Equation,
+}
+
+/// Builds the matcher for a search query: a literal LaTeX snippet by default,
+/// or a regular expression when `is_regex` is set.
+pub fn search_pattern(query: &str, is_regex: bool, ignore_case: bool) -> Result<Regex, String> {
+    let pattern = if is_regex {
+        query.to_string()
+    } else {
+        regex::escape(query)
+    };
+    let pattern = if ignore_case {
+        format!("(?i){}", pattern)
+    } else {
+        pattern
+    };
+    Regex::new(&pattern).map_err(|e| e.to_string())
+}
+
+/// Parses every supported file and returns the equations whose body or name
+/// matches. Unreadable files are skipped.
+pub fn search_equations(
+    files: &[PathBuf],
+    parsers: &ParserRegistry,
+    pattern: &Regex,
+) -> Vec<SearchHit> {
+    let mut hits = Vec::new();
+    for path in files {
+        // Only by extension; sniffing would read every file under the roots
+        if parsers.for_path(path).is_none() {
+            continue;
+        }
+        let Ok(equations) = parsers.load(path) else {
+            continue;
+        };
+        for equation in equations {
+            if pattern.is_match(&equation.body) || pattern.is_match(&equation.name) {
+                hits.push(SearchHit {
+                    path: path.clone(),
+                    equation,
+                });
+            }
+        }
+    }
+    hits
+}
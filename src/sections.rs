@@ -0,0 +1,89 @@
+use crate::{code_fences, Equation};
+
+/// A `#` heading of a markdown note.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    pub level: usize, // 1 for `#`, up to 6 for `######`
+    pub title: String,
+    pub line: usize, // 1-based
+}
+
+/// The ATX headings of `source` in order, leaving out `#` lines in fenced
+/// code blocks.
+pub fn headings(source: &str) -> Vec<Heading> {
+    let fences = code_fences(source);
+    let mut headings = Vec::new();
+    let mut offset = 0;
+    for (index, line) in source.split_inclusive('\n').enumerate() {
+        let start = offset;
+        offset += line.len();
+        if fences.iter().any(|fence| fence.contains(&start)) {
+            continue;
+        }
+        let trimmed = line.trim_end();
+        let level = trimmed.chars().take_while(|&c| c == '#').count();
+        let rest = &trimmed[level..];
+        if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+            continue;
+        }
+        // A closing run of `#`s isn't part of the title
+        let title = rest.trim().trim_end_matches('#').trim_end();
+        headings.push(Heading {
+            level,
+            title: title.to_string(),
+            line: index + 1,
+        });
+    }
+    headings
+}
+
+/// The headings of `source`, leaving out `#` lines inside equations, and for
+/// each of `equations` the index of the closest heading above it: `None`
+/// before the first heading or without a source position.
+pub fn equation_sections(
+    source: &str,
+    equations: &[Equation],
+) -> (Vec<Heading>, Vec<Option<usize>>) {
+    let spans: Vec<_> = equations
+        .iter()
+        .filter_map(|equation| equation.span)
+        .collect();
+    let headings: Vec<Heading> = headings(source)
+        .into_iter()
+        .filter(|heading| {
+            !spans
+                .iter()
+                .any(|span| (span.start_line..=span.end_line).contains(&heading.line))
+        })
+        .collect();
+    let sections = equations
+        .iter()
+        .map(|equation| {
+            let start = equation.span?.start_line;
+            headings.iter().rposition(|heading| heading.line < start)
+        })
+        .collect();
+    (headings, sections)
+}
+
+/// For each of `equations`, whether it sits under a heading of `source`
+/// titled `title` (ignoring case), subsections included.
+pub fn in_section(source: &str, equations: &[Equation], title: &str) -> Vec<bool> {
+    let (headings, sections) = equation_sections(source, equations);
+    let title = title.trim().to_lowercase();
+    let within = |section: usize| {
+        // Up through the enclosing headings
+        let mut level = usize::MAX;
+        headings[..=section].iter().rev().any(|heading| {
+            if heading.level >= level {
+                return false;
+            }
+            level = heading.level;
+            heading.title.to_lowercase() == title
+        })
+    };
+    sections
+        .into_iter()
+        .map(|section| section.is_some_and(within))
+        .collect()
+}
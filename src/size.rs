@@ -0,0 +1,83 @@
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::str::FromStr;
+
+// LaTeX's size switches, smallest first
+const SIZE_COMMANDS: [&str; 10] = [
+    "tiny",
+    "scriptsize",
+    "footnotesize",
+    "small",
+    "normalsize",
+    "large",
+    "Large",
+    "LARGE",
+    "huge",
+    "Huge",
+];
+
+/// Size equations are typeset at: one of LaTeX's size switches (`Large`,
+/// case-sensitive as in LaTeX) or an explicit size like `14pt`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum FontSize {
+    Command(&'static str), // One of `SIZE_COMMANDS`
+    Points(f32),
+}
+
+impl Default for FontSize {
+    fn default() -> Self {
+        FontSize::Command("Large")
+    }
+}
+
+impl FontSize {
+    /// The LaTeX that switches to this size, for use inside a group.
+    pub fn latex(self) -> String {
+        match self {
+            FontSize::Command(name) => format!(r"\{}", name),
+            // Usual 1.2 line spread; only matters for multi-line bodies
+            FontSize::Points(points) => format!(
+                r"\fontsize{{{}pt}}{{{:.2}pt}}\selectfont",
+                points,
+                points * 1.2
+            ),
+        }
+    }
+}
+
+impl fmt::Display for FontSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FontSize::Command(name) => f.write_str(name),
+            FontSize::Points(points) => write!(f, "{}pt", points),
+        }
+    }
+}
+
+impl FromStr for FontSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().trim_start_matches('\\');
+        if let Some(command) = SIZE_COMMANDS.into_iter().find(|command| *command == name) {
+            return Ok(FontSize::Command(command));
+        }
+        match name.strip_suffix("pt").map(str::parse::<f32>) {
+            Some(Ok(points)) if points > 0.0 && points.is_finite() => Ok(FontSize::Points(points)),
+            _ => Err(format!(
+                "unknown size '{}': expected {} or a size like 14pt",
+                s,
+                SIZE_COMMANDS.join(", ")
+            )),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FontSize {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tracing::warn;
+
+/// A reusable piece of LaTeX to insert while writing an equation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    pub name: String,
+    pub body: String,
+}
+
+const BUILT_IN: [(&str, &str); 12] = [
+    ("fraction", r"\frac{a}{b}"),
+    ("integral", r"\int_{a}^{b} f(x) \, dx"),
+    ("gaussian-integral", r"\int_{-\infty}^{\infty} e^{-x^2} \, dx = \sqrt{\pi}"),
+    ("sum", r"\sum_{i=1}^{n} a_i"),
+    ("limit", r"\lim_{x \to \infty} f(x)"),
+    ("derivative", r"\frac{d f}{d x}"),
+    ("partial", r"\frac{\partial f}{\partial x}"),
+    ("matrix-2x2", "\\begin{pmatrix}\na & b \\\\\nc & d\n\\end{pmatrix}"),
+    (
+        "matrix-3x3",
+        "\\begin{pmatrix}\na_{11} & a_{12} & a_{13} \\\\\na_{21} & a_{22} & a_{23} \\\\\na_{31} & a_{32} & a_{33}\n\\end{pmatrix}",
+    ),
+    ("cases", "f(x) = \\begin{cases}\n1 & x \\geq 0 \\\\\n0 & x < 0\n\\end{cases}"),
+    ("argmin", r"\operatorname*{arg\,min}_{x} f(x)"),
+    ("norm", r"\left\lVert x \right\rVert"),
+];
+
+/// The snippet library: the built-in snippets, then the configured ones
+/// (`[snippet]` in the config, `name = "body"`), then those of every `*.toml`
+/// file in `dir`, one `name = "body"` per snippet. A snippet replaces an
+/// earlier one of the same name. Files that can't be read are skipped with a
+/// warning.
+pub fn load_snippets(configured: &BTreeMap<String, String>, dir: &Path) -> Vec<Snippet> {
+    let mut snippets: Vec<Snippet> = BUILT_IN
+        .iter()
+        .map(|(name, body)| Snippet {
+            name: name.to_string(),
+            body: body.to_string(),
+        })
+        .collect();
+    merge_snippets(&mut snippets, configured);
+
+    let mut files: Vec<_> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "toml")
+        })
+        .collect();
+    files.sort();
+    for file in files {
+        let parsed = fs::read_to_string(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|text| {
+                toml::from_str::<BTreeMap<String, String>>(&text).map_err(|e| e.to_string())
+            });
+        match parsed {
+            Ok(file_snippets) => merge_snippets(&mut snippets, &file_snippets),
+            Err(e) => warn!("Skipping snippet file {}: {}", file.display(), e),
+        }
+    }
+    snippets
+}
+
+fn merge_snippets(snippets: &mut Vec<Snippet>, more: &BTreeMap<String, String>) {
+    for (name, body) in more {
+        let body = body.trim().to_string();
+        match snippets.iter_mut().find(|snippet| snippet.name == *name) {
+            Some(snippet) => snippet.body = body,
+            None => snippets.push(Snippet {
+                name: name.clone(),
+                body,
+            }),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Reads a source file as text whatever tool wrote it: see `decode_source`.
+pub fn load_source(path: &Path) -> io::Result<String> {
+    Ok(decode_source(&fs::read(path)?))
+}
+
+/// At most the first `limit` bytes of a source file, decoded like
+/// `load_source`, or None for a binary file: one with a NUL byte and no
+/// UTF-16 or UTF-32 BOM to explain it.
+pub(crate) fn load_source_start(path: &Path, limit: u64) -> io::Result<Option<String>> {
+    let mut bytes = Vec::new();
+    File::open(path)?.take(limit).read_to_end(&mut bytes)?;
+    if Encoding::for_bom(&bytes).is_none() && bytes.contains(&0) {
+        return Ok(None);
+    }
+    // A character cut in two at the limit doesn't make it Windows-1252
+    if let Err(e) = std::str::from_utf8(&bytes) {
+        if e.error_len().is_none() {
+            bytes.truncate(e.valid_up_to());
+        }
+    }
+    Ok(Some(decode_source(&bytes)))
+}
+
+/// Decodes `bytes` by their BOM, as UTF-8, or failing that as Windows-1252
+/// (which covers Latin-1), then strips the BOM and turns CRLF and lone CR
+/// line endings into LF so the parsers only ever see `\n`.
+pub fn decode_source(bytes: &[u8]) -> String {
+    let (encoding, bom_len) = Encoding::for_bom(bytes).unwrap_or((UTF_8, 0));
+    let bytes = &bytes[bom_len..];
+    let text = match encoding.decode_without_bom_handling_and_without_replacement(bytes) {
+        Some(text) => text,
+        None if encoding == UTF_8 => WINDOWS_1252.decode_without_bom_handling(bytes).0,
+        None => encoding.decode_without_bom_handling(bytes).0,
+    };
+    if text.contains('\r') {
+        text.replace("\r\n", "\n").replace('\r', "\n")
+    } else {
+        text.into_owned()
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{Equation, RenderOptions, SourceSpan};
+use regex::{Captures, Regex};
+
+/// Where a rendered equation came from, written into its `.tex` as a comment
+/// and into its SVG as a `<desc>`, so a stray output can be traced back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceMap {
+    pub file: Option<String>, // The source file as it was given
+    pub span: Option<SourceSpan>,
+    pub name: String, // Of the equation in the source, whatever the output is called
+    pub version: String, // Of the simptui that rendered it
+}
+
+impl SourceMap {
+    pub fn of(equation: &Equation, options: &RenderOptions) -> Self {
+        SourceMap {
+            file: options
+                .source_file
+                .as_ref()
+                .map(|file| file.display().to_string().replace(['\r', '\n'], " ")),
+            span: equation.span,
+            name: equation
+                .origin
+                .clone()
+                .unwrap_or_else(|| equation.name.clone()),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// `sum from notes.md:3-5 (simptui 0.1.0)`
+    pub fn describe(&self) -> String {
+        let Some(file) = &self.file else {
+            return format!("{} (simptui {})", self.name, self.version);
+        };
+        let place = match self.lines() {
+            Some(lines) => format!("{}:{}", file, lines),
+            None => file.clone(),
+        };
+        format!("{} from {} (simptui {})", self.name, place, self.version)
+    }
+
+    /// The map as the first line of a `.tex` file.
+    pub fn tex_comment(&self) -> String {
+        format!("% Source: {}\n", self.describe())
+    }
+
+    // `3-5`, or `3` for a one-line equation
+    fn lines(&self) -> Option<String> {
+        self.span
+            .map(|span| match span.start_line == span.end_line {
+                true => span.start_line.to_string(),
+                false => format!("{}-{}", span.start_line, span.end_line),
+            })
+    }
+}
+
+/// `svg` with `map` as a `<desc>` first thing in the root element, in place
+/// of any map it had.
+pub fn add_svg_source_map(svg: &str, map: &SourceMap) -> String {
+    let mut attributes = format!(r#" data-simptui="{}""#, xml_escape(&map.version));
+    if let Some(file) = &map.file {
+        attributes.push_str(&format!(r#" data-source="{}""#, xml_escape(file)));
+    }
+    if let Some(lines) = map.lines() {
+        attributes.push_str(&format!(r#" data-lines="{}""#, lines));
+    }
+    attributes.push_str(&format!(r#" data-name="{}""#, xml_escape(&map.name)));
+    let desc = format!("<desc{}>{}</desc>", attributes, xml_escape(&map.describe()));
+    let svg = strip_svg_source_map(svg);
+    let root = Regex::new(r"<svg\b[^>]*>").unwrap();
+    root.replace(&svg, |cap: &Captures| match cap[0].strip_suffix("/>") {
+        Some(open) => format!("{}>{}</svg>", open, desc),
+        None => format!("{}{}", &cap[0], desc),
+    })
+    .to_string()
+}
+
+/// `svg` without the `<desc>` `add_svg_source_map` put in.
+pub fn strip_svg_source_map(svg: &str) -> String {
+    source_desc().replace(svg, "").to_string()
+}
+
+/// The map `add_svg_source_map` left in `svg`, if any.
+pub fn read_svg_source_map(svg: &str) -> Option<SourceMap> {
+    let desc = source_desc().find(svg)?.as_str();
+    let attribute = |name: &str| {
+        let re = Regex::new(&format!(r#"\b{}="([^"]*)""#, name)).unwrap();
+        re.captures(desc).map(|cap| xml_unescape(&cap[1]))
+    };
+    let span = attribute("data-lines").and_then(|lines| {
+        let (start, end) = lines.split_once('-').unwrap_or((&lines, &lines));
+        Some(SourceSpan {
+            start_line: start.parse().ok()?,
+            end_line: end.parse().ok()?,
+        })
+    });
+    Some(SourceMap {
+        file: attribute("data-source"),
+        span,
+        name: attribute("data-name")?,
+        version: attribute("data-simptui")?,
+    })
+}
+
+fn source_desc() -> Regex {
+    Regex::new(r"(?s)<desc data-simptui=.*?</desc>").unwrap()
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn xml_unescape(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&gt;", ">")
+        .replace("&lt;", "<")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,135 @@
+use crate::Equation;
+use regex::Regex;
+
+// Multi-line environments whose rows can stand alone once the `&` are gone
+const LINE_ENVIRONMENTS: [&str; 10] = [
+    "align", "align*", "aligned", "alignat", "alignat*", "gather", "gather*", "gathered",
+    "multline", "eqnarray",
+];
+
+/// Splits every multi-line equation into one equation per line, named
+/// `<name>_l1`, `<name>_l2`, ... Single-line equations are kept as they are.
+pub(crate) fn split_equations(equations: &[Equation]) -> Vec<Equation> {
+    equations.iter().flat_map(split_lines).collect()
+}
+
+/// The lines of `equation`, split on `\\` outside of nested environments and
+/// braces (so `cases` and `matrix` stay whole). Alignment `&`, labels and
+/// `\nonumber` are dropped from each line.
+pub fn split_lines(equation: &Equation) -> Vec<Equation> {
+    let body = strip_environment(equation.body.trim());
+    let lines: Vec<String> = top_level_lines(body)
+        .into_iter()
+        .map(clean_line)
+        .filter(|line| !line.is_empty())
+        .collect();
+    if lines.len() < 2 {
+        return vec![equation.clone()];
+    }
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| Equation {
+            name: format!("{}_l{}", equation.name, i + 1),
+            body: line,
+            environment: None, // A single row needs no alignment
+            origin: Some(equation.name.clone()),
+            ..equation.clone()
+        })
+        .collect()
+}
+
+// `\begin{align} ... \end{align}` around the whole body -> its content
+fn strip_environment(body: &str) -> &str {
+    for env in LINE_ENVIRONMENTS {
+        let begin = format!(r"\begin{{{}}}", env);
+        let end = format!(r"\end{{{}}}", env);
+        if let Some(inner) = body
+            .strip_prefix(begin.as_str())
+            .and_then(|rest| rest.strip_suffix(end.as_str()))
+        {
+            // alignat takes the number of columns as an argument
+            let inner = match inner.trim_start().strip_prefix('{') {
+                Some(rest) if env.starts_with("alignat") => {
+                    rest.split_once('}').map_or(inner, |(_, rest)| rest)
+                }
+                _ => inner,
+            };
+            return inner;
+        }
+    }
+    body
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Token {
+    LineBreak, // `\\`
+    Column,    // `&`
+}
+
+// Byte offsets of `\\` and `&` at depth 0, where depth counts braces and
+// \begin/\end pairs
+fn top_level_tokens(body: &str) -> Vec<(usize, Token)> {
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while let Some(c) = body[i..].chars().next() {
+        let rest = &body[i..];
+        let mut step = c.len_utf8();
+        if rest.starts_with(r"\begin") {
+            depth += 1;
+            step = r"\begin".len();
+        } else if rest.starts_with(r"\end") {
+            depth = depth.saturating_sub(1);
+            step = r"\end".len();
+        } else if rest.starts_with(r"\\") {
+            if depth == 0 {
+                tokens.push((i, Token::LineBreak));
+            }
+            step = 2;
+        } else if c == '\\' {
+            // Escaped characters such as `\{` or `\&` are plain text
+            step += rest[1..].chars().next().map_or(0, char::len_utf8);
+        } else if c == '{' {
+            depth += 1;
+        } else if c == '}' {
+            depth = depth.saturating_sub(1);
+        } else if c == '&' && depth == 0 {
+            tokens.push((i, Token::Column));
+        }
+        i += step;
+    }
+    tokens
+}
+
+fn top_level_lines(body: &str) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for (offset, token) in top_level_tokens(body) {
+        if token != Token::LineBreak || offset < start {
+            continue;
+        }
+        lines.push(&body[start..offset]);
+        start = offset + 2;
+        // Optional spacing, `\\[2pt]`
+        let rest = &body[start..];
+        if rest.trim_start().starts_with('[') {
+            if let Some(close) = rest.find(']') {
+                start += close + 1;
+            }
+        }
+    }
+    lines.push(&body[start..]);
+    lines
+}
+
+fn clean_line(line: &str) -> String {
+    let markup = Regex::new(r"\\label\{[^}]*\}|\\nonumber\b|\\notag\b").unwrap();
+    let mut line = markup.replace_all(line, "").into_owned();
+    for (offset, token) in top_level_tokens(&line).into_iter().rev() {
+        if token == Token::Column {
+            line.remove(offset);
+        }
+    }
+    line.trim().to_string()
+}
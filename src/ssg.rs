@@ -0,0 +1,104 @@
+use crate::Equation;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Static site generator the equations are exported for. Each keeps static
+/// files, and the shortcode or include that shows an equation, somewhere
+/// else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SiteFlavor {
+    Hugo,
+    Zola,
+    Jekyll,
+}
+
+impl SiteFlavor {
+    /// Where the rendered files of `dir` go in the site at `site`.
+    pub fn asset_dir(self, site: &Path, dir: &str) -> PathBuf {
+        match self {
+            SiteFlavor::Hugo | SiteFlavor::Zola => site.join("static").join(dir),
+            SiteFlavor::Jekyll => site.join("assets").join(dir),
+        }
+    }
+
+    /// What shows the equation `name` in a content file.
+    pub fn snippet(self, name: &str) -> String {
+        let name = Equation::sanitize_filename(name);
+        match self {
+            SiteFlavor::Hugo => format!("{{{{< math name=\"{}\" >}}}}", name),
+            SiteFlavor::Zola => format!("{{{{ math(name=\"{}\") }}}}", name),
+            SiteFlavor::Jekyll => format!("{{% include math.html name=\"{}\" %}}", name),
+        }
+    }
+
+    /// The template behind `snippet`, relative to the site.
+    pub fn shortcode_file(self) -> &'static str {
+        match self {
+            SiteFlavor::Hugo => "layouts/shortcodes/math.html",
+            SiteFlavor::Zola => "templates/shortcodes/math.html",
+            SiteFlavor::Jekyll => "_includes/math.html",
+        }
+    }
+
+    /// An `<img>` of the SVG in `dir`, resolved against the site's base URL.
+    pub fn shortcode_template(self, dir: &str) -> String {
+        match self {
+            SiteFlavor::Hugo => format!(
+                "<img class=\"math\" src=\"{{{{ printf \"/{}/%s.svg\" (.Get \"name\") | relURL }}}}\" alt=\"{{{{ .Get \"name\" }}}}\">\n",
+                dir
+            ),
+            SiteFlavor::Zola => format!(
+                "<img class=\"math\" src=\"{{{{ get_url(path=\"{}/\" ~ name ~ \".svg\") }}}}\" alt=\"{{{{ name }}}}\">\n",
+                dir
+            ),
+            SiteFlavor::Jekyll => format!(
+                "<img class=\"math\" src=\"{{{{ '/assets/{}/' | append: include.name | append: '.svg' | relative_url }}}}\" alt=\"{{{{ include.name }}}}\">\n",
+                dir
+            ),
+        }
+    }
+
+    /// Writes the shortcode template into `site` unless the file exists, as
+    /// it may have been adapted. Returns the path when it was written.
+    pub fn write_shortcode(self, site: &Path, dir: &str) -> io::Result<Option<PathBuf>> {
+        let path = site.join(self.shortcode_file());
+        if path.exists() {
+            return Ok(None);
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, self.shortcode_template(dir))?;
+        Ok(Some(path))
+    }
+}
+
+impl FromStr for SiteFlavor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "hugo" => Ok(SiteFlavor::Hugo),
+            "zola" => Ok(SiteFlavor::Zola),
+            "jekyll" => Ok(SiteFlavor::Jekyll),
+            _ => Err(format!(
+                "unknown site flavor '{}': expected hugo, zola or jekyll",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SiteFlavor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            SiteFlavor::Hugo => "hugo",
+            SiteFlavor::Zola => "zola",
+            SiteFlavor::Jekyll => "jekyll",
+        })
+    }
+}
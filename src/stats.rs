@@ -0,0 +1,146 @@
+use crate::{normalize_body, Equation};
+use regex::Regex;
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+
+// Commands that only work with a package the default preamble doesn't load
+const PACKAGE_COMMANDS: [(&str, &str); 14] = [
+    ("ce", "mhchem"),
+    ("pu", "mhchem"),
+    ("SI", "siunitx"),
+    ("si", "siunitx"),
+    ("num", "siunitx"),
+    ("qty", "siunitx"),
+    ("unit", "siunitx"),
+    ("cancel", "cancel"),
+    ("bcancel", "cancel"),
+    ("mathscr", "mathrsfs"),
+    ("bm", "bm"),
+    ("ket", "braket"),
+    ("bra", "braket"),
+    ("tikz", "tikz"),
+];
+
+/// An equation and a number measured on it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ranked {
+    pub name: String,
+    pub value: usize,
+}
+
+/// What a document's equations look like, to size up a render before
+/// starting it: how many TeX runs it takes, which equations are heavy and
+/// which packages it pulls in.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EquationStats {
+    pub equations: usize,
+    pub active: usize,
+    pub distinct: usize,                // Active bodies that render differently
+    pub longest: Vec<Ranked>,           // Body length in characters
+    pub most_complex: Vec<Ranked>,      // See `complexity`
+    pub commands: Vec<(String, usize)>, // Uses across all bodies, most first
+    pub environments: Vec<(String, usize)>,
+    pub packages: Vec<(String, Vec<String>)>, // Equation and the packages it needs
+}
+
+impl EquationStats {
+    /// Stats over `equations`, keeping the `top` entries of each ranking.
+    pub fn of(equations: &[Equation], top: usize) -> Self {
+        let command = Regex::new(r"\\([A-Za-z]+)").unwrap();
+        let begin = Regex::new(r"\\begin\{([^}]*)\}").unwrap();
+        let mut commands: HashMap<String, usize> = HashMap::new();
+        let mut environments: HashMap<String, usize> = HashMap::new();
+        let mut distinct = BTreeSet::new();
+        let mut stats = EquationStats {
+            equations: equations.len(),
+            ..EquationStats::default()
+        };
+
+        for eq in equations {
+            if eq.active {
+                stats.active += 1;
+                distinct.insert(normalize_body(&eq.body, false));
+            }
+            let rank = |value| Ranked {
+                name: eq.name.clone(),
+                value,
+            };
+            stats.longest.push(rank(eq.body.chars().count()));
+            stats.most_complex.push(rank(complexity(&eq.body)));
+
+            let mut packages: Vec<String> = eq.packages.clone();
+            for cap in command.captures_iter(&eq.body) {
+                let name = &cap[1];
+                if matches!(name, "begin" | "end") {
+                    continue;
+                }
+                *commands.entry(format!(r"\{}", name)).or_default() += 1;
+                let needed = PACKAGE_COMMANDS
+                    .iter()
+                    .find(|(command, _)| *command == name)
+                    .map(|(_, package)| package.to_string());
+                if let Some(package) = needed.filter(|package| !packages.contains(package)) {
+                    packages.push(package);
+                }
+            }
+            if let Some(environment) = &eq.environment {
+                *environments.entry(environment.clone()).or_default() += 1;
+            }
+            for cap in begin.captures_iter(&eq.body) {
+                *environments.entry(cap[1].to_string()).or_default() += 1;
+            }
+            if !packages.is_empty() {
+                stats.packages.push((eq.name.clone(), packages));
+            }
+        }
+
+        stats.distinct = distinct.len();
+        for ranking in [&mut stats.longest, &mut stats.most_complex] {
+            ranking.sort_by(|a, b| b.value.cmp(&a.value).then_with(|| a.name.cmp(&b.name)));
+            ranking.truncate(top);
+        }
+        stats.commands = most_used(commands, top);
+        stats.environments = most_used(environments, top);
+        stats
+    }
+}
+
+/// Rough cost of typesetting `body`: one point per command, two per level of
+/// brace nesting at the deepest point and three per extra line.
+pub fn complexity(body: &str) -> usize {
+    let commands = body
+        .as_bytes()
+        .windows(2)
+        .filter(|pair| pair[0] == b'\\' && pair[1].is_ascii_alphabetic())
+        .count();
+    let mut depth = 0usize;
+    let mut deepest = 0;
+    for c in body.chars() {
+        match c {
+            '{' => {
+                depth += 1;
+                deepest = deepest.max(depth);
+            }
+            '}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    let lines = body.matches(r"\\").count();
+    commands + 2 * deepest + 3 * lines
+}
+
+/// How long TeX gets to compile `body` when a simple equation gets `base`:
+/// another `base` per 40 points of `complexity` (long bodies count a point
+/// per 80 bytes), up to ten times `base`. A large diagram gets minutes while
+/// a typo in a one-liner still fails fast.
+pub fn compile_timeout(body: &str, base: Duration) -> Duration {
+    let cost = complexity(body) + body.len() / 80;
+    base.mul_f64((1.0 + cost as f64 / 40.0).min(10.0))
+}
+
+fn most_used(counts: HashMap<String, usize>, top: usize) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts.truncate(top);
+    counts
+}
@@ -0,0 +1,235 @@
+use crate::RenderReport;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Name of the status file a batch with `status_file` keeps in its output
+/// directory.
+pub const STATUS_FILE_NAME: &str = ".simptui-status.json";
+
+/// Name of the lock file that keeps two batches out of one output directory.
+pub const LOCK_FILE_NAME: &str = ".simptui.lock";
+
+/// Where a batch stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JobState {
+    Running,
+    Finished,
+    Failed, // Finished with failed equations
+    Interrupted,
+}
+
+/// The outcome of the equation a batch finished last.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LastResult {
+    pub name: String,
+    pub status: String, // `rendered`, `cached`, `resumed` or `failed`
+    pub error: Option<String>,
+}
+
+/// Contents of `STATUS_FILE_NAME`, for build systems that wait on a batch.
+/// Times are seconds since the Unix epoch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct JobStatus {
+    pub pid: u32,               // Of the simptui running the batch
+    pub source: Option<String>, // Stem of the source file, if known
+    pub state: JobState,
+    pub current: Option<String>, // Equation being rendered
+    pub done: usize,             // Equations finished, failed ones included
+    pub total: usize,            // Active equations of the batch
+    pub failed: usize,
+    pub started: u64,
+    pub updated: u64,
+    pub last: Option<LastResult>,
+    pub summary: Option<String>, // `RenderReport::summary` once the batch ends
+}
+
+impl JobStatus {
+    /// Whether the status says running but its process is gone, e.g. after
+    /// a crash.
+    pub fn is_stale(&self) -> bool {
+        self.state == JobState::Running && !process_alive(self.pid)
+    }
+}
+
+/// The status file of `output_dir`, or None if no batch wrote one.
+pub fn read_status(output_dir: &Path) -> io::Result<Option<JobStatus>> {
+    let path = output_dir.join(STATUS_FILE_NAME);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    serde_json::from_str(&content).map(Some).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        )
+    })
+}
+
+/// A batch's hold on its output directory: the render lock plus the status
+/// file it rewrites after every equation. The lock goes when it's dropped;
+/// the status stays for whoever asks later.
+#[derive(Debug)]
+pub(crate) struct BatchStatus {
+    dir: PathBuf,
+    status: JobStatus,
+}
+
+impl BatchStatus {
+    /// Takes the lock on `output_dir` for a batch of `total` equations. Fails
+    /// while another live process holds it; a lock left by a dead one is
+    /// taken over.
+    pub fn start(output_dir: &Path, source: Option<&str>, total: usize) -> io::Result<Self> {
+        fs::create_dir_all(output_dir)?;
+        lock(output_dir)?;
+        let now = now();
+        let mut batch = BatchStatus {
+            dir: output_dir.to_path_buf(),
+            status: JobStatus {
+                pid: std::process::id(),
+                source: source.map(str::to_string),
+                state: JobState::Running,
+                current: None,
+                done: 0,
+                total,
+                failed: 0,
+                started: now,
+                updated: now,
+                last: None,
+                summary: None,
+            },
+        };
+        batch.write();
+        Ok(batch)
+    }
+
+    /// Notes that `name` is being rendered.
+    pub fn begin(&mut self, name: &str) {
+        self.status.current = Some(name.to_string());
+        self.write();
+    }
+
+    /// Notes the outcome of `name`, as `BatchHooks::after_equation` hears it.
+    pub fn finish(&mut self, name: &str, status: &str, error: Option<&str>) {
+        self.status.current = None;
+        self.status.done += 1;
+        self.status.failed += usize::from(status == "failed");
+        self.status.last = Some(LastResult {
+            name: name.to_string(),
+            status: status.to_string(),
+            error: error.map(str::to_string),
+        });
+        self.write();
+    }
+
+    /// Records how the batch ended.
+    pub fn end(&mut self, report: &RenderReport) {
+        self.status.current = None;
+        self.status.state = if report.is_interrupted() {
+            JobState::Interrupted
+        } else if report.failed.is_empty() {
+            JobState::Finished
+        } else {
+            JobState::Failed
+        };
+        self.status.failed = report.failed.len();
+        self.status.summary = Some(report.summary());
+        self.write();
+    }
+
+    // Written whole and renamed into place, so readers never see half of it.
+    // A status that can't be written only warns; the batch goes on
+    fn write(&mut self) {
+        self.status.updated = now();
+        let path = self.dir.join(STATUS_FILE_NAME);
+        let part = self.dir.join(format!("{}.part", STATUS_FILE_NAME));
+        let written = serde_json::to_string_pretty(&self.status)
+            .map_err(io::Error::other)
+            .and_then(|json| fs::write(&part, json + "\n"))
+            .and_then(|_| fs::rename(&part, &path));
+        if let Err(e) = written {
+            warn!("Can't write {}: {}", path.display(), e);
+        }
+    }
+}
+
+impl Drop for BatchStatus {
+    fn drop(&mut self) {
+        // A batch that never ended, say after an error, didn't finish
+        if self.status.state == JobState::Running {
+            self.status.state = JobState::Failed;
+            self.status.current = None;
+            self.write();
+        }
+        fs::remove_file(self.dir.join(LOCK_FILE_NAME)).ok();
+    }
+}
+
+fn lock(output_dir: &Path) -> io::Result<()> {
+    let path = output_dir.join(LOCK_FILE_NAME);
+    // A second try after clearing a stale lock
+    for _ in 0..2 {
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(mut file) => return write!(file, "{}", std::process::id()),
+            Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+            Err(_) => {}
+        }
+        let holder = fs::read_to_string(&path)
+            .ok()
+            .and_then(|pid| pid.trim().parse::<u32>().ok());
+        match holder {
+            Some(pid) if process_alive(pid) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::WouldBlock,
+                    format!(
+                        "{} is being rendered by another simptui (pid {}); remove {} if it isn't",
+                        output_dir.display(),
+                        pid,
+                        path.display()
+                    ),
+                ))
+            }
+            _ => fs::remove_file(&path).or_else(|e| match e.kind() {
+                io::ErrorKind::NotFound => Ok(()),
+                _ => Err(e),
+            })?,
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::WouldBlock,
+        format!("Can't lock {}", output_dir.display()),
+    ))
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // Signal 0 only checks; EPERM means it exists under another user
+    let found = unsafe { libc::kill(pid, 0) } == 0;
+    found || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+// Without a cheap check a lock is taken to be held
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
@@ -0,0 +1,142 @@
+use crate::{mathml_environments, Engine, Equation, Font, OutputFormat, RenderOptions};
+use regex::Regex;
+use tracing::warn;
+
+// What a construct takes beyond plain amsmath
+#[derive(Clone, Copy, PartialEq)]
+enum Needs {
+    UnicodeMath, // unicode-math loaded, i.e. a Unicode engine and an OpenType font
+    Tex,         // A TeX engine rather than the in-process MathML converter
+}
+
+// Commands and environments some backends choke on, by what they need.
+// Environments are those `Equation::math_body` may produce too, as MathML
+// gets them through `mathml_environments`.
+const CONSTRUCTS: [(&str, Needs); 26] = [
+    (r"\symbf", Needs::UnicodeMath),
+    (r"\symit", Needs::UnicodeMath),
+    (r"\symup", Needs::UnicodeMath),
+    (r"\symcal", Needs::UnicodeMath),
+    (r"\symscr", Needs::UnicodeMath),
+    (r"\symbb", Needs::UnicodeMath),
+    (r"\symfrak", Needs::UnicodeMath),
+    (r"\symsfup", Needs::UnicodeMath),
+    (r"\xfrac", Needs::Tex),
+    (r"\sfrac", Needs::Tex),
+    (r"\dfrac", Needs::Tex),
+    (r"\tfrac", Needs::Tex),
+    (r"\cfrac", Needs::Tex),
+    (r"\mathcal", Needs::Tex),
+    (r"\cancel", Needs::Tex),
+    (r"\boxed", Needs::Tex),
+    (r"\substack", Needs::Tex),
+    (r"\stackrel", Needs::Tex),
+    (r"\xrightarrow", Needs::Tex),
+    (r"\xleftarrow", Needs::Tex),
+    (r"\pmod", Needs::Tex),
+    (r"\textcolor", Needs::Tex),
+    (r"\phantom", Needs::Tex),
+    (r"\begin{alignedat}", Needs::Tex),
+    (r"\begin{cases}", Needs::Tex),
+    (r"\begin{array}", Needs::Tex),
+];
+
+/// Something in an equation that the backend it's rendered with can't
+/// typeset, and what to switch to instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unsupported {
+    pub equation: String,
+    pub construct: String, // A command, an environment or a non-ASCII character
+    pub backend: String,   // Engine and font, or MathML
+    pub fallback: String,  // Options that handle it
+}
+
+/// Looks through the active equations for constructs their backend under
+/// `options` lacks. Equations whose engine is still `Auto` are only checked
+/// against MathML, as the engine isn't known yet.
+pub fn unsupported_constructs(equations: &[Equation], options: &RenderOptions) -> Vec<Unsupported> {
+    let patterns: Vec<(Regex, &str, Needs)> = CONSTRUCTS
+        .iter()
+        .map(|&(construct, needs)| {
+            // Whole commands only: `\symbf` but not `\symbfup`
+            let boundary = if construct.ends_with('}') { "" } else { r"\b" };
+            let pattern = format!("{}{}", regex::escape(construct), boundary);
+            (Regex::new(&pattern).unwrap(), construct, needs)
+        })
+        .collect();
+
+    let mut found = Vec::new();
+    for eq in equations.iter().filter(|eq| eq.active) {
+        let engine = eq.engine.unwrap_or(options.engine);
+        let mathml = options.format == OutputFormat::MathML;
+        let unicode_math = mathml || loads_unicode_math(options.font, engine);
+        let known = mathml || engine != Engine::Auto;
+        let backend = match mathml {
+            true => "MathML".to_string(),
+            false => format!("{} with font {}", engine, options.font),
+        };
+        let lacks = |needs| match needs {
+            Needs::UnicodeMath => known && !unicode_math,
+            Needs::Tex => mathml,
+        };
+        let mut report = |construct: String, needs| {
+            found.push(Unsupported {
+                equation: eq.name.clone(),
+                construct,
+                backend: backend.clone(),
+                fallback: fallback(needs, engine, options.font),
+            });
+        };
+
+        let mut body = eq.math_body();
+        if mathml {
+            body = mathml_environments(&body).unwrap_or(body);
+        }
+        for (pattern, construct, needs) in &patterns {
+            if lacks(*needs) && pattern.is_match(&body) {
+                report(construct.to_string(), *needs);
+            }
+        }
+        if lacks(Needs::UnicodeMath) {
+            if let Some(c) = body.chars().find(|c| !c.is_ascii()) {
+                report(format!("'{}'", c), Needs::UnicodeMath);
+            }
+        }
+    }
+    found
+}
+
+/// Logs what `unsupported_constructs` finds as warnings. Called as a file
+/// is loaded, so problems show up before anything is rendered.
+pub fn warn_unsupported(equations: &[Equation], options: &RenderOptions) {
+    for issue in unsupported_constructs(equations, options) {
+        warn!(
+            "{}: {} is not supported by {}; try {}",
+            issue.equation, issue.construct, issue.backend, issue.fallback
+        );
+    }
+}
+
+fn loads_unicode_math(font: Font, engine: Engine) -> bool {
+    font.preamble(engine).contains("unicode-math")
+}
+
+fn fallback(needs: Needs, engine: Engine, font: Font) -> String {
+    match needs {
+        Needs::Tex => "--format svg, png or pdf (rendered with TeX)".to_string(),
+        Needs::UnicodeMath => {
+            let mut options = Vec::new();
+            let engine = match engine.supports_unicode_math() {
+                true => engine,
+                false => {
+                    options.push("--engine xelatex or lualatex");
+                    Engine::Xelatex
+                }
+            };
+            if !loads_unicode_math(font, engine) {
+                options.push("--font latin-modern");
+            }
+            options.join(" and ")
+        }
+    }
+}
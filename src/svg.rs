@@ -0,0 +1,121 @@
+use regex::{Captures, Regex};
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const COORDINATE_PRECISION: usize = 3;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct SvgSavings {
+    pub before: u64,
+    pub after: u64,
+}
+
+impl SvgSavings {
+    pub fn add(&mut self, other: SvgSavings) {
+        self.before += other.before;
+        self.after += other.after;
+    }
+
+    pub fn percent(&self) -> f64 {
+        if self.before == 0 {
+            return 0.0;
+        }
+        100.0 * (self.before - self.after) as f64 / self.before as f64
+    }
+}
+
+fn round_number(number: &str) -> String {
+    let value: f64 = match number.parse() {
+        Ok(value) => value,
+        Err(_) => return number.to_string(),
+    };
+    let rounded = format!("{:.*}", COORDINATE_PRECISION, value);
+    let trimmed = rounded.trim_end_matches('0').trim_end_matches('.');
+    match trimmed {
+        "-0" | "" => "0".to_string(),
+        _ => trimmed.to_string(),
+    }
+}
+
+/// svgo-style cleanup of pdftocairo output: drops comments and metadata,
+/// rounds coordinates and strips whitespace between tags. The drawing itself
+/// is left untouched.
+pub(crate) fn optimize_svg(svg: &str) -> String {
+    let comments = Regex::new(r"(?s)<!--.*?-->").unwrap();
+    let metadata = Regex::new(r"(?s)<metadata\b.*?</metadata>|<metadata\b[^>]*/>").unwrap();
+    let geometry =
+        Regex::new(r#"\b(d|transform|viewBox|x|y|width|height|points)="([^"]*)""#).unwrap();
+    let number = Regex::new(r"-?\d+\.\d+").unwrap();
+    let between_tags = Regex::new(r">\s+<").unwrap();
+
+    let svg = comments.replace_all(svg, "");
+    let svg = metadata.replace_all(&svg, "");
+    let svg = geometry.replace_all(&svg, |cap: &Captures| {
+        let value = number.replace_all(&cap[2], |n: &Captures| round_number(&n[0]));
+        format!(r#"{}="{}""#, &cap[1], value)
+    });
+    let svg = between_tags.replace_all(&svg, "><");
+    svg.trim().to_string()
+}
+
+/// Optimizes an SVG in place and reports its size before and after.
+pub(crate) fn optimize_svg_file(path: &Path) -> io::Result<SvgSavings> {
+    let original = fs::read_to_string(path)?;
+    let optimized = optimize_svg(&original);
+    fs::write(path, &optimized)?;
+    Ok(SvgSavings {
+        before: original.len() as u64,
+        after: optimized.len() as u64,
+    })
+}
+
+/// Adds (or replaces) `vertical-align` on the root `<svg>` element.
+pub(crate) fn set_vertical_align(svg: &str, offset_px: f64) -> String {
+    let root = Regex::new(r"<svg\b[^>]*>").unwrap();
+    let style = Regex::new(r#"\s*style="[^"]*""#).unwrap();
+    root.replace(svg, |cap: &Captures| {
+        let tag = style.replace(&cap[0], "");
+        format!(
+            r#"<svg style="vertical-align: {:.2}px"{}"#,
+            offset_px,
+            &tag["<svg".len()..]
+        )
+    })
+    .to_string()
+}
+
+pub(crate) fn svg_vertical_align(svg: &str) -> Option<f64> {
+    let re = Regex::new(r"<svg\b[^>]*vertical-align:\s*(-?[0-9.]+)px").unwrap();
+    re.captures(svg)?[1].parse().ok()
+}
+
+/// The SVG at `path` drawn at `scale` times its size, on transparent pixels.
+pub(crate) fn rasterize_svg(path: &Path, scale: f32) -> io::Result<Pixmap> {
+    let data = fs::read(path)?;
+    let tree = usvg::Tree::from_data(&data, &usvg::Options::default()).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Can't read {}: {}", path.display(), e),
+        )
+    })?;
+    let size = tree.size();
+    let mut pixmap = Pixmap::new(
+        ((size.width() * scale).ceil() as u32).max(1),
+        ((size.height() * scale).ceil() as u32).max(1),
+    )
+    .ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is too large to rasterize", path.display()),
+        )
+    })?;
+    resvg::render(
+        &tree,
+        Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    Ok(pixmap)
+}
@@ -0,0 +1,77 @@
+use ratatui::buffer::Buffer;
+use ratatui::style::{Color, Modifier};
+use std::env;
+use std::io::{self, IsTerminal};
+
+/// What the terminal can display, guessed from the environment since there
+/// is no reliable way to ask.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    pub color: bool,       // Off with NO_COLOR (https://no-color.org) or TERM=dumb
+    pub unicode: bool,     // Box drawing characters; off for non-UTF-8 locales
+    pub fullscreen: bool,  // Cursor addressing and an alternate screen
+    pub interactive: bool, // Stdout is a terminal, not a pipe or a CI log
+}
+
+impl Capabilities {
+    pub fn detect() -> Self {
+        let term = env::var("TERM").unwrap_or_default();
+        // Windows consoles don't set TERM but handle everything
+        let dumb = term == "dumb" || (term.is_empty() && cfg!(unix));
+        let no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        Capabilities {
+            color: !dumb && !no_color,
+            unicode: cfg!(windows) || utf8_locale(),
+            fullscreen: !dumb,
+            interactive: io::stdout().is_terminal(),
+        }
+    }
+
+    /// Rewrites a drawn frame so it survives on this terminal: colors become
+    /// reverse video where they marked something, box drawing becomes ASCII.
+    pub fn degrade(self, buffer: &mut Buffer) {
+        if self.color && self.unicode {
+            return;
+        }
+        for cell in buffer.content.iter_mut() {
+            if !self.color {
+                if cell.bg != Color::Reset {
+                    cell.modifier.insert(Modifier::REVERSED);
+                }
+                cell.fg = Color::Reset;
+                cell.bg = Color::Reset;
+            }
+            if !self.unicode {
+                if let Some(ascii) = ascii_symbol(cell.symbol()) {
+                    cell.set_symbol(ascii);
+                }
+            }
+        }
+    }
+}
+
+// The first locale variable that is set decides, as in libc
+fn utf8_locale() -> bool {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|v| !v.is_empty()))
+        .is_some_and(|locale| {
+            let locale = locale.to_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+}
+
+fn ascii_symbol(symbol: &str) -> Option<&'static str> {
+    let ascii = match symbol {
+        "─" | "━" | "═" => "-",
+        "│" | "┃" | "║" => "|",
+        "┌" | "┐" | "└" | "┘" | "├" | "┤" | "┬" | "┴" | "┼" | "╭" | "╮" | "╰" | "╯" | "╔" | "╗"
+        | "╚" | "╝" => "+",
+        "▲" => "^",
+        "▸" | "→" => ">",
+        "▼" | "▾" => "v",
+        "…" => "~",
+        _ => return None, // Text is left to the terminal's encoding
+    };
+    Some(ascii)
+}
@@ -0,0 +1,253 @@
+use crate::{
+    apply_option, load_source, unique_names, DuplicateNames, Equation, MathWrap, SourceSpan,
+};
+use regex::Regex;
+use std::fs;
+use std::io;
+use std::path::Path;
+use toml::{Table, Value};
+use tracing::warn;
+
+// The line opening an equation's table
+const TABLE_HEADER: &str = r"^[ \t]*\[\[[ \t]*equation[ \t]*\]\]";
+
+/// Equations declared in TOML, an `[[equation]]` table each:
+///
+/// ```toml
+/// [[equation]]
+/// name = "euler"
+/// body = 'e^{i\pi} + 1 = 0'
+/// active = true        # The default
+/// tags = ["identities"]
+/// color = "#cc3333"
+/// engine = "xelatex"
+/// packages = ["physics"]
+/// size = "large"
+/// wrap = "display"     # Or inline, none; auto (the default) goes by the body
+/// ```
+///
+/// Only `body` is required; a multi-line string holds a multi-line body.
+pub fn parse_toml(content: &str) -> Vec<Equation> {
+    let mut equations = toml_equations(content);
+    unique_names(&mut equations, DuplicateNames::Counter);
+    equations
+}
+
+// The tables with their names as written, repeats and all
+pub(crate) fn toml_equations(content: &str) -> Vec<Equation> {
+    let document = match content.parse::<Table>() {
+        Ok(document) => document,
+        Err(e) => {
+            warn!("Not valid TOML: {}", e);
+            return Vec::new();
+        }
+    };
+    let Some(tables) = document.get("equation").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    // Inline tables have no header line to point at
+    let lines: Vec<&str> = content.lines().collect();
+    let headers = table_headers(&lines);
+    let spans = (headers.len() == tables.len()).then(|| table_spans(&lines, &headers));
+
+    let mut equations = Vec::new();
+    for (i, table) in tables.iter().enumerate() {
+        let Some(mut equation) = table.as_table().and_then(table_equation) else {
+            continue;
+        };
+        equation.span = spans.as_ref().map(|spans| spans[i]);
+        equations.push(equation);
+    }
+    equations
+}
+
+fn table_equation(table: &Table) -> Option<Equation> {
+    let name = match table.get("name").and_then(Value::as_str) {
+        Some(name) if !name.is_empty() => name,
+        _ => "default_equation",
+    };
+    let Some(body) = table.get("body").and_then(Value::as_str) else {
+        warn!("{}: no body", name);
+        return None;
+    };
+    let active = match table.get("active") {
+        None => true,
+        Some(active) => active.as_bool().unwrap_or_else(|| {
+            warn!("{}: active is {}, expected true or false", name, active);
+            true
+        }),
+    };
+    let mut equation = Equation::new(active, name, body.trim());
+    for (key, value) in table {
+        match (key.as_str(), value) {
+            ("name" | "body" | "active", _) => {}
+            ("color" | "engine" | "size" | "wrap", Value::String(value)) => {
+                apply_option(&mut equation, key, value)
+            }
+            ("packages" | "tags", Value::Array(items)) => {
+                let items = items.iter().filter_map(Value::as_str).map(str::to_string);
+                match key.as_str() {
+                    "packages" => equation.packages.extend(items),
+                    _ => equation.tags.extend(items),
+                }
+            }
+            _ => warn!(
+                "{}: unknown or mistyped key '{}', expected name, body, active, tags, color, \
+                 engine, packages, size or wrap",
+                equation.name, key
+            ),
+        }
+    }
+    Some(equation)
+}
+
+// Indices of the `[[equation]]` lines
+fn table_headers(lines: &[&str]) -> Vec<usize> {
+    let header = Regex::new(TABLE_HEADER).unwrap();
+    (0..lines.len())
+        .filter(|&i| header.is_match(lines[i]))
+        .collect()
+}
+
+// From each header to the last line before the next with more than a
+// comment on it
+fn table_spans(lines: &[&str], headers: &[usize]) -> Vec<SourceSpan> {
+    headers
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let next = headers.get(i + 1).copied().unwrap_or(lines.len());
+            let end = (start..next)
+                .rev()
+                .find(|&line| {
+                    let line = lines[line].trim();
+                    !line.is_empty() && !line.starts_with('#')
+                })
+                .unwrap_or(start);
+            SourceSpan {
+                start_line: start + 1,
+                end_line: end + 1,
+            }
+        })
+        .collect()
+}
+
+/// `equations` as `[[equation]]` tables, with each body as it renders.
+/// Keys left at their defaults are left out.
+pub fn export_toml(equations: &[Equation]) -> String {
+    equations
+        .iter()
+        .map(equation_table)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn write_toml_file(path: &Path, equations: &[Equation]) -> io::Result<()> {
+    fs::write(path, export_toml(equations))
+}
+
+fn equation_table(equation: &Equation) -> String {
+    let mut table = format!(
+        "[[equation]]\nname = {}\nbody = {}\n",
+        Value::from(equation.name.as_str()),
+        toml_body(&equation.math_body())
+    );
+    if !equation.active {
+        table.push_str("active = false\n");
+    }
+    if !equation.tags.is_empty() {
+        table.push_str(&format!("tags = {}\n", Value::from(equation.tags.clone())));
+    }
+    if let Some(color) = &equation.color {
+        table.push_str(&format!("color = {}\n", Value::from(color.as_str())));
+    }
+    if let Some(engine) = equation.engine {
+        table.push_str(&format!("engine = \"{}\"\n", engine));
+    }
+    if !equation.packages.is_empty() {
+        let packages = Value::from(equation.packages.clone());
+        table.push_str(&format!("packages = {}\n", packages));
+    }
+    if let Some(size) = equation.size {
+        table.push_str(&format!("size = \"{}\"\n", size));
+    }
+    // The exported body has no delimiters left to tell display style by
+    match equation.math_wrap() {
+        MathWrap::Inline => {}
+        wrap => table.push_str(&format!("wrap = \"{}\"\n", wrap)),
+    }
+    table
+}
+
+// A literal string, so backslashes stay as TeX has them, unless the body
+// holds what a literal string can't
+pub(crate) fn toml_body(body: &str) -> String {
+    let literal = !body
+        .chars()
+        .any(|c| c.is_control() && c != '\n' && c != '\t');
+    if literal && body.contains('\n') && !body.contains("'''") {
+        format!("'''\n{}\n'''", body)
+    } else if literal && !body.contains(['\'', '\n']) {
+        format!("'{}'", body)
+    } else {
+        Value::from(body).to_string()
+    }
+}
+
+/// Rewrites the tables of the TOML file `path` in the order of `equations`,
+/// which must have been read from it. What comes before the first table
+/// stays first. A table moves with the comments right above its header and
+/// everything below it up to the next; tables none of `equations` came from
+/// follow the others. The file is written back as UTF-8 with LF line
+/// endings.
+pub fn reorder_toml_file(path: &Path, equations: &[Equation]) -> io::Result<()> {
+    let content = load_source(path)?;
+    let lines: Vec<&str> = content.lines().collect();
+    let headers = table_headers(&lines);
+    if headers.is_empty() {
+        return Ok(());
+    }
+    // Each table starts at the comments over its header
+    let starts: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(i, &header)| {
+            let floor = if i == 0 { 0 } else { headers[i - 1] + 1 };
+            let mut start = header;
+            while start > floor && lines[start - 1].trim_start().starts_with('#') {
+                start -= 1;
+            }
+            start
+        })
+        .collect();
+    let chunk = |i: usize| {
+        let end = starts.get(i + 1).copied().unwrap_or(lines.len());
+        let mut chunk = &lines[starts[i]..end];
+        while let Some((last, rest)) = chunk.split_last() {
+            if !last.trim().is_empty() {
+                break;
+            }
+            chunk = rest;
+        }
+        chunk.join("\n")
+    };
+    let ordered: Vec<usize> = equations
+        .iter()
+        .filter_map(|eq| eq.span)
+        .filter_map(|span| headers.iter().position(|&h| h + 1 == span.start_line))
+        .collect();
+
+    let mut tables: Vec<String> = ordered.iter().map(|&i| chunk(i)).collect();
+    tables.extend(
+        (0..headers.len())
+            .filter(|i| !ordered.contains(i))
+            .map(chunk),
+    );
+    let preamble = lines[..starts[0]].join("\n");
+    let mut output = preamble.trim_end().to_string();
+    if !output.is_empty() {
+        output.push_str("\n\n");
+    }
+    output.push_str(&tables.join("\n\n"));
+    fs::write(path, output + "\n")
+}
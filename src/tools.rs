@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+use tracing::debug;
+
+/// Where the external `program` (`pdflatex`, `pdftocairo`, ...) lives: the
+/// first match on the PATH, trying the executable suffixes on Windows
+/// (`.exe`, ...). Windows also looks in the usual MiKTeX, TeX Live and
+/// Poppler install directories, which installers don't always put on the
+/// PATH, and finally asks `where`. Tools once found are remembered.
+pub fn find_tool(program: &str) -> Option<PathBuf> {
+    static FOUND: OnceLock<Mutex<HashMap<String, PathBuf>>> = OnceLock::new();
+    let found = FOUND.get_or_init(Mutex::default);
+    if let Some(path) = found.lock().unwrap().get(program) {
+        return Some(path.clone());
+    }
+    // Misses aren't remembered, so installing a tool mid-session works
+    let path = search(program)?;
+    debug!("{} found at {}", program, path.display());
+    found
+        .lock()
+        .unwrap()
+        .insert(program.to_string(), path.clone());
+    Some(path)
+}
+
+/// A command running `program` from where `find_tool` found it, or by its
+/// bare name, which fails with `NotFound` when spawned.
+pub fn tool_command(program: &str) -> Command {
+    match find_tool(program) {
+        Some(path) => Command::new(path),
+        None => Command::new(program),
+    }
+}
+
+/// `path` as TeX should see it on the command line: TeX Live on Windows
+/// reads a backslash as the start of a control sequence, so separators
+/// become forward slashes there, which every Windows TeX accepts.
+pub fn tex_path(path: &Path) -> OsString {
+    if cfg!(windows) {
+        path.to_string_lossy().replace('\\', "/").into()
+    } else {
+        path.as_os_str().to_owned()
+    }
+}
+
+fn search(program: &str) -> Option<PathBuf> {
+    let path_dirs: Vec<PathBuf> = env::var_os("PATH")
+        .map(|path| env::split_paths(&path).collect())
+        .unwrap_or_default();
+    let dirs = path_dirs.into_iter().chain(install_dirs());
+    for dir in dirs {
+        for suffix in executable_suffixes() {
+            let candidate = dir.join(format!("{}{}", program, suffix));
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+        }
+    }
+    where_lookup(program)
+}
+
+fn executable_suffixes() -> Vec<String> {
+    if !cfg!(windows) {
+        return vec![String::new()];
+    }
+    let pathext = env::var("PATHEXT").unwrap_or_else(|_| ".EXE;.CMD;.BAT;.COM".to_string());
+    pathext
+        .split(';')
+        .filter(|ext| !ext.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+// Default install locations of MiKTeX, TeX Live, Poppler and the package
+// managers that ship them; only consulted on Windows
+fn install_dirs() -> Vec<PathBuf> {
+    if !cfg!(windows) {
+        return Vec::new();
+    }
+    let var = |name: &str| env::var_os(name).map(PathBuf::from);
+    let mut dirs = Vec::new();
+    for base in [
+        var("LOCALAPPDATA").map(|dir| dir.join("Programs")),
+        var("ProgramFiles"),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        dirs.push(base.join(r"MiKTeX\miktex\bin\x64"));
+        dirs.push(base.join(r"poppler\Library\bin"));
+        dirs.push(base.join(r"poppler\bin"));
+    }
+    // TeX Live installs into a directory per year; prefer the newest
+    if let Ok(entries) = std::fs::read_dir(r"C:\texlive") {
+        let mut years: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+        years.sort();
+        for year in years.iter().rev() {
+            dirs.push(year.join(r"bin\windows"));
+            dirs.push(year.join(r"bin\win64"));
+            dirs.push(year.join(r"bin\win32"));
+        }
+    }
+    if let Some(home) = var("USERPROFILE") {
+        dirs.push(home.join(r"scoop\shims"));
+    }
+    if let Some(data) = var("ProgramData") {
+        dirs.push(data.join(r"chocolatey\bin"));
+    }
+    dirs
+}
+
+// Last resort on Windows; `where` also looks in the current directory, as
+// cmd.exe would
+fn where_lookup(program: &str) -> Option<PathBuf> {
+    if !cfg!(windows) {
+        return None;
+    }
+    let output = Command::new("where").arg(program).output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let first = stdout.lines().next()?.trim();
+    (output.status.success() && !first.is_empty()).then(|| PathBuf::from(first))
+}
@@ -0,0 +1,86 @@
+use crate::rasterize_svg;
+use std::io;
+use std::path::Path;
+
+// Rasterization scale; 2x so thin strokes cover whole pixels
+const SCALE: f32 = 2.0;
+// Per-channel difference below which pixels count as equal, to absorb
+// antialiasing noise between renderer versions
+const CHANNEL_TOLERANCE: u8 = 16;
+
+/// How a re-rendered equation compares to its baseline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Verdict {
+    Unchanged,
+    Changed(f64), // Percentage of differing pixels
+    NoBaseline,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verification {
+    pub name: String,
+    pub verdict: Verdict,
+}
+
+/// Compares `<rendered>/<name>.svg` with `<baseline>/<name>.svg` for every
+/// name. Differences up to `tolerance` percent count as unchanged.
+pub fn verify_renders(
+    names: &[String],
+    rendered: &Path,
+    baseline: &Path,
+    tolerance: f64,
+) -> io::Result<Vec<Verification>> {
+    let mut results = Vec::new();
+    for name in names {
+        let file_name = format!("{}.svg", name);
+        let expected = baseline.join(&file_name);
+        let verdict = if !expected.exists() {
+            Verdict::NoBaseline
+        } else {
+            match svg_difference(&rendered.join(&file_name), &expected)? {
+                percent if percent > tolerance => Verdict::Changed(percent),
+                _ => Verdict::Unchanged,
+            }
+        };
+        results.push(Verification {
+            name: name.clone(),
+            verdict,
+        });
+    }
+    Ok(results)
+}
+
+/// Percentage of pixels that differ between two SVGs, rasterized at the
+/// same scale. Where the sizes differ, the uncovered area counts as changed.
+pub fn svg_difference(a: &Path, b: &Path) -> io::Result<f64> {
+    let (a, b) = (rasterize_svg(a, SCALE)?, rasterize_svg(b, SCALE)?);
+    let width = a.width().max(b.width());
+    let height = a.height().max(b.height());
+    let total = u64::from(width) * u64::from(height);
+    if total == 0 {
+        return Ok(0.0);
+    }
+
+    let mut differing = 0u64;
+    for y in 0..height {
+        for x in 0..width {
+            let same = match (a.pixel(x, y), b.pixel(x, y)) {
+                (Some(p), Some(q)) => [
+                    (p.red(), q.red()),
+                    (p.green(), q.green()),
+                    (p.blue(), q.blue()),
+                    (p.alpha(), q.alpha()),
+                ]
+                .iter()
+                .all(|(p, q)| p.abs_diff(*q) <= CHANNEL_TOLERANCE),
+                // Outside one image: only transparent pixels of the other match
+                (Some(p), None) | (None, Some(p)) => p.alpha() <= CHANNEL_TOLERANCE,
+                (None, None) => true,
+            };
+            if !same {
+                differing += 1;
+            }
+        }
+    }
+    Ok(differing as f64 * 100.0 / total as f64)
+}
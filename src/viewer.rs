@@ -0,0 +1,95 @@
+use crate::{missing_tool, read_manifest, tool_command, OutputFormat};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use tracing::debug;
+
+// Tried in this order when no format is asked for
+const VIEWABLE: [OutputFormat; 4] = [
+    OutputFormat::Svg,
+    OutputFormat::Pdf,
+    OutputFormat::Png,
+    OutputFormat::MathML,
+];
+
+/// The rendered output of the equation `name` in `output_dir`, in `format`
+/// or else the first of SVG, PDF, PNG and MathML that exists. Hashed and
+/// numbered names are looked up in the manifest.
+pub fn find_rendered(
+    output_dir: &Path,
+    name: &str,
+    format: Option<OutputFormat>,
+) -> Option<PathBuf> {
+    let wanted = |path: &Path| {
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        format.is_none_or(|format| extension == Some(format.extension())) && path.is_file()
+    };
+    if let Some(file) = read_manifest(output_dir).remove(name) {
+        let path = output_dir.join(file);
+        if wanted(&path) {
+            return Some(path);
+        }
+    }
+    let formats = match format {
+        Some(format) => vec![format],
+        None => VIEWABLE.to_vec(),
+    };
+    formats
+        .into_iter()
+        .map(|format| output_dir.join(format!("{}.{}", name, format.extension())))
+        .find(|path| wanted(path))
+}
+
+/// Opens `path` without waiting for the viewer to close: with the command
+/// `viewers` has for its extension (`[viewer]` in the config, e.g.
+/// `svg = "inkscape"`), or the system's default application through
+/// `xdg-open`, `open` or `start`.
+pub fn open_in_viewer(path: &Path, viewers: &BTreeMap<String, String>) -> io::Result<()> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let mut command = match viewers.get(&extension) {
+        Some(viewer) => {
+            let mut words = viewer.split_whitespace();
+            let program = words.next().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("The viewer for .{} files is empty", extension),
+                )
+            })?;
+            let mut command = tool_command(program);
+            command.args(words);
+            command
+        }
+        None => default_opener(),
+    };
+    command.arg(path);
+    debug!("Opening {} with {:?}", path.display(), command);
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| missing_tool(&program, e))?;
+    // Reaped in the background, as viewers may stay open for long
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+fn default_opener() -> Command {
+    if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        // `start` is built into cmd; the empty title keeps a quoted path
+        // from being taken for one
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        tool_command("xdg-open")
+    }
+}
@@ -0,0 +1,98 @@
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{
+    Block, Borders, Clear, List, ListItem, ListState, Paragraph, StatefulWidget, Widget,
+};
+use simptui::Bookmark;
+use tui_textarea::{Input, Key};
+
+pub enum BookmarkOutcome {
+    Open,
+    Jump(usize),   // Enter on a bookmark: show its file
+    Remove(usize), // `d` or Delete
+    RenderAll,     // `r`
+    Close,
+}
+
+/// Full-screen list of the bookmarked equations, from all files.
+pub struct BookmarkScreen {
+    bookmarks: Vec<Bookmark>,
+    selected: usize,
+}
+
+impl BookmarkScreen {
+    pub fn new(bookmarks: &[Bookmark]) -> Self {
+        BookmarkScreen {
+            bookmarks: bookmarks.to_vec(),
+            selected: 0,
+        }
+    }
+
+    // After a removal, keeping the selection in place
+    pub fn set_bookmarks(&mut self, bookmarks: &[Bookmark]) {
+        self.bookmarks = bookmarks.to_vec();
+        self.selected = self.selected.min(bookmarks.len().saturating_sub(1));
+    }
+
+    pub fn handle_input(&mut self, input: Input) -> BookmarkOutcome {
+        let any = !self.bookmarks.is_empty();
+        match input.key {
+            Key::Esc => BookmarkOutcome::Close,
+            Key::Up => {
+                self.selected = self.selected.saturating_sub(1);
+                BookmarkOutcome::Open
+            }
+            Key::Down => {
+                if self.selected + 1 < self.bookmarks.len() {
+                    self.selected += 1;
+                }
+                BookmarkOutcome::Open
+            }
+            Key::Enter if any => BookmarkOutcome::Jump(self.selected),
+            Key::Char('d') | Key::Delete if any => BookmarkOutcome::Remove(self.selected),
+            Key::Char('r') if any => BookmarkOutcome::RenderAll,
+            _ => BookmarkOutcome::Open,
+        }
+    }
+}
+
+impl Widget for &BookmarkScreen {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let block = Block::default().borders(Borders::ALL).title(format!(
+            "Bookmarks ({}) - Enter open, d remove, r render all, Esc close",
+            self.bookmarks.len()
+        ));
+        if self.bookmarks.is_empty() {
+            Paragraph::new("No bookmarks yet; press m on an equation to add it")
+                .block(block)
+                .render(area, buf);
+            return;
+        }
+
+        let items: Vec<ListItem> = self
+            .bookmarks
+            .iter()
+            .map(|bookmark| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{} ", bookmark.name),
+                        Style::default().fg(Color::Yellow),
+                    ),
+                    Span::styled(
+                        format!("{} ", bookmark.file.display()),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    Span::raw(bookmark.body.replace('\n', " ")),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        StatefulWidget::render(list, area, buf, &mut state);
+    }
+}
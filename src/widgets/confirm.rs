@@ -0,0 +1,83 @@
+use super::centered_rect;
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Alignment, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph, Widget, Wrap};
+use tui_textarea::{Input, Key};
+
+/// Modal Yes/No dialog drawn over the rest of the UI. Feed it keys with
+/// `handle_input` until it returns a decision.
+pub struct ConfirmDialog {
+    title: String,
+    message: String,
+    yes_selected: bool,
+}
+
+impl ConfirmDialog {
+    pub fn new(title: &str, message: &str) -> Self {
+        ConfirmDialog {
+            title: title.to_string(),
+            message: message.to_string(),
+            yes_selected: false, // Default to the harmless choice
+        }
+    }
+
+    /// Returns `Some(true)` on Yes, `Some(false)` on No/Esc, and `None` while
+    /// the dialog should stay open.
+    pub fn handle_input(&mut self, input: Input) -> Option<bool> {
+        match input.key {
+            Key::Char('y') | Key::Char('Y') => Some(true),
+            Key::Char('n') | Key::Char('N') | Key::Esc => Some(false),
+            Key::Enter => Some(self.yes_selected),
+            Key::Left | Key::Right | Key::Tab => {
+                self.yes_selected = !self.yes_selected;
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Widget for &ConfirmDialog {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let width = (self.message.len() as u16 + 4).clamp(30, 60);
+        let message_lines = (self.message.len() as u16).div_ceil(width - 2).max(1);
+        let height = message_lines + 4; // Blank line, buttons, borders
+        let popup = centered_rect(width, height, area);
+
+        let button = |label: &str, selected: bool| {
+            let style = if selected {
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            Span::styled(format!("[ {} ]", label), style)
+        };
+
+        let text = vec![
+            Line::from(self.message.as_str()),
+            Line::from(""),
+            Line::from(vec![
+                button("Yes", self.yes_selected),
+                Span::raw("   "),
+                button("No", !self.yes_selected),
+            ])
+            .alignment(Alignment::Center),
+        ];
+
+        Clear.render(popup, buf);
+        Paragraph::new(text)
+            .wrap(Wrap { trim: true })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Yellow))
+                    .title(self.title.as_str()),
+            )
+            .render(popup, buf);
+    }
+}
@@ -0,0 +1,236 @@
+use super::{highlight_latex, wrap_latex};
+use ratatui::layout::Constraint;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table};
+use regex::Regex;
+use simptui::{Equation, Heading, SourceSpan};
+use std::collections::{BTreeMap, HashSet};
+
+/// Lines of prose shown above and below the selected equation.
+pub const CONTEXT_RADIUS: usize = 10;
+
+/// Lines a body wraps to in the table before it is cut short.
+pub const BODY_LINES: usize = 3;
+
+const ACTIVE_WIDTH: u16 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortKey {
+    #[default]
+    Document, // Order of appearance in the source
+    Name,
+    Active,
+    Length,
+}
+
+impl SortKey {
+    pub fn next(self) -> Self {
+        match self {
+            SortKey::Document => SortKey::Name,
+            SortKey::Name => SortKey::Active,
+            SortKey::Active => SortKey::Length,
+            SortKey::Length => SortKey::Document,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SortOrder {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+/// Indices into `equations` that pass `filter` (matched against the body),
+/// in the requested order.
+pub fn sorted_view(equations: &[Equation], sort: SortOrder, filter: Option<&Regex>) -> Vec<usize> {
+    let mut view: Vec<usize> = (0..equations.len())
+        .filter(|&i| filter.is_none_or(|re| re.is_match(&equations[i].body)))
+        .collect();
+    match sort.key {
+        SortKey::Document => {}
+        SortKey::Name => view.sort_by(|&a, &b| equations[a].name.cmp(&equations[b].name)),
+        SortKey::Active => view.sort_by_key(|&i| !equations[i].active),
+        SortKey::Length => view.sort_by_key(|&i| equations[i].body.len()),
+    }
+    if sort.descending {
+        view.reverse();
+    }
+    view
+}
+
+/// A row of the table, by index into the equations or their headings.
+/// `Section(None)` holds the equations above the first heading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewRow {
+    Section(Option<usize>),
+    Equation(usize),
+}
+
+/// The equations of `view` under a row for each heading in `sections`
+/// (one per equation), headings in document order and the equations under
+/// each in the order of `view`. Collapsed sections keep only their row;
+/// sections without equations in `view` are left out.
+pub fn grouped_view(
+    view: &[usize],
+    sections: &[Option<usize>],
+    collapsed: &HashSet<Option<usize>>,
+) -> Vec<ViewRow> {
+    let mut groups: BTreeMap<Option<usize>, Vec<usize>> = BTreeMap::new();
+    for &i in view {
+        groups.entry(sections[i]).or_default().push(i);
+    }
+    let mut rows = Vec::new();
+    for (section, equations) in groups {
+        rows.push(ViewRow::Section(section));
+        if !collapsed.contains(&section) {
+            rows.extend(equations.into_iter().map(ViewRow::Equation));
+        }
+    }
+    rows
+}
+
+/// What a table row shows.
+pub enum TableRow<'a> {
+    Section {
+        heading: Option<&'a Heading>,
+        equations: usize,
+        collapsed: bool,
+    },
+    Equation(&'a Equation),
+}
+
+// Name and body columns of a table `width` wide, borders and spacing taken off
+fn column_widths(width: u16) -> (u16, u16) {
+    let inner = width.saturating_sub(2 + ACTIVE_WIDTH + 2);
+    let name = inner / 4;
+    (name, inner - name)
+}
+
+/// Whether `body` is cut short in a table `width` wide, so only the
+/// context pane shows all of it.
+pub fn body_truncated(body: &str, width: u16) -> bool {
+    wrap_latex(body, column_widths(width).1 as usize, BODY_LINES).1
+}
+
+/// The table drawn `width` columns wide, bodies wrapped to fit.
+pub fn equation_table<'a>(rows: &[TableRow<'a>], sort: SortOrder, width: u16) -> Table<'a> {
+    let (name_width, body_width) = column_widths(width);
+    let arrow = if sort.descending { " ▼" } else { " ▲" };
+    let header = |title: &str, key: SortKey| {
+        if sort.key == key {
+            format!("{}{}", title, arrow)
+        } else {
+            title.to_string()
+        }
+    };
+    let equation_header = if sort.key == SortKey::Length {
+        format!("Equation (length){}", arrow)
+    } else {
+        "Equation".to_string()
+    };
+
+    let rows = rows.iter().map(|row| match row {
+        TableRow::Section {
+            heading,
+            equations,
+            collapsed,
+        } => {
+            // The title goes in the wide column
+            let (level, title) = match heading {
+                Some(heading) => ("#".repeat(heading.level), heading.title.as_str()),
+                None => (String::new(), "(before the first heading)"),
+            };
+            Row::new(vec![
+                Cell::from(if *collapsed { "▸" } else { "▾" }),
+                Cell::from(
+                    format!("{} ({})", level, equations)
+                        .trim_start()
+                        .to_string(),
+                ),
+                Cell::from(title),
+            ])
+            .style(
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            )
+        }
+        TableRow::Equation(eq) => {
+            let (body, _) = wrap_latex(&eq.body, body_width as usize, BODY_LINES);
+            let height = body.len().max(1) as u16;
+            Row::new(vec![
+                Cell::from(if eq.active { "Yes" } else { "No" }),
+                Cell::from(eq.name.as_str()),
+                Cell::from(Text::from(body)),
+            ])
+            .height(height)
+        }
+    });
+
+    Table::new(
+        rows,
+        [
+            Constraint::Length(ACTIVE_WIDTH),
+            Constraint::Length(name_width),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            header("Active", SortKey::Active),
+            header("Name", SortKey::Name),
+            equation_header,
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::default().borders(Borders::ALL).title("Equations"))
+    .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+}
+
+/// The source lines around `span` with line numbers, the equation block
+/// itself highlighted.
+pub fn source_context(source: &str, span: Option<SourceSpan>) -> Paragraph<'_> {
+    let Some(span) = span else {
+        return Paragraph::new("No source position for this equation.")
+            .block(Block::default().borders(Borders::ALL).title("Context"));
+    };
+
+    // The block as a whole, so braces pair up across its lines
+    let block: Vec<&str> = source
+        .lines()
+        .skip(span.start_line.saturating_sub(1))
+        .take(span.end_line + 1 - span.start_line)
+        .collect();
+    let mut block = highlight_latex(&block.join("\n")).into_iter();
+
+    let first = span.start_line.saturating_sub(CONTEXT_RADIUS).max(1);
+    let last = span.end_line + CONTEXT_RADIUS;
+    let lines: Vec<Line> = source
+        .lines()
+        .enumerate()
+        .map(|(i, text)| (i + 1, text))
+        .filter(|(number, _)| (first..=last).contains(number))
+        .map(|(number, text)| {
+            let in_block = (span.start_line..=span.end_line).contains(&number);
+            let gutter = Span::styled(
+                format!("{:>5} {} ", number, if in_block { '>' } else { ' ' }),
+                Style::default().fg(Color::DarkGray),
+            );
+            let highlighted = if in_block { block.next() } else { None };
+            match highlighted {
+                Some(highlighted) => {
+                    let mut spans = vec![gutter];
+                    spans.extend(highlighted.spans);
+                    Line::from(spans).style(Style::default().add_modifier(Modifier::BOLD))
+                }
+                None => Line::from(vec![gutter, Span::raw(text)]),
+            }
+        })
+        .collect();
+
+    Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(format!(
+        "Context (lines {}-{})",
+        span.start_line, span.end_line
+    )))
+}
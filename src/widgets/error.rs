@@ -0,0 +1,291 @@
+use super::centered_rect;
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph, Widget, Wrap};
+use simptui::{tex_log_errors, RenderFailure};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// What went wrong, where, and what might fix it.
+pub struct ErrorReport {
+    pub kind: String,           // Short description, e.g. "Permission denied"
+    pub subject: String,        // The file or equation it happened to
+    pub message: String,        // The error itself
+    pub output: Option<String>, // The relevant part of the tool's output
+    pub log: Option<String>,    // The tool's whole output, for copying
+    pub fixes: Vec<String>,     // Suggested next steps
+}
+
+impl ErrorReport {
+    /// `error` while working on `subject`, usually a file.
+    pub fn io(subject: &str, error: &io::Error) -> Self {
+        let message = error.to_string();
+        let fixes = tool_fixes(&message).unwrap_or_else(|| match error.kind() {
+            io::ErrorKind::NotFound => {
+                vec![
+                    "Check that the file still exists; the file list follows changes on disk"
+                        .to_string(),
+                ]
+            }
+            io::ErrorKind::PermissionDenied => {
+                vec!["Check the permissions of the file and its directory".to_string()]
+            }
+            io::ErrorKind::InvalidData => vec!["Save the file as UTF-8".to_string()],
+            _ => Vec::new(),
+        });
+        ErrorReport {
+            kind: capitalize(&error.kind().to_string()),
+            subject: subject.to_string(),
+            message,
+            output: None,
+            log: None,
+            fixes,
+        }
+    }
+
+    /// No scanned file is called `name`.
+    pub fn unknown_file(name: &str, scanning: bool) -> Self {
+        let mut fixes = vec![
+            "Type part of the name and pick one of the files it matches".to_string(),
+            "Browse the scanned files with Ctrl-O".to_string(),
+            "Add its folder with --root or `roots` in the config".to_string(),
+        ];
+        if scanning {
+            fixes.insert(0, "Wait for the file scan to finish".to_string());
+        }
+        ErrorReport {
+            kind: "File not found".to_string(),
+            subject: name.to_string(),
+            message: format!("None of the scanned files is called '{}'", name),
+            output: None,
+            log: None,
+            fixes,
+        }
+    }
+
+    /// An equation a batch failed on, with the TeX errors from its log in
+    /// `failed_dir`.
+    pub fn render_failure(failure: &RenderFailure, failed_dir: &Path) -> Self {
+        let tex_file = failed_dir.join(format!("{}.tex", failure.name));
+        let log = fs::read_to_string(failed_dir.join(format!("{}.log", failure.name))).ok();
+        let errors = log
+            .as_deref()
+            .map(tex_log_errors)
+            .filter(|errors| !errors.is_empty());
+        let mut fixes = tool_fixes(&failure.error)
+            .or_else(|| errors.as_deref().map(tex_fixes))
+            .unwrap_or_default();
+        if let Some(line) = errors.as_deref().and_then(error_line) {
+            fixes.push(format!("See line {} of {}", line, tex_file.display()));
+        }
+        ErrorReport {
+            kind: if errors.is_some() {
+                "LaTeX error".to_string()
+            } else {
+                "Render failed".to_string()
+            },
+            subject: failure.name.clone(),
+            message: failure.error.clone(),
+            output: errors,
+            log,
+            fixes,
+        }
+    }
+
+    /// Everything in the report as plain text, the whole log included.
+    pub fn full_text(&self) -> String {
+        let mut text = format!("{}: {}\n{}\n", self.kind, self.subject, self.message);
+        if let Some(output) = self.log.as_ref().or(self.output.as_ref()) {
+            text.push_str(&format!("\n{}\n", output.trim_end()));
+        }
+        if !self.fixes.is_empty() {
+            text.push_str("\nSuggested fixes:\n");
+            for fix in &self.fixes {
+                text.push_str(&format!("- {}\n", fix));
+            }
+        }
+        text
+    }
+}
+
+// For `missing_tool` errors
+fn tool_fixes(message: &str) -> Option<Vec<String>> {
+    let tool = message.strip_suffix(" not found, please install it")?;
+    Some(vec![
+        format!("Install {} and make sure it is on the PATH", tool),
+        "Or pick a profile with another engine (Ctrl-P)".to_string(),
+    ])
+}
+
+// The usual causes of the commonest TeX errors
+fn tex_fixes(errors: &str) -> Vec<String> {
+    let mut fixes = Vec::new();
+    if errors.contains("Undefined control sequence") {
+        fixes.push(
+            "Check the spelling of the command, or load its package with \
+             `%%packages=...%%` above the equation"
+                .to_string(),
+        );
+    }
+    if let Some(package) = errors
+        .split("File `")
+        .nth(1)
+        .and_then(|rest| rest.split_once(".sty' not found"))
+        .map(|(package, _)| package)
+    {
+        fixes.push(format!(
+            "Install the LaTeX package {0} (e.g. `tlmgr install {0}`)",
+            package
+        ));
+    }
+    if errors.contains("Missing $ inserted") {
+        fixes.push("Leave out `$` and blank lines; the body is already math".to_string());
+    }
+    if ["Missing } inserted", "Missing { inserted", "Extra }"]
+        .iter()
+        .any(|error| errors.contains(error))
+    {
+        fixes.push("Balance the braces of the body".to_string());
+    }
+    if errors.contains("alignment tab") {
+        fixes.push("Use `&` only inside align-like environments".to_string());
+    }
+    fixes
+}
+
+// Where TeX stopped, from its `l.<n>` line
+fn error_line(errors: &str) -> Option<usize> {
+    errors.lines().find_map(|line| {
+        let rest = line.strip_prefix("l.")?;
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        rest[..digits].parse().ok()
+    })
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// Popup explaining an error, scrollable for long logs, until a key other
+/// than the copy and scroll keys closes it.
+pub struct ErrorScreen {
+    pub report: ErrorReport,
+    pub copy_key: String, // Label of the key copying `full_text`
+    scroll: u16,
+    note: Option<String>, // Outcome of the last copy
+}
+
+impl ErrorScreen {
+    pub fn new(report: ErrorReport, copy_key: String) -> Self {
+        ErrorScreen {
+            report,
+            copy_key,
+            scroll: 0,
+            note: None,
+        }
+    }
+
+    pub fn scroll_by(&mut self, lines: i32) {
+        self.scroll = (self.scroll as i32 + lines).max(0) as u16;
+    }
+
+    pub fn set_note(&mut self, note: String) {
+        self.note = Some(note);
+    }
+}
+
+impl Widget for &ErrorScreen {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let report = &self.report;
+        let heading = |text: &'static str| {
+            Line::from(Span::styled(
+                text,
+                Style::default().add_modifier(Modifier::BOLD),
+            ))
+        };
+        let mut text = vec![
+            Line::from(Span::styled(
+                report.subject.as_str(),
+                Style::default().add_modifier(Modifier::BOLD),
+            )),
+            Line::from(report.message.as_str()),
+        ];
+        if let Some(output) = &report.output {
+            text.push(Line::from(""));
+            text.push(heading("Output"));
+            text.extend(
+                output
+                    .lines()
+                    .map(|line| Line::from(Span::styled(line, Style::default().fg(Color::Gray)))),
+            );
+        }
+        if !report.fixes.is_empty() {
+            text.push(Line::from(""));
+            text.push(heading("Try"));
+            text.extend(
+                report
+                    .fixes
+                    .iter()
+                    .map(|fix| Line::from(format!("- {}", fix))),
+            );
+        }
+
+        let width = area.width.saturating_sub(10).max(40);
+        let wrapped: usize = text
+            .iter()
+            .map(|line| {
+                line.width()
+                    .max(1)
+                    .div_ceil(width.saturating_sub(2).max(1) as usize)
+            })
+            .sum();
+        let height = wrapped as u16 + 4; // Blank line, footer, borders
+        let popup = centered_rect(width, height, area);
+        Clear.render(popup, buf);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::LightRed))
+            .title(report.kind.as_str());
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+        if inner.height < 2 {
+            return;
+        }
+
+        let body = Rect {
+            height: inner.height - 1,
+            ..inner
+        };
+        Paragraph::new(text)
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0))
+            .render(body, buf);
+        let footer = self.note.clone().unwrap_or_else(|| {
+            format!(
+                "{} copies the full log, Up/Down scroll, any other key closes",
+                self.copy_key
+            )
+        });
+        Paragraph::new(Line::from(Span::styled(
+            footer,
+            Style::default().fg(Color::DarkGray),
+        )))
+        .render(
+            Rect {
+                y: inner.bottom() - 1,
+                height: 1,
+                ..inner
+            },
+            buf,
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use super::centered_rect;
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph, Widget, Wrap};
+use simptui::RenderFailure;
+
+/// Panel listing the equations a render left behind, shown after returning
+/// from a batch until any key dismisses it.
+pub struct FailuresPanel<'a> {
+    pub failures: &'a [RenderFailure],
+}
+
+impl Widget for FailuresPanel<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut text: Vec<Line> = Vec::new();
+        for failure in self.failures {
+            text.push(Line::from(Span::styled(
+                failure.name.as_str(),
+                Style::default().add_modifier(Modifier::BOLD),
+            )));
+            text.push(Line::from(format!("  {}", failure.error)));
+        }
+        text.push(Line::from(""));
+        text.push(Line::from(
+            "Sources and logs were moved to failed/ next to the outputs. Enter explains the first failure, any other key closes.",
+        ));
+
+        let height = text.len() as u16 + 2;
+        let popup = centered_rect(area.width.saturating_sub(10).max(40), height, area);
+        Clear.render(popup, buf);
+        Paragraph::new(text)
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::LightRed))
+                    .title(format!("{} equation(s) failed", self.failures.len())),
+            )
+            .render(popup, buf);
+    }
+}
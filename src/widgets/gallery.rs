@@ -0,0 +1,162 @@
+use super::preview::{paint, rasterize};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Alignment, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Block, Borders, Paragraph, StatefulWidget, Widget};
+use resvg::tiny_skia::Pixmap;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// Size of one tile in cells, borders and name line included
+const TILE_WIDTH: u16 = 24;
+const TILE_HEIGHT: u16 = 8;
+
+/// Rasterized thumbnails and the scroll position of the grid.
+#[derive(Default)]
+pub struct GalleryState {
+    thumbnails: HashMap<PathBuf, Thumbnail>,
+    columns: usize,   // Tiles per row at the last draw
+    first_row: usize, // Topmost visible row of tiles
+}
+
+struct Thumbnail {
+    modified: Option<SystemTime>, // Re-rasterize after a re-render
+    area: (u16, u16),
+    pixmap: Option<Pixmap>, // `None` until the equation is rendered
+}
+
+impl GalleryState {
+    /// Tiles per row at the last draw, what Up and Down move by.
+    pub fn columns(&self) -> usize {
+        self.columns.max(1)
+    }
+
+    // Rasterizes `path` to fit the tile unless the cached one is current
+    fn thumbnail(&mut self, path: &Path, width: u16, height: u16) -> Option<&Pixmap> {
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+        let fresh = self.thumbnails.get(path).is_some_and(|thumbnail| {
+            thumbnail.modified == modified && thumbnail.area == (width, height)
+        });
+        if !fresh {
+            let thumbnail = Thumbnail {
+                modified,
+                area: (width, height),
+                pixmap: rasterize(path, width, height * 2, 1.0).ok(),
+            };
+            self.thumbnails.insert(path.to_path_buf(), thumbnail);
+        }
+        self.thumbnails[path].pixmap.as_ref()
+    }
+}
+
+/// One tile: the equation's name and where its SVG would be.
+pub struct GalleryItem {
+    pub name: String,
+    pub svg: Option<PathBuf>,
+}
+
+/// The rendered equations as a grid of thumbnails with their names
+/// underneath, scrolled to keep the selected one in view.
+pub struct ThumbnailGrid<'a> {
+    pub items: &'a [GalleryItem],
+    pub selected: usize,
+}
+
+impl StatefulWidget for ThumbnailGrid<'_> {
+    type State = GalleryState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut GalleryState) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan))
+            .title(format!("Gallery ({} equations)", self.items.len()));
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let columns = (inner.width / TILE_WIDTH).max(1) as usize;
+        let rows = (inner.height / TILE_HEIGHT).max(1) as usize;
+        state.columns = columns;
+        let selected_row = self.selected / columns;
+        if selected_row < state.first_row {
+            state.first_row = selected_row;
+        } else if selected_row >= state.first_row + rows {
+            state.first_row = selected_row + 1 - rows;
+        }
+
+        let visible = self
+            .items
+            .iter()
+            .enumerate()
+            .skip(state.first_row * columns)
+            .take(rows * columns);
+        for (i, item) in visible {
+            let slot = i - state.first_row * columns;
+            let tile = Rect::new(
+                inner.x + (slot % columns) as u16 * TILE_WIDTH,
+                inner.y + (slot / columns) as u16 * TILE_HEIGHT,
+                TILE_WIDTH.min(inner.width),
+                TILE_HEIGHT.min(inner.height),
+            )
+            .intersection(inner);
+            render_tile(item, i == self.selected, tile, buf, state);
+        }
+    }
+}
+
+fn render_tile(
+    item: &GalleryItem,
+    selected: bool,
+    area: Rect,
+    buf: &mut Buffer,
+    state: &mut GalleryState,
+) {
+    let mut block = Block::default().borders(Borders::ALL);
+    if selected {
+        block = block.border_style(Style::default().fg(Color::Yellow));
+    }
+    let inner = block.inner(area);
+    block.render(area, buf);
+    if inner.height == 0 {
+        return;
+    }
+
+    // Name on the last line, the image above it
+    let mut name_style = Style::default();
+    if selected {
+        name_style = name_style.add_modifier(Modifier::BOLD | Modifier::REVERSED);
+    }
+    let name_area = Rect::new(inner.x, inner.bottom() - 1, inner.width, 1);
+    Paragraph::new(item.name.as_str())
+        .style(name_style)
+        .alignment(Alignment::Center)
+        .render(name_area, buf);
+
+    let image = Rect::new(inner.x, inner.y, inner.width, inner.height - 1);
+    let thumbnail = match &item.svg {
+        Some(svg) if image.height > 0 => state.thumbnail(svg, image.width, image.height),
+        _ => return,
+    };
+    match thumbnail {
+        Some(pixmap) => {
+            // Centered in the tile
+            let width = (pixmap.width() as u16).min(image.width);
+            let height = (pixmap.height().div_ceil(2) as u16).min(image.height);
+            let centered = Rect::new(
+                image.x + (image.width - width) / 2,
+                image.y + (image.height - height) / 2,
+                width,
+                height,
+            );
+            paint(pixmap, (0, 0), centered, buf);
+        }
+        None => {
+            let middle = Rect::new(image.x, image.y + image.height / 2, image.width, 1);
+            Paragraph::new("not rendered")
+                .style(Style::default().fg(Color::DarkGray))
+                .alignment(Alignment::Center)
+                .render(middle, buf);
+        }
+    }
+}
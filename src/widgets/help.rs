@@ -0,0 +1,73 @@
+use super::centered_rect;
+use crate::keymap::{Focus, KeyMap};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph, Row, Table, Widget};
+
+/// Modal listing every binding of the key map; any key closes it.
+pub struct HelpOverlay<'a> {
+    pub keymap: &'a KeyMap,
+}
+
+impl Widget for HelpOverlay<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let rows: Vec<Row> = self
+            .keymap
+            .bindings()
+            .iter()
+            .map(|binding| {
+                let context = match binding.focus {
+                    None => "",
+                    Some(Focus::Input) => "filename",
+                    Some(Focus::Table) => "table",
+                    Some(Focus::Preview) => "preview",
+                    Some(Focus::Tree) => "files",
+                    Some(Focus::Gallery) => "gallery",
+                    Some(Focus::Log) => "log",
+                };
+                Row::new(vec![
+                    binding.label(),
+                    context.to_string(),
+                    binding.action.description().to_string(),
+                ])
+            })
+            .collect();
+
+        let height = rows.len() as u16 + 4; // Header, borders
+        let popup = centered_rect(68, height, area);
+        Clear.render(popup, buf);
+        let header = Row::new(vec!["Key", "Where", "Action"])
+            .style(Style::default().add_modifier(Modifier::BOLD));
+        Table::new(
+            rows,
+            [
+                Constraint::Length(12),
+                Constraint::Length(9),
+                Constraint::Min(10),
+            ],
+        )
+        .header(header)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow))
+                .title("Keys"),
+        )
+        .render(popup, buf);
+    }
+}
+
+/// One-line bar listing the shortcuts that work in `focus`.
+pub fn hint_bar(keymap: &KeyMap, focus: Focus) -> Paragraph<'static> {
+    let mut spans = Vec::new();
+    for (key, hint) in keymap.hints(focus) {
+        spans.push(Span::styled(
+            key,
+            Style::default().fg(Color::Black).bg(Color::Gray),
+        ));
+        spans.push(Span::raw(format!(" {}  ", hint)));
+    }
+    Paragraph::new(Line::from(spans))
+}
@@ -0,0 +1,371 @@
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
+use regex::{Captures, Regex};
+use std::collections::HashSet;
+
+// Command -> character, for the approximation
+const SYMBOLS: [(&str, &str); 62] = [
+    ("alpha", "α"),
+    ("beta", "β"),
+    ("gamma", "γ"),
+    ("delta", "δ"),
+    ("epsilon", "ε"),
+    ("varepsilon", "ε"),
+    ("zeta", "ζ"),
+    ("eta", "η"),
+    ("theta", "θ"),
+    ("iota", "ι"),
+    ("kappa", "κ"),
+    ("lambda", "λ"),
+    ("mu", "μ"),
+    ("nu", "ν"),
+    ("xi", "ξ"),
+    ("pi", "π"),
+    ("rho", "ρ"),
+    ("sigma", "σ"),
+    ("tau", "τ"),
+    ("phi", "φ"),
+    ("varphi", "φ"),
+    ("chi", "χ"),
+    ("psi", "ψ"),
+    ("omega", "ω"),
+    ("Gamma", "Γ"),
+    ("Delta", "Δ"),
+    ("Theta", "Θ"),
+    ("Lambda", "Λ"),
+    ("Xi", "Ξ"),
+    ("Pi", "Π"),
+    ("Sigma", "Σ"),
+    ("Phi", "Φ"),
+    ("Psi", "Ψ"),
+    ("Omega", "Ω"),
+    ("cdot", "·"),
+    ("times", "×"),
+    ("div", "÷"),
+    ("pm", "±"),
+    ("mp", "∓"),
+    ("le", "≤"),
+    ("leq", "≤"),
+    ("ge", "≥"),
+    ("geq", "≥"),
+    ("neq", "≠"),
+    ("ne", "≠"),
+    ("approx", "≈"),
+    ("equiv", "≡"),
+    ("infty", "∞"),
+    ("sum", "∑"),
+    ("prod", "∏"),
+    ("int", "∫"),
+    ("oint", "∮"),
+    ("partial", "∂"),
+    ("nabla", "∇"),
+    ("to", "→"),
+    ("rightarrow", "→"),
+    ("leftarrow", "←"),
+    ("Rightarrow", "⇒"),
+    ("in", "∈"),
+    ("forall", "∀"),
+    ("exists", "∃"),
+    ("sqrt", "√"),
+];
+
+const SUPERSCRIPTS: [(char, char); 14] = [
+    ('0', '⁰'),
+    ('1', '¹'),
+    ('2', '²'),
+    ('3', '³'),
+    ('4', '⁴'),
+    ('5', '⁵'),
+    ('6', '⁶'),
+    ('7', '⁷'),
+    ('8', '⁸'),
+    ('9', '⁹'),
+    ('+', '⁺'),
+    ('-', '⁻'),
+    ('n', 'ⁿ'),
+    ('i', 'ⁱ'),
+];
+
+const SUBSCRIPTS: [(char, char); 12] = [
+    ('0', '₀'),
+    ('1', '₁'),
+    ('2', '₂'),
+    ('3', '₃'),
+    ('4', '₄'),
+    ('5', '₅'),
+    ('6', '₆'),
+    ('7', '₇'),
+    ('8', '₈'),
+    ('9', '₉'),
+    ('+', '₊'),
+    ('-', '₋'),
+];
+
+/// The body with TeX syntax colored: commands, groups, scripts, operators,
+/// alignment and comments, with braces that don't pair up marked.
+pub fn latex_source(body: &str) -> Paragraph<'static> {
+    Paragraph::new(highlight_latex(body))
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title("LaTeX"))
+}
+
+/// `body` as colored lines, one per line of the body. A `{` that is never
+/// closed, or a `}` that closes nothing, shows in red.
+pub fn highlight_latex(body: &str) -> Vec<Line<'static>> {
+    let unmatched = unmatched_braces(body);
+    let error = Style::default().fg(Color::White).bg(Color::Red);
+    let mut offset = 0;
+    body.split('\n')
+        .map(|line| {
+            let chars: Vec<char> = line.chars().collect();
+            let spans: Vec<Span> = tokens(&chars)
+                .into_iter()
+                .map(|(start, len, style)| {
+                    let text: String = chars[start..start + len].iter().collect();
+                    let style = if unmatched.contains(&(offset + start)) {
+                        error
+                    } else {
+                        style
+                    };
+                    Span::styled(text, style)
+                })
+                .collect();
+            offset += chars.len() + 1;
+            Line::from(spans)
+        })
+        .collect()
+}
+
+/// `body` on one line, colored as `highlight_latex` does, then wrapped to
+/// `width` columns between TeX tokens, preferably outside groups. Past
+/// `max_lines` it is cut short with `…`; the flag says whether it was.
+pub fn wrap_latex(body: &str, width: usize, max_lines: usize) -> (Vec<Line<'static>>, bool) {
+    let flat = body
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut styled: Vec<(char, Style)> = Vec::new();
+    for span in highlight_latex(&flat).pop().unwrap_or_default().spans {
+        styled.extend(span.content.chars().map(|c| (c, span.style)));
+    }
+    let chars: Vec<char> = styled.iter().map(|&(c, _)| c).collect();
+    let width = width.max(2);
+
+    // Where a line may start, and whether that's outside every group
+    let mut breaks = Vec::new();
+    let mut depth = 0usize;
+    let mut escaped = false;
+    for (i, &c) in chars.iter().enumerate() {
+        let after_space = i > 0 && matches!(chars[i - 1], ' ' | ',');
+        let before_token = i > 0
+            && !escaped
+            && matches!(c, '\\' | '=' | '+' | '-' | '<' | '>')
+            && chars[i - 1] != ' ';
+        if after_space || before_token {
+            breaks.push((i, depth == 0));
+        }
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        while chars.get(start) == Some(&' ') {
+            start += 1;
+        }
+        if start >= chars.len() {
+            break;
+        }
+        let end = if chars.len() - start <= width {
+            chars.len()
+        } else {
+            let fits = |&&(i, _): &&(usize, bool)| i > start && i <= start + width;
+            let top = breaks.iter().filter(fits).rfind(|(_, top)| *top);
+            let any = breaks.iter().rfind(fits);
+            // Outside groups, unless that leaves the line mostly empty
+            match (top, any) {
+                (Some(&(top, _)), _) if top - start >= width / 2 => top,
+                (_, Some(&(any, _))) => any,
+                _ => start + width,
+            }
+        };
+        lines.push(start..end);
+        start = end;
+    }
+
+    let truncated = lines.len() > max_lines;
+    lines.truncate(max_lines.max(1));
+    let count = lines.len();
+    let lines = lines
+        .into_iter()
+        .enumerate()
+        .map(|(n, range)| {
+            let mut range = range;
+            if truncated && n + 1 == count {
+                range.end = range.end.min(range.start + width - 1);
+            }
+            let mut spans: Vec<Span> = Vec::new();
+            for &(c, style) in &styled[range] {
+                match spans.last_mut() {
+                    Some(span) if span.style == style => span.content.to_mut().push(c),
+                    _ => spans.push(Span::styled(c.to_string(), style)),
+                }
+            }
+            if truncated && n + 1 == count {
+                spans.push(Span::styled("…", Style::default().fg(Color::DarkGray)));
+            }
+            Line::from(spans)
+        })
+        .collect();
+    (lines, truncated)
+}
+
+/// Colors the text already drawn into `area` as LaTeX, row by row, for
+/// widgets that draw their own text such as the text editor.
+pub fn highlight_area(buf: &mut Buffer, area: Rect) {
+    for y in area.top()..area.bottom() {
+        // Cells with their characters; the second half of a wide character
+        // has an empty symbol
+        let mut cells = Vec::new();
+        let mut chars = Vec::new();
+        for x in area.left()..area.right() {
+            if let Some(c) = buf[(x, y)].symbol().chars().next() {
+                cells.push(x);
+                chars.push(c);
+            }
+        }
+        for (start, len, style) in tokens(&chars) {
+            for &x in &cells[start..start + len] {
+                buf[(x, y)].set_style(style);
+            }
+        }
+    }
+}
+
+// Runs of `line` by what they are in TeX: (start, length, style)
+fn tokens(line: &[char]) -> Vec<(usize, usize, Style)> {
+    let command = Style::default().fg(Color::Cyan);
+    let group = Style::default().fg(Color::DarkGray);
+    let script = Style::default().fg(Color::Magenta);
+    let operator = Style::default().fg(Color::Green);
+    let align = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+    let number = Style::default().fg(Color::LightBlue);
+
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < line.len() {
+        let (len, style) = match line[i] {
+            '%' => (line.len() - i, Style::default().fg(Color::DarkGray)),
+            '\\' => {
+                let letters = line[i + 1..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_alphabetic())
+                    .count();
+                match letters {
+                    0 if line.get(i + 1) == Some(&'\\') => (2, align),
+                    0 => (2.min(line.len() - i), command),
+                    _ => (letters + 1, command),
+                }
+            }
+            '{' | '}' | '[' | ']' => (1, group),
+            '^' | '_' => (1, script),
+            '=' | '+' | '-' | '<' | '>' | '*' | '/' | '|' | '!' => (1, operator),
+            '&' => (1, align),
+            c if c.is_ascii_digit() => (1, number),
+            _ => (1, Style::default()),
+        };
+        tokens.push((i, len, style));
+        i += len;
+    }
+    tokens
+}
+
+// Character offsets of the braces in `body` without a partner; escaped
+// braces and comments don't count
+fn unmatched_braces(body: &str) -> HashSet<usize> {
+    let mut unmatched = HashSet::new();
+    let mut open = Vec::new();
+    let mut chars = body.chars().enumerate();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '%' => {
+                for (_, c) in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '{' => open.push(i),
+            '}' if open.pop().is_none() => {
+                unmatched.insert(i);
+            }
+            _ => {}
+        }
+    }
+    unmatched.extend(open);
+    unmatched
+}
+
+/// A rough plain-text rendering of `body`: Greek letters and common
+/// operators as Unicode, simple scripts raised or lowered, fractions as
+/// `(a)/(b)` and the remaining markup dropped. Good enough to recognize an
+/// equation that hasn't been rendered yet.
+pub fn unicode_approximation(body: &str) -> String {
+    let frac = Regex::new(r"\\[dt]?frac\{([^{}]*)\}\{([^{}]*)\}").unwrap();
+    let command = Regex::new(r"\\([A-Za-z]+) ?").unwrap();
+    let script = Regex::new(r"([\^_])(?:\{([^{}]*)\}|(.))").unwrap();
+
+    let mut text = body.replace(r"\\", "\n").replace('&', "");
+    // Innermost fractions first
+    loop {
+        let next = frac.replace_all(&text, "($1)/($2)").into_owned();
+        if next == text {
+            break;
+        }
+        text = next;
+    }
+    let text = command.replace_all(&text, |cap: &Captures| {
+        SYMBOLS
+            .iter()
+            .find(|(name, _)| *name == &cap[1])
+            .map_or(String::new(), |(_, symbol)| symbol.to_string())
+    });
+    let text = script.replace_all(&text, |cap: &Captures| {
+        let content = cap.get(2).or(cap.get(3)).map_or("", |m| m.as_str());
+        let table: &[(char, char)] = match &cap[1] {
+            "^" => &SUPERSCRIPTS,
+            _ => &SUBSCRIPTS,
+        };
+        let mapped: Option<String> = content
+            .chars()
+            .map(|c| table.iter().find(|(from, _)| *from == c).map(|(_, to)| *to))
+            .collect();
+        mapped.unwrap_or_else(|| format!("{}({})", &cap[1], content))
+    });
+    let text = text
+        .replace(['{', '}'], "")
+        .replace(r"\,", " ")
+        .replace(r"\;", " ")
+        .replace('\\', "");
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,187 @@
+use crate::logging::LogEntry;
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, StatefulWidget, Widget};
+use tracing::Level;
+use tui_textarea::{Input, Key, TextArea};
+
+/// Scroll position and search of the session log pane.
+#[derive(Default)]
+pub struct LogState {
+    offset: usize,                     // Lines scrolled up from the newest
+    query: String,                     // Entries shown contain it, any case
+    prompt: Option<TextArea<'static>>, // Open search prompt
+    note: Option<String>,              // Outcome of the last export
+}
+
+impl LogState {
+    pub fn scroll(&mut self, delta: isize) {
+        // Up is older, which is further from the bottom
+        self.offset = self.offset.saturating_add_signed(-delta);
+    }
+
+    pub fn searching(&self) -> bool {
+        self.prompt.is_some()
+    }
+
+    pub fn open_search(&mut self) {
+        let mut prompt = TextArea::new(vec![self.query.clone()]);
+        prompt.set_cursor_line_style(Style::default());
+        prompt.move_cursor(tui_textarea::CursorMove::End);
+        self.prompt = Some(prompt);
+    }
+
+    /// Narrows the log as the query is typed. Enter keeps the query, Esc
+    /// drops it.
+    pub fn handle_search_input(&mut self, input: Input) {
+        let Some(prompt) = self.prompt.as_mut() else {
+            return;
+        };
+        match input.key {
+            Key::Enter => self.prompt = None,
+            Key::Esc => {
+                self.prompt = None;
+                self.query.clear();
+            }
+            _ => {
+                prompt.input(input);
+                self.query = prompt.lines()[0].clone();
+            }
+        }
+        self.offset = 0;
+    }
+
+    pub fn set_note(&mut self, note: String) {
+        self.note = Some(note);
+    }
+}
+
+/// The session log under the content area, newest entries at the bottom.
+pub struct LogPane<'a> {
+    pub entries: &'a [LogEntry],
+    pub focused: bool,
+}
+
+impl StatefulWidget for LogPane<'_> {
+    type State = LogState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut LogState) {
+        let query = state.query.to_lowercase();
+        let shown: Vec<&LogEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| query.is_empty() || entry.to_string().to_lowercase().contains(&query))
+            .collect();
+        let mut title = if query.is_empty() {
+            format!("Session log ({})", self.entries.len())
+        } else {
+            format!(
+                "Session log ({} of {} matching \"{}\")",
+                shown.len(),
+                self.entries.len(),
+                state.query
+            )
+        };
+        if let Some(note) = &state.note {
+            title.push_str(&format!(" - {}", note));
+        }
+        let mut block = Block::default().borders(Borders::ALL).title(title);
+        if self.focused {
+            block = block.border_style(Style::default().fg(Color::Cyan));
+        }
+        let mut inner = block.inner(area);
+        block.render(area, buf);
+
+        if let Some(prompt) = &state.prompt {
+            let line = Rect::new(inner.x, inner.bottom().saturating_sub(1), inner.width, 1);
+            Span::raw("/").render(line, buf);
+            let field = Rect::new(
+                line.x + 1,
+                line.y,
+                line.width.saturating_sub(1),
+                line.height,
+            );
+            prompt.render(field, buf);
+            inner.height = inner.height.saturating_sub(1);
+        }
+
+        let lines: Vec<Line> = shown
+            .iter()
+            .flat_map(|entry| entry_lines(entry, &query))
+            .collect();
+        let height = inner.height as usize;
+        state.offset = state.offset.min(lines.len().saturating_sub(height));
+        let end = lines.len() - state.offset;
+        let start = end.saturating_sub(height);
+        Paragraph::new(lines[start..end].to_vec()).render(inner, buf);
+    }
+}
+
+// The entry's lines, continuation lines indented under its message, with
+// `query` (already lowercase) highlighted
+fn entry_lines(entry: &LogEntry, query: &str) -> Vec<Line<'static>> {
+    let level_style = match entry.level {
+        Level::ERROR => Style::default().fg(Color::Red),
+        Level::WARN => Style::default().fg(Color::Yellow),
+        Level::INFO => Style::default().fg(Color::Green),
+        _ => Style::default().fg(Color::DarkGray),
+    };
+    let mut lines = Vec::new();
+    for (i, text) in entry.message.split('\n').enumerate() {
+        let mut spans = if i == 0 {
+            vec![
+                Span::styled(
+                    format!("[{:>7.1}s] ", entry.elapsed.as_secs_f64()),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::styled(format!("{:<5} ", entry.level), level_style),
+            ]
+        } else {
+            vec![Span::raw(" ".repeat(17))]
+        };
+        spans.extend(highlighted(text, query));
+        lines.push(Line::from(spans));
+    }
+    lines
+}
+
+fn highlighted(text: &str, query: &str) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let (lower, origins) = lowercased(text);
+    let mut shown = 0; // Where in `text` the next span starts
+    let mut from = 0; // Where in `lower` to look next
+    while let Some(found) = lower[from..].find(query).filter(|_| !query.is_empty()) {
+        let (start, end) = (from + found, from + found + query.len());
+        // Only whole characters of `text` are highlighted
+        let whole = |at: usize| at == 0 || origins[at] != origins[at - 1];
+        if whole(start) && whole(end) {
+            spans.push(Span::raw(text[shown..origins[start]].to_string()));
+            spans.push(Span::styled(
+                text[origins[start]..origins[end]].to_string(),
+                Style::default().add_modifier(Modifier::REVERSED),
+            ));
+            shown = origins[end];
+            from = end;
+        } else {
+            from = start + lower[start..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    spans.push(Span::raw(text[shown..].to_string()));
+    spans
+}
+
+// `text` lowercased, with the offset in `text` of the character each byte
+// came from, and one more for the end
+fn lowercased(text: &str) -> (String, Vec<usize>) {
+    let mut lower = String::with_capacity(text.len());
+    let mut origins = Vec::with_capacity(text.len() + 1);
+    for (at, c) in text.char_indices() {
+        let before = lower.len();
+        lower.extend(c.to_lowercase());
+        origins.resize(origins.len() + lower.len() - before, at);
+    }
+    origins.push(text.len());
+    (lower, origins)
+}
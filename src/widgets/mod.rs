@@ -0,0 +1,52 @@
+mod bookmarks;
+mod confirm;
+mod equations;
+mod error;
+mod failures;
+mod gallery;
+mod help;
+mod latex;
+mod log;
+mod new_equation;
+mod overwrite;
+mod palette;
+mod picker;
+mod preview;
+mod search;
+mod status;
+mod tree;
+
+pub use bookmarks::{BookmarkOutcome, BookmarkScreen};
+pub use confirm::ConfirmDialog;
+pub use equations::{
+    body_truncated, equation_table, grouped_view, sorted_view, source_context, SortOrder, TableRow,
+    ViewRow,
+};
+pub use error::{ErrorReport, ErrorScreen};
+pub use failures::FailuresPanel;
+pub use gallery::{GalleryItem, GalleryState, ThumbnailGrid};
+pub use help::{hint_bar, HelpOverlay};
+pub use latex::{highlight_area, highlight_latex, latex_source, unicode_approximation, wrap_latex};
+pub use log::{LogPane, LogState};
+pub use new_equation::{NewEquationForm, NewEquationOutcome};
+pub use overwrite::{OverwriteDialog, OverwriteOutcome};
+pub use palette::{CommandPalette, PaletteOutcome};
+pub use picker::{ListPicker, PickerOutcome};
+pub use preview::{PreviewPane, PreviewState};
+pub use search::{SearchOutcome, SearchScreen};
+pub use status::StatusLine;
+pub use tree::{FileTree, FileTreeView};
+
+use ratatui::layout::Rect;
+
+/// Centers a `width` x `height` box inside `area`, shrinking it to fit.
+pub fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
@@ -0,0 +1,152 @@
+use super::{centered_rect, highlight_area};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Layout, Margin, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Clear, Widget};
+use simptui::balance;
+use tui_textarea::{Input, Key, TextArea};
+
+pub enum NewEquationOutcome {
+    Open,
+    Submit { name: String, body: String }, // Ctrl-S; the form stays open until closed
+    PickSnippet,                           // Ctrl-T; answered with `insert_snippet`
+    Cancel,
+}
+
+/// Popup with a name field above a multi-line body. Tab switches fields,
+/// Ctrl-T asks for a snippet, Ctrl-S submits and Esc cancels. Whoever checks the submission reports
+/// problems back with `set_error`. Under the body, a status line keeps up
+/// with unclosed braces and environments and the nesting depth at the cursor.
+pub struct NewEquationForm {
+    name: TextArea<'static>,
+    body: TextArea<'static>,
+    title: String, // Names the file the equation goes to
+    on_body: bool,
+    error: Option<String>,
+}
+
+impl NewEquationForm {
+    pub fn new(file_name: &str) -> Self {
+        let mut name = TextArea::default();
+        name.set_cursor_line_style(Style::default());
+        name.set_placeholder_text("Name, e.g. euler_identity");
+        let mut body = TextArea::default();
+        body.set_cursor_line_style(Style::default());
+        body.set_placeholder_text("LaTeX body, e.g. e^{i\\pi} + 1 = 0");
+        let mut form = NewEquationForm {
+            name,
+            body,
+            title: format!("New equation in {}", file_name),
+            on_body: false,
+            error: None,
+        };
+        form.update_blocks();
+        form
+    }
+
+    pub fn set_error(&mut self, error: Option<String>) {
+        self.error = error;
+        self.update_blocks();
+    }
+
+    /// Puts `body` into the body at the cursor.
+    pub fn insert_snippet(&mut self, body: &str) {
+        self.body.insert_str(body);
+        self.on_body = true;
+        self.update_blocks();
+    }
+
+    pub fn handle_input(&mut self, input: Input) -> NewEquationOutcome {
+        match input.key {
+            Key::Esc => return NewEquationOutcome::Cancel,
+            Key::Char('s') if input.ctrl => {
+                return NewEquationOutcome::Submit {
+                    name: self.name.lines()[0].trim().to_string(),
+                    body: self.body.lines().join("\n"),
+                }
+            }
+            Key::Char('t') if input.ctrl => return NewEquationOutcome::PickSnippet,
+            Key::Tab => {
+                self.on_body = !self.on_body;
+                self.update_blocks();
+            }
+            // Enter moves on from the one-line name
+            Key::Enter if !self.on_body => {
+                self.on_body = true;
+                self.update_blocks();
+            }
+            _ if self.on_body => {
+                self.body.input(input);
+                self.update_blocks();
+            }
+            _ => {
+                self.name.input(input);
+            }
+        }
+        NewEquationOutcome::Open
+    }
+
+    // "balanced · depth 2" in green, or the first problem in red
+    fn balance_status(&self) -> Line<'static> {
+        let (row, col) = self.body.cursor();
+        let lines = self.body.lines();
+        let line = &lines[row];
+        let end = line.char_indices().nth(col).map_or(line.len(), |(i, _)| i);
+        let before = lines[..row]
+            .iter()
+            .map(String::as_str)
+            .chain([&line[..end]]);
+        let depth = balance(&before.collect::<Vec<_>>().join("\n")).depth();
+        match balance(&lines.join("\n")).problem() {
+            Some(problem) => Line::styled(
+                format!(" {} · depth {} ", problem, depth),
+                Style::default().fg(Color::LightRed),
+            ),
+            None => Line::styled(
+                format!(" balanced · depth {} ", depth),
+                Style::default().fg(Color::Green),
+            ),
+        }
+    }
+
+    // Highlights the focused field, puts the error, if any, in the title and
+    // the balance of the body under it
+    fn update_blocks(&mut self) {
+        let focused = Style::default().fg(Color::Cyan);
+        let title = self.error.as_ref().unwrap_or(&self.title).clone();
+        let mut name_block = Block::default().borders(Borders::ALL).title(title);
+        if self.error.is_some() {
+            name_block = name_block.title_style(Style::default().fg(Color::LightRed));
+        }
+        let mut body_block = Block::default()
+            .borders(Borders::ALL)
+            .title("Body (Tab switches, Ctrl-T snippets, Ctrl-S adds, Esc cancels)");
+        if !self.body.is_empty() {
+            body_block = body_block.title_bottom(self.balance_status());
+        }
+        if self.on_body {
+            body_block = body_block.border_style(focused);
+        } else {
+            name_block = name_block.border_style(focused);
+        }
+        self.name.set_block(name_block);
+        self.body.set_block(body_block);
+    }
+}
+
+impl Widget for &NewEquationForm {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let popup = centered_rect(70, 12, area);
+        let layout = Layout::default()
+            .constraints([Constraint::Length(3), Constraint::Min(3)])
+            .split(popup);
+        Clear.render(popup, buf);
+        self.name.render(layout[0], buf);
+        self.body.render(layout[1], buf);
+        // Not the placeholder, which isn't LaTeX the user wrote
+        if !self.body.is_empty() {
+            highlight_area(buf, layout[1].inner(Margin::new(1, 1)));
+        }
+    }
+}
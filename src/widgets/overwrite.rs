@@ -0,0 +1,117 @@
+use super::centered_rect;
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Alignment, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph, Widget};
+use tui_textarea::{Input, Key};
+
+const CHOICES: [&str; 3] = ["Overwrite", "Skip", "Backup (.bak)"];
+const MAX_LISTED: usize = 8; // Files listed before "and N more"
+
+pub enum OverwriteOutcome {
+    Open,
+    Overwrite, // Render over the changed files
+    Skip,      // Render everything else
+    Backup,    // Move them to `.bak` first, then render
+    Cancelled,
+}
+
+/// Modal listing outputs that were changed since they were rendered, asking
+/// what a render should do with them.
+pub struct OverwriteDialog {
+    files: Vec<String>,
+    selected: usize, // Index into `CHOICES`
+}
+
+impl OverwriteDialog {
+    pub fn new(files: Vec<String>) -> Self {
+        OverwriteDialog {
+            files,
+            selected: 1, // Skip, which loses nothing
+        }
+    }
+
+    pub fn handle_input(&mut self, input: Input) -> OverwriteOutcome {
+        let choice = match input.key {
+            Key::Char('o') | Key::Char('O') => 0,
+            Key::Char('s') | Key::Char('S') => 1,
+            Key::Char('b') | Key::Char('B') => 2,
+            Key::Enter => self.selected,
+            Key::Esc => return OverwriteOutcome::Cancelled,
+            Key::Left => {
+                self.selected = (self.selected + CHOICES.len() - 1) % CHOICES.len();
+                return OverwriteOutcome::Open;
+            }
+            Key::Right | Key::Tab => {
+                self.selected = (self.selected + 1) % CHOICES.len();
+                return OverwriteOutcome::Open;
+            }
+            _ => return OverwriteOutcome::Open,
+        };
+        match choice {
+            0 => OverwriteOutcome::Overwrite,
+            1 => OverwriteOutcome::Skip,
+            _ => OverwriteOutcome::Backup,
+        }
+    }
+}
+
+impl Widget for &OverwriteDialog {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut text = vec![
+            Line::from(format!(
+                "{} output(s) changed since they were rendered:",
+                self.files.len()
+            )),
+            Line::from(""),
+        ];
+        text.extend(self.files.iter().take(MAX_LISTED).map(|file| {
+            Line::styled(
+                format!("  {}", file),
+                Style::default().add_modifier(Modifier::BOLD),
+            )
+        }));
+        if self.files.len() > MAX_LISTED {
+            text.push(Line::from(format!(
+                "  and {} more",
+                self.files.len() - MAX_LISTED
+            )));
+        }
+        text.push(Line::from(""));
+        let mut buttons = Vec::new();
+        for (i, choice) in CHOICES.iter().enumerate() {
+            let style = if i == self.selected {
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            if i > 0 {
+                buttons.push(Span::raw("  "));
+            }
+            buttons.push(Span::styled(format!("[ {} ]", choice), style));
+        }
+        text.push(Line::from(buttons).alignment(Alignment::Center));
+
+        let width = self
+            .files
+            .iter()
+            .map(|file| file.len() as u16 + 6)
+            .max()
+            .unwrap_or(0)
+            .clamp(50, 72);
+        let popup = centered_rect(width, text.len() as u16 + 2, area);
+        Clear.render(popup, buf);
+        Paragraph::new(text)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Yellow))
+                    .title("Overwrite changed outputs?"),
+            )
+            .render(popup, buf);
+    }
+}
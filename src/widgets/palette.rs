@@ -0,0 +1,158 @@
+use super::centered_rect;
+use crate::keymap::{Action, KeyMap};
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, StatefulWidget, Widget};
+use tui_textarea::{Input, Key};
+
+pub enum PaletteOutcome {
+    Open,
+    Run(Action),
+    Close,
+}
+
+/// Modal list of the actions that make sense at the moment, narrowed as a query is typed: the query's
+/// letters must appear in order in the description or the hint bar's name
+/// for the action, and the closer they sit, the higher the action ranks.
+pub struct CommandPalette {
+    entries: Vec<(Action, String)>, // Actions and their first key, if any
+    query: String,
+    matches: Vec<usize>, // Indices into `entries`, best first
+    selected: usize,
+}
+
+impl CommandPalette {
+    /// Lists the actions `offered` takes, among those `Action::in_palette`.
+    pub fn new(keymap: &KeyMap, offered: impl Fn(Action) -> bool) -> Self {
+        let entries: Vec<(Action, String)> = Action::ALL
+            .into_iter()
+            .filter(|&action| action.in_palette() && offered(action))
+            .map(|action| (action, keymap.label(action).unwrap_or_default()))
+            .collect();
+        CommandPalette {
+            matches: (0..entries.len()).collect(),
+            entries,
+            query: String::new(),
+            selected: 0,
+        }
+    }
+
+    pub fn handle_input(&mut self, input: Input) -> PaletteOutcome {
+        match input.key {
+            Key::Esc => return PaletteOutcome::Close,
+            Key::Enter => {
+                return match self.matches.get(self.selected) {
+                    Some(&i) => PaletteOutcome::Run(self.entries[i].0),
+                    None => PaletteOutcome::Open,
+                }
+            }
+            Key::Up => self.selected = self.selected.saturating_sub(1),
+            Key::Down if self.selected + 1 < self.matches.len() => self.selected += 1,
+            Key::Backspace => {
+                self.query.pop();
+                self.narrow();
+            }
+            Key::Char(c) if !input.ctrl && !input.alt => {
+                self.query.push(c);
+                self.narrow();
+            }
+            _ => {}
+        }
+        PaletteOutcome::Open
+    }
+
+    fn narrow(&mut self) {
+        let mut scored: Vec<(usize, usize)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (action, _))| {
+                // The hint bar's word for it counts too
+                let gaps = [action.description(), action.hint()]
+                    .into_iter()
+                    .filter_map(|text| fuzzy_gaps(&self.query, text))
+                    .min()?;
+                Some((gaps, i))
+            })
+            .collect();
+        // Stable, so equally good matches keep the palette's order
+        scored.sort_by_key(|&(gaps, _)| gaps);
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = 0;
+    }
+}
+
+// Letters of `text` skipped between those matching `query`, any case, or
+// `None` when `query` isn't a subsequence of it. Spaces in the query match
+// anything.
+fn fuzzy_gaps(query: &str, text: &str) -> Option<usize> {
+    let mut gaps = 0;
+    let mut started = false;
+    let mut text = text.chars().flat_map(char::to_lowercase);
+    for wanted in query.chars().filter(|c| !c.is_whitespace()) {
+        let wanted = wanted.to_lowercase().next()?;
+        loop {
+            let c = text.next()?;
+            if c == wanted {
+                started = true;
+                break;
+            }
+            if started {
+                gaps += 1;
+            }
+        }
+    }
+    Some(gaps)
+}
+
+impl Widget for &CommandPalette {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let height = self.entries.len() as u16 + 3; // Query line, borders
+        let popup = centered_rect(64, height, area);
+        Clear.render(popup, buf);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan))
+            .title("Commands");
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+        if inner.height == 0 {
+            return;
+        }
+
+        let query = Line::from(vec![
+            Span::styled("> ", Style::default().fg(Color::Cyan)),
+            Span::raw(self.query.as_str()),
+            Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+        ]);
+        query.render(Rect { height: 1, ..inner }, buf);
+
+        let key_width = inner.width.saturating_sub(4) as usize;
+        let items: Vec<ListItem> = self
+            .matches
+            .iter()
+            .map(|&i| {
+                let (action, key) = &self.entries[i];
+                let description = action.description();
+                let padding = key_width.saturating_sub(description.len() + key.len());
+                ListItem::new(Line::from(vec![
+                    Span::raw(description),
+                    Span::raw(" ".repeat(padding)),
+                    Span::styled(key.as_str(), Style::default().fg(Color::DarkGray)),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        let rest = Rect {
+            y: inner.y + 1,
+            height: inner.height - 1,
+            ..inner
+        };
+        StatefulWidget::render(list, rest, buf, &mut state);
+    }
+}
@@ -0,0 +1,79 @@
+use super::centered_rect;
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, StatefulWidget, Widget};
+use tui_textarea::{Input, Key};
+
+pub enum PickerOutcome {
+    Open,
+    Picked(usize),
+    Cancelled,
+}
+
+/// Modal list to choose one entry from, navigated with Up/Down.
+pub struct ListPicker {
+    title: String,
+    items: Vec<String>,
+    selected: usize,
+}
+
+impl ListPicker {
+    pub fn new(title: &str, items: Vec<String>, selected: usize) -> Self {
+        ListPicker {
+            title: title.to_string(),
+            selected: selected.min(items.len().saturating_sub(1)),
+            items,
+        }
+    }
+
+    pub fn handle_input(&mut self, input: Input) -> PickerOutcome {
+        match input.key {
+            Key::Esc => PickerOutcome::Cancelled,
+            Key::Enter if !self.items.is_empty() => PickerOutcome::Picked(self.selected),
+            Key::Up => {
+                self.selected = self.selected.saturating_sub(1);
+                PickerOutcome::Open
+            }
+            Key::Down => {
+                if self.selected + 1 < self.items.len() {
+                    self.selected += 1;
+                }
+                PickerOutcome::Open
+            }
+            _ => PickerOutcome::Open,
+        }
+    }
+}
+
+impl Widget for &ListPicker {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let width = self
+            .items
+            .iter()
+            .map(|item| item.len() as u16 + 6)
+            .chain([self.title.len() as u16 + 4, 30])
+            .max()
+            .unwrap_or(30);
+        let popup = centered_rect(width, self.items.len() as u16 + 2, area);
+
+        let items: Vec<ListItem> = self
+            .items
+            .iter()
+            .map(|item| ListItem::new(item.as_str()))
+            .collect();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Cyan))
+                    .title(self.title.as_str()),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        Clear.render(popup, buf);
+        StatefulWidget::render(list, popup, buf, &mut state);
+    }
+}
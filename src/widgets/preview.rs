@@ -0,0 +1,215 @@
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph, StatefulWidget, Widget, Wrap};
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// Multiples of the size that fits the pane
+const ZOOM_LEVELS: [f32; 7] = [1.0, 1.5, 2.0, 3.0, 4.0, 6.0, 8.0];
+
+/// Zoom, pan and the last rasterization of the previewed SVG.
+#[derive(Default)]
+pub struct PreviewState {
+    path: Option<PathBuf>,
+    zoom: usize,     // Index into `ZOOM_LEVELS`
+    pan: (u16, u16), // Top-left cell of the visible part
+    raster: Option<Raster>,
+}
+
+struct Raster {
+    path: PathBuf,
+    modified: Option<SystemTime>, // Re-rasterize after a re-render
+    zoom: usize,
+    area: (u16, u16),
+    pixmap: Pixmap,
+}
+
+impl PreviewState {
+    /// Shows `path`, keeping zoom and pan only if it is the same file.
+    pub fn show(&mut self, path: Option<PathBuf>) {
+        if path != self.path {
+            self.path = path;
+            self.zoom = 0;
+            self.pan = (0, 0);
+        }
+    }
+
+    pub fn zoom_in(&mut self) {
+        self.zoom = (self.zoom + 1).min(ZOOM_LEVELS.len() - 1);
+    }
+
+    pub fn zoom_out(&mut self) {
+        self.zoom = self.zoom.saturating_sub(1);
+        if self.zoom == 0 {
+            self.pan = (0, 0);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.zoom = 0;
+        self.pan = (0, 0);
+    }
+
+    /// Moves the view by whole cells; clamped to the image when drawn.
+    pub fn pan(&mut self, dx: i16, dy: i16) {
+        self.pan = (
+            self.pan.0.saturating_add_signed(dx),
+            self.pan.1.saturating_add_signed(dy),
+        );
+    }
+
+    pub fn zoom_label(&self) -> String {
+        format!("{}x", ZOOM_LEVELS[self.zoom])
+    }
+
+    // Rasterizes at the current zoom unless the cached pixmap still fits
+    fn pixmap(&mut self, width: u16, height: u16) -> Result<&Pixmap, String> {
+        let path = self.path.clone().ok_or("Nothing selected")?;
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+        let fresh = self.raster.as_ref().is_some_and(|raster| {
+            raster.path == path
+                && raster.modified == modified
+                && raster.zoom == self.zoom
+                && raster.area == (width, height)
+        });
+        if !fresh {
+            let pixmap = rasterize(&path, width, height * 2, ZOOM_LEVELS[self.zoom])?;
+            self.raster = Some(Raster {
+                path,
+                modified,
+                zoom: self.zoom,
+                area: (width, height),
+                pixmap,
+            });
+        }
+        Ok(&self.raster.as_ref().unwrap().pixmap)
+    }
+}
+
+// Scales the SVG to fit `width` x `height` pixels, times `zoom`
+pub(super) fn rasterize(path: &Path, width: u16, height: u16, zoom: f32) -> Result<Pixmap, String> {
+    let data = fs::read(path).map_err(|_| {
+        format!(
+            "{} isn't rendered yet, Ctrl-R renders it",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        )
+    })?;
+    let tree = usvg::Tree::from_data(&data, &usvg::Options::default())
+        .map_err(|e| format!("Can't read {}: {}", path.display(), e))?;
+    let size = tree.size();
+    let fit = (width as f32 / size.width()).min(height as f32 / size.height());
+    let scale = fit * zoom;
+    let mut pixmap = Pixmap::new(
+        ((size.width() * scale).ceil() as u32).max(1),
+        ((size.height() * scale).ceil() as u32).max(1),
+    )
+    .ok_or("Image too large to preview")?;
+    resvg::render(
+        &tree,
+        Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    Ok(pixmap)
+}
+
+/// The rendered SVG of the selected equation, drawn with half-block
+/// characters (two pixels per cell) on a white background.
+pub struct PreviewPane<'a> {
+    pub focused: bool,
+    pub live: bool,                // A template is being watched
+    pub error: Option<&'a str>,    // Shown instead of the image
+    pub fallback: Option<&'a str>, // Text stand-in while there is no image
+}
+
+impl StatefulWidget for PreviewPane<'_> {
+    type State = PreviewState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut PreviewState) {
+        let live = if self.live { ", live template" } else { "" };
+        let mut block = Block::default().borders(Borders::ALL).title(format!(
+            "Preview ({}{})",
+            state.zoom_label(),
+            live
+        ));
+        if self.focused {
+            block = block.border_style(Style::default().fg(Color::Cyan));
+        }
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        if let Some(error) = self.error {
+            Paragraph::new(error)
+                .style(Style::default().fg(Color::LightRed))
+                .wrap(Wrap { trim: false })
+                .render(inner, buf);
+            return;
+        }
+
+        let (width, height) = (inner.width, inner.height);
+        let pan = state.pan;
+        let pixmap = match state.pixmap(width, height) {
+            Ok(pixmap) => pixmap,
+            Err(message) => {
+                let mut text = vec![Line::from(message)];
+                if let Some(fallback) = self.fallback {
+                    text.push(Line::from(""));
+                    text.extend(
+                        fallback
+                            .lines()
+                            .map(|line| Line::styled(line.to_string(), Style::default().bold())),
+                    );
+                }
+                Paragraph::new(text)
+                    .wrap(Wrap { trim: true })
+                    .render(inner, buf);
+                return;
+            }
+        };
+
+        // Keep the view on the image; the state learns the clamped pan
+        state.pan = paint(pixmap, pan, inner, buf);
+    }
+}
+
+/// Draws `pixmap` into `area` from cell `pan` on, two pixels per cell.
+/// Returns `pan` clamped so the view stays on the image.
+pub(super) fn paint(pixmap: &Pixmap, pan: (u16, u16), area: Rect, buf: &mut Buffer) -> (u16, u16) {
+    let cols = pixmap.width() as u16;
+    let rows = pixmap.height().div_ceil(2) as u16;
+    let pan = (
+        pan.0.min(cols.saturating_sub(area.width)),
+        pan.1.min(rows.saturating_sub(area.height)),
+    );
+    for y in 0..area.height.min(rows - pan.1) {
+        for x in 0..area.width.min(cols - pan.0) {
+            let px = (pan.0 + x) as u32;
+            let py = (pan.1 + y) as u32 * 2;
+            let top = on_white(pixmap, px, py);
+            let bottom = on_white(pixmap, px, py + 1);
+            buf[(area.x + x, area.y + y)]
+                .set_symbol("▀")
+                .set_fg(top)
+                .set_bg(bottom);
+        }
+    }
+    pan
+}
+
+// Blends a pixel onto white; rows past the bottom count as white
+fn on_white(pixmap: &Pixmap, x: u32, y: u32) -> Color {
+    let Some(pixel) = pixmap.pixel(x, y) else {
+        return Color::Rgb(255, 255, 255);
+    };
+    // Premultiplied, so the white shows through by the missing alpha
+    let white = 255 - pixel.alpha();
+    Color::Rgb(
+        pixel.red() + white,
+        pixel.green() + white,
+        pixel.blue() + white,
+    )
+}
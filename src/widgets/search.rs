@@ -0,0 +1,140 @@
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{
+    Block, Borders, Clear, List, ListItem, ListState, Paragraph, StatefulWidget, Widget,
+};
+use simptui::SearchHit;
+use tui_textarea::{Input, Key, TextArea};
+
+pub enum SearchOutcome {
+    Open,
+    Run(String),          // Query changed and Enter was pressed
+    Jump(Box<SearchHit>), // Enter on a result of the current query
+    Close,
+}
+
+/// Full-screen vault search: a query line above the list of matching
+/// equations. Enter runs the query, Enter again opens the highlighted hit.
+pub struct SearchScreen {
+    query: TextArea<'static>,
+    hits: Vec<SearchHit>,
+    selected: usize,
+    searched: Option<String>, // Query the current hits belong to
+    status: String,
+}
+
+impl SearchScreen {
+    pub fn new() -> Self {
+        let mut query = TextArea::default();
+        query.set_cursor_line_style(Style::default());
+        query.set_placeholder_text("LaTeX snippet to search for (prefix with re: for a regex)");
+        query.set_block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Search equations"),
+        );
+        SearchScreen {
+            query,
+            hits: Vec::new(),
+            selected: 0,
+            searched: None,
+            status: "Type a query and press Enter".to_string(),
+        }
+    }
+
+    pub fn set_results(&mut self, query: String, result: Result<Vec<SearchHit>, String>) {
+        self.selected = 0;
+        match result {
+            Ok(hits) => {
+                self.status = format!("{} matching equation(s)", hits.len());
+                self.hits = hits;
+            }
+            Err(e) => {
+                self.status = format!("Invalid query: {}", e);
+                self.hits.clear();
+            }
+        }
+        self.searched = Some(query);
+    }
+
+    pub fn handle_input(&mut self, input: Input) -> SearchOutcome {
+        match input.key {
+            Key::Esc => SearchOutcome::Close,
+            Key::Up => {
+                self.selected = self.selected.saturating_sub(1);
+                SearchOutcome::Open
+            }
+            Key::Down => {
+                if self.selected + 1 < self.hits.len() {
+                    self.selected += 1;
+                }
+                SearchOutcome::Open
+            }
+            Key::Enter => {
+                let query = self.query.lines()[0].trim().to_string();
+                if self.searched.as_deref() == Some(query.as_str()) {
+                    match self.hits.get(self.selected) {
+                        Some(hit) => SearchOutcome::Jump(Box::new(hit.clone())),
+                        None => SearchOutcome::Open,
+                    }
+                } else if query.is_empty() {
+                    SearchOutcome::Open
+                } else {
+                    SearchOutcome::Run(query)
+                }
+            }
+            _ => {
+                self.query.input(input);
+                SearchOutcome::Open
+            }
+        }
+    }
+}
+
+impl Widget for &SearchScreen {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let layout = Layout::default()
+            .constraints([Constraint::Length(3), Constraint::Min(1)])
+            .split(area);
+
+        Clear.render(area, buf);
+        self.query.render(layout[0], buf);
+
+        if self.hits.is_empty() {
+            Paragraph::new(self.status.as_str())
+                .block(Block::default().borders(Borders::ALL).title("Results"))
+                .render(layout[1], buf);
+            return;
+        }
+
+        let items: Vec<ListItem> = self
+            .hits
+            .iter()
+            .map(|hit| {
+                let line = hit.equation.span.map_or(0, |span| span.start_line);
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{}:{} ", hit.path.display(), line),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    Span::styled(
+                        format!("{} ", hit.equation.name),
+                        Style::default().fg(Color::Yellow),
+                    ),
+                    Span::raw(hit.equation.body.replace('\n', " ")),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("Results: {}", self.status)),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        StatefulWidget::render(list, layout[1], buf, &mut state);
+    }
+}
@@ -0,0 +1,70 @@
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Paragraph, Widget};
+use simptui::{Document, RenderReport};
+use std::path::Path;
+
+/// One line about the loaded file: its title and equations, how the last
+/// render of it went, and where the next one goes with which engine.
+pub struct StatusLine<'a> {
+    pub document: Option<&'a Document>,
+    pub report: Option<&'a RenderReport>, // Last batch render of `document`
+    pub backend: &'a str,                 // Engine and output format
+    pub output_dir: Option<&'a Path>,
+    pub rendering: bool, // A live render is still running
+}
+
+impl Widget for StatusLine<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let base = Style::default().fg(Color::Black).bg(Color::Gray);
+        let separator = || Span::styled(" │ ", base);
+        let Some(document) = self.document else {
+            Paragraph::new(Span::styled(" No file loaded", base))
+                .style(base)
+                .render(area, buf);
+            return;
+        };
+
+        let file = document.path.as_path();
+        let name = file.file_name().unwrap_or(file.as_os_str());
+        let equations = &document.equations;
+        let active = equations.iter().filter(|eq| eq.active).count();
+        let mut spans = vec![Span::styled(format!(" {}", name.to_string_lossy()), base)];
+        if let Some(title) = document.title() {
+            spans.push(Span::styled(format!(" ({})", title), base));
+        }
+        spans.push(separator());
+        spans.push(Span::styled(
+            format!("{} equations, {} active", equations.len(), active),
+            base,
+        ));
+        if let Some(report) = self.report {
+            let failed = report.failed.len();
+            let style = match failed {
+                0 => base,
+                _ => base.fg(Color::Red),
+            };
+            spans.push(Span::styled(format!(", {} failed", failed), style));
+            spans.push(separator());
+            spans.push(Span::styled(
+                format!("cache {}/{}", report.cache_hits, report.rendered.len()),
+                base,
+            ));
+        }
+        spans.push(separator());
+        spans.push(Span::styled(self.backend.to_string(), base));
+        if let Some(dir) = self.output_dir {
+            spans.push(separator());
+            spans.push(Span::styled(format!("→ {}", dir.display()), base));
+        }
+        if self.rendering {
+            spans.push(separator());
+            spans.push(Span::styled("rendering…", base.fg(Color::Blue)));
+        }
+        Paragraph::new(Line::from(spans))
+            .style(base)
+            .render(area, buf);
+    }
+}
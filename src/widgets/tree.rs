@@ -0,0 +1,267 @@
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, StatefulWidget};
+use simptui::detect_file_type;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// Directory tree of the scanned files under the scan roots. Directories
+/// are derived from the file paths, so empty ones don't show up.
+#[derive(Default)]
+pub struct FileTree {
+    roots: Vec<PathBuf>,
+    files: BTreeSet<PathBuf>,
+    expanded: HashSet<PathBuf>,
+    selected: Option<PathBuf>, // Kept by path so rows can come and go
+    types: HashMap<PathBuf, Option<&'static str>>, // Badges; None while detecting
+    detector: TypeDetector,
+}
+
+/// Detects file types on a thread of its own, so expanding a directory of
+/// big files doesn't hold up the interface.
+struct TypeDetector {
+    requests: Sender<PathBuf>,
+    results: Receiver<(PathBuf, &'static str)>,
+}
+
+impl Default for TypeDetector {
+    fn default() -> Self {
+        let (requests, pending) = mpsc::channel::<PathBuf>();
+        let (found, results) = mpsc::channel();
+        // Ends with the tree, when `requests` is dropped
+        thread::spawn(move || {
+            for path in pending {
+                let file_type = detect_file_type(&path);
+                if found.send((path, file_type)).is_err() {
+                    break;
+                }
+            }
+        });
+        TypeDetector { requests, results }
+    }
+}
+
+pub struct TreeRow {
+    pub path: PathBuf,
+    pub depth: usize,
+    pub is_dir: bool,
+}
+
+impl FileTree {
+    /// Starts with the roots expanded. Roots are made absolute, as the
+    /// indexer reports paths under the absolute roots.
+    pub fn new(roots: Vec<PathBuf>) -> Self {
+        let roots: Vec<PathBuf> = roots
+            .iter()
+            .map(|root| root.canonicalize().unwrap_or_else(|_| root.clone()))
+            .collect();
+        FileTree {
+            expanded: roots.iter().cloned().collect(),
+            selected: roots.first().cloned(),
+            roots,
+            ..FileTree::default()
+        }
+    }
+
+    pub fn insert(&mut self, path: PathBuf) {
+        self.types.remove(&path);
+        self.files.insert(path);
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.types.remove(path);
+        self.files.remove(path);
+    }
+
+    /// Takes in the file types detected since the last call. Returns
+    /// whether any badge changed.
+    pub fn poll_types(&mut self) -> bool {
+        let mut changed = false;
+        while let Ok((path, file_type)) = self.detector.results.try_recv() {
+            // Files changed or gone since are asked about again
+            if let Some(badge @ None) = self.types.get_mut(&path) {
+                *badge = Some(file_type);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    // The badge of `path`, asking for it the first time
+    fn file_type(&mut self, path: &Path) -> Option<&'static str> {
+        if let Some(&file_type) = self.types.get(path) {
+            return file_type;
+        }
+        self.types.insert(path.to_path_buf(), None);
+        self.detector.requests.send(path.to_path_buf()).ok();
+        None
+    }
+
+    /// The rows currently visible, depth first.
+    pub fn rows(&self) -> Vec<TreeRow> {
+        let mut rows = Vec::new();
+        for root in &self.roots {
+            rows.push(TreeRow {
+                path: root.clone(),
+                depth: 0,
+                is_dir: true,
+            });
+            if self.expanded.contains(root) {
+                self.push_children(root, 1, &mut rows);
+            }
+        }
+        rows
+    }
+
+    fn push_children(&self, dir: &Path, depth: usize, rows: &mut Vec<TreeRow>) {
+        let mut dirs = BTreeSet::new();
+        let mut files = Vec::new();
+        for path in self.files.iter().filter(|path| path.starts_with(dir)) {
+            let Ok(rest) = path.strip_prefix(dir) else {
+                continue;
+            };
+            let mut components = rest.components();
+            let Some(first) = components.next() else {
+                continue;
+            };
+            if components.next().is_some() {
+                dirs.insert(dir.join(first));
+            } else {
+                files.push(path.clone());
+            }
+        }
+        for sub in dirs {
+            let expanded = self.expanded.contains(&sub);
+            rows.push(TreeRow {
+                path: sub.clone(),
+                depth,
+                is_dir: true,
+            });
+            if expanded {
+                self.push_children(&sub, depth + 1, rows);
+            }
+        }
+        rows.extend(files.into_iter().map(|path| TreeRow {
+            path,
+            depth,
+            is_dir: false,
+        }));
+    }
+
+    fn selected_index(&self, rows: &[TreeRow]) -> usize {
+        self.selected
+            .as_ref()
+            .and_then(|selected| rows.iter().position(|row| row.path == *selected))
+            .unwrap_or(0)
+    }
+
+    pub fn selected(&self) -> Option<TreeRow> {
+        let mut rows = self.rows();
+        let i = self.selected_index(&rows);
+        (i < rows.len()).then(|| rows.swap_remove(i))
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        let rows = self.rows();
+        let last = rows.len().saturating_sub(1);
+        let i = self
+            .selected_index(&rows)
+            .saturating_add_signed(delta)
+            .min(last);
+        self.selected = rows.get(i).map(|row| row.path.clone());
+    }
+
+    /// Flips a directory open or closed. Returns the file to load when a
+    /// file is selected instead.
+    pub fn activate(&mut self) -> Option<PathBuf> {
+        let row = self.selected()?;
+        if !row.is_dir {
+            return Some(row.path);
+        }
+        if !self.expanded.remove(&row.path) {
+            self.expanded.insert(row.path);
+        }
+        None
+    }
+
+    pub fn expand(&mut self) {
+        if let Some(row) = self.selected().filter(|row| row.is_dir) {
+            self.expanded.insert(row.path);
+        }
+    }
+
+    /// Closes the selected directory, or moves to the parent of a file or
+    /// a closed directory.
+    pub fn collapse(&mut self) {
+        let Some(row) = self.selected() else {
+            return;
+        };
+        if row.is_dir && self.expanded.remove(&row.path) {
+            return;
+        }
+        if row.depth > 0 {
+            self.selected = row.path.parent().map(Path::to_path_buf);
+        }
+    }
+}
+
+/// The tree as a list: `▾`/`▸` for open/closed directories and a badge for
+/// the file types that have equations.
+pub struct FileTreeView {
+    pub focused: bool,
+}
+
+impl StatefulWidget for FileTreeView {
+    type State = FileTree;
+
+    fn render(self, area: Rect, buf: &mut Buffer, tree: &mut FileTree) {
+        let rows = tree.rows();
+        let items: Vec<ListItem> = rows
+            .iter()
+            .map(|row| {
+                let indent = "  ".repeat(row.depth);
+                let name = row.path.file_name().map_or_else(
+                    || row.path.display().to_string(),
+                    |name| name.to_string_lossy().into_owned(),
+                );
+                if row.is_dir {
+                    let marker = if tree.expanded.contains(&row.path) {
+                        "▾"
+                    } else {
+                        "▸"
+                    };
+                    return ListItem::new(format!("{}{} {}/", indent, marker, name));
+                }
+                let badge = match tree.file_type(&row.path).unwrap_or("unknown") {
+                    "markdown" => Span::styled("md  ", Style::default().fg(Color::Cyan)),
+                    "csv" => Span::styled("csv ", Style::default().fg(Color::Green)),
+                    "html" => Span::styled("html", Style::default().fg(Color::Magenta)),
+                    "org" => Span::styled("org ", Style::default().fg(Color::Yellow)),
+                    "notebook" => Span::styled("nb  ", Style::default().fg(Color::Blue)),
+                    "wiki" => Span::styled("wiki", Style::default().fg(Color::White)),
+                    "toml" => Span::styled("toml", Style::default().fg(Color::LightRed)),
+                    _ => Span::styled("    ", Style::default()),
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{}  ", indent)),
+                    badge,
+                    Span::raw(format!(" {}", name)),
+                ]))
+            })
+            .collect();
+
+        let mut block = Block::default().borders(Borders::ALL).title("Files");
+        if self.focused {
+            block = block.border_style(Style::default().fg(Color::Cyan));
+        }
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(Some(tree.selected_index(&rows)));
+        StatefulWidget::render(list, area, buf, &mut state);
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{slug, unique_names, DuplicateNames, Equation, SourceSpan};
+use regex::Regex;
+use std::ops::Range;
+
+// Wikitext where tags are text, not markup
+const VERBATIM: &str = r"(?is)<!--.*?-->|<nowiki>.*?</nowiki>|<pre\b[^>]*>.*?</pre>|<syntaxhighlight\b.*?</syntaxhighlight>|<source\b.*?</source>";
+// `<math>`, `<math display="block">`, `<math chem>` and `<chem>`/`<ce>`
+const MATH: &str =
+    r"(?is)<(?P<tag>math|chem|ce)\b(?P<attributes>[^>]*)>(?P<body>.*?)</(?:math|chem|ce)\s*>";
+const HEADING: &str = r"(?m)^=+[ \t]*(?P<title>[^=\n].*?)[ \t]*=+[ \t]*$";
+
+/// Equations in MediaWiki markup: `<math>` elements, and `<chem>` ones as
+/// mhchem formulas. Each is named after the section it is in. An XML dump
+/// is read page by page, the page title naming equations above the first
+/// heading and prefixing the sections below it.
+pub fn parse_wiki(content: &str) -> Vec<Equation> {
+    let mut equations = wiki_equations(content);
+    unique_names(&mut equations, DuplicateNames::Counter);
+    equations
+}
+
+// The equations with their names as written, repeats and all
+pub(crate) fn wiki_equations(content: &str) -> Vec<Equation> {
+    if !content.contains("<mediawiki") {
+        return wikitext_equations(content, None, 1);
+    }
+    let page = Regex::new(
+        r"(?s)<page>.*?<title>(?P<title>.*?)</title>.*?<text\b[^>]*?(?:/>|>(?P<text>.*?)</text>)",
+    )
+    .unwrap();
+    let mut equations = Vec::new();
+    let (mut line, mut line_offset) = (1, 0);
+    for cap in page.captures_iter(content) {
+        let Some(text) = cap.name("text") else {
+            continue; // Empty revision
+        };
+        line += content[line_offset..text.start()].matches('\n').count();
+        line_offset = text.start();
+        let first_line = line;
+        let title = unescape(&cap["title"]);
+        equations.extend(wikitext_equations(
+            &unescape(text.as_str()),
+            Some(&title),
+            first_line,
+        ));
+    }
+    equations
+}
+
+// `first_line` is the line of `text` in the file, for the spans
+fn wikitext_equations(text: &str, page: Option<&str>, first_line: usize) -> Vec<Equation> {
+    let verbatim: Vec<Range<usize>> = Regex::new(VERBATIM)
+        .unwrap()
+        .find_iter(text)
+        .map(|m| m.range())
+        .collect();
+    let quoted = |at: usize| verbatim.iter().any(|range| range.contains(&at));
+    let headings: Vec<(usize, String)> = Regex::new(HEADING)
+        .unwrap()
+        .captures_iter(text)
+        .filter(|cap| !quoted(cap.get(0).unwrap().start()))
+        .map(|cap| (cap.get(0).unwrap().start(), slug(&cap["title"])))
+        .collect();
+    let page = page.map(slug);
+    let mut line = first_line;
+    let mut line_offset = 0;
+    let mut line_at = |offset: usize| {
+        line += text[line_offset..offset].matches('\n').count();
+        line_offset = offset;
+        line
+    };
+
+    let mut equations = Vec::new();
+    for cap in Regex::new(MATH).unwrap().captures_iter(text) {
+        let whole = cap.get(0).unwrap();
+        let body = unescape(cap["body"].trim());
+        if quoted(whole.start()) || body.is_empty() {
+            continue;
+        }
+        let section = headings
+            .iter()
+            .take_while(|(at, _)| *at < whole.start())
+            .last()
+            .map(|(_, title)| title.as_str());
+        let name = match (page.as_deref(), section) {
+            (Some(page), Some(section)) => format!("{}_{}", page, section),
+            (None, Some(name)) | (Some(name), None) => name.to_string(),
+            (None, None) => "default_equation".to_string(),
+        };
+        let chem = !cap["tag"].eq_ignore_ascii_case("math")
+            || cap["attributes"].split_whitespace().any(|a| a == "chem");
+        let mut equation = if chem {
+            let mut equation = Equation::new(true, &name, &format!(r"\ce{{{}}}", body));
+            equation.packages.push("mhchem".to_string());
+            equation
+        } else {
+            Equation::new(true, &name, &body)
+        };
+        equation.span = Some(SourceSpan {
+            start_line: line_at(whole.start()),
+            end_line: line_at(whole.end()),
+        });
+        equations.push(equation);
+    }
+    equations
+}
+
+// The entities wikitext and XML dumps escape TeX with
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
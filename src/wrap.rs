@@ -0,0 +1,218 @@
+use crate::TEXT_COMMANDS;
+use regex::{Captures, Regex};
+use std::fmt;
+use std::str::FromStr;
+
+/// How an equation's body is put into math mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum MathWrap {
+    #[default]
+    Auto, // Decided by the body, see `unwrap_body`
+    Inline,  // `$ body $`
+    Display, // In display style, as between `\[ \]`
+    None,    // As written; the body switches to math mode itself
+}
+
+impl MathWrap {
+    /// What goes around the body in the TeX source. Display style stays in
+    /// `$ $`, as the equation is set in a box where `\[ \]` can't go.
+    pub fn delimiters(self) -> (&'static str, &'static str) {
+        match self {
+            MathWrap::None => ("", ""),
+            MathWrap::Display => (r"$\displaystyle ", " $"),
+            MathWrap::Auto | MathWrap::Inline => ("$ ", " $"),
+        }
+    }
+}
+
+impl FromStr for MathWrap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(MathWrap::Auto),
+            "inline" => Ok(MathWrap::Inline),
+            "display" => Ok(MathWrap::Display),
+            "none" => Ok(MathWrap::None),
+            _ => Err(format!(
+                "unknown wrap '{}', expected auto, inline, display or none",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for MathWrap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            MathWrap::Auto => "auto",
+            MathWrap::Inline => "inline",
+            MathWrap::Display => "display",
+            MathWrap::None => "none",
+        })
+    }
+}
+
+// Display environments, by their unstarred name
+const DISPLAY_ENVIRONMENTS: &str = "equation|align|flalign|alignat|gather|multline|eqnarray";
+
+/// `body` as it goes between the delimiters of the returned wrap, which is
+/// `wrap` with `Auto` decided. Unless the wrap is `None`:
+/// - `$$ $$`, `\[ \]`, `\( \)` or `$ $` around the whole body come off, the
+///   first two making `Auto` display style. A body with math of its own
+///   inside, like `$a$ and $b$`, is left to `None`; `$` within `\text{..}`
+///   doesn't count;
+/// - display environments (`align`, `gather`, ...) become their inline
+///   counterparts (`aligned`, `gathered`, ...), without the tagging commands
+///   that only work in display math. `environment` is the one a parser took
+///   off around the body, if any.
+pub fn unwrap_body(body: &str, environment: Option<&str>, wrap: MathWrap) -> (MathWrap, String) {
+    if wrap == MathWrap::None {
+        return (wrap, body.to_string());
+    }
+    if let Some(environment) = environment {
+        let wrap = match wrap {
+            MathWrap::Auto => MathWrap::Inline,
+            wrap => wrap,
+        };
+        return (wrap, in_environment(body, environment));
+    }
+
+    let (body, display) = match strip_delimiters(body.trim()) {
+        Some((inner, display)) => (inner, display),
+        None => (body, false),
+    };
+    let resolved = match wrap {
+        MathWrap::Auto if has_math_mode(body) => return (MathWrap::None, body.to_string()),
+        MathWrap::Auto if display => MathWrap::Display,
+        MathWrap::Auto => MathWrap::Inline,
+        wrap => wrap,
+    };
+
+    let environments = Regex::new(&format!(
+        r"\\(begin|end)\{{({})\*?\}}",
+        DISPLAY_ENVIRONMENTS
+    ))
+    .unwrap();
+    if !environments.is_match(body) {
+        return (resolved, body.to_string());
+    }
+    let inline =
+        environments.replace_all(body, |cap: &Captures| match inline_environment(&cap[2]) {
+            Some(inline) => format!(r"\{}{{{}}}", &cap[1], inline),
+            None => String::new(),
+        });
+    (resolved, strip_numbering(&inline).trim().to_string())
+}
+
+// The body of `environment` as it goes between `$ $`
+fn in_environment(body: &str, environment: &str) -> String {
+    let body = strip_numbering(body);
+    match inline_environment(environment.trim_end_matches('*')) {
+        Some(inline) => format!(r"\begin{{{}}}{}\end{{{}}}", inline, body, inline),
+        None => body.trim().to_string(),
+    }
+}
+
+// What stands in for a display environment inside `$ $`; None if the body
+// needs nothing around it
+fn inline_environment(environment: &str) -> Option<&'static str> {
+    match environment {
+        "align" | "flalign" | "eqnarray" => Some("aligned"),
+        "alignat" => Some("alignedat"), // The body starts with its `{n}`
+        "gather" | "multline" => Some("gathered"),
+        _ => None,
+    }
+}
+
+/// A body from `unwrap_body` with its environments named the way
+/// latex2mathml knows them: `align` for `aligned` and `matrix` for
+/// `gathered`. Err with the first environment it has no counterpart for.
+pub(crate) fn mathml_environments(body: &str) -> Result<String, String> {
+    let environments = Regex::new(r"\\(begin|end)\{([^}]*)\}").unwrap();
+    let mut unsupported = None;
+    let body = environments.replace_all(body, |cap: &Captures| {
+        let name = match &cap[2] {
+            "aligned" => "align",
+            "gathered" => "matrix",
+            name @ ("align" | "matrix" | "pmatrix" | "bmatrix" | "vmatrix") => name,
+            name => {
+                unsupported.get_or_insert_with(|| name.to_string());
+                name
+            }
+        };
+        format!(r"\{}{{{}}}", &cap[1], name)
+    });
+    match unsupported {
+        Some(name) => Err(name),
+        None => Ok(body.into_owned()),
+    }
+}
+
+fn strip_numbering(body: &str) -> String {
+    let numbering = Regex::new(r"\\tag\*?\{[^}]*\}|\\(notag|nonumber)\b").unwrap();
+    numbering.replace_all(body, "").into_owned()
+}
+
+// The body inside math delimiters around all of it, and whether they were
+// display ones
+fn strip_delimiters(body: &str) -> Option<(&str, bool)> {
+    let inside = |open: &str, close: &str| {
+        let inner = body.strip_prefix(open)?.strip_suffix(close)?;
+        // `$a$ + $b$` is two formulas, not one
+        (!inner.contains(open) && !inner.contains(close)).then_some(inner)
+    };
+    if let Some(inner) = inside("$$", "$$") {
+        return Some((inner, true));
+    }
+    if body.starts_with(r"\\") {
+        return None; // A line break, `\\[2pt]`
+    }
+    if let Some(inner) = inside(r"\[", r"\]") {
+        return Some((inner, true));
+    }
+    if let Some(inner) = inside(r"\(", r"\)") {
+        return Some((inner, false));
+    }
+    let inner = body.strip_prefix('$')?.strip_suffix('$')?;
+    (!inner.ends_with('\\') && !has_math_mode(inner)).then_some((inner, false))
+}
+
+// Unescaped `$` or `\(` in `body` outside text arguments: in
+// `\text{for $x>0$}` they go back into math rather than leave it
+fn has_math_mode(body: &str) -> bool {
+    let mut chars = body.chars().peekable();
+    let mut depth = 0usize;
+    let mut text_depth = None; // Brace depth where a text argument started
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let mut command = String::new();
+                while let Some(next) = chars.next_if(|next| next.is_ascii_alphabetic()) {
+                    command.push(next);
+                }
+                if command.is_empty() {
+                    if chars.next() == Some('(') && text_depth.is_none() {
+                        return true;
+                    }
+                } else if text_depth.is_none() && TEXT_COMMANDS.contains(&command.as_str()) {
+                    while chars.next_if(|next| next.is_whitespace()).is_some() {}
+                    if chars.peek() == Some(&'{') {
+                        text_depth = Some(depth);
+                    }
+                }
+            }
+            '{' => depth += 1,
+            '}' => {
+                depth = depth.saturating_sub(1);
+                if text_depth.is_some_and(|start| depth <= start) {
+                    text_depth = None;
+                }
+            }
+            '$' if text_depth.is_none() => return true,
+            _ => {}
+        }
+    }
+    false
+}
@@ -0,0 +1,83 @@
+use simptui::{load_equations, set_active_in_source, ExtractRule, Extractor, ParserRegistry};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+
+#[test]
+fn markdown_markers_are_replaced_or_added() {
+    let dir = tempfile::tempdir().unwrap();
+    let notes = dir.path().join("notes.md");
+    fs::write(
+        &notes,
+        "%%yes%%\n$$\na\n$$\n%%a%%\n\n$$\nb\n$$\n%%b%%\n\nSo $$c$$\n%%c%%\n",
+    )
+    .unwrap();
+    let equations = load_equations(&notes).unwrap();
+    set_active_in_source(&notes, &equations, false).unwrap();
+    assert_eq!(
+        fs::read_to_string(&notes).unwrap(),
+        "%%no%%\n$$\na\n$$\n%%a%%\n\n%%no%%\n$$\nb\n$$\n%%b%%\n\nSo %%no%%$$c$$\n%%c%%\n"
+    );
+    let equations = load_equations(&notes).unwrap();
+    assert!(equations.iter().all(|eq| !eq.active));
+
+    set_active_in_source(&notes, &equations[1..2], true).unwrap();
+    let active: Vec<bool> = load_equations(&notes)
+        .unwrap()
+        .iter()
+        .map(|eq| eq.active)
+        .collect();
+    assert_eq!(active, [false, true, false]);
+}
+
+#[test]
+fn csv_and_toml_entries_are_switched() {
+    let dir = tempfile::tempdir().unwrap();
+    let table = dir.path().join("equations.csv");
+    fs::write(&table, "active,body,name\nyes,\"a,b\",x\nyes,c,y\n").unwrap();
+    let equations = load_equations(&table).unwrap();
+    set_active_in_source(&table, &equations[..1], false).unwrap();
+    assert_eq!(
+        fs::read_to_string(&table).unwrap(),
+        "active,body,name\nno,\"a,b\",x\nyes,c,y\n"
+    );
+
+    let table = dir.path().join("equations.toml");
+    fs::write(
+        &table,
+        "[[equation]]\nname = \"a\"\nbody = 'x'\n\n[[equation]]\nname = \"b\"\nactive = false\nbody = 'y'\n",
+    )
+    .unwrap();
+    let equations = load_equations(&table).unwrap();
+    set_active_in_source(&table, &equations, false).unwrap();
+    let equations = load_equations(&table).unwrap();
+    set_active_in_source(&table, &equations[1..], true).unwrap();
+    assert_eq!(
+        fs::read_to_string(&table).unwrap(),
+        "[[equation]]\nactive = false\nname = \"a\"\nbody = 'x'\n\n[[equation]]\nname = \"b\"\nactive = true\nbody = 'y'\n"
+    );
+}
+
+#[test]
+fn equations_of_custom_rules_are_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let notes = dir.path().join("notes.md");
+    let content = "$$\na\n$$\n%%a%%\n\n[[math:b]] b [[/math]]\n";
+    fs::write(&notes, content).unwrap();
+    let rule: ExtractRule = toml::from_str(
+        "pattern = '\\[\\[math:(?P<name>\\w+)\\]\\](?P<body>.*?)\\[\\[/math\\]\\]'\nname = 'name'",
+    )
+    .unwrap();
+    let rules = BTreeMap::from([("wiki".to_string(), rule)]);
+    let parsers = ParserRegistry::with_extractor(Extractor::new(&rules).unwrap());
+    let equations = parsers.load_document(&notes).unwrap().equations;
+    assert_eq!(equations.len(), 2);
+
+    let error = set_active_in_source(&notes, &equations, false).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::Unsupported);
+    assert_eq!(fs::read_to_string(&notes).unwrap(), content);
+    set_active_in_source(&notes, &equations[..1], false).unwrap();
+    assert!(fs::read_to_string(&notes)
+        .unwrap()
+        .starts_with("%%no%%\n$$"));
+}
@@ -0,0 +1,45 @@
+use simptui::{balance, Opener};
+
+#[test]
+fn nested_groups_are_balanced() {
+    let body = "\\begin{aligned} \\left( \\frac{a}{b} \\right) &= \\{x\\} % }\n\\end{aligned}";
+    let result = balance(body);
+    assert!(result.is_balanced());
+    assert_eq!(result.depth(), 0);
+    assert_eq!(result.problem(), None);
+
+    let prefix = balance("\\begin{aligned} \\left( \\frac{a");
+    let groups: Vec<Opener> = prefix.open.into_iter().map(|(group, _)| group).collect();
+    assert_eq!(
+        groups,
+        vec![
+            Opener::Environment("aligned".to_string()),
+            Opener::Delimiter("(".to_string()),
+            Opener::Brace,
+        ]
+    );
+}
+
+#[test]
+fn reports_the_first_problem_with_its_line() {
+    let unclosed = balance("x\n\\begin{cases} a");
+    assert_eq!(
+        unclosed.open,
+        vec![(Opener::Environment("cases".to_string()), 2)]
+    );
+    assert_eq!(
+        unclosed.problem().unwrap(),
+        "\\begin{cases} on line 2 is never closed"
+    );
+
+    let mismatched = balance("\\begin{align} a \\\\\n\\end{gather}");
+    assert_eq!(
+        mismatched.problem().unwrap(),
+        "\\end{gather} on line 2 doesn't close \\begin{align}"
+    );
+
+    assert_eq!(
+        balance("a}{").problem().unwrap(),
+        "} on line 1 closes nothing"
+    );
+}
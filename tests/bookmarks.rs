@@ -0,0 +1,58 @@
+use simptui::{load_equations, Bookmarks, Config};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn bookmarks_are_kept_between_sessions() {
+    let dir = TempDir::new().unwrap();
+    let notes = dir.path().join("notes.md");
+    fs::write(&notes, "$$\na\n$$\n%%first%%\n\n$$\nb\n$$\n%%second%%\n").unwrap();
+    let equations = load_equations(&notes).unwrap();
+    let list = dir.path().join("state/bookmarks.json");
+
+    let mut bookmarks = Bookmarks::load(&list).unwrap();
+    assert!(bookmarks.is_empty());
+    assert!(bookmarks.toggle(&notes, &equations[1]).unwrap());
+    assert!(bookmarks.toggle(&notes, &equations[0]).unwrap());
+    assert!(!bookmarks.toggle(&notes, &equations[1]).unwrap());
+
+    let reloaded = Bookmarks::load(&list).unwrap();
+    let names: Vec<&str> = reloaded.list().iter().map(|b| b.name.as_str()).collect();
+    assert_eq!(names, ["first"]);
+    assert!(reloaded.contains(&notes, "first"));
+    assert!(!reloaded.contains(&notes, "second"));
+}
+
+#[test]
+fn bookmarked_equations_are_collected_from_their_files() {
+    let dir = TempDir::new().unwrap();
+    let mechanics = dir.path().join("mechanics.md");
+    let optics = dir.path().join("optics.md");
+    fs::write(
+        &mechanics,
+        "%%no%%\n$$\nF = ma\n$$\n%%law%%\n\n$$\nv = s/t\n$$\n%%speed%%\n\n$$\nx\n$$\n%%gone%%\n",
+    )
+    .unwrap();
+    fs::write(&optics, "$$\nn_1 \\sin a = n_2 \\sin b\n$$\n%%law%%\n").unwrap();
+    let mut bookmarks = Bookmarks::load(&dir.path().join("bookmarks.json")).unwrap();
+    for file in [&mechanics, &optics] {
+        for equation in load_equations(file).unwrap() {
+            bookmarks.toggle(file, &equation).unwrap();
+        }
+    }
+    // One renamed, one deleted since
+    fs::write(
+        &mechanics,
+        "$$\nF = ma\n$$\n%%law%%\n\n$$\nv = s/t\n$$\n%%velocity%%\n",
+    )
+    .unwrap();
+
+    let parsers = Config::default().parsers().unwrap();
+    let (equations, missing) = bookmarks.equations(&parsers);
+    let names: Vec<&str> = equations.iter().map(|eq| eq.name.as_str()).collect();
+    assert_eq!(names, ["mechanics_law", "velocity", "optics_law"]);
+    assert!(equations.iter().all(|eq| eq.active));
+    assert_eq!(equations[0].origin.as_deref(), Some("law"));
+    assert_eq!(missing.len(), 1);
+    assert!(missing[0].starts_with("gone ("), "{:?}", missing);
+}
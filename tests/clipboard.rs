@@ -0,0 +1,32 @@
+mod common;
+
+use common::MockBackend;
+use simptui::{render_png, Equation, RenderOptions, Rgb};
+
+// Width and height from the IHDR chunk
+fn png_size(png: &[u8]) -> (u32, u32) {
+    let word = |at: usize| u32::from_be_bytes(png[at..at + 4].try_into().unwrap());
+    (word(16), word(20))
+}
+
+#[test]
+fn equations_render_to_png_in_memory() {
+    let out = tempfile::tempdir().unwrap();
+    let mut options = RenderOptions::new(out.path(), "#000000");
+    options.cache_dir = None;
+    options.dpi = 192;
+    let equation = Equation::new(false, "square", "x^2");
+
+    let png = render_png(
+        &equation,
+        &options,
+        &MockBackend::new(),
+        Some(Rgb(255, 255, 255)),
+    )
+    .unwrap();
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+    // The mock's 10pt square at twice the CSS resolution
+    assert_eq!(png_size(&png), (27, 27));
+    // Rendered in a scratch directory, not the output directory
+    assert_eq!(out.path().read_dir().unwrap().count(), 0);
+}
@@ -0,0 +1,34 @@
+use simptui::{adjust_contrast, strip_colors, Fill, Rgb, MIN_CONTRAST};
+
+#[test]
+fn low_contrast_colors_are_adjusted() {
+    let white = Rgb(0xff, 0xff, 0xff);
+    assert!((Rgb(0, 0, 0).contrast(white) - 21.0).abs() < 1e-9);
+
+    let pale = Rgb(0xee, 0xee, 0xee);
+    let adjusted = adjust_contrast(pale, white, MIN_CONTRAST);
+    assert!(adjusted.contrast(white) >= MIN_CONTRAST);
+    // Only as dark as needed, not black
+    assert_ne!(adjusted, Rgb(0, 0, 0));
+}
+
+#[test]
+fn fills_are_transparent_or_hex() {
+    assert_eq!("transparent".parse(), Ok(Fill::Transparent));
+    assert_eq!("#1e1e2e".parse(), Ok(Fill::Color(Rgb(0x1e, 0x1e, 0x2e))));
+    assert!("dark".parse::<Fill>().is_err());
+}
+
+#[test]
+fn color_commands_are_stripped_but_their_contents_kept() {
+    assert_eq!(
+        strip_colors(r"a + \textcolor{red}{b^2} = \textcolor[HTML]{FF0000}c"),
+        r"a + {b^2} = c"
+    );
+    assert_eq!(
+        strip_colors(r"{\color{blue} x} + \colorbox{y}{z}"),
+        r"{ x} + \colorbox{y}{z}"
+    );
+    // An unbalanced color is left for TeX to complain about
+    assert_eq!(strip_colors(r"\color{red x"), r"\color{red x");
+}
@@ -0,0 +1,178 @@
+//! Shared by the integration tests: a render backend and a rendering
+//! service, neither of which needs TeX.
+#![allow(dead_code)] // Each test binary uses its own part
+
+use sha2::{Digest, Sha256};
+use simptui::{Engine, OutputFormat, RenderBackend, RenderRequest, RenderResponse};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// The short hash hashed output names carry, of a file's bytes.
+pub fn content_hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)[..3]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackendCall {
+    Compile(PathBuf, Engine),
+    Convert(PathBuf, OutputFormat),
+}
+
+/// Test double that records every call and writes placeholder outputs: the
+/// "PDF" echoes the `.tex` source and the SVG/PNG embed a marker. Compilation
+/// fails for sources containing one of the `failing` snippets.
+#[derive(Debug, Default)]
+pub struct MockBackend {
+    calls: Mutex<Vec<BackendCall>>,
+    timeouts: Mutex<Vec<Option<Duration>>>, // Given to each compile
+    failing: Vec<String>,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        MockBackend::default()
+    }
+
+    /// Makes compilation fail for any `.tex` containing `snippet`.
+    pub fn failing(mut self, snippet: &str) -> Self {
+        self.failing.push(snippet.to_string());
+        self
+    }
+
+    pub fn calls(&self) -> Vec<BackendCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// The timeout each compilation got, in order.
+    pub fn timeouts(&self) -> Vec<Option<Duration>> {
+        self.timeouts.lock().unwrap().clone()
+    }
+
+    pub fn compile_count(&self) -> usize {
+        self.calls()
+            .iter()
+            .filter(|call| matches!(call, BackendCall::Compile(..)))
+            .count()
+    }
+
+    fn record(&self, call: BackendCall) {
+        self.calls.lock().unwrap().push(call);
+    }
+}
+
+impl RenderBackend for MockBackend {
+    fn compile(
+        &self,
+        engine: Engine,
+        tex_file: &Path,
+        output_dir: &Path,
+        keep_logs: bool,
+        timeout: Option<Duration>,
+    ) -> io::Result<bool> {
+        self.record(BackendCall::Compile(tex_file.to_path_buf(), engine));
+        self.timeouts.lock().unwrap().push(timeout);
+        let source = fs::read_to_string(tex_file)?;
+        let stem = tex_file.file_stem().unwrap_or_default().to_string_lossy();
+        let output = |ext: &str| output_dir.join(format!("{}.{}", stem, ext));
+
+        if self.failing.iter().any(|snippet| source.contains(snippet)) {
+            fs::write(output("log"), "! Mock compilation failure.\n")?;
+            return Ok(false);
+        }
+        // Comment lines don't reach a real PDF either
+        let body: String = source
+            .lines()
+            .filter(|line| !line.starts_with('%'))
+            .map(|line| format!("{}\n", line))
+            .collect();
+        fs::write(output("pdf"), format!("%PDF-mock\n{}", body))?;
+        if keep_logs {
+            fs::write(output("log"), "SIMPTUI-DEPTH=2.5pt\n")?;
+        }
+        Ok(true)
+    }
+
+    fn convert(
+        &self,
+        pdf_file: &Path,
+        target: &Path,
+        format: OutputFormat,
+        _dpi: u32,
+    ) -> io::Result<()> {
+        self.record(BackendCall::Convert(pdf_file.to_path_buf(), format));
+        let marker = content_hash(&fs::read(pdf_file)?); // Differs per source, like real output
+        let content = match format {
+            OutputFormat::Png => format!("\u{89}PNG mock {}", marker),
+            _ => format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="10pt" height="10pt"><!-- mock {} --></svg>"#,
+                marker
+            ),
+        };
+        fs::write(target, content)
+    }
+}
+
+// Answers `requests` POSTs like a rendering service would, failing the
+// documents that contain `\broken`. Sends back each request's
+// Authorization header and body.
+pub fn serve(requests: usize) -> (String, Receiver<(String, RenderRequest)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/render", listener.local_addr().unwrap());
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming().take(requests) {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let (mut length, mut auth) = (0, String::new());
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                let (name, value) = line.split_once(": ").unwrap_or((line, ""));
+                match name.to_ascii_lowercase().as_str() {
+                    "content-length" => length = value.parse().unwrap(),
+                    "authorization" => auth = value.to_string(),
+                    _ => {}
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let request: RenderRequest = serde_json::from_slice(&body).unwrap();
+            let response = if request.document.contains("\\broken") {
+                RenderResponse {
+                    ok: false,
+                    svg: None,
+                    log: "! Undefined control sequence.\n".to_string(),
+                }
+            } else {
+                RenderResponse {
+                    ok: true,
+                    svg: Some("<svg xmlns=\"http://www.w3.org/2000/svg\"/>".to_string()),
+                    log: String::new(),
+                }
+            };
+            let json = serde_json::to_string(&response).unwrap();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                json.len(),
+                json
+            )
+            .unwrap();
+            sender.send((auth, request)).unwrap();
+        }
+    });
+    (url, receiver)
+}
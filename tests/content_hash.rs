@@ -0,0 +1,62 @@
+mod common;
+
+use common::serve;
+use simptui::{render_equations, Engine, Equation, FontSize, RemoteBackend, RenderOptions};
+use tempfile::TempDir;
+
+#[test]
+fn hash_follows_what_changes_the_output() {
+    let mut options = RenderOptions::new("out", "#000000");
+    options.engine = Engine::Pdflatex;
+    let hash = |equation: &Equation| equation.content_hash(&options).unwrap();
+    let sum = Equation::new(true, "sum", "a + b = c");
+
+    assert_eq!(hash(&sum).len(), 64);
+    // Neither the name nor the spacing of the body matters
+    assert_eq!(hash(&sum), hash(&Equation::new(false, "other", "a+b=c")));
+
+    let mut colored = sum.clone();
+    colored.color = Some("#ff0000".to_string());
+    let mut larger = sum.clone();
+    larger.size = Some(FontSize::Points(14.0));
+    let mut engine = sum.clone();
+    engine.engine = Some(Engine::Lualatex);
+    for changed in [colored, larger, engine] {
+        assert_ne!(hash(&sum), hash(&changed));
+    }
+    let mut sharper = options.clone();
+    sharper.dpi = 300;
+    assert_ne!(hash(&sum), sum.content_hash(&sharper).unwrap());
+}
+
+#[test]
+fn auto_hashes_as_the_engine_it_picks() {
+    let mut options = RenderOptions::new("out", "#000000");
+    let sum = Equation::new(true, "sum", "a + b = c");
+    let auto = sum.content_hash(&options);
+    match Engine::Auto.resolve() {
+        Ok(engine) => {
+            options.engine = engine;
+            assert_eq!(auto.unwrap(), sum.content_hash(&options).unwrap());
+        }
+        // Like `render_equations`, without TeX there's nothing to hash for
+        Err(_) => assert!(auto.is_err()),
+    }
+}
+
+#[test]
+fn hash_names_the_cached_output() {
+    let out = TempDir::new().unwrap();
+    let cache = TempDir::new().unwrap();
+    let mut options = RenderOptions::new(out.path(), "#000000");
+    options.cache_dir = Some(cache.path().to_path_buf());
+    // The service renders, so `render_equations` runs without TeX
+    let (url, _requests) = serve(1);
+    options.remote = Some(RemoteBackend::new(&url, None));
+    let equation = Equation::new(true, "sum", "a + b = c");
+
+    render_equations(std::slice::from_ref(&equation), &options).unwrap();
+
+    let expected = format!("{}.svg", equation.content_hash(&options).unwrap());
+    assert!(cache.path().join(expected).is_file());
+}
@@ -0,0 +1,92 @@
+use simptui::{export_csv, parse_csv, parse_markdown, read_csv_file, write_csv_file, Engine};
+
+const NOTES: &str = "\
+%%engine=lualatex packages=mhchem,siunitx%%
+$$
+\\ce{H2O} % water
+$$
+%%water%%
+
+%%no%%
+$$
+f(x, y) = \\text{\"x\"} + 10\\%
+$$
+%%pair%%
+";
+
+#[test]
+fn exported_equations_read_back_the_same() {
+    let equations = parse_markdown(NOTES);
+    let csv = export_csv(&equations);
+    let rows: Vec<&str> = csv.lines().collect();
+    assert_eq!(rows[0], "Active,Body,Name,Options,Line");
+    assert_eq!(
+        rows[1],
+        "yes,\\ce{H2O},water,\"engine=lualatex packages=mhchem,siunitx\",1"
+    );
+    assert_eq!(
+        rows[2],
+        "no,\"f(x, y) = \\text{\"\"x\"\"} + 10\\%\",pair,,7"
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("eqs.csv");
+    write_csv_file(&path, &equations).unwrap();
+    let read = read_csv_file(&path).unwrap();
+    assert_eq!(read.len(), 2);
+    assert_eq!(read[0].body, "\\ce{H2O}");
+    assert_eq!(read[0].engine, Some(Engine::Lualatex));
+    assert_eq!(read[0].packages, ["mhchem", "siunitx"]);
+    assert_eq!(read[1].body, "f(x, y) = \\text{\"x\"} + 10\\%");
+    assert!(!read[1].active);
+    assert_eq!(parse_csv(&csv)[1].name, "pair");
+}
+
+#[test]
+fn quoted_fields_keep_their_commas() {
+    let csv =
+        "Active,Body,Name\nyes, a+b ,c\nyes,\"f(x, y)\",pair\nyes,\"say \"\"hi\"\"\",hi\nno,,\n";
+    let found: Vec<(bool, String, String)> = parse_csv(csv)
+        .into_iter()
+        .map(|eq| (eq.active, eq.body, eq.name))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (true, "a+b".to_string(), "c".to_string()),
+            (true, "f(x, y)".to_string(), "pair".to_string()),
+            (true, "say \"hi\"".to_string(), "hi".to_string()),
+            (false, String::new(), "default_equation".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn unquoted_body_commas_are_joined_back() {
+    // Brace depth keeps `{1,2}` together even with a spare options column
+    let sub = &parse_csv("Active,Body,Name\nyes,x_{1,2} + \\frac{a, b}{c},sub,tags=t\n")[0];
+    assert_eq!(
+        (sub.body.as_str(), sub.name.as_str()),
+        ("x_{1,2} + \\frac{a, b}{c}", "sub")
+    );
+    assert_eq!(sub.tags, ["t"]);
+    // Without braces the extra fields go to the body
+    let pair = &parse_csv("Active,Body,Name,Options,Line\nno,f(a, b),pair,,4\n")[0];
+    assert_eq!(
+        (pair.body.as_str(), pair.name.as_str()),
+        ("f(a, b)", "pair")
+    );
+    let set = &parse_csv("Active,Body,Name\nyes,\\{a,b\\},set\n")[0];
+    assert_eq!(set.body, "\\{a,b\\}");
+    let sum = &parse_csv("Active,Body,Name\nyes,a+b,sum,tags=t\n")[0];
+    assert_eq!((sum.body.as_str(), sum.name.as_str()), ("a+b", "sum"));
+    assert_eq!(sum.tags, ["t"]);
+
+    let csv = "Active,Body,Name\nyes,f(x, y),two\nyes,\"g(x, y)\",quoted\nno,\\binom{n,k},choose\n";
+    let equations = parse_csv(csv);
+    assert_eq!(equations.len(), 3);
+    assert_eq!(equations[0].body, "f(x, y)");
+    assert_eq!(equations[0].name, "two");
+    assert_eq!(equations[2].body, "\\binom{n,k}");
+    assert!(!equations[2].active);
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use common::MockBackend;
+use simptui::{load_document, render_equations_with, Macro, RenderOptions, Retention};
+use std::fs;
+
+#[test]
+fn markdown_documents_carry_frontmatter_and_macros() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.md");
+    fs::write(
+        &path,
+        "---\ntitle: \"Linear algebra\"\nTags:\n  - math\nauthor: Ada\n---\n\n\
+$$\n\\newcommand{\\R}{\\mathbb{R}}\n\\DeclareMathOperator*{\\argmax}{arg\\,max}\nx \\in \\R^n\n$$\n%%space%%\n",
+    )
+    .unwrap();
+
+    let document = load_document(&path).unwrap();
+
+    assert_eq!(document.format, "markdown");
+    assert_eq!(document.title(), Some("Linear algebra"));
+    assert_eq!(document.frontmatter["author"], "Ada");
+    assert_eq!(document.frontmatter["tags"], "");
+    let names: Vec<&str> = document.macros.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, ["\\R", "\\argmax"]);
+    assert!(document.equation("space").is_some());
+    assert!(document.source.starts_with("---\n"));
+}
+
+#[test]
+fn org_keywords_are_frontmatter() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.org");
+    fs::write(
+        &path,
+        "#+TITLE: Mechanics\n#+AUTHOR: Newton\n\n* Forces\n#+NAME: second_law\n\\[ F = ma \\]\n",
+    )
+    .unwrap();
+
+    let document = load_document(&path).unwrap();
+
+    assert_eq!(document.format, "org");
+    assert_eq!(document.title(), Some("Mechanics"));
+    assert_eq!(document.frontmatter.len(), 2);
+    assert!(document.equation("second_law").is_some());
+}
+
+#[test]
+fn macro_definitions_keep_their_arguments() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.md");
+    fs::write(
+        &path,
+        "$$\n\\renewcommand*{\\vec}[1]{\\mathbf{#1}} \\def\\half#1{\\frac{#1}{2}} \\newcommand{\\broken}{\\frac{\n$$\n%%defs%%\n",
+    )
+    .unwrap();
+    assert_eq!(
+        load_document(&path).unwrap().macros,
+        [
+            Macro {
+                name: "\\vec".to_string(),
+                definition: "\\renewcommand*{\\vec}[1]{\\mathbf{#1}}".to_string(),
+            },
+            Macro {
+                name: "\\half".to_string(),
+                definition: "\\def\\half#1{\\frac{#1}{2}}".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn macros_reach_the_other_equations() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.md");
+    fs::write(
+        &path,
+        "$$\n\\newcommand{\\R}{\\mathbb{R}}\nx \\in \\R\n$$\n%%defines%%\n\n$$\ny \\in \\R\n$$\n%%uses%%\n",
+    )
+    .unwrap();
+    let document = load_document(&path).unwrap();
+    let mut options = RenderOptions::new(dir.path().join("out"), "#000000");
+    options.retention = Retention::KeepAll;
+    options.macros = document.macros;
+
+    render_equations_with(&document.equations, &options, &MockBackend::new()).unwrap();
+    let tex =
+        |name: &str| fs::read_to_string(dir.path().join(format!("out/{}.tex", name))).unwrap();
+    let definition = "\\newcommand{\\R}{\\mathbb{R}}";
+    assert_eq!(tex("uses").matches(definition).count(), 1);
+    // Not a second time where the body has it
+    assert_eq!(tex("defines").matches(definition).count(), 1);
+}
+
+#[test]
+fn macros_come_from_equations_once_each() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.md");
+    fs::write(
+        &path,
+        "```latex\n\\renewcommand{\\vec}[1]{\\mathbf{#1}}\n```\n\n\
+$$\n\\newcommand{\\R}{\\mathbb{R}}\nx \\in \\R\n$$\n%%first%%\n\n\
+$$\n\\newcommand{\\R}{\\mathbb{R}}\ny \\in \\R\n$$\n%%second%%\n\n\
+$$\n\\vec{v} \\in \\R\n$$\n%%third%%\n",
+    )
+    .unwrap();
+    let document = load_document(&path).unwrap();
+    let names: Vec<&str> = document.macros.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, ["\\R"]);
+
+    let mut options = RenderOptions::new(dir.path().join("out"), "#000000");
+    options.retention = Retention::KeepAll;
+    options.macros = document.macros;
+    render_equations_with(&document.equations, &options, &MockBackend::new()).unwrap();
+    let tex =
+        |name: &str| fs::read_to_string(dir.path().join(format!("out/{}.tex", name))).unwrap();
+    let definition = "\\newcommand{\\R}{\\mathbb{R}}";
+    for name in ["first", "second", "third"] {
+        assert_eq!(tex(name).matches(definition).count(), 1, "{}", name);
+    }
+    assert!(!tex("third").contains("\\renewcommand"));
+}
@@ -0,0 +1,73 @@
+use simptui::{embed_images, link_target, load_equations, EmbedReport};
+use std::fs;
+
+#[test]
+fn links_are_added_after_blocks_and_kept_up_to_date() {
+    let dir = tempfile::tempdir().unwrap();
+    let notes = dir.path().join("notes.md");
+    fs::write(
+        &notes,
+        "$$\na\n$$\n%%a%%\n\nText.\n$$\nb\n$$\n%%b%%\n\n![b](old/b.svg)\n",
+    )
+    .unwrap();
+    let equations = load_equations(&notes).unwrap();
+    let links: Vec<_> = equations
+        .iter()
+        .map(|eq| (eq, format!("equations/{}.svg", eq.name)))
+        .collect();
+
+    let report = embed_images(&notes, &links).unwrap();
+    assert_eq!(report.added, ["a"]);
+    assert_eq!(report.updated, ["b"]);
+    let embedded = "$$\na\n$$\n%%a%%\n![a](equations/a.svg)\n\nText.\n$$\nb\n$$\n%%b%%\n\n\
+                    ![b](equations/b.svg)\n";
+    assert_eq!(fs::read_to_string(&notes).unwrap(), embedded);
+
+    // Again: nothing to do, and the equations still read the same
+    let equations = load_equations(&notes).unwrap();
+    assert_eq!(equations.len(), 2);
+    let links: Vec<_> = equations
+        .iter()
+        .map(|eq| (eq, format!("equations/{}.svg", eq.name)))
+        .collect();
+    assert_eq!(
+        embed_images(&notes, &links).unwrap(),
+        EmbedReport::default()
+    );
+    assert_eq!(fs::read_to_string(&notes).unwrap(), embedded);
+}
+
+#[test]
+fn targets_are_relative_to_the_markdown_file() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("notes")).unwrap();
+    fs::create_dir_all(dir.path().join("out/my equations")).unwrap();
+    let image = dir.path().join("out/my equations/a.svg");
+    fs::write(&image, "<svg/>").unwrap();
+
+    let notes = dir.path().join("notes/a.md");
+    assert_eq!(link_target(&notes, &image), "<../out/my equations/a.svg>");
+    let top = dir.path().join("a.md");
+    assert_eq!(link_target(&top, &image), "<out/my equations/a.svg>");
+}
+
+#[test]
+fn renamed_equations_keep_their_link() {
+    let dir = tempfile::tempdir().unwrap();
+    let notes = dir.path().join("notes.md");
+    fs::write(
+        &notes,
+        "$$\na\n$$\n%%area%%\n![a](equations/a.svg)\n![photo](img/photo.png)\n",
+    )
+    .unwrap();
+    let equations = load_equations(&notes).unwrap();
+    let links = [(&equations[0], "equations/area.svg".to_string())];
+
+    let report = embed_images(&notes, &links).unwrap();
+    assert_eq!(report.updated, ["area"]);
+    assert!(report.added.is_empty());
+    assert_eq!(
+        fs::read_to_string(&notes).unwrap(),
+        "$$\na\n$$\n%%area%%\n![area](equations/area.svg)\n![photo](img/photo.png)\n"
+    );
+}
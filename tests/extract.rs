@@ -0,0 +1,216 @@
+use simptui::{parse_html, parse_markdown, Config, ExtractRule, Extractor, Group, SourceSpan};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[test]
+fn markdown_names_spans_and_duplicates() {
+    let notes = "# Title\n$$\na\n$$\n%%dup%%\n\n$$\nb\n$$\n%%dup%%\n\n$$\nc\n$$\n";
+    let equations = parse_markdown(notes);
+
+    let names: Vec<&str> = equations.iter().map(|eq| eq.name.as_str()).collect();
+    assert_eq!(names, ["dup", "dup_1", "default_equation"]);
+    assert_eq!(
+        equations[0].span,
+        Some(SourceSpan {
+            start_line: 2,
+            end_line: 5
+        })
+    );
+    assert!(equations.iter().all(|eq| eq.active));
+}
+
+#[test]
+fn environments_outside_code_fences() {
+    let notes = "\
+\\begin{align}
+a &= b \\\\
+c &= d \\label{eq:pair}
+\\end{align}
+
+```latex
+\\begin{equation}
+ignored
+\\end{equation}
+```
+
+%%no%%
+\\begin{gather*}
+x
+\\end{gather*}
+%%plain%%
+
+$$\\begin{equation}y\\end{equation}$$
+";
+    let equations = parse_markdown(notes);
+
+    let found: Vec<(&str, Option<&str>, bool)> = equations
+        .iter()
+        .map(|eq| (eq.name.as_str(), eq.environment.as_deref(), eq.active))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("eq_pair", Some("align"), true),
+            ("plain", Some("gather*"), false),
+            ("default_equation", None, true),
+        ]
+    );
+    assert_eq!(
+        equations[0].math_body(),
+        "\\begin{aligned}a &= b \\\\\nc &= d \\label{eq:pair}\\end{aligned}"
+    );
+    assert_eq!(
+        equations[0].span,
+        Some(SourceSpan {
+            start_line: 1,
+            end_line: 4
+        })
+    );
+}
+
+#[test]
+fn custom_rules_merge_with_markdown() {
+    let rules = BTreeMap::from([
+        (
+            "fenced".to_string(),
+            ExtractRule {
+                pattern: r"(?s):::math (?P<name>\w+)\n(?P<body>.*?):::".to_string(),
+                body: Group::Name("body".to_string()),
+                name: Some(Group::Name("name".to_string())),
+                active: None,
+                options: None,
+                environment: None,
+                skip_code: false,
+            },
+        ),
+        (
+            "tag".to_string(),
+            ExtractRule {
+                pattern: r#"<eq(?: active="(\w+)")?>(.*?)</eq>"#.to_string(),
+                body: Group::Index(2),
+                name: None,
+                active: Some(Group::Index(1)),
+                options: None,
+                environment: None,
+                skip_code: false,
+            },
+        ),
+    ]);
+    let notes =
+        "$$\nx\n$$\n%%first%%\n:::math energy\nE = mc^2\n:::\nsee <eq active=\"no\">y</eq>\n";
+
+    let equations = Extractor::new(&rules).unwrap().parse(notes);
+
+    let found: Vec<(&str, &str, bool)> = equations
+        .iter()
+        .map(|eq| (eq.name.as_str(), eq.body.as_str(), eq.active))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("first", "x", true),
+            ("energy", "E = mc^2", true),
+            ("default_equation", "y", false),
+        ]
+    );
+}
+
+#[test]
+fn invalid_rule_pattern_is_reported() {
+    let rules = BTreeMap::from([(
+        "broken".to_string(),
+        ExtractRule {
+            pattern: "(".to_string(),
+            body: Group::Index(1),
+            name: None,
+            active: None,
+            options: None,
+            environment: None,
+            skip_code: false,
+        },
+    )]);
+
+    let error = Extractor::new(&rules).unwrap_err();
+    assert!(error.to_string().contains("broken"));
+}
+
+#[test]
+fn html_exports_yield_annotations_and_delimiters() {
+    let html = r#"<html><body>
+<p>Inline \(a^2\) and display:</p>
+<math id="euler"><semantics><mi>e</mi>
+<annotation encoding="application/x-tex">e^{i\pi} + 1 = 0</annotation></semantics></math>
+<p>\[\int_0^1 x \, dx\]</p>
+<script>var s = "\(not math\)";</script>
+</body></html>"#;
+    let equations = parse_html(html);
+
+    let found: Vec<(&str, &str)> = equations
+        .iter()
+        .map(|eq| (eq.name.as_str(), eq.body.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("default_equation", "a^2"),
+            ("euler", r"e^{i\pi} + 1 = 0"),
+            ("default_equation_1", r"\int_0^1 x \, dx"),
+        ]
+    );
+    assert_eq!(equations[1].span.map(|span| span.start_line), Some(4));
+}
+
+#[test]
+fn skipped_fenced_and_ignored_blocks_are_left_out() {
+    let notes = "\
+$$
+a
+$$
+%%kept%%
+
+%%skip%%
+$$
+b
+$$
+%%example%%
+
+```md
+$$
+c
+$$
+```
+
+Lunch was $$12.50$$ today.
+
+%%skip%%
+\\begin{align}
+d &= e
+\\end{align}
+";
+    let names: Vec<String> = parse_markdown(notes)
+        .into_iter()
+        .map(|equation| equation.name)
+        .collect();
+    assert_eq!(names, ["kept", "default_equation"]);
+
+    let mut config = Config::default();
+    config.ignore = vec![r"^\d+(\.\d+)?$".to_string()];
+    let equations = config
+        .parsers()
+        .unwrap()
+        .parse(Path::new("notes.md"), notes)
+        .unwrap();
+    assert_eq!(equations.len(), 1);
+    assert_eq!(equations[0].name, "kept");
+
+    config.ignore = vec!["(".to_string()];
+    assert!(config.parsers().is_err());
+}
+
+#[test]
+fn blocks_right_after_a_code_fence_are_kept() {
+    let equations = parse_markdown("```\nfoo\n```\n\n$$x$$\n");
+    assert_eq!(equations.len(), 1);
+    assert_eq!(equations[0].body, "x");
+    assert_eq!(equations[0].span.map(|span| span.start_line), Some(5));
+}
@@ -0,0 +1,64 @@
+use simptui::{parse_markdown, EquationFilter};
+
+const NOTES: &str = "\
+%%tags=exam%%
+$$
+\\frac{a}{b}
+$$
+%%ratio%%
+
+$$
+x^2
+$$
+%%square%%
+
+%%tags=exam,hard%%
+$$
+\\int_0^1 f(x) \\, dx = F(1) - F(0) + \\text{a rather long explanation}
+$$
+%%integral%%
+";
+
+fn selected(filter: &str) -> Vec<String> {
+    let filter: EquationFilter = filter.parse().unwrap();
+    parse_markdown(NOTES)
+        .into_iter()
+        .filter(|equation| filter.matches(equation))
+        .map(|equation| equation.name)
+        .collect()
+}
+
+#[test]
+fn filters_select_equations() {
+    assert_eq!(
+        selected(r#"active && tags.contains("exam") && len(body) < 40"#),
+        ["ratio"]
+    );
+    assert_eq!(selected(r#"!tags.contains("exam")"#), ["square"]);
+    assert_eq!(
+        selected(r#"body.contains("\frac") || name.matches("^int")"#),
+        ["ratio", "integral"]
+    );
+    assert_eq!(selected("len(tags) >= 1 && line > 5"), ["integral"]);
+    assert_eq!(selected("(name == 'square') == true"), ["square"]);
+    assert_eq!(
+        selected(r#"lower(name).starts_with("SQ") || false"#),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn mistakes_are_reported_while_parsing() {
+    for (filter, error) in [
+        ("len(body)", "not a condition"),
+        ("tags < 3", "can't compare"),
+        ("active && ", "ends too early"),
+        ("nmae == 'x'", "unknown field"),
+        ("name.contains(1)", "takes text"),
+        ("body.contains(\"x\"", "expected ')'"),
+        ("name == 'x", "unclosed string"),
+    ] {
+        let message = filter.parse::<EquationFilter>().unwrap_err();
+        assert!(message.contains(error), "{}: {}", filter, message);
+    }
+}
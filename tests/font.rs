@@ -0,0 +1,16 @@
+use simptui::{Engine, Font};
+
+#[test]
+fn fonts_follow_the_engine() {
+    assert_eq!(
+        Font::Stix2.preamble(Engine::Pdflatex),
+        r"\usepackage{stix2}"
+    );
+    assert_eq!(
+        Font::Stix2.preamble(Engine::Lualatex),
+        r"\usepackage{unicode-math}\setmathfont{STIX Two Math}"
+    );
+    assert!(Font::FiraMath.check(Engine::Xelatex).is_ok());
+    assert!(Font::FiraMath.check(Engine::Latexmk).is_err());
+    assert_eq!("latin-modern".parse(), Ok(Font::LatinModern));
+}
@@ -0,0 +1,90 @@
+use simptui::{format_body, load_equations, rewrite_bodies};
+use std::fs;
+
+#[test]
+fn spacing_is_normalized() {
+    assert_eq!(
+        format_body(r"E=mc ^ 2  +  \frac {a} {b}"),
+        r"E = mc^2 + \frac{a}{b}"
+    );
+    assert_eq!(format_body(r"\sum _ {i=1}^n x_ i"), r"\sum_{i=1}^n x_i");
+    assert_eq!(format_body(r"a\le b,\quad x>=0"), r"a\le b,\quad x >= 0");
+    // Delimiters, text and comments are left alone
+    assert_eq!(format_body(r"\left< x \right>=1"), r"\left< x \right> = 1");
+    assert_eq!(
+        format_body(r"x  \text{if  a=b} %  keep=this"),
+        r"x \text{if  a=b} %  keep=this"
+    );
+}
+
+#[test]
+fn environments_are_indented_and_aligned() {
+    let body = "\\begin{aligned}\nf(x)&=x^2+1\\\\\ng(x) &= \\frac{1}{x}\n\\end{aligned}";
+    assert_eq!(
+        format_body(body),
+        "\\begin{aligned}\n  f(x) &= x^2+1\\\\\n  g(x) &= \\frac{1}{x}\n\\end{aligned}"
+    );
+
+    let body = "\\begin{pmatrix}\n1 & 20 & 3 \\\\\n400 & 5 & \\begin{smallmatrix} a & b \\end{smallmatrix}\n\\end{pmatrix}";
+    assert_eq!(
+        format_body(body),
+        "\\begin{pmatrix}\n  1   & 20 & 3 \\\\\n  400 & 5  & \\begin{smallmatrix} a & b \\end{smallmatrix}\n\\end{pmatrix}"
+    );
+
+    let body = "\\frac{\n\\sqrt{\nx\n}\n}{2}";
+    assert_eq!(format_body(body), "\\frac{\n  \\sqrt{\n    x\n  }\n}{2}");
+}
+
+#[test]
+fn formatting_twice_changes_nothing() {
+    for body in [
+        r"E=mc ^ 2",
+        "&= a\\\\\nx&= b & y &=c",
+        "\\begin{cases}\n x&\\text{if } x>0\\\\\n-x & \\text{otherwise}\n\\end{cases}",
+        "  a =b  ",
+    ] {
+        let once = format_body(body);
+        assert_eq!(format_body(&once), once, "{:?}", body);
+    }
+}
+
+#[test]
+fn formatted_bodies_are_written_back() {
+    let dir = tempfile::tempdir().unwrap();
+    let notes = dir.path().join("notes.md");
+    fs::write(
+        &notes,
+        "# Sums\n\n$$\n\\sum _{k=1}^n k=\\frac{n(n+1)}{2}\n$$\n%%gauss%%\n\n\\begin{align}\na&=b\\\\\nc &=d\n\\end{align}\n\nSo\n$$x = 1$$\n%%unit%%\n",
+    )
+    .unwrap();
+    let equations = load_equations(&notes).unwrap();
+    let bodies: Vec<String> = equations.iter().map(|eq| format_body(&eq.body)).collect();
+    rewrite_bodies(&notes, &equations, &bodies).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(&notes).unwrap(),
+        "# Sums\n\n$$\n\\sum_{k=1}^n k = \\frac{n(n+1)}{2}\n$$\n%%gauss%%\n\n\\begin{align}\na &= b\\\\\nc &= d\n\\end{align}\n\nSo\n$$x = 1$$\n%%unit%%\n"
+    );
+    let reloaded = load_equations(&notes).unwrap();
+    assert_eq!(reloaded.len(), 3);
+    assert_eq!(reloaded[0].name, "gauss");
+}
+
+#[test]
+fn toml_bodies_are_rewritten_in_place() {
+    let dir = tempfile::tempdir().unwrap();
+    let table = dir.path().join("equations.toml");
+    fs::write(
+        &table,
+        "[[equation]]\nname = \"a\"\nbody = '''\nx&=1\\\\\nyy&=2\n'''\ntags = [\"t\"]\n\n[[equation]]\nname = \"b\"\nbody = 'y=2'\n",
+    )
+    .unwrap();
+    let equations = load_equations(&table).unwrap();
+    let bodies: Vec<String> = equations.iter().map(|eq| format_body(&eq.body)).collect();
+    rewrite_bodies(&table, &equations, &bodies).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(&table).unwrap(),
+        "[[equation]]\nname = \"a\"\nbody = '''\nx  &= 1\\\\\nyy &= 2\n'''\ntags = [\"t\"]\n\n[[equation]]\nname = \"b\"\nbody = 'y = 2'\n"
+    );
+}
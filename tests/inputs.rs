@@ -0,0 +1,46 @@
+use simptui::{expand_inputs, ScanConfig};
+use std::fs;
+use std::path::PathBuf;
+
+#[test]
+fn globs_expand_below_their_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let notes = dir.path().join("notes");
+    fs::create_dir_all(notes.join("physics")).unwrap();
+    fs::write(notes.join("intro.md"), "").unwrap();
+    fs::write(notes.join("physics/energy.md"), "").unwrap();
+    fs::write(notes.join("physics/data.csv"), "").unwrap();
+    let config = ScanConfig::default();
+
+    let inputs = expand_inputs(&[notes.join("**/*.md"), notes.join("intro.md")], &config).unwrap();
+    let relative: Vec<PathBuf> = inputs.iter().map(|input| input.relative.clone()).collect();
+    assert_eq!(
+        relative,
+        [
+            PathBuf::from("intro.md"),
+            PathBuf::from("physics/energy.md")
+        ]
+    );
+    assert_eq!(inputs[1].path, notes.join("physics/energy.md"));
+
+    let only_top = expand_inputs(&[notes.join("*.md")], &config).unwrap();
+    assert_eq!(only_top.len(), 1);
+    assert!(expand_inputs(&[notes.join("*.tex")], &config).is_err());
+}
+
+#[test]
+fn globs_reach_past_the_scan_caps() {
+    let dir = tempfile::tempdir().unwrap();
+    let deep = dir.path().join("a/b/c");
+    fs::create_dir_all(&deep).unwrap();
+    for name in ["one.md", "two.md", "three.md"] {
+        fs::write(dir.path().join(name), "").unwrap();
+    }
+    fs::write(deep.join("deep.md"), "").unwrap();
+    let mut config = ScanConfig::default();
+    config.max_depth = 1;
+    config.max_files = 2;
+
+    let inputs = expand_inputs(&[dir.path().join("**/*.md")], &config).unwrap();
+    assert_eq!(inputs.len(), 4);
+}
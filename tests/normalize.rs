@@ -0,0 +1,40 @@
+use simptui::normalize_body;
+
+#[test]
+fn insignificant_differences_compare_equal() {
+    assert_eq!(
+        normalize_body("a+b", false),
+        normalize_body(" a + b ", false)
+    );
+    assert_eq!(
+        normalize_body(r"\frac {x} {2} \le y", false),
+        normalize_body(r"\frac{x}{2}\leq y", false)
+    );
+    assert_eq!(normalize_body(r"\alpha b", false), r"\alpha b");
+    assert_eq!(normalize_body(r"x \lbrace y", false), r"x\{y");
+    // Spaces inside text are kept
+    assert_eq!(normalize_body(r"\text{if  x} > 0", false), r"\text{if x}>0");
+    assert_eq!(normalize_body(r"\text {a , b}", false), r"\text{a , b}");
+    assert_ne!(
+        normalize_body(r"\text {a , b}", false),
+        normalize_body(r"\text{a,b}", false)
+    );
+}
+
+#[test]
+fn comments_are_left_out() {
+    assert_eq!(
+        normalize_body("a + b % the sum\n= c", false),
+        normalize_body("a+b=c % checked", false)
+    );
+    // Ends the command name like TeX does, and `\%` stays a percent sign
+    assert_eq!(normalize_body("\\alpha% no space\nb", false), r"\alpha b");
+    assert_eq!(normalize_body("\\text{a% joined\nb}", false), r"\text{ab}");
+    assert_eq!(normalize_body(r"50\% % of x", false), r"50\%");
+}
+
+#[test]
+fn style_aliases_are_opt_in() {
+    assert_eq!(normalize_body(r"\dfrac{1}{2}", false), r"\dfrac{1}{2}");
+    assert_eq!(normalize_body(r"\dfrac{1}{2}", true), r"\frac{1}{2}");
+}
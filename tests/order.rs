@@ -0,0 +1,56 @@
+use simptui::{read_csv_file, reorder_csv_file, ParserRegistry, Project};
+use std::fs;
+
+#[test]
+fn csv_rows_follow_the_new_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let csv = dir.path().join("eqs.csv");
+    fs::write(
+        &csv,
+        "Active,Body,Name\nyes,a,first\nbroken row\nno,b,second\n",
+    )
+    .unwrap();
+
+    let mut equations = read_csv_file(&csv).unwrap();
+    equations.swap(0, 1);
+    reorder_csv_file(&csv, &equations).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(&csv).unwrap(),
+        "Active,Body,Name\nno,b,second\nyes,a,first\nbroken row\n"
+    );
+    let names: Vec<String> = read_csv_file(&csv)
+        .unwrap()
+        .into_iter()
+        .map(|eq| eq.name)
+        .collect();
+    assert_eq!(names, ["second", "first"]);
+}
+
+#[test]
+fn project_order_survives_a_reload() {
+    let dir = tempfile::tempdir().unwrap();
+    let notes = dir.path().join("notes.md");
+    fs::write(
+        &notes,
+        "$$\na\n$$\n%%a%%\n\n$$\nb\n$$\n%%b%%\n\n$$\nc\n$$\n%%c%%\n",
+    )
+    .unwrap();
+
+    let mut project = Project::new(dir.path());
+    project.add_source(&notes).unwrap();
+    project
+        .set_order(&notes, vec!["c".to_string(), "a".to_string()])
+        .unwrap();
+    project.save().unwrap();
+
+    let project = Project::load(&dir.path().join(".simptui")).unwrap();
+    let names: Vec<String> = project
+        .equations(&ParserRegistry::default())
+        .unwrap()
+        .into_iter()
+        .map(|eq| eq.name)
+        .collect();
+    // Names missing from the saved order keep their place after it
+    assert_eq!(names, ["c", "a", "b"]);
+}
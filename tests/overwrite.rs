@@ -0,0 +1,60 @@
+mod common;
+
+use common::MockBackend;
+use simptui::{
+    back_up, changed_outputs, parse_markdown, render_equations_with, Engine, RenderOptions,
+};
+use std::fs;
+
+const NOTES: &str = "\
+$$
+a + b = c
+$$
+%%sum%%
+
+$$
+x^2
+$$
+%%square%%
+";
+
+#[test]
+fn hand_edited_outputs_are_reported() {
+    let out = tempfile::tempdir().unwrap();
+    let cache = tempfile::tempdir().unwrap();
+    let mut options = RenderOptions::new(out.path(), "#000000");
+    options.engine = Engine::Pdflatex;
+    options.cache_dir = Some(cache.path().to_path_buf());
+    let equations = parse_markdown(NOTES);
+
+    render_equations_with(&equations, &options, &MockBackend::new()).unwrap();
+    assert!(changed_outputs(&equations, &options).is_empty());
+
+    let sum = out.path().join("sum.svg");
+    fs::write(
+        &sum,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\"><!-- tweaked --></svg>",
+    )
+    .unwrap();
+    let changed = changed_outputs(&equations, &options);
+    assert_eq!(changed.len(), 1);
+    assert_eq!(changed[0].name, "sum");
+    assert_eq!(changed[0].path, sum);
+
+    // Nothing to compare against without a cache
+    options.cache_dir = None;
+    assert!(changed_outputs(&equations, &options).is_empty());
+}
+
+#[test]
+fn backups_sit_next_to_the_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let sum = dir.path().join("sum.svg");
+    fs::write(&sum, "tweaked").unwrap();
+
+    let backup = back_up(&sum).unwrap();
+
+    assert_eq!(backup, dir.path().join("sum.svg.bak"));
+    assert_eq!(fs::read_to_string(&backup).unwrap(), "tweaked");
+    assert!(!sum.exists());
+}
@@ -0,0 +1,137 @@
+use simptui::{DuplicateNames, Equation, Parser, ParserRegistry};
+use std::fs;
+use std::path::Path;
+
+// One equation per `\[ ... \]` line of a .tex file
+struct TexParser;
+
+impl Parser for TexParser {
+    fn name(&self) -> &'static str {
+        "tex"
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["tex"]
+    }
+
+    fn parse(&self, content: &str) -> Vec<Equation> {
+        content
+            .lines()
+            .filter_map(|line| line.strip_prefix(r"\[")?.strip_suffix(r"\]"))
+            .enumerate()
+            .map(|(i, body)| Equation::new(true, &format!("tex_{}", i + 1), body.trim()))
+            .collect()
+    }
+}
+
+#[test]
+fn formats_are_picked_by_extension_then_content() {
+    let dir = tempfile::tempdir().unwrap();
+    let tex = dir.path().join("paper.tex");
+    fs::write(&tex, "\\[ a^2 \\]\ntext\n\\[ b^2 \\]\n").unwrap();
+    let notes = dir.path().join("notes.txt");
+    fs::write(&notes, "$$\nx\n$$\n%%sniffed%%\n").unwrap();
+
+    let mut parsers = ParserRegistry::default();
+    assert!(parsers.load(&tex).is_err());
+    assert_eq!(parsers.load(&notes).unwrap()[0].name, "sniffed");
+
+    parsers.register(TexParser);
+    let equations = parsers.load(&tex).unwrap();
+    let bodies: Vec<&str> = equations.iter().map(|eq| eq.body.as_str()).collect();
+    assert_eq!(bodies, ["a^2", "b^2"]);
+    assert_eq!(parsers.for_path(&tex).unwrap().name(), "tex");
+    assert!(parsers.for_path(&notes).is_none());
+}
+
+#[test]
+fn files_without_extensions_are_sniffed() {
+    let dir = tempfile::tempdir().unwrap();
+    let write = |name: &str, content: &str| {
+        let path = dir.path().join(name);
+        fs::write(&path, content).unwrap();
+        path
+    };
+    let csv = write("table", "Active,Body,Name\nyes,x^2,square\n");
+    let org = write(
+        "daily",
+        "#+TITLE: Daily\n\n#+NAME: euler\n\\[ e^{i\\pi} + 1 = 0 \\]\n",
+    );
+    let notebook = write(
+        "scratch",
+        r#"{"nbformat": 4, "cells": [
+            {"cell_type": "code", "source": "x = 1"},
+            {"cell_type": "markdown", "source": ["$$\n", "a^2\n", "$$\n", "%%area%%\n"]}
+        ]}"#,
+    );
+    let plain = write("todo", "buy milk\n");
+
+    let parsers = ParserRegistry::default();
+    let detected = |path| parsers.detect(path, &fs::read_to_string(path).unwrap());
+    assert_eq!(detected(&csv).unwrap().parser.name(), "csv");
+    assert_eq!(detected(&org).unwrap().parser.name(), "org");
+    assert_eq!(detected(&notebook).unwrap().parser.name(), "notebook");
+    assert!(detected(&plain).is_none());
+    assert_eq!(simptui::detect_file_type(&org), "org");
+
+    assert_eq!(parsers.load(&csv).unwrap()[0].name, "square");
+    let euler = &parsers.load(&org).unwrap()[0];
+    assert_eq!(
+        (euler.name.as_str(), euler.body.as_str()),
+        ("euler", r"e^{i\pi} + 1 = 0")
+    );
+    let area = &parsers.load(&notebook).unwrap()[0];
+    assert_eq!((area.name.as_str(), area.body.as_str()), ("area", "a^2"));
+    assert!(area.span.is_none());
+
+    // The extension settles a markdown note that also carries HTML
+    let note = write("note.md", "<div>\n\\begin{align} x \\end{align}\n</div>\n");
+    let detection = detected(&note).unwrap();
+    assert_eq!(detection.parser.name(), "markdown");
+    assert!(detection.by_extension);
+
+    // As it does when the sniffs are equally sure
+    let fragment = write("fragment.md", "<div>\nsee below\n</div>\n");
+    let detection = detected(&fragment).unwrap();
+    assert_eq!(detection.parser.name(), "markdown");
+    assert!(detection.by_extension);
+}
+
+#[test]
+fn hashed_duplicate_names_survive_insertions() {
+    let mut parsers = ParserRegistry::default();
+    parsers.set_duplicate_names(DuplicateNames::Hash);
+    let stems = |content: &str| -> Vec<String> {
+        let equations = parsers.parse(Path::new("notes.md"), content).unwrap();
+        // The names stay as written
+        let names: Vec<&str> = equations.iter().map(|eq| eq.name.as_str()).collect();
+        assert!(names.iter().all(|name| ["x", "only"].contains(name)));
+        equations
+            .iter()
+            .map(|equation| equation.file_stem().to_string())
+            .collect()
+    };
+
+    let before = stems("$$\nb^2\n$$\n%%x%%\n\n$$\nc\n$$\n%%only%%\n\n$$\nb^2\n$$\n%%x%%\n");
+    let after = stems("$$\na^2\n$$\n%%x%%\n\n$$\nb^2\n$$\n%%x%%\n\n$$\nc\n$$\n%%only%%\n");
+    assert_eq!(before[1], "only");
+    assert!(before[0].starts_with("x_") && before[2] == format!("{}_1", before[0]));
+    // The new `x` above takes a file name of its own instead of shifting the others
+    assert_eq!(after[1], before[0]);
+    assert_eq!(after[2], "only");
+    assert!(!before.contains(&after[0]));
+}
+
+#[test]
+fn file_types_are_told_from_the_start_of_the_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let big = dir.path().join("big");
+    let mut notes = "%%yes%%\n$$\na + b\n$$\n%%sum%%\n".to_string();
+    notes.push_str(&"é words and more words\n".repeat(10_000));
+    fs::write(&big, notes).unwrap();
+    assert_eq!(simptui::detect_file_type(&big), "markdown");
+
+    let binary = dir.path().join("image.md");
+    fs::write(&binary, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR$$ $$").unwrap();
+    assert_eq!(simptui::detect_file_type(&binary), "unknown");
+}
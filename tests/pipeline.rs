@@ -0,0 +1,407 @@
+mod common;
+
+use common::{BackendCall, MockBackend};
+use simptui::{
+    parse_markdown, read_manifest, render_equations_with, DuplicateNames, Engine, Equation, Fill,
+    FontSize, OutputFormat, OutputNaming, ParserRegistry, RenderOptions, Retention, Rgb,
+};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tempfile::TempDir;
+
+const NOTES: &str = "\
+%%yes%%
+$$
+a + b = c
+$$
+%%sum%%
+
+%%no%%
+$$
+e = mc^2
+$$
+%%energy%%
+
+$$
+x^2
+$$
+%%square%%
+";
+
+fn options(output_dir: &Path) -> RenderOptions {
+    RenderOptions::new(output_dir, "#000000")
+}
+
+#[test]
+fn renders_only_active_equations() {
+    let out = TempDir::new().unwrap();
+    let backend = MockBackend::new();
+
+    let report =
+        render_equations_with(&parse_markdown(NOTES), &options(out.path()), &backend).unwrap();
+
+    assert_eq!(report.rendered, ["sum", "square"]);
+    assert!(report.failed.is_empty());
+    assert!(out.path().join("sum.svg").exists());
+    assert!(out.path().join("square.svg").exists());
+    assert!(!out.path().join("energy.svg").exists());
+    // Intermediates are cleaned up by default
+    assert!(!out.path().join("sum.tex").exists());
+    assert!(!out.path().join("sum.pdf").exists());
+
+    let calls = backend.calls();
+    assert_eq!(backend.compile_count(), 2);
+    assert!(calls.contains(&BackendCall::Convert(
+        out.path().join("sum.pdf"),
+        OutputFormat::Svg
+    )));
+}
+
+#[test]
+fn identical_equations_compile_once() {
+    let out = TempDir::new().unwrap();
+    let backend = MockBackend::new();
+    let notes = "$$\nx + y\n$$\n%%first%%\n\n$$\nx + y\n$$\n%%second%%\n";
+
+    render_equations_with(&parse_markdown(notes), &options(out.path()), &backend).unwrap();
+
+    assert_eq!(backend.compile_count(), 1);
+    let read = |file: &str| fs::read_to_string(out.path().join(file)).unwrap();
+    assert_eq!(read("first.svg"), read("second.svg"));
+}
+
+#[test]
+fn cache_is_reused_across_batches() {
+    let out = TempDir::new().unwrap();
+    let cache = TempDir::new().unwrap();
+    let mut options = options(out.path());
+    options.cache_dir = Some(cache.path().to_path_buf());
+    let equations = parse_markdown(NOTES);
+
+    let first = MockBackend::new();
+    render_equations_with(&equations, &options, &first).unwrap();
+    assert_eq!(first.compile_count(), 2);
+
+    fs::remove_dir_all(out.path()).unwrap();
+    let second = MockBackend::new();
+    let report = render_equations_with(&equations, &options, &second).unwrap();
+    assert_eq!(second.compile_count(), 0);
+    assert_eq!(report.rendered.len(), 2);
+    assert!(out.path().join("sum.svg").exists());
+}
+
+#[test]
+fn unfinished_batches_resume() {
+    let out = TempDir::new().unwrap();
+    let cache = TempDir::new().unwrap();
+    let mut options = options(out.path());
+    options.cache_dir = Some(cache.path().to_path_buf());
+    let equations = parse_markdown(NOTES);
+
+    let first = MockBackend::new().failing("x^2");
+    render_equations_with(&equations, &options, &first).unwrap();
+
+    // `sum` is done and skipped outright, not even copied from the cache
+    let second = MockBackend::new();
+    let report = render_equations_with(&equations, &options, &second).unwrap();
+    assert_eq!(report.resumed, 1);
+    assert_eq!(report.cache_hits, 0);
+    assert_eq!(report.rendered, ["sum", "square"]);
+    assert!(report.summary().ends_with("1 resumed"));
+
+    // A clean batch leaves nothing to resume
+    let third = render_equations_with(&equations, &options, &second).unwrap();
+    assert_eq!((third.resumed, third.cache_hits), (0, 2));
+
+    // Restarting goes back to the cache for `sum` too
+    render_equations_with(&equations, &options, &first).unwrap();
+    options.resume = false;
+    let restarted = render_equations_with(&equations, &options, &second).unwrap();
+    assert_eq!((restarted.resumed, restarted.cache_hits), (0, 2));
+}
+
+#[test]
+fn failed_equations_are_quarantined() {
+    let out = TempDir::new().unwrap();
+    let backend = MockBackend::new().failing("x^2");
+
+    let report =
+        render_equations_with(&parse_markdown(NOTES), &options(out.path()), &backend).unwrap();
+
+    assert_eq!(report.rendered, ["sum"]);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].name, "square");
+    assert!(!report.is_failure(false));
+    assert!(report.is_failure(true));
+    assert!(out.path().join("failed/square.tex").exists());
+    assert!(out.path().join("failed/square.log").exists());
+    assert!(!out.path().join("square.svg").exists());
+}
+
+#[test]
+fn fail_fast_stops_the_batch() {
+    let out = TempDir::new().unwrap();
+    let backend = MockBackend::new().failing("a + b");
+    let mut options = options(out.path());
+    options.fail_fast = true;
+
+    let report = render_equations_with(&parse_markdown(NOTES), &options, &backend).unwrap();
+
+    assert!(report.rendered.is_empty());
+    assert_eq!(backend.compile_count(), 1);
+}
+
+#[test]
+fn hashed_names_are_recorded_in_the_manifest() {
+    let out = TempDir::new().unwrap();
+    let mut options = options(out.path());
+    options.hash_names = true;
+
+    render_equations_with(&parse_markdown(NOTES), &options, &MockBackend::new()).unwrap();
+
+    let manifest = read_manifest(out.path());
+    let sum = &manifest["sum"];
+    assert!(sum.starts_with("sum-") && sum.ends_with(".svg"));
+    assert!(out.path().join(sum).exists());
+    assert!(!out.path().join("sum.svg").exists());
+}
+
+#[test]
+fn baseline_alignment_writes_css() {
+    let out = TempDir::new().unwrap();
+    let mut options = options(out.path());
+    options.baseline_align = true;
+
+    render_equations_with(&parse_markdown(NOTES), &options, &MockBackend::new()).unwrap();
+
+    let css = fs::read_to_string(out.path().join("baseline.css")).unwrap();
+    assert!(css.contains(r#"img[src$="sum.svg"]"#));
+    let svg = fs::read_to_string(out.path().join("sum.svg")).unwrap();
+    assert!(svg.contains("vertical-align"));
+}
+
+#[test]
+fn filled_backgrounds_replace_the_transparent_border() {
+    let out = TempDir::new().unwrap();
+    let mut options = options(out.path());
+    options.retention = Retention::KeepAll;
+    options.padding = 4.0;
+    let equations = parse_markdown(NOTES);
+
+    render_equations_with(&equations, &options, &MockBackend::new()).unwrap();
+    let tex = fs::read_to_string(out.path().join("sum.tex")).unwrap();
+    assert!(tex.contains("border=4pt") && !tex.contains("tikz"));
+
+    options.fill = Fill::Color(Rgb(0x20, 0x20, 0x30));
+    options.corner_radius = 3.0;
+    render_equations_with(&equations, &options, &MockBackend::new()).unwrap();
+    let tex = fs::read_to_string(out.path().join("sum.tex")).unwrap();
+    assert!(tex.contains("border=0pt"));
+    assert!(tex.contains(r"\definecolor{equationbackground}{HTML}{202030}"));
+    assert!(tex.contains("inner sep=4pt") && tex.contains("rounded corners=3pt"));
+}
+
+#[test]
+fn equation_directives_override_engine_and_packages() {
+    let out = TempDir::new().unwrap();
+    let mut options = options(out.path());
+    options.retention = Retention::KeepAll;
+    let notes = "%%engine=lualatex packages=mhchem,siunitx%%\n$$\n\\ce{H2O}\n$$\n%%water%%\n\n$$\nx\n$$\n%%plain%%\n";
+    let equations = parse_markdown(notes);
+    assert_eq!(equations[0].engine, Some(Engine::Lualatex));
+    assert_eq!(equations[0].packages, ["mhchem", "siunitx"]);
+    assert_eq!(equations[1].engine, None);
+
+    let backend = MockBackend::new();
+    render_equations_with(&equations, &options, &backend).unwrap();
+
+    let calls = backend.calls();
+    assert!(calls.contains(&BackendCall::Compile(
+        out.path().join("water.tex"),
+        Engine::Lualatex
+    )));
+    assert!(calls.contains(&BackendCall::Compile(
+        out.path().join("plain.tex"),
+        options.engine
+    )));
+    let tex = fs::read_to_string(out.path().join("water.tex")).unwrap();
+    assert!(tex.contains(r"\usepackage{mhchem}"));
+    let tex = fs::read_to_string(out.path().join("plain.tex")).unwrap();
+    assert!(!tex.contains("mhchem"));
+}
+
+#[test]
+fn whitespace_variants_share_a_render() {
+    let out = TempDir::new().unwrap();
+    let backend = MockBackend::new();
+    let notes = "$$\na+b\n$$\n%%tight%%\n\n$$\na + b\n$$\n%%spaced%%\n";
+
+    let report =
+        render_equations_with(&parse_markdown(notes), &options(out.path()), &backend).unwrap();
+
+    assert_eq!(backend.compile_count(), 1);
+    assert_eq!(report.cache_hits, 1);
+    assert!(out.path().join("spaced.svg").exists());
+}
+
+#[test]
+fn labels_name_equations_and_resolve_references() {
+    let out = TempDir::new().unwrap();
+    let mut options = options(out.path());
+    options.retention = Retention::KeepAll;
+    let notes = "$$\nE = mc^2 \\label{eq:energy}\n$$\n\n$$\nF = ma \\label{eq:force}\n$$\n\n$$\n\\text{by } \\eqref{eq:force}, \\ref{eq:energy}\n$$\n%%combined%%\n";
+    let equations = parse_markdown(notes);
+    let names: Vec<&str> = equations.iter().map(|eq| eq.name.as_str()).collect();
+    assert_eq!(names, ["eq_energy", "eq_force", "combined"]);
+    assert_eq!(equations[1].label.as_deref(), Some("eq:force"));
+
+    render_equations_with(&equations, &options, &MockBackend::new()).unwrap();
+
+    let tex = fs::read_to_string(out.path().join("combined.tex")).unwrap();
+    assert!(tex.contains(r"\text{by } (2), 1"));
+    let tex = fs::read_to_string(out.path().join("eq_energy.tex")).unwrap();
+    assert!(!tex.contains(r"\label"));
+}
+
+#[test]
+fn sizes_reach_the_latex_and_the_cache_key() {
+    let out = TempDir::new().unwrap();
+    let mut options = options(out.path());
+    options.retention = Retention::KeepAll;
+    options.size = "small".parse().unwrap();
+    let notes = "$$\nx\n$$\n%%small%%\n\n%%size=14pt%%\n$$\nx\n$$\n%%big%%\n";
+    let backend = MockBackend::new();
+
+    render_equations_with(&parse_markdown(notes), &options, &backend).unwrap();
+
+    assert_eq!(backend.compile_count(), 2);
+    let tex = fs::read_to_string(out.path().join("small.tex")).unwrap();
+    assert!(tex.contains(r"\small \textcolor"));
+    let tex = fs::read_to_string(out.path().join("big.tex")).unwrap();
+    assert!(tex.contains(r"\fontsize{14pt}{16.80pt}\selectfont"));
+    assert!("Large".parse::<FontSize>().is_ok());
+    assert!("large-ish".parse::<FontSize>().is_err());
+}
+
+#[test]
+fn body_colors_are_kept_unless_stripped() {
+    let out = TempDir::new().unwrap();
+    let mut options = options(out.path());
+    options.retention = Retention::KeepAll;
+    let notes = "$$\na + \\textcolor{red}{b}\n$$\n%%sum%%\n";
+    let tex = |options: &RenderOptions| {
+        render_equations_with(&parse_markdown(notes), options, &MockBackend::new()).unwrap();
+        fs::read_to_string(out.path().join("sum.tex")).unwrap()
+    };
+
+    assert!(tex(&options).contains(r"\textcolor{red}{b}"));
+    options.keep_colors = false;
+    assert!(!tex(&options).contains(r"\textcolor{red}"));
+}
+
+#[test]
+fn naming_schemes_pick_file_names_and_fill_the_manifest() {
+    let out = TempDir::new().unwrap();
+    let mut options = options(out.path());
+    options.naming = OutputNaming::FileIndex;
+    options.source_name = Some("physics".to_string());
+
+    let report =
+        render_equations_with(&parse_markdown(NOTES), &options, &MockBackend::new()).unwrap();
+
+    assert_eq!(report.rendered, ["sum", "square"]);
+    assert!(out.path().join("physics_1.svg").exists());
+    assert!(out.path().join("physics_2.svg").exists());
+    let manifest = read_manifest(out.path());
+    assert_eq!(manifest["square"], "physics_2.svg");
+
+    options.naming = OutputNaming::Number;
+    render_equations_with(&parse_markdown(NOTES), &options, &MockBackend::new()).unwrap();
+    assert_eq!(read_manifest(out.path())["sum"], "001.svg");
+    assert!(!out.path().join("physics_1.svg").exists());
+}
+
+#[test]
+fn hashed_duplicates_render_under_their_stems() {
+    let out = TempDir::new().unwrap();
+    let mut parsers = ParserRegistry::default();
+    parsers.set_duplicate_names(DuplicateNames::Hash);
+    let content = "$$\na^2\n$$\n%%x%%\n\n$$\nb^2\n$$\n%%x%%\n";
+    let equations = parsers.parse(Path::new("notes.md"), content).unwrap();
+
+    let report =
+        render_equations_with(&equations, &options(out.path()), &MockBackend::new()).unwrap();
+    assert_eq!(report.rendered, ["x", "x"]);
+    for equation in &equations {
+        let file = out.path().join(format!("{}.svg", equation.file_stem()));
+        assert!(file.exists(), "{} is missing", file.display());
+    }
+    assert!(!out.path().join("x.svg").exists());
+}
+
+#[test]
+fn equations_render_to_bytes_without_an_output_dir() {
+    let out = TempDir::new().unwrap();
+    let mut options = options(&out.path().join("unused"));
+    options.cache_dir = None;
+    let equation = Equation::new(false, "energy", "E = mc^2");
+
+    let rendered = equation
+        .render_to_bytes_with(&options, &MockBackend::new())
+        .unwrap();
+
+    assert!(String::from_utf8(rendered.svg).unwrap().contains("<svg"));
+    assert!(rendered.pdf.unwrap().starts_with(b"%PDF"));
+    assert!(!out.path().join("unused").exists());
+}
+
+#[test]
+fn retention_decides_which_intermediates_stay() {
+    let notes = "$$\na\n$$\n%%good%%\n\n$$\n\\broken\n$$\n%%bad%%\n";
+    let render = |retention: Retention| {
+        let out = TempDir::new().unwrap();
+        let mut options = options(out.path());
+        options.retention = retention;
+        let backend = MockBackend::new().failing("broken");
+        render_equations_with(&parse_markdown(notes), &options, &backend).unwrap();
+        let exists = |file: &str| out.path().join(file).exists();
+        (
+            [exists("good.tex"), exists("good.log"), exists("good.pdf")],
+            exists("failed/bad.log"),
+        )
+    };
+
+    assert_eq!(render(Retention::KeepAll), ([true, true, true], true));
+    assert_eq!(render(Retention::KeepPdf), ([false, false, true], true));
+    assert_eq!(
+        render(Retention::KeepTexOnFailure),
+        ([false, false, false], true)
+    );
+    assert_eq!(render(Retention::DeleteAll), ([false, false, false], false));
+}
+
+#[test]
+fn complex_equations_get_longer_to_compile() {
+    let out = TempDir::new().unwrap();
+    let mut options = options(out.path());
+    assert_eq!(options.timeout, None); // No limit unless one is asked for
+    options.timeout = Some(Duration::from_secs(10));
+    let diagram = format!(
+        "$$\n\\begin{{CD}}\n{}\n\\end{{CD}}\n$$\n%%diagram%%\n",
+        r"A @>{\alpha}>> B \\ @V{\beta}VV @VV{\gamma}V \\ ".repeat(20)
+    );
+    let notes = format!("$$\nx\n$$\n%%simple%%\n\n{}", diagram);
+    let backend = MockBackend::new();
+    render_equations_with(&parse_markdown(&notes), &options, &backend).unwrap();
+
+    let timeouts: Vec<Duration> = backend.timeouts().into_iter().flatten().collect();
+    assert_eq!(timeouts[0], Duration::from_secs(10));
+    assert!(timeouts[1] >= Duration::from_secs(50), "{:?}", timeouts);
+    assert!(timeouts[1] <= Duration::from_secs(100));
+
+    options.timeout = None;
+    let backend = MockBackend::new();
+    render_equations_with(&parse_markdown("$$\nx\n$$\n"), &options, &backend).unwrap();
+    assert_eq!(backend.timeouts(), [None]);
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use common::content_hash;
+use simptui::{
+    dominant_color, read_manifest, recolor_dir, recolor_svg, update_manifest, Manifest, Rgb,
+};
+use std::fs;
+
+// Like pdftocairo draws an equation on a filled background
+const SVG: &str = r##"<svg xmlns="http://www.w3.org/2000/svg"><rect style="fill:rgb(100%,100%,100%);fill-opacity:1" width="9" height="9"/><path style="fill:none;stroke-width:0.4;stroke:rgb(0%,0%,0%);" d="M0 0"/><use fill="rgb(0%,0%,0%)" x="1"/><use fill="#000" x="2"/></svg>"##;
+
+#[test]
+fn only_the_equation_color_changes() {
+    assert_eq!(dominant_color(SVG), Some(Rgb(0, 0, 0)));
+    let recolored = recolor_svg(SVG, None, Rgb(0xff, 0x88, 0x00));
+    assert_eq!(
+        recolored,
+        r##"<svg xmlns="http://www.w3.org/2000/svg"><rect style="fill:rgb(100%,100%,100%);fill-opacity:1" width="9" height="9"/><path style="fill:none;stroke-width:0.4;stroke:#ff8800;" d="M0 0"/><use fill="#ff8800" x="1"/><use fill="#ff8800" x="2"/></svg>"##
+    );
+    // Asked for, the background goes instead
+    let background = recolor_svg(SVG, Some(Rgb(255, 255, 255)), Rgb(0, 0, 0x22));
+    assert!(background.contains("fill:#000022;fill-opacity:1"));
+    assert!(background.contains(r##"fill="#000""##));
+}
+
+#[test]
+fn hashed_outputs_are_renamed_in_the_manifest() {
+    let out = tempfile::tempdir().unwrap();
+    let hashed = format!("energy-{}.svg", content_hash(SVG.as_bytes()));
+    fs::create_dir(out.path().join("appendix")).unwrap();
+    fs::write(out.path().join("appendix").join(&hashed), SVG).unwrap();
+    fs::write(out.path().join("plain.svg"), SVG).unwrap();
+    fs::write(out.path().join("blank.svg"), "<svg/>").unwrap();
+    // Quarantined, so left as it failed
+    fs::create_dir(out.path().join("failed")).unwrap();
+    fs::write(out.path().join("failed/broken.svg"), SVG).unwrap();
+    update_manifest(
+        out.path(),
+        Manifest::from([("energy".to_string(), format!("appendix/{}", hashed))]),
+    )
+    .unwrap();
+
+    let report = recolor_dir(out.path(), None, Rgb(0xff, 0xff, 0xff)).unwrap();
+
+    assert_eq!(report.recolored.len(), 2);
+    assert_eq!(report.unchanged, 1);
+    let file = &read_manifest(out.path())["energy"];
+    assert_ne!(*file, format!("appendix/{}", hashed));
+    let svg = fs::read_to_string(out.path().join(file)).unwrap();
+    assert!(file.ends_with(&format!("-{}.svg", content_hash(svg.as_bytes()))));
+    assert!(!out.path().join("appendix").join(&hashed).exists());
+    assert!(fs::read_to_string(out.path().join("plain.svg"))
+        .unwrap()
+        .contains("#ffffff"));
+    assert_eq!(
+        fs::read_to_string(out.path().join("failed/broken.svg")).unwrap(),
+        SVG
+    );
+}
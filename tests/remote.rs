@@ -0,0 +1,73 @@
+mod common;
+
+use common::serve;
+use simptui::{
+    parse_markdown, render_equations, OutputFormat, RemoteBackend, RenderOptions,
+    REMOTE_PROTOCOL_VERSION,
+};
+use std::fs;
+use std::io;
+
+const NOTES: &str = "\
+$$
+a + b = c
+$$
+%%sum%%
+
+$$
+\\broken
+$$
+%%broken%%
+";
+
+#[test]
+fn renders_through_the_service() {
+    let (url, requests) = serve(2);
+    let out = tempfile::tempdir().unwrap();
+    let mut options = RenderOptions::new(out.path(), "#000000");
+    options.remote = Some(RemoteBackend::new(&url, Some("secret")));
+
+    let report = render_equations(&parse_markdown(NOTES), &options).unwrap();
+
+    assert_eq!(report.rendered, ["sum"]);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].name, "broken");
+    assert_eq!(
+        fs::read_to_string(out.path().join("sum.svg")).unwrap(),
+        "<svg xmlns=\"http://www.w3.org/2000/svg\"/>"
+    );
+    // The service's log explains the failure, like a local one would
+    let log = fs::read_to_string(out.path().join("failed").join("broken.log")).unwrap();
+    assert!(log.contains("Undefined control sequence"));
+
+    let (auth, request) = requests.recv().unwrap();
+    assert_eq!(auth, "Bearer secret");
+    assert_eq!(request.version, REMOTE_PROTOCOL_VERSION);
+    assert!(request.document.contains("a + b = c"));
+    assert!(request.document.contains("\\begin{document}"));
+}
+
+#[test]
+fn only_svg_comes_back_from_the_service() {
+    let out = tempfile::tempdir().unwrap();
+    let mut options = RenderOptions::new(out.path(), "#000000");
+    options.format = OutputFormat::Png;
+    options.remote = Some(RemoteBackend::new("http://127.0.0.1:9/render", None));
+
+    let error = render_equations(&parse_markdown(NOTES), &options).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::Unsupported);
+}
+
+#[test]
+fn mathml_is_converted_without_the_service() {
+    let out = tempfile::tempdir().unwrap();
+    let mut options = RenderOptions::new(out.path(), "#000000");
+    options.format = OutputFormat::MathML;
+    // Nothing listens here
+    options.remote = Some(RemoteBackend::new("http://127.0.0.1:9/render", None));
+
+    let equations = parse_markdown("$$\na + b = c\n$$\n%%sum%%\n");
+    let report = render_equations(&equations, &options).unwrap();
+    assert_eq!(report.rendered, ["sum"]);
+    assert!(out.path().join("sum.mml").exists());
+}
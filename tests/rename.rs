@@ -0,0 +1,126 @@
+use simptui::{append_equation, check_new_equation, load_equations, rename_in_source, NamePattern};
+use std::fs;
+
+const NOTES: &str = "\
+# Kinematics
+
+$$
+v = at
+$$
+%%speed%%
+
+## Energy & Mass
+%%no%%
+$$
+E = mc^2
+$$
+
+Inline $$a$$ too
+";
+
+#[test]
+fn pattern_names_are_written_back() {
+    let dir = tempfile::tempdir().unwrap();
+    let notes = dir.path().join("physics.md");
+    fs::write(&notes, NOTES).unwrap();
+
+    let equations = load_equations(&notes).unwrap();
+    let pattern: NamePattern = "{file_stem}_{index:02}_{heading}".parse().unwrap();
+    let names = pattern.apply(&equations, &notes, NOTES).unwrap();
+    assert_eq!(
+        names,
+        [
+            "physics_01_kinematics",
+            "physics_02_energy_mass",
+            "physics_03_energy_mass"
+        ]
+    );
+
+    rename_in_source(&notes, &equations, &names).unwrap();
+    let renamed = fs::read_to_string(&notes).unwrap();
+    assert!(renamed.contains("$$\n%%physics_01_kinematics%%\n"));
+    assert!(renamed.contains("E = mc^2\n$$\n%%physics_02_energy_mass%%\n"));
+    assert!(renamed.contains("$$a$$%%physics_03_energy_mass%% too"));
+
+    let reloaded = load_equations(&notes).unwrap();
+    let found: Vec<(&str, bool)> = reloaded
+        .iter()
+        .map(|eq| (eq.name.as_str(), eq.active))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("physics_01_kinematics", true),
+            ("physics_02_energy_mass", false),
+            ("physics_03_energy_mass", true)
+        ]
+    );
+}
+
+#[test]
+fn patterns_are_validated() {
+    assert!("{index}_{nope}".parse::<NamePattern>().is_err());
+    assert!("{name:02}".parse::<NamePattern>().is_err());
+    assert!("fixed".parse::<NamePattern>().is_err());
+
+    let dir = tempfile::tempdir().unwrap();
+    let notes = dir.path().join("notes.md");
+    fs::write(&notes, NOTES).unwrap();
+    let equations = load_equations(&notes).unwrap();
+    let pattern: NamePattern = "{heading}".parse().unwrap();
+    let error = pattern.apply(&equations, &notes, NOTES).unwrap_err();
+    assert!(error.to_string().contains("energy_mass"));
+}
+
+#[test]
+fn new_equations_are_checked_and_appended() {
+    let dir = tempfile::tempdir().unwrap();
+    let notes = dir.path().join("physics.md");
+    fs::write(&notes, NOTES).unwrap();
+    let equations = load_equations(&notes).unwrap();
+
+    let check = |name, body| check_new_equation(&notes, &equations, name, body);
+    assert!(check("speed", "v = 2").unwrap_err().contains("already"));
+    assert!(check("two words", "x").is_err());
+    assert!(check("force", "  ").is_err());
+    assert!(check("force", r"\frac{F}{m")
+        .unwrap_err()
+        .contains("braces"));
+    assert!(check("force", "a $$ b").is_err());
+    check("force", r"F = \frac{dp}{dt}").unwrap();
+
+    append_equation(&notes, "force", r"F = \frac{dp}{dt}").unwrap();
+    let equations = load_equations(&notes).unwrap();
+    let force = equations.last().unwrap();
+    assert_eq!(force.name, "force");
+    assert_eq!(force.body.trim(), r"F = \frac{dp}{dt}");
+    assert!(force.active);
+
+    let table = dir.path().join("table.csv");
+    fs::write(&table, "Active,Body,Name\nyes,x,first\n").unwrap();
+    let rows = load_equations(&table).unwrap();
+    assert!(check_new_equation(&table, &rows, "second", "a\nb").is_err());
+    let body = r#"f(a, b) = \text{"pair"}"#;
+    assert!(check_new_equation(&table, &rows, "second", body).is_ok());
+    append_equation(&table, "second", body).unwrap();
+    let rows = load_equations(&table).unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(
+        (rows[1].name.as_str(), rows[1].body.as_str()),
+        ("second", body)
+    );
+}
+
+#[test]
+fn comments_in_code_fences_are_not_headings() {
+    let dir = tempfile::tempdir().unwrap();
+    let notes = dir.path().join("notes.md");
+    let content = "# Setup\n\n```bash\n# install it\n```\n\nThen\n\n$$\nx\n$$\n%%x%%\n";
+    fs::write(&notes, content).unwrap();
+    let equations = load_equations(&notes).unwrap();
+    let pattern: NamePattern = "{heading}".parse().unwrap();
+    assert_eq!(
+        pattern.apply(&equations, &notes, content).unwrap(),
+        ["setup"]
+    );
+}
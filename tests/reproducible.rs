@@ -0,0 +1,111 @@
+mod common;
+
+use common::MockBackend;
+use simptui::{
+    parse_markdown, render_equations_with, scrub_pdf, scrub_png, verify_reproducible_with,
+    write_checksums, RenderOptions, Scrub, ShellHook, STATUS_FILE_NAME,
+};
+use std::fs;
+
+#[test]
+fn pdf_dates_and_ids_are_fixed_in_place() {
+    let pdf = b"<< /CreationDate (D:20241017093015+02'00') /ModDate (D:20241017093015Z) >>\n\
+        trailer << /ID [<8F3A21> <8f3a21>] >>";
+    let scrubbed = scrub_pdf(pdf);
+    assert_eq!(scrubbed.len(), pdf.len());
+    assert_eq!(
+        String::from_utf8(scrubbed).unwrap(),
+        "<< /CreationDate (D:19700101000000+00'00') /ModDate (D:19700101000000Z) >>\n\
+        trailer << /ID [<000000> <000000>] >>"
+    );
+}
+
+#[test]
+fn png_time_chunks_are_dropped() {
+    let chunk = |kind: &[u8], data: &[u8]| {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(&[0; 4]); // CRC, unchecked
+        chunk
+    };
+    let signature = b"\x89PNG\r\n\x1a\n".to_vec();
+    let header = chunk(b"IHDR", &[0; 13]);
+    let end = chunk(b"IEND", &[]);
+    let png = [
+        signature.clone(),
+        header.clone(),
+        chunk(b"tIME", &[7, 232, 10, 17, 9, 30, 15]),
+        end.clone(),
+    ]
+    .concat();
+    assert_eq!(scrub_png(&png), [signature, header, end].concat());
+}
+
+#[test]
+fn rerenders_are_compared_and_checksummed() {
+    let out = tempfile::tempdir().unwrap();
+    let mut options = RenderOptions::new(out.path(), "#000000");
+    options
+        .pipeline
+        .insert_after("post-process", Scrub)
+        .unwrap();
+    let equations = parse_markdown("$$\na + b\n$$\n%%sum%%\n\n$$\nx^2\n$$\n%%square%%\n");
+    let backend = MockBackend::new();
+    render_equations_with(&equations, &options, &backend).unwrap();
+
+    assert!(verify_reproducible_with(&equations, &options, &backend)
+        .unwrap()
+        .is_empty());
+    let checksums = fs::read_to_string(write_checksums(out.path()).unwrap()).unwrap();
+    let files: Vec<&str> = checksums
+        .lines()
+        .map(|line| line.split_once("  ").unwrap().1)
+        .collect();
+    assert_eq!(files, ["square.svg", "sum.svg"]);
+
+    fs::write(out.path().join("sum.svg"), "<svg/>").unwrap();
+    assert_eq!(
+        verify_reproducible_with(&equations, &options, &backend).unwrap(),
+        ["sum.svg"]
+    );
+}
+
+#[test]
+fn hooks_run_for_the_real_render_only() {
+    if !cfg!(unix) {
+        return;
+    }
+    let out = tempfile::tempdir().unwrap();
+    let logs = tempfile::tempdir().unwrap();
+    let log = logs.path().join("hooks.txt");
+    let mut options = RenderOptions::new(out.path(), "#000000");
+    options.pipeline.push(ShellHook {
+        name: "upload".to_string(),
+        command: format!("echo \"$SIMPTUI_NAME\" >> '{}'", log.display()),
+        on_cache_hit: false,
+    });
+    let equations = parse_markdown("$$\na + b\n$$\n%%sum%%\n");
+    let backend = MockBackend::new();
+    render_equations_with(&equations, &options, &backend).unwrap();
+
+    verify_reproducible_with(&equations, &options, &backend).unwrap();
+    assert_eq!(fs::read_to_string(&log).unwrap(), "sum\n");
+}
+
+#[test]
+fn batch_status_is_not_an_output() {
+    let out = tempfile::tempdir().unwrap();
+    let mut options = RenderOptions::new(out.path(), "#000000");
+    options.status_file = true;
+    let equations = parse_markdown("$$\na + b\n$$\n%%sum%%\n");
+    let backend = MockBackend::new();
+    render_equations_with(&equations, &options, &backend).unwrap();
+    assert!(out.path().join(STATUS_FILE_NAME).exists());
+
+    assert!(verify_reproducible_with(&equations, &options, &backend)
+        .unwrap()
+        .is_empty());
+    let checksums = fs::read_to_string(write_checksums(out.path()).unwrap()).unwrap();
+    assert!(!checksums.contains(STATUS_FILE_NAME));
+}
@@ -0,0 +1,147 @@
+use proptest::prelude::*;
+use simptui::{export_csv, load_equations, parse_csv, parse_markdown, rename_in_source, Equation};
+use std::fs;
+use std::path::Path;
+
+// One generated `$$` block
+#[derive(Debug, Clone)]
+struct Block {
+    prose: String,
+    flag: Option<bool>, // `%%yes%%` / `%%no%%` / nothing
+    lines: Vec<String>,
+}
+
+const TOKENS: [&str; 14] = [
+    "a",
+    "b",
+    "x^2",
+    "y_i",
+    "+",
+    "-",
+    "=",
+    ",",
+    "(",
+    ")",
+    r"\alpha",
+    r"\frac{1}{2}",
+    r"\sqrt{x}",
+    r"\sum_{i=1}^{n}",
+];
+
+fn body_line() -> impl Strategy<Value = String> {
+    prop::collection::vec(prop::sample::select(TOKENS.to_vec()), 1..8)
+        .prop_map(|tokens| tokens.join(" "))
+}
+
+fn block() -> impl Strategy<Value = Block> {
+    (
+        "[A-Za-z][A-Za-z ,.]{0,40}",
+        prop::option::of(any::<bool>()),
+        prop::collection::vec(body_line(), 1..4),
+    )
+        .prop_map(|(prose, flag, lines)| Block { prose, flag, lines })
+}
+
+// Names are numbered, so no duplicate counter gets in the way
+fn document(blocks: &[Block]) -> String {
+    let mut document = String::new();
+    for (i, block) in blocks.iter().enumerate() {
+        document.push_str(&format!("{}\n\n", block.prose));
+        match block.flag {
+            Some(true) => document.push_str("%%yes%%\n"),
+            Some(false) => document.push_str("%%no%%\n"),
+            None => {}
+        }
+        document.push_str(&format!(
+            "$$\n{}\n$$\n%%eq_{}%%\n\n",
+            block.lines.join("\n"),
+            i
+        ));
+    }
+    document
+}
+
+fn summary(equations: &[Equation]) -> Vec<(bool, String, String)> {
+    equations
+        .iter()
+        .map(|equation| {
+            let body = equation.body.split_whitespace().collect::<Vec<_>>();
+            (equation.active, equation.name.clone(), body.join(" "))
+        })
+        .collect()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn generated_blocks_are_found(blocks in prop::collection::vec(block(), 0..6)) {
+        let equations = parse_markdown(&document(&blocks));
+        let expected: Vec<(bool, String, String)> = blocks
+            .iter()
+            .enumerate()
+            .map(|(i, block)| {
+                let body = block.lines.join(" ");
+                (block.flag.unwrap_or(true), format!("eq_{}", i), body)
+            })
+            .collect();
+        prop_assert_eq!(summary(&equations), expected);
+    }
+
+    #[test]
+    fn csv_exports_read_back(blocks in prop::collection::vec(block(), 0..6)) {
+        let equations = parse_markdown(&document(&blocks));
+        let read = parse_csv(&export_csv(&equations));
+        prop_assert_eq!(summary(&read), summary(&equations));
+    }
+
+    #[test]
+    fn renames_are_written_back(blocks in prop::collection::vec(block(), 1..6)) {
+        let dir = tempfile::tempdir().unwrap();
+        let notes = dir.path().join("notes.md");
+        fs::write(&notes, document(&blocks)).unwrap();
+        let equations = load_equations(&notes).unwrap();
+        let names: Vec<String> = (0..equations.len())
+            .map(|i| format!("renamed_{}", i))
+            .collect();
+
+        rename_in_source(&notes, &equations, &names).unwrap();
+        let renamed = load_equations(&notes).unwrap();
+        let expected: Vec<(bool, String, String)> = summary(&equations)
+            .into_iter()
+            .zip(&names)
+            .map(|((active, _, body), name)| (active, name.clone(), body))
+            .collect();
+        prop_assert_eq!(summary(&renamed), expected);
+    }
+
+    #[test]
+    fn any_text_parses_without_panicking(content in "(?s).{0,400}") {
+        parse_markdown(&content);
+        parse_csv(&content);
+    }
+
+    #[test]
+    fn fragments_of_the_syntax_parse_without_panicking(
+        pieces in prop::collection::vec(
+            prop::sample::select(vec![
+                "$$", "%%", "%%yes%%", "%%no%%", "%%skip%%", "%%engine=xelatex%%", "\n",
+                "```\n", r"\begin{align}", r"\end{align}", "x", ",", "\"", "yes,",
+            ]),
+            0..40,
+        )
+    ) {
+        let content = pieces.concat();
+        parse_markdown(&content);
+        parse_csv(&content);
+    }
+}
+
+#[test]
+fn example_notes_round_trip_through_csv() {
+    let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+    let markdown = load_equations(&examples.join("sample.md")).unwrap();
+    let csv = load_equations(&examples.join("equations.csv")).unwrap();
+    assert_eq!(summary(&markdown), summary(&csv));
+    assert_eq!(summary(&parse_csv(&export_csv(&markdown))), summary(&csv));
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use common::MockBackend;
+use regex::Regex;
+use simptui::{
+    parse_markdown, read_manifest, render_equations_with, Config, OutputRoute, RenderOptions,
+};
+use std::fs;
+use std::path::Path;
+
+const NOTES: &str = "\
+%%tags=appendix,draft%%
+$$
+a + b = c
+$$
+%%sum%%
+
+$$
+x^2
+$$
+%%lecture_square%%
+
+$$
+e = mc^2
+$$
+%%energy%%
+";
+
+#[test]
+fn routed_equations_land_in_their_subdirectory() {
+    let out = tempfile::tempdir().unwrap();
+    let mut options = RenderOptions::new(out.path(), "#000000");
+    options.routes = vec![
+        OutputRoute::new(Some("appendix"), None, Path::new("appendix")).unwrap(),
+        OutputRoute::new(
+            None,
+            Some(Regex::new("^lecture_").unwrap()),
+            Path::new("lectures"),
+        )
+        .unwrap(),
+    ];
+    let equations = parse_markdown(NOTES);
+    assert_eq!(equations[0].tags, ["appendix", "draft"]);
+
+    let report = render_equations_with(&equations, &options, &MockBackend::new()).unwrap();
+
+    assert_eq!(report.rendered, ["sum", "lecture_square", "energy"]);
+    assert!(out.path().join("appendix/sum.svg").exists());
+    assert!(out.path().join("lectures/lecture_square.svg").exists());
+    assert!(out.path().join("energy.svg").exists());
+    let manifest = read_manifest(out.path());
+    assert_eq!(manifest["sum"], "appendix/sum.svg");
+    assert_eq!(manifest["lecture_square"], "lectures/lecture_square.svg");
+    assert_eq!(manifest["energy"], "energy.svg");
+
+    // Rerouted, the output moves and the old one goes
+    options.routes = vec![OutputRoute::new(Some("draft"), None, Path::new("drafts")).unwrap()];
+    render_equations_with(&equations, &options, &MockBackend::new()).unwrap();
+    assert!(out.path().join("drafts/sum.svg").exists());
+    assert!(!out.path().join("appendix/sum.svg").exists());
+    assert!(out.path().join("lecture_square.svg").exists());
+    assert!(!out.path().join("lectures/lecture_square.svg").exists());
+}
+
+#[test]
+fn routes_are_read_from_the_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_file = dir.path().join("simptui.toml");
+    fs::write(
+        &config_file,
+        "[[route]]\ntag = \"appendix\"\ndir = \"appendix\"\n\n[[route]]\nname = \"^lec\"\ndir = \"out/lectures\"\n",
+    )
+    .unwrap();
+    let routes = Config::from_file(&config_file).unwrap().routes().unwrap();
+    assert_eq!(routes.len(), 2);
+    assert_eq!(routes[1].dir, Path::new("out/lectures"));
+
+    fs::write(&config_file, "[[route]]\ndir = \"../elsewhere\"\n").unwrap();
+    assert!(Config::from_file(&config_file).unwrap().routes().is_err());
+}
+
+#[test]
+fn routed_failures_stay_with_their_route() {
+    let out = tempfile::tempdir().unwrap();
+    let mut options = RenderOptions::new(out.path(), "#000000");
+    options.routes = vec![OutputRoute::new(
+        None,
+        Some(Regex::new("^lecture_").unwrap()),
+        Path::new("lectures"),
+    )
+    .unwrap()];
+    let backend = MockBackend::new().failing("x^2");
+    let report = render_equations_with(&parse_markdown(NOTES), &options, &backend).unwrap();
+
+    assert_eq!(report.failed[0].name, "lecture_square");
+    let failed = out.path().join("lectures/failed");
+    assert!(failed.join("lecture_square.tex").exists());
+    assert!(!out.path().join("failed").exists());
+}
@@ -0,0 +1,55 @@
+use simptui::{equation_sections, headings, in_section, parse_markdown};
+
+const NOTES: &str = "\
+$$
+z
+$$
+%%zero%%
+
+# Mechanics
+
+$$
+F = ma
+$$
+%%newton%%
+
+```
+# not a heading
+```
+
+## Energy ##
+$$
+E = mc^2
+$$
+%%energy%%
+";
+
+#[test]
+fn equations_fall_under_the_heading_above_them() {
+    let titles: Vec<(usize, String)> = headings(NOTES)
+        .into_iter()
+        .map(|heading| (heading.level, heading.title))
+        .collect();
+    assert_eq!(
+        titles,
+        [(1, "Mechanics".to_string()), (2, "Energy".to_string())]
+    );
+
+    let (headings, sections) = equation_sections(NOTES, &parse_markdown(NOTES));
+    assert_eq!(headings.len(), 2);
+    assert_eq!(sections, [None, Some(0), Some(1)]);
+}
+
+#[test]
+fn a_section_takes_in_its_subsections() {
+    let equations = parse_markdown(NOTES);
+    assert_eq!(
+        in_section(NOTES, &equations, "mechanics"),
+        [false, true, true]
+    );
+    assert_eq!(
+        in_section(NOTES, &equations, "Energy"),
+        [false, false, true]
+    );
+    assert_eq!(in_section(NOTES, &equations, "Optics"), [false; 3]);
+}
@@ -0,0 +1,32 @@
+use simptui::load_snippets;
+use std::collections::BTreeMap;
+use std::fs;
+
+#[test]
+fn user_snippets_extend_and_override_the_built_in_ones() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("physics.toml"),
+        "hamiltonian = 'H = \\frac{p^2}{2m} + V(x)'\nfraction = '\\tfrac{a}{b}'\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("broken.toml"), "not toml").unwrap();
+    fs::write(dir.path().join("notes.txt"), "ignored = 'x'").unwrap();
+    let configured = BTreeMap::from([("norm".to_string(), "\\|x\\|".to_string())]);
+
+    let snippets = load_snippets(&configured, dir.path());
+    let body = |name: &str| {
+        snippets
+            .iter()
+            .find(|snippet| snippet.name == name)
+            .map(|snippet| snippet.body.as_str())
+    };
+    assert_eq!(body("fraction"), Some("\\tfrac{a}{b}"));
+    assert_eq!(body("norm"), Some("\\|x\\|"));
+    assert_eq!(body("hamiltonian"), Some("H = \\frac{p^2}{2m} + V(x)"));
+    assert_eq!(body("ignored"), None);
+    assert!(body("matrix-2x2").unwrap().contains("pmatrix"));
+    // Built-in snippets keep their place; new ones come last
+    assert_eq!(snippets[0].name, "fraction");
+    assert_eq!(snippets.last().unwrap().name, "hamiltonian");
+}
@@ -0,0 +1,26 @@
+use simptui::{decode_source, load_source, parse_markdown};
+use std::fs;
+
+#[test]
+fn bom_and_crlf_are_normalized() {
+    let text = decode_source(b"\xEF\xBB\xBF$$\r\na + b\r\n$$\r\n%%sum%%\r\n");
+    assert_eq!(text, "$$\na + b\n$$\n%%sum%%\n");
+
+    let equations = parse_markdown(&text);
+    assert_eq!(equations[0].name, "sum");
+    assert_eq!(equations[0].body.trim(), "a + b");
+}
+
+#[test]
+fn latin1_and_utf16_files_decode() {
+    let dir = tempfile::tempdir().unwrap();
+    let latin1 = dir.path().join("latin1.md");
+    fs::write(&latin1, b"Fl\xE4che\n").unwrap();
+    assert_eq!(load_source(&latin1).unwrap(), "Fläche\n");
+
+    let utf16: Vec<u8> = [0xFF, 0xFE]
+        .into_iter()
+        .chain("x\r\n".encode_utf16().flat_map(u16::to_le_bytes))
+        .collect();
+    assert_eq!(decode_source(&utf16), "x\n");
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use common::MockBackend;
+use simptui::{
+    add_svg_source_map, parse_markdown, read_manifest, read_svg_source_map, render_equations_with,
+    strip_svg_source_map, OutputNaming, RenderOptions, Retention, SourceMap, SourceSpan,
+};
+use std::fs;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+const NOTES: &str = "# Sums\n\n$$\na + b\n$$\n%%sum%%\n";
+
+#[test]
+fn outputs_name_their_source() {
+    let out = TempDir::new().unwrap();
+    let mut options = RenderOptions::new(out.path(), "#000000");
+    options.source_file = Some(PathBuf::from("notes/a&b.md"));
+    options.source_map = true;
+    options.retention = Retention::KeepAll;
+    options.naming = OutputNaming::Number;
+    render_equations_with(&parse_markdown(NOTES), &options, &MockBackend::new()).unwrap();
+
+    let svg = fs::read_to_string(out.path().join("001.svg")).unwrap();
+    let map = read_svg_source_map(&svg).unwrap();
+    assert_eq!(map.file.as_deref(), Some("notes/a&b.md"));
+    assert_eq!(map.name, "sum");
+    assert_eq!(
+        map.span,
+        Some(SourceSpan {
+            start_line: 3,
+            end_line: 6
+        })
+    );
+    assert_eq!(map.version, env!("CARGO_PKG_VERSION"));
+    assert!(svg.contains("sum from notes/a&amp;b.md:3-6"));
+
+    let tex = fs::read_to_string(out.path().join("001.tex")).unwrap();
+    assert_eq!(tex.lines().next(), Some(map.tex_comment().trim_end()));
+}
+
+#[test]
+fn maps_are_replaced_not_stacked() {
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><path d="M0 0"/></svg>"#;
+    let map = |name: &str| SourceMap {
+        file: None,
+        span: None,
+        name: name.to_string(),
+        version: "1.2.3".to_string(),
+    };
+    let once = add_svg_source_map(svg, &map("a"));
+    let twice = add_svg_source_map(&once, &map("b"));
+    assert_eq!(twice.matches("<desc").count(), 1);
+    assert_eq!(read_svg_source_map(&twice), Some(map("b")));
+    assert_eq!(strip_svg_source_map(&twice), svg);
+    assert_eq!(read_svg_source_map(svg), None);
+}
+
+#[test]
+fn maps_are_opt_in_and_left_out_of_hashes() {
+    let out = TempDir::new().unwrap();
+    let mut options = RenderOptions::new(out.path(), "#000000");
+    options.source_file = Some(PathBuf::from("notes.md"));
+    options.hash_names = true;
+    let render = |options: &RenderOptions, notes: &str| {
+        render_equations_with(&parse_markdown(notes), options, &MockBackend::new()).unwrap();
+        read_manifest(out.path())["sum"].clone()
+    };
+
+    let plain = render(&options, NOTES);
+    let svg = fs::read_to_string(out.path().join(&plain)).unwrap();
+    assert_eq!(read_svg_source_map(&svg), None);
+
+    options.source_map = true;
+    assert_eq!(render(&options, NOTES), plain);
+    let moved = format!("\n\n{}", NOTES);
+    assert_eq!(render(&options, &moved), plain);
+    let svg = fs::read_to_string(out.path().join(&plain)).unwrap();
+    assert_eq!(
+        read_svg_source_map(&svg).unwrap().span.unwrap().start_line,
+        5
+    );
+}
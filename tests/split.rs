@@ -0,0 +1,27 @@
+use simptui::{split_lines, Equation};
+
+#[test]
+fn align_lines_become_separate_equations() {
+    let eq = Equation::new(
+        true,
+        "steps",
+        r"\begin{align} f(x) &= (x+1)^2 \label{eq:f} \\[4pt] &= x^2 + 2x + 1 \nonumber \end{align}",
+    );
+    let lines = split_lines(&eq);
+
+    let names: Vec<&str> = lines.iter().map(|eq| eq.name.as_str()).collect();
+    assert_eq!(names, ["steps_l1", "steps_l2"]);
+    assert_eq!(lines[0].body, "f(x) = (x+1)^2");
+    assert_eq!(lines[1].body, "= x^2 + 2x + 1");
+}
+
+#[test]
+fn nested_environments_stay_whole() {
+    let body = r"|x| = \begin{cases} x & x \ge 0 \\ -x & x < 0 \end{cases}";
+    let eq = Equation::new(true, "abs", body);
+    let lines = split_lines(&eq);
+
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0].name, "abs");
+    assert_eq!(lines[0].body, body);
+}
@@ -0,0 +1,45 @@
+use simptui::SiteFlavor;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn flavors_place_assets_and_shortcodes() {
+    let site = Path::new("site");
+    let zola: SiteFlavor = "zola".parse().unwrap();
+    assert_eq!(
+        zola.asset_dir(site, "equations"),
+        Path::new("site/static/equations")
+    );
+    assert_eq!(zola.snippet("energy"), r#"{{ math(name="energy") }}"#);
+    assert_eq!(
+        SiteFlavor::Hugo.snippet("mass energy"),
+        r#"{{< math name="mass_energy" >}}"#
+    );
+    assert_eq!(
+        SiteFlavor::Jekyll.asset_dir(site, "eq"),
+        Path::new("site/assets/eq")
+    );
+    assert!("gatsby".parse::<SiteFlavor>().is_err());
+}
+
+#[test]
+fn existing_shortcodes_are_kept() {
+    let site = tempfile::tempdir().unwrap();
+    let written = SiteFlavor::Jekyll
+        .write_shortcode(site.path(), "eq")
+        .unwrap()
+        .unwrap();
+    assert_eq!(written, site.path().join("_includes/math.html"));
+    assert!(fs::read_to_string(&written)
+        .unwrap()
+        .contains("'/assets/eq/'"));
+
+    fs::write(&written, "custom").unwrap();
+    assert_eq!(
+        SiteFlavor::Jekyll
+            .write_shortcode(site.path(), "eq")
+            .unwrap(),
+        None
+    );
+    assert_eq!(fs::read_to_string(&written).unwrap(), "custom");
+}
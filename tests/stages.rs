@@ -0,0 +1,168 @@
+mod common;
+
+use common::MockBackend;
+use simptui::{
+    parse_markdown, render_equations_with, Config, RenderOptions, RenderStage, ShellHook,
+    StageContext,
+};
+use std::fs;
+use std::io;
+use tempfile::TempDir;
+
+const NOTES: &str = "$$\nx^2\n$$\n%%square%%\n";
+
+// Appends a comment to the SVG
+struct Watermark;
+
+impl RenderStage for Watermark {
+    fn name(&self) -> &str {
+        "watermark"
+    }
+
+    fn run(&self, context: &StageContext) -> io::Result<()> {
+        let svg = fs::read_to_string(&context.output_file)?;
+        fs::write(&context.output_file, svg + "<!-- watermark -->")
+    }
+}
+
+#[test]
+fn custom_stages_run_in_place_and_key_the_cache() {
+    let out = TempDir::new().unwrap();
+    let cache = TempDir::new().unwrap();
+    let equations = parse_markdown(NOTES);
+    let mut options = RenderOptions::new(out.path(), "#000000");
+    options.cache_dir = Some(cache.path().to_path_buf());
+
+    // Without the stage, to fill the cache with an unmarked render
+    render_equations_with(&equations, &options, &MockBackend::new()).unwrap();
+
+    options.pipeline.insert_after("convert", Watermark).unwrap();
+    assert_eq!(
+        options.pipeline.names(),
+        [
+            "generate-tex",
+            "compile",
+            "convert",
+            "watermark",
+            "post-process",
+            "cleanup"
+        ]
+    );
+    let backend = MockBackend::new();
+    render_equations_with(&equations, &options, &backend).unwrap();
+    assert_eq!(backend.compile_count(), 1, "a new stage misses the cache");
+    let svg = fs::read_to_string(out.path().join("square.svg")).unwrap();
+    assert!(svg.ends_with("<!-- watermark -->"));
+
+    assert!(options.pipeline.insert_before("upload", Watermark).is_err());
+}
+
+#[test]
+fn config_hooks_run_shell_commands() {
+    let out = TempDir::new().unwrap();
+    let config_file = out.path().join("simptui.toml");
+    fs::write(
+        &config_file,
+        r#"
+[[hook]]
+name = "list"
+run = 'echo "$SIMPTUI_NAME" >> hooks.txt'
+
+[[hook]]
+name = "check"
+run = 'test -f "$SIMPTUI_TEX" && echo tex >> hooks.txt'
+after = "generate-tex"
+
+[notify]
+after_batch = "echo done"
+"#,
+    )
+    .unwrap();
+    let config = Config::from_file(&config_file).unwrap();
+    let mut options = RenderOptions::new(out.path().join("eq"), "#000000");
+    config.apply_hooks(&mut options).unwrap();
+    assert_eq!(options.hooks.after_batch.as_deref(), Some("echo done"));
+    assert_eq!(
+        options.pipeline.names(),
+        [
+            "generate-tex",
+            "check",
+            "compile",
+            "convert",
+            "post-process",
+            "list",
+            "cleanup"
+        ]
+    );
+
+    if cfg!(unix) {
+        let equations = parse_markdown(NOTES);
+        render_equations_with(&equations, &options, &MockBackend::new()).unwrap();
+        let log = fs::read_to_string(out.path().join("eq/hooks.txt")).unwrap();
+        assert_eq!(log, "tex\nsquare\n");
+    }
+}
+
+#[test]
+fn hook_commands_key_the_cache() {
+    let out = TempDir::new().unwrap();
+    let cache = TempDir::new().unwrap();
+    let equations = parse_markdown(NOTES);
+    let with_hook = |command: &str| {
+        let mut options = RenderOptions::new(out.path(), "#000000");
+        options.cache_dir = Some(cache.path().to_path_buf());
+        let hook = ShellHook {
+            name: "greet".to_string(),
+            command: command.to_string(),
+            on_cache_hit: false,
+        };
+        options.pipeline.push(hook);
+        options
+    };
+    let compiles = |options: &RenderOptions| {
+        let backend = MockBackend::new();
+        render_equations_with(&equations, options, &backend).unwrap();
+        backend.compile_count()
+    };
+
+    assert_eq!(compiles(&with_hook("echo hello")), 1);
+    assert_eq!(compiles(&with_hook("echo hello")), 0);
+    assert_eq!(
+        compiles(&with_hook("echo goodbye")),
+        1,
+        "a new command misses the cache"
+    );
+}
+
+#[test]
+fn batch_hooks_hear_about_every_equation() {
+    if !cfg!(unix) {
+        return;
+    }
+    let out = TempDir::new().unwrap();
+    let log = out.path().join("hooks.txt");
+    let mut options = RenderOptions::new(out.path().join("eq"), "#000000");
+    options.hooks.before_batch = Some(format!(
+        r#"echo "start $SIMPTUI_EQUATIONS" >> {}"#,
+        log.display()
+    ));
+    options.hooks.after_equation = Some(format!(
+        r#"echo "$SIMPTUI_NAME $SIMPTUI_STATUS $(basename "$SIMPTUI_OUTPUT")" >> {}"#,
+        log.display()
+    ));
+    options.hooks.after_batch = Some(format!(
+        r#"echo "end $SIMPTUI_STATUS $SIMPTUI_RENDERED/$SIMPTUI_FAILED" >> {}"#,
+        log.display()
+    ));
+    let equations = parse_markdown("$$\nx^2\n$$\n%%square%%\n$$\n\\broken\n$$\n%%broken%%\n");
+    let backend = MockBackend::new().failing(r"\broken");
+    render_equations_with(&equations, &options, &backend).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(&log).unwrap(),
+        "start 2\nsquare rendered square.svg\nbroken failed \nend failed 1/1\n"
+    );
+
+    options.hooks.before_batch = Some("exit 3".to_string());
+    assert!(render_equations_with(&equations, &options, &backend).is_err());
+}
@@ -0,0 +1,53 @@
+use simptui::{compile_timeout, complexity, parse_markdown, EquationStats};
+use std::time::Duration;
+
+#[test]
+fn stats_rank_equations_and_count_usage() {
+    let notes = "\
+$$
+\\ce{H2O} + \\frac{a}{\\sqrt{b}}
+$$
+%%water%%
+
+$$
+a+b
+$$
+%%sum%%
+
+$$
+a + b
+$$
+%%again%%
+
+\\begin{align}
+x &= \\frac{1}{2} \\\\
+y &= 3
+\\end{align}
+%%lines%%
+";
+    let stats = EquationStats::of(&parse_markdown(notes), 2);
+    assert_eq!((stats.equations, stats.active), (4, 4));
+    assert_eq!(stats.distinct, 3); // `a+b` and `a + b` render alike
+    assert_eq!(stats.longest[0].name, "water");
+    assert_eq!(stats.longest.len(), 2);
+    assert_eq!(stats.commands[0], (r"\frac".to_string(), 2));
+    assert_eq!(stats.environments, [("align".to_string(), 1)]);
+    assert_eq!(
+        stats.packages,
+        [("water".to_string(), vec!["mhchem".to_string()])]
+    );
+
+    assert_eq!(complexity("a + b"), 0);
+    assert_eq!(complexity(r"\frac{a}{\sqrt{b}}"), 2 + 2 * 2);
+    assert_eq!(complexity(r"a \\ b"), 3);
+}
+
+#[test]
+fn timeouts_grow_with_complexity() {
+    let base = Duration::from_secs(30);
+    assert_eq!(compile_timeout("a + b", base), base);
+    // 40 commands buy another `base`, their 240 bytes a little more
+    let forty = r"\alpha".repeat(40);
+    assert_eq!(compile_timeout(&forty, base), base.mul_f64(2.075));
+    assert_eq!(compile_timeout(&r"\x".repeat(5000), base), base * 10);
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use common::MockBackend;
+use simptui::{
+    parse_markdown, read_status, render_equations_with, JobState, RenderOptions, LOCK_FILE_NAME,
+    STATUS_FILE_NAME,
+};
+use std::fs;
+use tempfile::TempDir;
+
+const NOTES: &str = "$$\na + b\n$$\n%%sum%%\n\n$$\n\\broken\n$$\n%%bad%%\n";
+
+#[test]
+fn batches_leave_their_status_behind() {
+    let out = TempDir::new().unwrap();
+    let mut options = RenderOptions::new(out.path(), "#000000");
+    options.status_file = true;
+    options.source_name = Some("notes".to_string());
+    let backend = MockBackend::new().failing("broken");
+
+    render_equations_with(&parse_markdown(NOTES), &options, &backend).unwrap();
+
+    let status = read_status(out.path()).unwrap().unwrap();
+    assert_eq!(status.state, JobState::Failed);
+    assert_eq!(status.source.as_deref(), Some("notes"));
+    assert_eq!((status.done, status.total, status.failed), (2, 2, 1));
+    let last = status.last.unwrap();
+    assert_eq!(
+        (last.name.as_str(), last.status.as_str()),
+        ("bad", "failed")
+    );
+    assert!(last.error.is_some());
+    assert_eq!(status.summary.as_deref(), Some("1 rendered, 1 failed"));
+    assert!(!out.path().join(LOCK_FILE_NAME).exists());
+}
+
+#[test]
+fn held_locks_stop_a_second_batch() {
+    let out = TempDir::new().unwrap();
+    let mut options = RenderOptions::new(out.path(), "#000000");
+    options.status_file = true;
+    let equations = parse_markdown(NOTES);
+
+    // This process is alive, so its lock holds
+    fs::write(
+        out.path().join(LOCK_FILE_NAME),
+        std::process::id().to_string(),
+    )
+    .unwrap();
+    let error = render_equations_with(&equations, &options, &MockBackend::new()).unwrap_err();
+    assert!(error.to_string().contains("another simptui"), "{}", error);
+    assert!(read_status(out.path()).unwrap().is_none());
+
+    // One left by a process that is gone is taken over
+    fs::write(out.path().join(LOCK_FILE_NAME), u32::MAX.to_string()).unwrap();
+    render_equations_with(&equations, &options, &MockBackend::new()).unwrap();
+    assert!(out.path().join(STATUS_FILE_NAME).exists());
+    assert!(!out.path().join(LOCK_FILE_NAME).exists());
+}
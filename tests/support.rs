@@ -0,0 +1,41 @@
+use simptui::{unsupported_constructs, Engine, Equation, Font, OutputFormat, RenderOptions};
+
+#[test]
+fn constructs_are_checked_against_the_backend() {
+    let equations = [
+        Equation::new(true, "bold", r"\symbf{v} = \xfrac{1}{2}"),
+        Equation::new(true, "greek", "α + β"),
+        Equation::new(false, "off", r"\symbf{x}"),
+    ];
+    let mut options = RenderOptions::new("out", "#000000");
+    options.engine = Engine::Pdflatex;
+    let found = unsupported_constructs(&equations, &options);
+    let constructs: Vec<(&str, &str)> = found
+        .iter()
+        .map(|issue| (issue.equation.as_str(), issue.construct.as_str()))
+        .collect();
+    assert_eq!(constructs, [("bold", r"\symbf"), ("greek", "'α'")]);
+    assert_eq!(found[0].backend, "pdflatex with font gfs-neohellenic");
+    assert_eq!(
+        found[0].fallback,
+        "--engine xelatex or lualatex and --font latin-modern"
+    );
+
+    // unicode-math handles both, MathML only lacks \xfrac
+    options.engine = Engine::Lualatex;
+    options.font = Font::Stix2;
+    assert!(unsupported_constructs(&equations, &options).is_empty());
+    options.format = OutputFormat::MathML;
+    let found = unsupported_constructs(&equations, &options);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].construct, r"\xfrac");
+
+    // Environments are checked in the form they're rendered in
+    let mut align = Equation::new(true, "align", "a &= b \\\\ c &= d");
+    align.environment = Some("align".to_string());
+    assert!(unsupported_constructs(&[align], &options).is_empty());
+    let mut alignat = Equation::new(true, "alignat", "{2} a &= b");
+    alignat.environment = Some("alignat".to_string());
+    let found = unsupported_constructs(&[alignat], &options);
+    assert_eq!(found[0].construct, r"\begin{alignedat}");
+}
@@ -0,0 +1,106 @@
+use simptui::{
+    append_equation, export_toml, parse_toml, rename_in_source, reorder_toml_file, Engine,
+    FontSize, ParserRegistry,
+};
+use std::fs;
+use std::path::Path;
+
+const EQUATIONS: &str = r##"# Shared by the lecture notes
+
+[[equation]]
+name = "euler"
+body = 'e^{i\pi} + 1 = 0'
+tags = ["identities"]
+color = "#CC3333"
+
+# Spans lines
+[[equation]]
+body = '''
+a &= b \\
+  &= c
+'''
+active = false
+engine = "lualatex"
+packages = ["physics"]
+size = "14pt"
+"##;
+
+#[test]
+fn tables_read_into_equations() {
+    let equations = parse_toml(EQUATIONS);
+    assert_eq!(equations.len(), 2);
+    let euler = &equations[0];
+    assert_eq!(euler.name, "euler");
+    assert_eq!(euler.body, r"e^{i\pi} + 1 = 0");
+    assert!(euler.active);
+    assert_eq!(euler.tags, ["identities"]);
+    assert_eq!(euler.color.as_deref(), Some("#cc3333"));
+    assert_eq!(euler.span.map(|span| span.start_line), Some(3));
+
+    let aligned = &equations[1];
+    assert_eq!(aligned.name, "default_equation");
+    assert_eq!(aligned.body, "a &= b \\\\\n  &= c");
+    assert!(!aligned.active);
+    assert_eq!(aligned.engine, Some(Engine::Lualatex));
+    assert_eq!(aligned.packages, ["physics"]);
+    assert_eq!(aligned.size, Some(FontSize::Points(14.0)));
+    let span = aligned.span.unwrap();
+    assert_eq!((span.start_line, span.end_line), (10, 18));
+
+    assert!(parse_toml("body = [").is_empty());
+}
+
+#[test]
+fn exported_tables_read_back_the_same() {
+    let equations = parse_toml(EQUATIONS);
+    let toml = export_toml(&equations);
+    let read = parse_toml(&toml);
+    assert_eq!(read.len(), equations.len());
+    for (read, equation) in read.iter().zip(&equations) {
+        assert_eq!(read.body, equation.body);
+        assert_eq!(read.name, equation.name);
+        assert_eq!(read.active, equation.active);
+        assert_eq!(read.tags, equation.tags);
+        assert_eq!(read.color, equation.color);
+        assert_eq!(read.engine, equation.engine);
+        assert_eq!(read.size, equation.size);
+    }
+    // Bodies a literal string can't hold
+    let quoted = parse_toml(&export_toml(&parse_toml(
+        "[[equation]]\nbody = \"\\\\text{it's} '''\"\n",
+    )));
+    assert_eq!(quoted[0].body, r"\text{it's} '''");
+}
+
+#[test]
+fn toml_files_are_detected_by_their_tables() {
+    let registry = ParserRegistry::default();
+    let detection = registry.detect(Path::new("notes.txt"), EQUATIONS).unwrap();
+    assert_eq!(detection.parser.name(), "toml");
+}
+
+#[test]
+fn toml_files_are_renamed_appended_to_and_reordered_in_place() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("equations.toml");
+    fs::write(&path, EQUATIONS).unwrap();
+
+    let equations = parse_toml(EQUATIONS);
+    let names = ["euler_identity".to_string(), "aligned".to_string()];
+    rename_in_source(&path, &equations, &names).unwrap();
+    append_equation(&path, "pythagoras", "a^2 + b^2 = c^2").unwrap();
+    let content = fs::read_to_string(&path).unwrap();
+    assert!(content.starts_with("# Shared by the lecture notes\n"));
+    let renamed: Vec<String> = parse_toml(&content).into_iter().map(|eq| eq.name).collect();
+    assert_eq!(renamed, ["euler_identity", "aligned", "pythagoras"]);
+
+    let mut equations = parse_toml(&content);
+    equations.reverse();
+    reorder_toml_file(&path, &equations).unwrap();
+    let content = fs::read_to_string(&path).unwrap();
+    let reordered: Vec<String> = parse_toml(&content).into_iter().map(|eq| eq.name).collect();
+    assert_eq!(reordered, ["pythagoras", "aligned", "euler_identity"]);
+    // The comment stays above its table
+    assert!(content.contains("# Spans lines\n[[equation]]\nname = \"aligned\""));
+    assert!(content.starts_with("# Shared by the lecture notes\n\n[[equation]]"));
+}
@@ -0,0 +1,16 @@
+use simptui::{find_tool, tex_path, tool_command};
+use std::path::Path;
+
+#[test]
+fn tools_are_found_on_the_path() {
+    let sh = find_tool("sh").expect("sh is on the PATH");
+    assert!(sh.is_absolute());
+    assert_eq!(tool_command("sh").get_program(), sh.as_os_str());
+
+    assert_eq!(find_tool("simptui-no-such-tool"), None);
+    assert_eq!(
+        tool_command("simptui-no-such-tool").get_program(),
+        "simptui-no-such-tool"
+    );
+    assert_eq!(tex_path(Path::new("out/a.tex")), "out/a.tex");
+}
@@ -0,0 +1,45 @@
+use simptui::{svg_difference, verify_renders, Verdict};
+use std::fs;
+
+fn svg(bar_width: u32) -> String {
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="10"><rect width="{}" height="10"/></svg>"#,
+        bar_width
+    )
+}
+
+#[test]
+fn changed_pixels_are_reported_as_a_percentage() {
+    let rendered = tempfile::tempdir().unwrap();
+    let baseline = tempfile::tempdir().unwrap();
+    fs::write(rendered.path().join("same.svg"), svg(20)).unwrap();
+    fs::write(baseline.path().join("same.svg"), svg(20)).unwrap();
+    fs::write(rendered.path().join("wider.svg"), svg(30)).unwrap();
+    fs::write(baseline.path().join("wider.svg"), svg(20)).unwrap();
+    fs::write(rendered.path().join("new.svg"), svg(20)).unwrap();
+
+    let difference = svg_difference(
+        &rendered.path().join("wider.svg"),
+        &baseline.path().join("wider.svg"),
+    )
+    .unwrap();
+    assert!((difference - 25.0).abs() < 0.01);
+
+    let names = ["same", "wider", "new"].map(String::from);
+    let verdicts: Vec<Verdict> = verify_renders(&names, rendered.path(), baseline.path(), 0.0)
+        .unwrap()
+        .into_iter()
+        .map(|result| result.verdict)
+        .collect();
+    assert_eq!(
+        verdicts,
+        [
+            Verdict::Unchanged,
+            Verdict::Changed(difference),
+            Verdict::NoBaseline
+        ]
+    );
+
+    let tolerant = verify_renders(&names[1..2], rendered.path(), baseline.path(), 30.0).unwrap();
+    assert_eq!(tolerant[0].verdict, Verdict::Unchanged);
+}
@@ -0,0 +1,28 @@
+use simptui::{find_rendered, open_in_viewer, update_manifest, Manifest, OutputFormat};
+use std::collections::BTreeMap;
+use std::fs;
+
+#[test]
+fn rendered_outputs_are_found_by_name_format_and_manifest() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path();
+    fs::write(out.join("sum.pdf"), "").unwrap();
+    fs::write(out.join("sum.png"), "").unwrap();
+    fs::write(out.join("003.svg"), "").unwrap();
+    let mut manifest = Manifest::new();
+    manifest.insert("square".to_string(), "003.svg".to_string());
+    update_manifest(out, manifest).unwrap();
+
+    assert_eq!(find_rendered(out, "sum", None), Some(out.join("sum.pdf")));
+    let png = find_rendered(out, "sum", Some(OutputFormat::Png));
+    assert_eq!(png, Some(out.join("sum.png")));
+    assert_eq!(find_rendered(out, "sum", Some(OutputFormat::Svg)), None);
+    assert_eq!(
+        find_rendered(out, "square", None),
+        Some(out.join("003.svg"))
+    );
+    assert_eq!(find_rendered(out, "missing", None), None);
+
+    let viewers = BTreeMap::from([("pdf".to_string(), " ".to_string())]);
+    assert!(open_in_viewer(&out.join("sum.pdf"), &viewers).is_err());
+}
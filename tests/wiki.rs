@@ -0,0 +1,78 @@
+use simptui::{detect_file_type, parse_wiki, ParserRegistry};
+use std::fs;
+
+#[test]
+fn math_is_named_after_its_section() {
+    let wikitext = "\
+'''Euler's identity''' is <math>e^{i\\pi} + 1 = 0</math>.
+
+== Derivation ==
+From <math display=\"block\">e^{ix} = \\cos x + i \\sin x</math>
+and <math>x &lt; y</math>.
+<!-- <math>commented out</math> -->
+<nowiki><math>shown as text</math></nowiki>
+
+=== Water ===
+<chem>2H2 + O2 -> 2H2O</chem>
+";
+    let equations = parse_wiki(wikitext);
+    let names: Vec<&str> = equations.iter().map(|eq| eq.name.as_str()).collect();
+    assert_eq!(
+        names,
+        ["default_equation", "derivation", "derivation_1", "water"]
+    );
+    assert_eq!(equations[1].body, r"e^{ix} = \cos x + i \sin x");
+    assert_eq!(equations[2].body, "x < y");
+    assert_eq!(equations[3].body, r"\ce{2H2 + O2 -> 2H2O}");
+    assert_eq!(equations[3].packages, ["mhchem"]);
+    assert_eq!(equations[1].span.unwrap().start_line, 4);
+}
+
+#[test]
+fn dumps_are_read_page_by_page() {
+    let dump = r#"<mediawiki xmlns="http://www.mediawiki.org/xml/export-0.11/">
+  <page>
+    <title>Pythagorean theorem</title>
+    <revision>
+      <text bytes="80" xml:space="preserve">&lt;math&gt;a^2 + b^2 = c^2&lt;/math&gt;
+== Proof ==
+&lt;math&gt;x &amp;lt; y&lt;/math&gt;</text>
+    </revision>
+  </page>
+  <page>
+    <title>Empty</title>
+    <revision><text bytes="0" /></revision>
+  </page>
+</mediawiki>
+"#;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("enwiki-pages.xml");
+    fs::write(&path, dump).unwrap();
+
+    assert_eq!(detect_file_type(&path), "wiki");
+    let equations = ParserRegistry::default().load(&path).unwrap();
+    let found: Vec<(&str, &str)> = equations
+        .iter()
+        .map(|eq| (eq.name.as_str(), eq.body.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("pythagorean_theorem", "a^2 + b^2 = c^2"),
+            ("pythagorean_theorem_proof", "x < y"),
+        ]
+    );
+    assert_eq!(equations[1].span.unwrap().start_line, 7);
+}
+
+#[test]
+fn setext_underlines_are_not_wiki_headings() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes");
+    fs::write(&path, "Notes\n=====\n\n<math>x</math> in prose\n").unwrap();
+    assert_ne!(detect_file_type(&path), "wiki");
+
+    let wiki = dir.path().join("article");
+    fs::write(&wiki, "== Notes ==\n<math>x</math>\n").unwrap();
+    assert_eq!(detect_file_type(&wiki), "wiki");
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use common::MockBackend;
+use simptui::{
+    export_csv, parse_csv, parse_markdown, render_equations_with, unwrap_body, MathWrap,
+    RenderOptions, Retention,
+};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn auto_goes_by_the_body() {
+    let auto = |body: &str| unwrap_body(body, None, MathWrap::Auto);
+    assert_eq!(auto("x^2"), (MathWrap::Inline, "x^2".to_string()));
+    assert_eq!(auto(r"\[ x^2 \]"), (MathWrap::Display, " x^2 ".to_string()));
+    assert_eq!(auto("$$x$$"), (MathWrap::Display, "x".to_string()));
+    assert_eq!(auto(r"\(x\)"), (MathWrap::Inline, "x".to_string()));
+    assert_eq!(
+        auto(r"$x = \$5$"),
+        (MathWrap::Inline, r"x = \$5".to_string())
+    );
+    assert_eq!(
+        auto(r"$a$ and $b$"),
+        (MathWrap::None, r"$a$ and $b$".to_string())
+    );
+    // Math inside text is still inside math
+    assert_eq!(
+        auto(r"f(x) = x^2 \text{ for $x>0$}"),
+        (
+            MathWrap::Inline,
+            r"f(x) = x^2 \text{ for $x>0$}".to_string()
+        )
+    );
+    assert_eq!(auto(r"\mbox{$a$} + \(b\)").0, MathWrap::None);
+    assert_eq!(
+        auto("\\begin{align}\na &= b \\tag{1}\n\\end{align}"),
+        (
+            MathWrap::Inline,
+            "\\begin{aligned}\na &= b \n\\end{aligned}".to_string()
+        )
+    );
+    assert_eq!(
+        auto(r"\[\begin{equation*} x \end{equation*}\]"),
+        (MathWrap::Display, "x".to_string())
+    );
+    // A line break with spacing isn't display math
+    assert_eq!(auto(r"\\[2pt] x").0, MathWrap::Inline);
+}
+
+#[test]
+fn the_wrap_can_be_set_per_equation() {
+    assert_eq!(
+        unwrap_body(r"\[x\]", None, MathWrap::Inline),
+        (MathWrap::Inline, "x".to_string())
+    );
+    assert_eq!(
+        unwrap_body(r"\[x\]", None, MathWrap::None),
+        (MathWrap::None, r"\[x\]".to_string())
+    );
+    let equations = parse_markdown("%%wrap=display%%\n$$\nx\n$$\n%%big%%\n");
+    assert_eq!(equations[0].wrap, MathWrap::Display);
+
+    // Exports keep the style the delimiters gave
+    let equations = parse_csv("Active,Body,Name\nyes,$$\\sum x$$,sum\n");
+    let csv = export_csv(&equations);
+    let exported = parse_csv(&csv);
+    assert_eq!(exported[0].body, r"\sum x");
+    assert_eq!(exported[0].math_wrap(), MathWrap::Display);
+}
+
+#[test]
+fn the_tex_source_uses_the_wrap() {
+    let out = TempDir::new().unwrap();
+    let mut options = RenderOptions::new(out.path(), "#000000");
+    options.retention = Retention::KeepAll;
+    let equations = parse_csv(
+        "Active,Body,Name\nyes,\\begin{gather}a\\\\b\\end{gather},stack\nyes,$$x$$,shown\n",
+    );
+
+    render_equations_with(&equations, &options, &MockBackend::new()).unwrap();
+    let tex = fs::read_to_string(out.path().join("stack.tex")).unwrap();
+    assert!(
+        tex.contains(r"{$ \begin{gathered}a\\b\end{gathered} $}"),
+        "{}",
+        tex
+    );
+    let tex = fs::read_to_string(out.path().join("shown.tex")).unwrap();
+    assert!(tex.contains(r"{$\displaystyle x $}"), "{}", tex);
+}
+
+#[test]
+fn display_environments_convert_to_mathml() {
+    let equations = parse_csv(
+        "Active,Body,Name\nyes,\\begin{align}a &= b\\\\c &= d\\end{align},pair\n\
+         yes,\\begin{gather}a\\\\b\\end{gather},stack\n\
+         yes,\\begin{cases}a & x\\end{cases},split\n",
+    );
+    assert!(equations[0]
+        .to_mathml("#000000")
+        .unwrap()
+        .contains("<mtable"));
+    assert!(equations[1]
+        .to_mathml("#000000")
+        .unwrap()
+        .contains("<mtable"));
+    let error = equations[2].to_mathml("#000000").unwrap_err();
+    assert!(
+        error.to_string().contains("the cases environment"),
+        "{}",
+        error
+    );
+}